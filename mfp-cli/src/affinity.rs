@@ -0,0 +1,187 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::runtime::{Builder, Runtime};
+use tracing::warn;
+
+/// Number of CPUs a cpuset can name, as many as the kernel's CPU masks hold
+const MAX_CPUS: usize = 1024;
+
+/// CPUs listed as in a cpuset, e.g. '0-7,16-23'
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuSet(Vec<usize>);
+
+impl FromStr for CpuSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid CPU list '{s}', expected e.g. '0-7,16-23'");
+        let mut cpus = Vec::new();
+        for range in s.split(',').map(str::trim) {
+            let (first, last) = range.split_once('-').unwrap_or((range, range));
+            let first: usize = first.trim().parse().map_err(|_| invalid())?;
+            let last: usize = last.trim().parse().map_err(|_| invalid())?;
+            if first > last {
+                return Err(invalid());
+            }
+            if last >= MAX_CPUS {
+                return Err(format!(
+                    "Invalid CPU list '{s}', CPUs are numbered below {MAX_CPUS}"
+                ));
+            }
+            cpus.extend(first..=last);
+        }
+        cpus.sort_unstable();
+        cpus.dedup();
+        Ok(Self(cpus))
+    }
+}
+
+impl fmt::Display for CpuSet {
+    /// Lists the CPUs with consecutive ones as ranges, e.g. '0-3,8'
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for &cpu in &self.0 {
+            match ranges.last_mut() {
+                Some((_, last)) if *last + 1 == cpu => *last = cpu,
+                _ => ranges.push((cpu, cpu)),
+            }
+        }
+        let ranges: Vec<String> = ranges
+            .into_iter()
+            .map(|(first, last)| {
+                if first == last {
+                    first.to_string()
+                } else {
+                    format!("{first}-{last}")
+                }
+            })
+            .collect();
+        write!(f, "{}", ranges.join(","))
+    }
+}
+
+impl CpuSet {
+    /// CPUs in increasing order
+    pub fn cpus(&self) -> &[usize] {
+        &self.0
+    }
+}
+
+/// CPUs the files are processed on, for dedicated hosts where keeping the worker
+/// threads on the same cores - or the same socket - avoids moving their frequency
+/// tables across memory nodes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Affinity {
+    /// CPUs the process is restricted to, all of those allowed by default
+    pub cpuset: Option<CpuSet>,
+    /// Whether each worker thread is pinned to a CPU of its own, in turn
+    pub pin_threads: bool,
+}
+
+/// Runtime processing the files along an affinity: restricted to the cpuset, with
+/// one worker thread per CPU of it, each pinned to its CPU if requested
+pub fn runtime(affinity: &Affinity) -> io::Result<Runtime> {
+    if affinity.cpuset.is_none() && !affinity.pin_threads {
+        return Runtime::new();
+    }
+
+    // Threads inherit the affinity of the thread creating them, blocking ones included
+    let cpus = match &affinity.cpuset {
+        Some(cpuset) => {
+            set_thread_affinity(cpuset.cpus())?;
+            cpuset.cpus().to_vec()
+        }
+        None => allowed_cpus()?,
+    };
+    let mut builder = Builder::new_multi_thread();
+    builder.enable_all().worker_threads(cpus.len().max(1));
+    if affinity.pin_threads {
+        let cpus = Arc::new(cpus);
+        let next = AtomicUsize::new(0);
+        builder.on_thread_start(move || {
+            // Only the first threads started are the workers, the blocking ones
+            // keep the whole cpuset
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            if let Some(&cpu) = cpus.get(index) {
+                if let Err(e) = set_thread_affinity(&[cpu]) {
+                    warn!("Failed to pin a worker thread to CPU {}: {}", cpu, e);
+                }
+            }
+        });
+    }
+    builder.build()
+}
+
+// Restricts the calling thread, and the threads it creates later, to some CPUs
+#[cfg(target_os = "linux")]
+fn set_thread_affinity(cpus: &[usize]) -> io::Result<()> {
+    // SAFETY: the set is plain data, zeroed as CPU_ZERO does, its CPUs are below
+    // CPU_SETSIZE as parsed, and the call only reads it
+    let result = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for &cpu in cpus {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    match result {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+// CPUs the calling thread may run on
+#[cfg(target_os = "linux")]
+fn allowed_cpus() -> io::Result<Vec<usize>> {
+    // SAFETY: the set is plain data the call fills in, and CPU_ISSET reads it
+    // within CPU_SETSIZE
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((0..MAX_CPUS)
+            .filter(|&cpu| libc::CPU_ISSET(cpu, &set))
+            .collect())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_thread_affinity(_cpus: &[usize]) -> io::Result<()> {
+    Err(unsupported())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn allowed_cpus() -> io::Result<Vec<usize>> {
+    Err(unsupported())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "CPU affinity is only supported on Linux",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpuset() {
+        let cpuset: CpuSet = "16-19, 0-3,2,8".parse().unwrap();
+        assert_eq!(cpuset.cpus(), [0, 1, 2, 3, 8, 16, 17, 18, 19]);
+        assert_eq!(cpuset.to_string(), "0-3,8,16-19");
+        assert_eq!("5".parse::<CpuSet>().unwrap().cpus(), [5]);
+
+        assert!("".parse::<CpuSet>().is_err());
+        assert!("3-1".parse::<CpuSet>().is_err());
+        assert!("0-".parse::<CpuSet>().is_err());
+        assert!("a".parse::<CpuSet>().is_err());
+        assert!("0-1024".parse::<CpuSet>().is_err());
+    }
+}
@@ -1,29 +1,651 @@
-use crate::format::OutputFormat;
-use clap::Parser;
-use std::path::PathBuf;
+use crate::affinity::{Affinity, CpuSet};
+use crate::config::Config;
+use crate::format::{OutputCompat, OutputFormat, OutputOptions, ReportFormat, SortKey};
+use crate::i18n::Lang;
+use crate::target::{Target, TargetScope};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use mfp_lib::{
+    CommandScorer, CountingPolicy, Encoding, Glob, JoinPolicy, LatexOptions, LineRange,
+    PartialRead, Preset, Region, SampleSize, Scorer, Script, Segmentation, TextProcessor,
+    TokenizerConfig, WalkOptions, WordRegex, DEFAULT_NGRAM, DEFAULT_WINDOW,
+};
+use std::env;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 use tracing::error;
 
+/// File argument standing for stdin
+pub const STDIN_PATH: &str = "-";
+
 #[derive(Parser, Debug)]
-#[command(name = "mfp", about = "Multi-files text processor", version)]
+#[command(
+    name = "mfp",
+    about = "Multi-files text processor",
+    version,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Cli {
-    /// Files to process - e.g., 'file1.txt file2.txt'
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Files or directories to process - e.g., 'file1.txt file2.txt docs/'
+    /// Directories are walked recursively, '-' reads stdin
+    /// A line range may be appended to a file - e.g., 'file1.txt:100-200'
     #[arg(required = true)]
     pub files: Vec<PathBuf>,
 
     /// Output format: 'text' by default - shows simple format
     /// 'json' provides structured output
+    /// 'frontmatter-patch' sets 'wordcount' and 'reading_time' in Markdown front matter
     #[arg(long, short, value_enum, default_value_t = OutputFormat::Text)]
     pub format: OutputFormat,
 
-    /// Display detailed formatted figures as per-line word counts
+    /// Update files in place instead of printing, for formats patching files
+    #[arg(long)]
+    pub write: bool,
+
+    /// Write the formatted results to this file rather than to stdout - the file
+    /// is replaced at once, so readers never see partial results
     #[arg(long, short)]
-    pub verbose: bool,
+    pub output: Option<PathBuf>,
+
+    /// Also place the report on the system clipboard, formatted as Markdown unless
+    /// another format is given, e.g. '--copy=text'
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "markdown"
+    )]
+    pub copy: Option<OutputFormat>,
+
+    /// Append the formatted results to the --output file instead of replacing it
+    #[arg(long, requires = "output")]
+    pub append: bool,
+
+    /// Keep running and print the results of files again as they change, until Ctrl-C
+    #[arg(long, conflicts_with = "output")]
+    pub watch: bool,
+
+    /// Show more figures, once per level: -v adds the totals, unique words and words per
+    /// line statistics of each file, -vv the per-line counts and timing, -vvv the detected
+    /// encoding and byte order mark
+    #[arg(long, short, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Maximum depth directories are walked to - files directly inside a directory are at depth 1
+    #[arg(long)]
+    pub max_depth: Option<usize>,
+
+    /// Skip the files and directories matching a pattern while walking directories,
+    /// e.g. '**/node_modules/**' - patterns without '/' match names, repeatable
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<Glob>,
+
+    /// Walk directories whole, instead of leaving out the paths ignored by the
+    /// '.gitignore' and '.mfpignore' files met along the way and the '.git' directories
+    #[arg(long)]
+    pub no_ignore: bool,
+
+    /// Only run on these CPUs, e.g. '0-15' for the first socket, with one worker
+    /// thread per CPU - overrides mfp.toml
+    #[arg(long, value_name = "CPUS")]
+    pub cpuset: Option<CpuSet>,
+
+    /// Pin each worker thread to a CPU of its own, among --cpuset or the CPUs
+    /// allowed - overrides mfp.toml
+    #[arg(long)]
+    pub pin_threads: bool,
+
+    /// Maximum number of files processed at once - the number of CPUs by default
+    #[arg(long, value_name = "N")]
+    pub max_concurrent: Option<usize>,
+
+    /// Give up on a file taking longer than this to process, e.g. '30s', '500ms'
+    /// or '2m', reporting it as failed while the other files carry on
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+
+    /// Reject files larger than this, e.g. '512K', '100M' or '2G' in multiples
+    /// of 1024 bytes, reporting them as failed without reading them
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_file_size: Option<u64>,
+
+    /// Decompress gzip files whatever their name, recognized by their content -
+    /// files ending in '.gz' always are
+    #[arg(long)]
+    pub decompress: bool,
+
+    /// Encoding of the input files: utf-8, utf-16le, utf-16be, latin1 or
+    /// windows-1252 - detected from the first bytes of each file by default
+    #[arg(long, value_name = "NAME")]
+    pub encoding: Option<Encoding>,
+
+    /// Count files that look binary instead of skipping them
+    #[arg(long)]
+    pub include_binary: bool,
+
+    /// External command scoring each file - receives the content on stdin
+    /// and prints a number, e.g. 'python perplexity.py'
+    #[arg(long)]
+    pub scorer_cmd: Option<String>,
+
+    /// Maximum number of concurrent scorer invocations
+    #[arg(long, default_value_t = 4)]
+    pub scorer_jobs: usize,
+
+    /// Only process a random sample of the files - e.g., '5%' or '0.05' -
+    /// and extrapolate corpus totals
+    #[arg(long, conflicts_with = "sample_files")]
+    pub sample: Option<SampleSize>,
+
+    /// Only process a random sample of this many files and extrapolate corpus totals
+    #[arg(long)]
+    pub sample_files: Option<usize>,
+
+    /// Seed making the random sample reproducible
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Only process the first N lines of each file
+    #[arg(long, group = "partial", value_parser = clap::value_parser!(u64).range(1..))]
+    pub head_lines: Option<u64>,
+
+    /// Only process the first N bytes of each file
+    #[arg(long, group = "partial")]
+    pub head_bytes: Option<u64>,
+
+    /// Only process the last N lines of each file
+    #[arg(long, group = "partial", value_parser = clap::value_parser!(u64).range(1..))]
+    pub tail_lines: Option<u64>,
+
+    /// Only process the last N bytes of each file, from the first full line within them
+    #[arg(long, group = "partial")]
+    pub tail_bytes: Option<u64>,
+
+    /// Only process a range of lines of each file - e.g., '100..200' or '100-'
+    #[arg(long, group = "partial")]
+    pub lines: Option<LineRange>,
+
+    /// Write a sidecar index next to each file - e.g., 'file1.txt.lineidx' -
+    /// holding the byte offset of each counted line
+    #[arg(long)]
+    pub line_index: bool,
+
+    /// Write a Bloom filter of the vocabulary of each file next to it - e.g.,
+    /// 'file1.txt.bloom' - sized for this false positive rate (0.01 when omitted),
+    /// for 'mfp query --word'
+    #[arg(
+        long,
+        value_name = "RATE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "0.01",
+        value_parser = parse_rate,
+        conflicts_with = "no_content_retention"
+    )]
+    pub bloom_filters: Option<f64>,
+
+    /// Write a manifest of the processed files - path, size, modification time and
+    /// SHA-256 of each - as JSON, to verify the report against its exact inputs later
+    #[arg(long, value_name = "FILE", conflicts_with = "watch")]
+    pub emit_manifest: Option<PathBuf>,
+
+    /// Show the N most frequent words of each file (10 when N is omitted)
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "10"
+    )]
+    pub frequencies: Option<usize>,
+
+    /// Show the N most frequent words across all processed files
+    #[arg(long, value_name = "N")]
+    pub top: Option<usize>,
+
+    /// Fit Zipf's law to the corpus word frequencies (slope, R²) and write the
+    /// rank-frequency curve as CSV to this file
+    #[arg(long, value_name = "CSV")]
+    pub zipf: Option<PathBuf>,
+
+    /// Hold at most N distinct words of the corpus frequencies in memory, spilling
+    /// sorted runs to temporary files beyond that, for --top and --zipf over corpora
+    /// whose vocabulary does not fit in memory
+    #[arg(long, value_name = "N", conflicts_with = "frequencies")]
+    pub max_frequency_words: Option<usize>,
+
+    /// Keep no word text in memory, in the cache nor in the report - numeric
+    /// aggregates only, unique words being left out too - for regulated environments
+    #[arg(long, conflicts_with_all = ["frequencies", "top", "zipf"])]
+    pub no_content_retention: bool,
+
+    /// Count words joined by an apostrophe, e.g. "don't", as one word ('join')
+    /// or several ('split') - overrides mfp.toml
+    #[arg(long, value_name = "POLICY")]
+    pub apostrophes: Option<JoinPolicy>,
+
+    /// Characters splitting words on top of the segmentation mode, e.g. ',;|'
+    /// for CSV-ish and log lines, '\t' standing for a tab - overrides mfp.toml
+    #[arg(long, value_name = "CHARS")]
+    pub delimiters: Option<TokenizerConfig>,
+
+    /// Count the matches of this regex as the words instead of those of the segmentation
+    /// mode, e.g. '\w+' for identifiers, '\d+' for numbers or '#\w+' for hashtags - overrides mfp.toml
+    #[arg(long, value_name = "REGEX")]
+    pub word_regex: Option<WordRegex>,
+
+    /// Count the occurrences of this regex per line and per file instead of the words,
+    /// like grep with statistics, e.g. 'TODO|FIXME' or 'ERROR'
+    #[arg(long, value_name = "REGEX", conflicts_with = "word_regex")]
+    pub count_pattern: Option<WordRegex>,
+
+    /// Fold the case of words when counting the unique ones, so that "The" and "the"
+    /// are the same word
+    #[arg(long)]
+    pub case_insensitive: bool,
+
+    /// Estimate the unique words with a HyperLogLog sketch of 2^PRECISION registers
+    /// (14 when omitted, 0.81% standard error) rather than keeping every word
+    #[arg(
+        long,
+        value_name = "PRECISION",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "14",
+        value_parser = clap::value_parser!(u8).range(4..=18)
+    )]
+    pub approximate_unique_words: Option<u8>,
+
+    /// Count hyphenated compounds, e.g. "well-known", as one word ('join')
+    /// or several ('split') - overrides mfp.toml
+    #[arg(long, value_name = "POLICY")]
+    pub hyphens: Option<JoinPolicy>,
+
+    /// Count slash-joined terms, e.g. "and/or", as one word ('join')
+    /// or several ('split') - overrides mfp.toml
+    #[arg(long, value_name = "POLICY")]
+    pub slashes: Option<JoinPolicy>,
+
+    /// Word list splitting a language written without spaces into words, one word
+    /// per line - e.g., 'th=thai-words.txt' (th, lo, km or my) - repeatable
+    #[arg(long, value_name = "LANG=FILE", value_parser = parse_dictionary)]
+    pub dictionary: Vec<(Script, PathBuf)>,
+
+    /// Common words left out of the word frequencies and unique words: a built-in list
+    /// ('english', 'german' or 'french') or a file of one word per line - repeatable
+    #[arg(long, value_name = "LIST|FILE")]
+    pub stopwords: Vec<String>,
+
+    /// Configuration file - 'mfp.toml' in the current directory is read when present,
+    /// on top of /etc/mfp/config.toml and $XDG_CONFIG_HOME/mfp/config.toml
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Ignore the system-wide and user configuration files, only reading --config or
+    /// 'mfp.toml'
+    #[arg(long)]
+    pub no_system_config: bool,
+
+    /// How lines are split into words: 'whitespace' by default, or 'unicode' word
+    /// boundaries splitting punctuation-attached words and counting each CJK ideograph
+    #[arg(long, default_value = "whitespace")]
+    pub segmentation: Segmentation,
+
+    /// Count like another tool: 'msword' for Microsoft Word, 'texcount' for LaTeX
+    /// sources, skipping markup, comments and math - overrides the counting policy
+    /// of mfp.toml, and is overridden by --apostrophes, --hyphens and --slashes
+    #[arg(long, value_name = "PRESET", conflicts_with = "segmentation")]
+    pub preset: Option<Preset>,
+
+    /// Read files as LaTeX sources, counting the text only: command names, the
+    /// preamble, comments and math are skipped
+    #[arg(long)]
+    pub latex: bool,
+
+    /// Count the words of LaTeX comments - implies --latex
+    #[arg(long)]
+    pub latex_comments: bool,
+
+    /// Count the symbols of LaTeX math - implies --latex
+    #[arg(long)]
+    pub latex_math: bool,
+
+    /// Exclude citation keys and bibliography sections of LaTeX sources (--latex)
+    /// and Markdown files from the counts, showing the citations per file instead
+    #[arg(long)]
+    pub citations: bool,
+
+    /// Show the Flesch-Kincaid grade level and Flesch reading ease of each file,
+    /// from its sentences and estimated syllables
+    #[arg(long)]
+    pub readability: bool,
+
+    /// Count the words of footnotes, captions, tables and block quotes of LaTeX sources
+    /// (--latex) and Markdown files, and of double-quoted spans, separately
+    #[arg(long)]
+    pub regions: bool,
+
+    /// Leave a region out of the word counts: 'footnotes', 'captions', 'tables' or
+    /// 'quotes' - implies --regions, repeatable
+    #[arg(long, value_name = "REGION")]
+    pub exclude_region: Vec<Region>,
+
+    /// Show corpus statistics of the words per file: mean, standard deviation,
+    /// percentiles and Gini coefficient
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Word count to reach, with the progress and words remaining shown in the
+    /// text and Markdown outputs - overrides mfp.toml
+    #[arg(long, value_name = "WORDS")]
+    pub target: Option<usize>,
+
+    /// Language of the text and Markdown output labels: 'en' (default), 'de' or
+    /// 'fr' - JSON keys stay English
+    #[arg(long, value_name = "LANG", default_value = "en")]
+    pub lang: Lang,
+
+    /// Whether the target applies to each 'file' or the whole 'corpus' (default)
+    /// - overrides mfp.toml
+    #[arg(long, value_name = "SCOPE")]
+    pub target_scope: Option<TargetScope>,
+
+    /// Count every file again, neither reusing the results of unchanged files cached
+    /// by previous runs in $XDG_CACHE_HOME/mfp (or ~/.cache/mfp) nor caching those of this run
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Do not draw the progress bar shown on stderr when it is a terminal
+    #[arg(long)]
+    pub no_progress: bool,
+
+    /// Screen-reader friendly text output: one labelled line per figure, files in
+    /// path order, without separators, colors nor progress bar
+    #[arg(long, conflicts_with = "format")]
+    pub plain: bool,
+
+    /// Editor quick mode: count a single buffer read from stdin (given as '-')
+    /// under this name and print one compact JSON object
+    #[arg(long, value_name = "NAME")]
+    pub stdin_name: Option<String>,
+
+    /// Write the run metadata with the report: mfp version, start time, host, effective
+    /// options and total elapsed time, to reproduce and audit the results later
+    #[arg(long)]
+    pub metadata: bool,
+
+    /// Stick to the text and JSON layout of an earlier version, so that existing
+    /// parsers keep working while they migrate: '1' for file names without their
+    /// directory, the per-line counts and, with -v, the total words only
+    #[arg(long, value_name = "VERSION", conflicts_with = "plain")]
+    pub output_compat: Option<OutputCompat>,
+
+    /// Only write the aggregate summary - total files, lines and words, and the
+    /// average words per file - and the corpus-wide figures, leaving out the files,
+    /// e.g. for huge batches
+    #[arg(long, conflicts_with = "output_compat")]
+    pub summary_only: bool,
+
+    /// Order of the files in the report, ties broken by path: 'name' (their path)
+    /// by default, 'words' or 'lines' for the fewest first. Streamed formats wait
+    /// for every file unless sorted by name
+    #[arg(long, value_enum, default_value_t = SortKey::Name)]
+    pub sort_by: SortKey,
+
+    /// List the files in reverse order, e.g. the largest first with --sort-by words
+    #[arg(long)]
+    pub reverse: bool,
+
+    /// Only list the files of at least this many words, the summary covering the
+    /// files listed - e.g. 1 to leave out empty files
+    #[arg(long, value_name = "WORDS")]
+    pub min_words: Option<usize>,
+
+    /// Only list the files of at most this many words, the summary covering the
+    /// files listed - e.g. 0 to find empty files
+    #[arg(long, value_name = "WORDS")]
+    pub max_words: Option<usize>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Compare two versions of a file: lines added/removed and word count deltas
+    Delta(DeltaArgs),
+    /// Report shared n-gram overlap between every file pair of two document sets
+    Overlap(OverlapArgs),
+    /// Export a sparse word co-occurrence matrix as CSV
+    Cooccurrence(CooccurrenceArgs),
+    /// Report word counts of an mdBook per part and chapter, in reading order
+    Book(BookArgs),
+    /// Serve live word counts of editor buffers over a JSON-RPC stdio protocol
+    Lsp,
+    /// Count words of a Pandoc JSON AST read from stdin, per block type
+    Pandoc(PandocArgs),
+    /// Record the word count of a directory and report the words added per day
+    Progress(ProgressArgs),
+    /// List the files whose Bloom filters, written with --bloom-filters, may contain
+    /// a word
+    Query(QueryArgs),
+    /// Check the environment and run a quick self-benchmark, printing what to fix
+    Doctor(DoctorArgs),
+    /// Generate a reproducible synthetic corpus along with its expected counts
+    Gen(GenArgs),
+    /// Print the CLI surface (flags, values, defaults) and the JSON schema of the
+    /// report as JSON, for wrapper tools and GUIs
+    Introspect,
+    /// Write the man page, covering the options, subcommands, output formats,
+    /// configuration file and exit statuses
+    Man(ManArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct DeltaArgs {
+    /// Old version of the file
+    pub old: PathBuf,
+
+    /// New version of the file
+    pub new: PathBuf,
+
+    /// Output format: 'text' by default - 'json' provides structured output
+    #[arg(long, short, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+}
+
+#[derive(Args, Debug)]
+pub struct ProgressArgs {
+    /// Watched directory, walked recursively
+    pub dir: PathBuf,
+
+    /// Number of days reported, today included
+    #[arg(long, default_value_t = 7)]
+    pub days: usize,
+
+    /// History file the word counts are recorded in (default:
+    /// $XDG_DATA_HOME/mfp/history.tsv, or ~/.local/share/mfp/history.tsv)
+    #[arg(long, value_name = "FILE")]
+    pub history: Option<PathBuf>,
+
+    /// Word count to reach for the whole directory - overrides mfp.toml
+    #[arg(long, value_name = "WORDS")]
+    pub target: Option<usize>,
+
+    /// Screen-reader friendly output: one labelled line per day, without table
+    /// nor sparkline
+    #[arg(long)]
+    pub plain: bool,
+
+    /// Output format: 'text' by default - 'json' provides structured output
+    #[arg(long, short, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+}
+
+#[derive(Args, Debug)]
+pub struct DoctorArgs {
+    /// Configuration file to check - 'mfp.toml' in the current directory by default
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Skip the self-benchmark, only checking the environment
+    #[arg(long)]
+    pub no_benchmark: bool,
+
+    /// Output format: 'text' by default - 'json' provides structured output
+    #[arg(long, short, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+}
+
+#[derive(Args, Debug)]
+pub struct GenArgs {
+    /// Directory the corpus is written to: the files in its 'texts' directory,
+    /// the expected counts in 'manifest.json'
+    #[arg(long, value_name = "DIR")]
+    pub out_dir: PathBuf,
+
+    /// Number of files
+    #[arg(long, default_value_t = 100)]
+    pub files: usize,
+
+    /// Number of lines of each file
+    #[arg(long, default_value_t = 100)]
+    pub lines: usize,
+
+    /// Average number of words per line, the actual counts ranging from none
+    /// to twice as many
+    #[arg(long, default_value_t = 12)]
+    pub words_per_line: usize,
+
+    /// Seed of the generated words - the same seed always yields the same corpus
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct ManArgs {
+    /// Directory the page is written to as 'mfp.1' - stdout by default
+    #[arg(long, value_name = "DIR")]
+    pub out_dir: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct OverlapArgs {
+    /// First document set - a directory walked recursively, or a single file
+    #[arg(long)]
+    pub set_a: PathBuf,
+
+    /// Second document set - a directory walked recursively, or a single file
+    #[arg(long)]
+    pub set_b: PathBuf,
+
+    /// Number of consecutive words per compared n-gram
+    #[arg(long, default_value_t = DEFAULT_NGRAM)]
+    pub ngram: usize,
+
+    /// Minimum percentage of either file found in the other for a pair to be reported
+    #[arg(long, default_value_t = 10.0)]
+    pub threshold: f64,
+
+    /// Output format: 'text' by default - 'json' provides structured output
+    #[arg(long, short, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+}
+
+#[derive(Args, Debug)]
+pub struct QueryArgs {
+    /// Word looked up, case-insensitively and without surrounding punctuation
+    #[arg(long)]
+    pub word: String,
+
+    /// Files or directories whose Bloom filters are read, directories being walked
+    /// recursively
+    #[arg(default_value = ".")]
+    pub paths: Vec<PathBuf>,
+
+    /// Output format: 'text' by default - 'json' provides structured output
+    #[arg(long, short, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+}
+
+#[derive(Args, Debug)]
+pub struct PandocArgs {
+    /// Filter mode: pass the AST through to stdout unchanged and report on stderr
+    #[arg(long)]
+    pub filter: bool,
+
+    /// Output format of the report: 'text' by default - 'json' provides structured output
+    #[arg(long, short, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+
+    /// Target format Pandoc passes to filters, ignored
+    #[arg(hide = true)]
+    pub target_format: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct CooccurrenceArgs {
+    /// Files to analyze - directories are walked recursively
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+
+    /// Number of following words each word co-occurs with
+    #[arg(long, default_value_t = DEFAULT_WINDOW)]
+    pub window: usize,
+
+    /// Prune the vocabulary to words occurring at least this many times
+    #[arg(long, default_value_t = 1)]
+    pub min_count: usize,
+
+    /// Prune the vocabulary to the N most frequent words, bounding memory
+    #[arg(long, value_name = "N")]
+    pub max_vocab: Option<usize>,
+
+    /// How lines are split into words: 'whitespace' or 'unicode'
+    #[arg(long, default_value = "whitespace")]
+    pub segmentation: Segmentation,
+
+    /// Write the CSV to this file rather than to stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct BookArgs {
+    /// mdBook source directory holding SUMMARY.md
+    pub src_dir: PathBuf,
+
+    /// Report format: 'text' by default - 'markdown' and 'html' render a table
+    /// suitable for committing into the book, 'json' provides structured output
+    #[arg(long, short, value_enum, default_value_t = BookFormat::Text)]
+    pub format: BookFormat,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum BookFormat {
+    /// Indented text outline
+    Text,
+    /// GitHub-flavored Markdown table
+    Markdown,
+    /// HTML table
+    Html,
+    /// Structured JSON format
+    Json,
 }
 
 impl Cli {
-    /// Validates all input files exist and are readable
+    /// Validates all input files and directories exist
     pub fn validate(&self) -> Result<(), String> {
-        let invalid_files: Vec<_> = self.files.iter().filter(|path| !path.is_file()).collect();
+        let inputs = self.inputs();
+        let invalid_files: Vec<_> = inputs
+            .iter()
+            .map(|(path, _)| path)
+            .filter(|path| path.as_os_str() != STDIN_PATH && !path.is_file() && !path.is_dir())
+            .collect();
 
         if !invalid_files.is_empty() {
             let error_msg = format!(
@@ -38,6 +660,346 @@ impl Cli {
             return Err(error_msg);
         }
 
+        if self
+            .scorer_cmd
+            .as_deref()
+            .is_some_and(|cmd| cmd.trim().is_empty())
+        {
+            return Err("Scorer command must not be empty".to_string());
+        }
+
+        if self.watch
+            && inputs
+                .iter()
+                .any(|(path, _)| path.as_os_str() == STDIN_PATH)
+        {
+            return Err("Stdin cannot be watched".to_string());
+        }
+
+        if self.max_frequency_words.is_some() && self.top.is_none() && self.zipf.is_none() {
+            return Err("--max-frequency-words only applies to --top and --zipf".to_string());
+        }
+
+        if self.output_compat.is_some()
+            && !matches!(self.format, OutputFormat::Text | OutputFormat::Json)
+        {
+            return Err("--output-compat only applies to the text and JSON formats".to_string());
+        }
+
+        if let (Some(min), Some(max)) = (self.min_words, self.max_words) {
+            if min > max {
+                return Err(format!(
+                    "--min-words ({min}) is greater than --max-words ({max})"
+                ));
+            }
+        }
+
+        if self.summary_only && matches!(self.format, OutputFormat::FrontmatterPatch) {
+            return Err(
+                "--summary-only does not apply to the frontmatter-patch format".to_string(),
+            );
+        }
+
         Ok(())
     }
+
+    /// Whether the report is written file by file as results arrive, which keeps
+    /// path order only
+    pub fn streams(&self) -> bool {
+        self.format.streams(self.plain)
+            && self.output.is_none()
+            && self.sort_by == SortKey::Name
+            && !self.reverse
+    }
+
+    /// Files to process along with their own line range, if one was appended
+    pub fn inputs(&self) -> Vec<(PathBuf, Option<LineRange>)> {
+        self.files
+            .iter()
+            .map(|path| split_line_range(path))
+            .collect()
+    }
+
+    /// Output settings shared across formats
+    pub fn output_options(&self, config: &Config) -> OutputOptions {
+        OutputOptions {
+            target: self.target(config),
+            lang: self.lang,
+            plain: self.plain,
+            verbosity: self.verbose.into(),
+            write: self.write,
+            top_words: self.frequencies,
+            // Set once the processor is configured, see `effective_options`
+            metadata: None,
+            compat: self.output_compat,
+            summary_only: self.summary_only,
+            sort_by: self.sort_by,
+            reverse: self.reverse,
+            min_words: self.min_words,
+            max_words: self.max_words,
+            skip_header: self.append
+                && self
+                    .output
+                    .as_ref()
+                    .is_some_and(|path| path.metadata().is_ok_and(|m| m.len() > 0)),
+        }
+    }
+
+    /// Options the results depend on, as given or defaulted, for the run metadata:
+    /// those choosing the files and listing them, then the settings of the processor
+    pub fn effective_options(&self, processor: &TextProcessor) -> Vec<(String, String)> {
+        let jobs = self
+            .max_concurrent
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, NonZeroUsize::get));
+        let mut options = vec![("jobs", jobs.to_string())];
+        let optional = [
+            ("timeout", self.timeout.map(|t| format!("{:?}", t))),
+            ("max_depth", self.max_depth.map(|n| n.to_string())),
+            (
+                "exclude",
+                (!self.exclude.is_empty()).then(|| {
+                    let patterns: Vec<&str> = self.exclude.iter().map(Glob::as_str).collect();
+                    patterns.join(",")
+                }),
+            ),
+            ("ignore_files", self.no_ignore.then(|| "false".to_string())),
+            ("sample", self.sample_size().map(|s| format!("{:?}", s))),
+            ("seed", self.sample_size().map(|_| self.seed.to_string())),
+            ("min_words", self.min_words.map(|n| n.to_string())),
+            ("max_words", self.max_words.map(|n| n.to_string())),
+            (
+                "max_frequency_words",
+                self.max_frequency_words.map(|n| n.to_string()),
+            ),
+        ];
+        options.extend(
+            optional
+                .into_iter()
+                .filter_map(|(name, value)| Some((name, value?))),
+        );
+        options.extend(processor.settings());
+        options
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+
+    /// CPUs of the configuration the files are processed on, overridden by the
+    /// command line
+    pub fn affinity(&self, config: &Config) -> Affinity {
+        Affinity {
+            cpuset: self.cpuset.clone().or_else(|| config.cpuset.clone()),
+            pin_threads: self.pin_threads || config.pin_threads.unwrap_or(false),
+        }
+    }
+
+    /// How directories given as input are walked
+    pub fn walk_options(&self) -> WalkOptions {
+        WalkOptions {
+            max_depth: self.max_depth,
+            excludes: self.exclude.clone(),
+            ignore_files: !self.no_ignore,
+        }
+    }
+
+    /// Requested sample size, if sampling is enabled
+    pub fn sample_size(&self) -> Option<SampleSize> {
+        self.sample
+            .or_else(|| self.sample_files.map(SampleSize::Count))
+    }
+
+    /// Requested partial read of each file, if any
+    pub fn partial_read(&self) -> Option<PartialRead> {
+        self.head_lines
+            .map(|n| PartialRead::HeadLines(n as usize))
+            .or(self.head_bytes.map(PartialRead::HeadBytes))
+            .or(self.tail_lines.map(|n| PartialRead::TailLines(n as usize)))
+            .or(self.tail_bytes.map(PartialRead::TailBytes))
+            .or(self.lines.map(PartialRead::Lines))
+    }
+
+    /// Processor splitting lines along the segmentation mode or preset, reading LaTeX if requested
+    pub fn processor(&self) -> TextProcessor {
+        let processor = match self.preset {
+            Some(preset) => TextProcessor::new().with_preset(preset),
+            None => TextProcessor::new().with_segmentation(self.segmentation),
+        }
+        .with_citations(self.citations)
+        // Front matter word counts follow static site generators, not counting heading markup
+        .with_markdown_headings(matches!(self.format, OutputFormat::FrontmatterPatch))
+        .with_readability(self.readability)
+        .with_decompress(self.decompress)
+        .with_include_binary(self.include_binary)
+        .with_unique_words(self.verbose > 0)
+        .with_content_retention(!self.no_content_retention)
+        .with_case_insensitive(self.case_insensitive);
+        let processor = match self.bloom_filters {
+            Some(rate) => processor.with_bloom_filters(rate),
+            None => processor,
+        };
+        let processor = match self.approximate_unique_words {
+            Some(precision) => processor.with_approximate_unique_words(precision),
+            None => processor,
+        };
+        let processor = match self.max_file_size {
+            Some(max_file_size) => processor.with_max_file_size(max_file_size),
+            None => processor,
+        };
+        let processor = match self.encoding {
+            Some(encoding) => processor.with_encoding(encoding),
+            None => processor,
+        };
+        let processor = match &self.count_pattern {
+            Some(pattern) => processor.with_count_pattern(pattern.clone()),
+            None => processor,
+        };
+        let processor = if self.regions || !self.exclude_region.is_empty() {
+            processor.with_regions(self.exclude_region.iter().copied())
+        } else {
+            processor
+        };
+        let processor = if self.latex || self.latex_comments || self.latex_math {
+            processor.with_latex(LatexOptions {
+                comments: self.latex_comments,
+                math: self.latex_math,
+            })
+        } else {
+            processor
+        };
+        match cache_dir().filter(|_| !self.no_cache) {
+            Some(dir) => processor.with_cache(dir),
+            None => processor,
+        }
+    }
+
+    /// Processor of in-memory buffers, counting as set by the command line and the
+    /// configuration
+    pub fn buffer_processor(&self, config: &Config) -> TextProcessor {
+        config
+            .apply(self.processor())
+            .with_counting_policy(self.counting_policy(config.counting_policy))
+            .with_tokenizer_config(self.tokenizer_config(&config.tokenizer_config))
+    }
+
+    /// Word count target of the configuration, overridden by the command line
+    pub fn target(&self, config: &Config) -> Option<Target> {
+        Some(Target {
+            words: self.target.or(config.target_words)?,
+            scope: self
+                .target_scope
+                .or(config.target_scope)
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Counting policy of the configuration, overridden by the preset then the command line
+    pub fn counting_policy(&self, configured: CountingPolicy) -> CountingPolicy {
+        let configured = self
+            .preset
+            .map_or(configured, |preset| preset.counting_policy());
+        CountingPolicy {
+            apostrophes: self.apostrophes.unwrap_or(configured.apostrophes),
+            hyphens: self.hyphens.unwrap_or(configured.hyphens),
+            slashes: self.slashes.unwrap_or(configured.slashes),
+        }
+    }
+
+    /// Custom word delimiters and word regex of the configuration, each overridden by
+    /// the command line
+    pub fn tokenizer_config(&self, configured: &TokenizerConfig) -> TokenizerConfig {
+        let mut config = self.delimiters.clone().unwrap_or_else(|| {
+            TokenizerConfig::new().with_delimiters(configured.delimiters().iter().copied())
+        });
+        if let Some(regex) = self.word_regex.as_ref().or(configured.word_regex()) {
+            config = config.with_word_regex(regex.clone());
+        }
+        config
+    }
+
+    /// Builds the configured external scorer, if any
+    pub fn scorer(&self) -> Option<Arc<dyn Scorer>> {
+        self.scorer_cmd
+            .as_deref()
+            .and_then(CommandScorer::from_command_line)
+            .map(|s| Arc::new(s) as Arc<dyn Scorer>)
+    }
+}
+
+// Splits a trailing ':<range>' from a path, unless the path exists as written
+fn split_line_range(path: &Path) -> (PathBuf, Option<LineRange>) {
+    if path.exists() {
+        return (path.to_path_buf(), None);
+    }
+
+    path.to_str()
+        .and_then(|s| s.rsplit_once(':'))
+        .and_then(|(file, range)| Some((PathBuf::from(file), Some(range.parse().ok()?))))
+        .unwrap_or_else(|| (path.to_path_buf(), None))
+}
+
+/// Cache directory of the results, following the XDG base directory spec
+pub fn cache_dir() -> Option<PathBuf> {
+    let cache = env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(cache.join("mfp"))
+}
+
+// Parses a 'LANG=FILE' dictionary argument
+fn parse_dictionary(s: &str) -> Result<(Script, PathBuf), String> {
+    let (language, file) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid dictionary '{s}', expected e.g. 'th=thai-words.txt'"))?;
+    Ok((language.parse()?, PathBuf::from(file)))
+}
+
+// Parses a false positive rate, strictly between 0 and 1
+fn parse_rate(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate < 1.0 => Ok(rate),
+        _ => Err(format!("Invalid rate '{s}', expected e.g. '0.01'")),
+    }
+}
+
+// Parses a size such as '512K', '100M' or '2G', plain numbers being bytes
+fn parse_size(s: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid size '{s}', expected e.g. '512K', '100M' or '2G'");
+    let s = s.trim();
+    let (value, unit) = s.split_at(
+        s.find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len()),
+    );
+    let value: f64 = value.parse().map_err(|_| invalid())?;
+    let multiple: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => return Err(invalid()),
+    };
+    Ok((value * multiple as f64) as u64)
+}
+
+// Parses a duration such as '30s', '500ms' or '2m', plain numbers being seconds
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("Invalid duration '{s}', expected e.g. '30s', '500ms' or '2m'");
+    let (value, unit) = s.trim().split_at(
+        s.trim()
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.trim().len()),
+    );
+    let value: f64 = value.parse().map_err(|_| invalid())?;
+    let seconds = match unit.trim() {
+        "ms" => value / 1000.0,
+        "" | "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        _ => return Err(invalid()),
+    };
+    Duration::try_from_secs_f64(seconds)
+        .ok()
+        .filter(|duration| !duration.is_zero())
+        .ok_or_else(invalid)
 }
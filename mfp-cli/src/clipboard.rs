@@ -0,0 +1,80 @@
+use crate::error::CliError;
+use std::env;
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+// Clipboard tools of the platform, with their arguments, in order of preference
+fn tools() -> Vec<(&'static str, &'static [&'static str])> {
+    if cfg!(target_os = "macos") {
+        vec![("pbcopy", &[])]
+    } else if cfg!(windows) {
+        vec![("clip", &[])]
+    } else {
+        let mut tools: Vec<(&'static str, &'static [&'static str])> = vec![
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ];
+        if env::var_os("WAYLAND_DISPLAY").is_some() {
+            tools.insert(0, ("wl-copy", &[]));
+        }
+        tools
+    }
+}
+
+/// First clipboard tool of the platform found in the PATH, if any
+pub fn find_tool() -> Option<&'static str> {
+    let path = env::var_os("PATH")?;
+    tools()
+        .into_iter()
+        .map(|(program, _)| program)
+        .find(|program| {
+            env::split_paths(&path).any(|dir| {
+                dir.join(program).is_file() || dir.join(format!("{program}.exe")).is_file()
+            })
+        })
+}
+
+/// Places the content on the system clipboard through the first clipboard tool found
+pub fn copy(content: &[u8]) -> Result<(), CliError> {
+    for (program, args) in tools() {
+        // Tools such as xclip stay around to serve the selection, so they must
+        // not hold on to our output
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                debug!("Clipboard tool {} not found", program);
+                continue;
+            }
+            Err(e) => {
+                return Err(CliError::FormatError(format!(
+                    "Failed to run {}: {}",
+                    program, e
+                )))
+            }
+        };
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(content)?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(CliError::FormatError(format!(
+                "{} failed to copy to the clipboard: {}",
+                program, status
+            )));
+        }
+        debug!("Copied {} bytes with {}", content.len(), program);
+        return Ok(());
+    }
+
+    Err(CliError::FormatError(
+        "No clipboard tool found - install wl-copy, xclip or xsel".to_string(),
+    ))
+}
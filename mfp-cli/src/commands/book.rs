@@ -0,0 +1,197 @@
+use crate::args::{BookArgs, BookFormat};
+use crate::error::CliError;
+use mfp_lib::{parse_summary, SummaryChapter, TextProcessor};
+use std::collections::BTreeSet;
+use std::fs;
+use tracing::{debug, info};
+
+/// Name of the table of contents file of an mdBook src directory
+const SUMMARY_FILE: &str = "SUMMARY.md";
+
+/// Row of the report, in reading order
+#[derive(serde::Serialize)]
+struct ChapterRow {
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    depth: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    part: Option<String>,
+    words: usize,
+}
+
+#[derive(serde::Serialize)]
+struct PartRow {
+    title: Option<String>,
+    words: usize,
+    chapters: Vec<ChapterRow>,
+}
+
+#[derive(serde::Serialize)]
+struct BookReport {
+    total_words: usize,
+    parts: Vec<PartRow>,
+}
+
+/// Reports mdBook word counts per part and chapter, in reading order
+pub async fn run(args: BookArgs) -> Result<(), CliError> {
+    let summary_path = args.src_dir.join(SUMMARY_FILE);
+    let summary = fs::read_to_string(&summary_path).map_err(|e| {
+        CliError::InputError(format!("Failed to read {}: {}", summary_path.display(), e))
+    })?;
+    let chapters = parse_summary(&summary);
+    info!(
+        "Found {} chapters in {}",
+        chapters.len(),
+        summary_path.display()
+    );
+
+    let files: BTreeSet<_> = chapters
+        .iter()
+        .filter_map(|chapter| chapter.path.as_ref())
+        .map(|path| args.src_dir.join(path))
+        .collect();
+    let mut processor = TextProcessor::new();
+    if !files.is_empty() {
+        processor
+            .process_files(files.into_iter().collect())
+            .await
+            .map_err(|e| CliError::InputError(format!("Failed to process chapters: {}", e)))?;
+    }
+
+    let words = |chapter: &SummaryChapter| {
+        chapter
+            .path
+            .as_ref()
+            .and_then(|path| processor.get_results().get(&args.src_dir.join(path)))
+            .map_or(0, |result| result.total_words)
+    };
+    let report = build_report(&chapters, words);
+
+    let output = match args.format {
+        BookFormat::Text => format_text(&report),
+        BookFormat::Markdown => format_markdown(&report),
+        BookFormat::Html => format_html(&report),
+        BookFormat::Json => serde_json::to_string_pretty(&report)?,
+    };
+    println!("{}", output);
+    Ok(())
+}
+
+// Groups consecutive chapters of the same part, keeping reading order
+fn build_report(
+    chapters: &[SummaryChapter],
+    words: impl Fn(&SummaryChapter) -> usize,
+) -> BookReport {
+    let mut parts: Vec<PartRow> = Vec::new();
+    for chapter in chapters {
+        let row = ChapterRow {
+            title: chapter.title.clone(),
+            path: chapter.path.as_ref().map(|p| p.display().to_string()),
+            depth: chapter.depth,
+            part: chapter.part.clone(),
+            words: words(chapter),
+        };
+
+        match parts.last_mut() {
+            Some(part) if part.title == chapter.part => {
+                part.words += row.words;
+                part.chapters.push(row);
+            }
+            _ => parts.push(PartRow {
+                title: chapter.part.clone(),
+                words: row.words,
+                chapters: vec![row],
+            }),
+        }
+    }
+
+    BookReport {
+        total_words: parts.iter().map(|part| part.words).sum(),
+        parts,
+    }
+}
+
+// Helper to format the report as indented text
+fn format_text(report: &BookReport) -> String {
+    debug!("Formatting book report as text");
+    let mut out = format!(
+        "\nBook: {} words in total\n------------------",
+        report.total_words
+    );
+    for part in &report.parts {
+        let indent = if let Some(title) = &part.title {
+            out.push_str(&format!("\n{}: {} words", title, part.words));
+            1
+        } else {
+            0
+        };
+        for chapter in &part.chapters {
+            out.push_str(&format!(
+                "\n{}{}: {}",
+                "  ".repeat(indent + chapter.depth),
+                chapter.title,
+                chapter.words
+            ));
+        }
+    }
+    out
+}
+
+// Helper to format the report as a GitHub-flavored Markdown table
+fn format_markdown(report: &BookReport) -> String {
+    debug!("Formatting book report as Markdown");
+    let escape = |s: &str| s.replace('|', "\\|");
+    let mut out = String::from("# Word count\n\n| Chapter | Words |\n| --- | ---: |");
+    for part in &report.parts {
+        if let Some(title) = &part.title {
+            out.push_str(&format!("\n| **{}** | **{}** |", escape(title), part.words));
+        }
+        for chapter in &part.chapters {
+            out.push_str(&format!(
+                "\n| {}{} | {} |",
+                "&nbsp;&nbsp;".repeat(chapter.depth),
+                escape(&chapter.title),
+                chapter.words
+            ));
+        }
+    }
+    out.push_str(&format!("\n| **Total** | **{}** |", report.total_words));
+    out
+}
+
+// Helper to format the report as a standalone HTML table
+fn format_html(report: &BookReport) -> String {
+    debug!("Formatting book report as HTML");
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    };
+    let mut out = String::from(
+        "<table class=\"mfp-word-count\">\n<thead><tr><th>Chapter</th><th>Words</th></tr></thead>\n<tbody>",
+    );
+    for part in &report.parts {
+        if let Some(title) = &part.title {
+            out.push_str(&format!(
+                "\n<tr class=\"part\"><th>{}</th><th>{}</th></tr>",
+                escape(title),
+                part.words
+            ));
+        }
+        for chapter in &part.chapters {
+            out.push_str(&format!(
+                "\n<tr><td style=\"padding-left: {}em\">{}</td><td>{}</td></tr>",
+                chapter.depth * 2,
+                escape(&chapter.title),
+                chapter.words
+            ));
+        }
+    }
+    out.push_str(&format!(
+        "\n</tbody>\n<tfoot><tr><th>Total</th><th>{}</th></tr></tfoot>\n</table>",
+        report.total_words
+    ));
+    out
+}
@@ -0,0 +1,47 @@
+use crate::args::CooccurrenceArgs;
+use crate::error::CliError;
+use crate::format::csv_field;
+use mfp_lib::{CooccurrenceMatrix, TextProcessor};
+use std::fs;
+use tracing::info;
+
+/// Exports the co-occurrence matrix of the vocabulary words as sparse CSV
+pub async fn run(args: CooccurrenceArgs) -> Result<(), CliError> {
+    // First pass: word frequencies to prune the vocabulary before counting pairs
+    let mut processor = TextProcessor::new()
+        .with_segmentation(args.segmentation)
+        .with_frequencies(true);
+    processor
+        .process_files(args.files)
+        .await
+        .map_err(|e| CliError::InputError(format!("Failed to process files: {}", e)))?;
+    let vocabulary = processor
+        .get_frequencies()
+        .vocabulary(args.min_count, args.max_vocab);
+    info!("Vocabulary pruned to {} words", vocabulary.len());
+
+    // Second pass: pairs of vocabulary words within the window
+    let mut matrix = CooccurrenceMatrix::new(vocabulary, args.window);
+    let mut paths: Vec<_> = processor.get_results().keys().collect();
+    paths.sort();
+    for path in paths {
+        let text = tokio::fs::read_to_string(path).await.map_err(|e| {
+            CliError::InputError(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        matrix.add_text(&text, args.segmentation);
+    }
+    info!("Counted {} co-occurring pairs", matrix.len());
+
+    let mut csv = String::from("word_a,word_b,count\n");
+    for (a, b, count) in matrix.entries() {
+        csv.push_str(&format!("{},{},{}\n", csv_field(a), csv_field(b), count));
+    }
+
+    match &args.output {
+        Some(path) => fs::write(path, csv).map_err(|e| {
+            CliError::FormatError(format!("Failed to write {}: {}", path.display(), e))
+        })?,
+        None => print!("{}", csv),
+    }
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use crate::args::DeltaArgs;
+use crate::error::CliError;
+use crate::format::ReportFormat;
+use mfp_lib::{compute_delta, FileDelta};
+use tracing::debug;
+
+#[derive(serde::Serialize)]
+struct DeltaResult<'a> {
+    old: &'a str,
+    new: &'a str,
+    lines_added: usize,
+    lines_removed: usize,
+    words_added: usize,
+    words_removed: usize,
+    old_total_words: usize,
+    new_total_words: usize,
+    net_words: isize,
+}
+
+/// Reports line and word count changes between two files
+pub async fn run(args: DeltaArgs) -> Result<(), CliError> {
+    let delta = compute_delta(&args.old, &args.new)
+        .await
+        .map_err(|e| CliError::InputError(format!("Failed to compare files: {}", e)))?;
+
+    let old = args.old.display().to_string();
+    let new = args.new.display().to_string();
+    match args.format {
+        ReportFormat::Text => format_text(&old, &new, &delta),
+        ReportFormat::Json => format_json(&old, &new, &delta)?,
+    }
+
+    Ok(())
+}
+
+// Helper to format the delta like a `diff --stat` line, followed by word figures
+fn format_text(old: &str, new: &str, delta: &FileDelta) {
+    debug!("Formatting delta as text");
+    println!("{} -> {}", old, new);
+    println!(
+        "  {} lines added (+), {} lines removed (-)",
+        delta.lines_added, delta.lines_removed
+    );
+    println!(
+        "  Words: +{} -{} (net {:+}), {} -> {} in total",
+        delta.words_added,
+        delta.words_removed,
+        delta.net_words(),
+        delta.old_total_words,
+        delta.new_total_words
+    );
+}
+
+// Helper to format the delta as JSON
+fn format_json(old: &str, new: &str, delta: &FileDelta) -> Result<(), CliError> {
+    debug!("Formatting delta as JSON");
+    let output = DeltaResult {
+        old,
+        new,
+        lines_added: delta.lines_added,
+        lines_removed: delta.lines_removed,
+        words_added: delta.words_added,
+        words_removed: delta.words_removed,
+        old_total_words: delta.old_total_words,
+        new_total_words: delta.new_total_words,
+        net_words: delta.net_words(),
+    };
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
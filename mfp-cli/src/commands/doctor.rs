@@ -0,0 +1,460 @@
+use crate::args::{cache_dir, DoctorArgs};
+use crate::clipboard;
+use crate::config::{system_config_paths, Config};
+use crate::error::CliError;
+use crate::format::ReportFormat;
+use mfp_lib::TextProcessor;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::thread;
+use std::time::Instant;
+use tracing::debug;
+
+/// Soft open file limit below which large batches may fail with "Too many open files"
+const MIN_OPEN_FILES: u64 = 256;
+/// Files written for the self-benchmark, and words in each
+const BENCHMARK_FILES: usize = 200;
+const BENCHMARK_WORDS: usize = 4000;
+/// Throughput below which the self-benchmark warns, in MB/s
+const MIN_THROUGHPUT: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ok,
+    Info,
+    Warning,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+#[derive(serde::Serialize)]
+struct DoctorReport {
+    checks: Vec<Check>,
+    warnings: usize,
+}
+
+/// Checks the environment and runs a self-benchmark, reporting what to fix
+pub async fn run(args: DoctorArgs) -> Result<(), CliError> {
+    let mut checks = vec![
+        build_check(),
+        open_files_check(),
+        locale_check(),
+        case_sensitivity_check(&env::current_dir()?),
+    ];
+    // System-wide and user files apply to every run, an invalid one failing them all
+    checks.extend(
+        system_config_paths()
+            .iter()
+            .filter(|path| path.is_file())
+            .map(|path| config_check(Some(path))),
+    );
+    checks.extend([
+        config_check(args.config.as_deref()),
+        cache_check(),
+        clipboard_check(),
+        Check::new(
+            "Input formats",
+            Status::Ok,
+            "gzip files and zip, tar and tar.gz archives are read without external tools",
+        ),
+    ]);
+    if !args.no_benchmark {
+        checks.push(benchmark().await);
+    }
+
+    let report = DoctorReport {
+        warnings: checks
+            .iter()
+            .filter(|check| check.status == Status::Warning)
+            .count(),
+        checks,
+    };
+    match args.format {
+        ReportFormat::Text => format_text(&report),
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
+
+// Helper to format the report as one line per check, hints indented below
+fn format_text(report: &DoctorReport) {
+    debug!("Formatting doctor report as text");
+    for check in &report.checks {
+        let status = match check.status {
+            Status::Ok => "ok",
+            Status::Info => "info",
+            Status::Warning => "warning",
+        };
+        println!("[{}] {}: {}", status, check.name, check.detail);
+        if let Some(hint) = &check.hint {
+            println!("    {}", hint);
+        }
+    }
+    match report.warnings {
+        0 => println!("\nNo problems found."),
+        1 => println!("\n1 warning."),
+        n => println!("\n{} warnings.", n),
+    }
+}
+
+fn build_check() -> Check {
+    let version = env!("CARGO_PKG_VERSION");
+    if cfg!(debug_assertions) {
+        Check::new(
+            "Build",
+            Status::Warning,
+            format!("mfp {} built without optimizations", version),
+        )
+        .with_hint("Debug builds count several times slower: build with 'cargo build --release'")
+    } else {
+        Check::new(
+            "Build",
+            Status::Ok,
+            format!("mfp {} release build", version),
+        )
+    }
+}
+
+fn open_files_check() -> Check {
+    let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+    open_files_status(open_file_limits(), cpus)
+}
+
+// Checks the soft and hard limits on open files against the files opened at once
+fn open_files_status(limits: Option<(u64, u64)>, cpus: usize) -> Check {
+    let Some((soft, hard)) = limits else {
+        return Check::new(
+            "Open files",
+            Status::Info,
+            format!("limit unknown on this platform ({} CPUs)", cpus),
+        );
+    };
+    let detail = format!(
+        "soft limit {}, hard limit {}, up to {} files open at once by default",
+        soft, hard, cpus
+    );
+    if soft < MIN_OPEN_FILES {
+        Check::new("Open files", Status::Warning, detail).with_hint(format!(
+            "Large batches may fail with 'Too many open files': raise the limit with 'ulimit -n {}' or lower --max-concurrent",
+            hard.min(4096)
+        ))
+    } else {
+        Check::new("Open files", Status::Ok, detail)
+    }
+}
+
+// Soft and hard limits on open files, from procfs or the shell
+fn open_file_limits() -> Option<(u64, u64)> {
+    if let Ok(limits) = fs::read_to_string("/proc/self/limits") {
+        let line = limits
+            .lines()
+            .find(|line| line.starts_with("Max open files"))?;
+        let mut values = line["Max open files".len()..].split_whitespace();
+        let mut limit = || match values.next()? {
+            "unlimited" => Some(u64::MAX),
+            value => value.parse().ok(),
+        };
+        return Some((limit()?, limit()?));
+    }
+    if cfg!(unix) {
+        let output = Command::new("sh")
+            .args(["-c", "ulimit -Sn; ulimit -Hn"])
+            .output()
+            .ok()?;
+        let output = String::from_utf8(output.stdout).ok()?;
+        let mut values = output.lines().map(|value| match value.trim() {
+            "unlimited" => Some(u64::MAX),
+            value => value.parse().ok(),
+        });
+        return Some((values.next()??, values.next()??));
+    }
+    None
+}
+
+fn locale_check() -> Check {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"].into_iter().find_map(|var| {
+        env::var(var)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .map(|value| (var, value))
+    });
+    locale_status(locale)
+}
+
+// Checks the first locale variable set, if any, is a UTF-8 one
+fn locale_status(locale: Option<(&str, String)>) -> Check {
+    let hint = "Non-ASCII file names and the progress sparkline may be garbled: \
+                use a UTF-8 locale, e.g. 'export LANG=C.UTF-8' - files are read as UTF-8 regardless";
+    match locale {
+        Some((var, value)) => {
+            let normalized = value.to_ascii_lowercase().replace('-', "");
+            if normalized.contains("utf8") {
+                Check::new("Locale", Status::Ok, format!("{}={}", var, value))
+            } else {
+                Check::new(
+                    "Locale",
+                    Status::Warning,
+                    format!("{}={} is not a UTF-8 locale", var, value),
+                )
+                .with_hint(hint)
+            }
+        }
+        None if cfg!(windows) => Check::new("Locale", Status::Info, "set by the system"),
+        None => Check::new(
+            "Locale",
+            Status::Warning,
+            "none of LC_ALL, LC_CTYPE and LANG is set",
+        )
+        .with_hint(hint),
+    }
+}
+
+// Tells case sensitivity apart by writing a file and looking it up in upper case
+fn case_sensitivity_check(dir: &Path) -> Check {
+    let probe = dir.join(format!(".mfp-doctor-{}", process::id()));
+    let upper = dir.join(format!(".MFP-DOCTOR-{}", process::id()));
+    if let Err(e) = fs::write(&probe, "") {
+        return Check::new(
+            "Filesystem",
+            Status::Info,
+            format!(
+                "case sensitivity unknown, {} is not writable: {}",
+                dir.display(),
+                e
+            ),
+        );
+    }
+    let insensitive = upper.exists();
+    let _ = fs::remove_file(&probe);
+
+    if insensitive {
+        Check::new(
+            "Filesystem",
+            Status::Info,
+            format!("{} is case-insensitive", dir.display()),
+        )
+        .with_hint(
+            "Paths differing only in case name the same file, while the globs of mfp.toml \
+             match case-sensitively: write them in the case used on disk",
+        )
+    } else {
+        Check::new(
+            "Filesystem",
+            Status::Ok,
+            format!("{} is case-sensitive", dir.display()),
+        )
+    }
+}
+
+fn config_check(path: Option<&Path>) -> Check {
+    let name = path.map_or_else(|| "mfp.toml".to_string(), |p| p.display().to_string());
+    if path.is_none() && !Path::new(&name).is_file() {
+        return Check::new(
+            "Configuration",
+            Status::Info,
+            "no mfp.toml in the current directory, defaults apply",
+        );
+    }
+    match Config::load(path) {
+        Ok(_) => Check::new("Configuration", Status::Ok, format!("{} is valid", name)),
+        Err(e) => Check::new("Configuration", Status::Warning, e.to_string()).with_hint(format!(
+            "Fix {} - every run reading it fails until then",
+            name
+        )),
+    }
+}
+
+fn cache_check() -> Check {
+    let Some(dir) = cache_dir() else {
+        return Check::new("Result cache", Status::Warning, "no cache directory").with_hint(
+            "Set HOME or XDG_CACHE_HOME so that the results of unchanged files can be reused",
+        );
+    };
+    let probe = dir.join(format!(".doctor-{}", process::id()));
+    let writable = fs::create_dir_all(&dir).and_then(|_| fs::write(&probe, ""));
+    let _ = fs::remove_file(&probe);
+    match writable {
+        Ok(()) => Check::new("Result cache", Status::Ok, dir.display().to_string()),
+        Err(e) => Check::new(
+            "Result cache",
+            Status::Warning,
+            format!("{} is not writable: {}", dir.display(), e),
+        )
+        .with_hint("Every run counts all files again: fix the permissions or set XDG_CACHE_HOME"),
+    }
+}
+
+fn clipboard_check() -> Check {
+    match clipboard::find_tool() {
+        Some(tool) => Check::new("Clipboard", Status::Ok, format!("--copy uses {}", tool)),
+        None => Check::new("Clipboard", Status::Info, "no clipboard tool found")
+            .with_hint("Install wl-copy, xclip or xsel to use --copy"),
+    }
+}
+
+// Counts generated files in a temporary directory, measuring the throughput
+async fn benchmark() -> Check {
+    let dir = env::temp_dir().join(format!("mfp-doctor-{}", process::id()));
+    let result = run_benchmark(&dir).await;
+    let _ = fs::remove_dir_all(&dir);
+
+    match result {
+        Ok((bytes, elapsed)) => {
+            let seconds = elapsed.max(1e-6);
+            let throughput = bytes as f64 / 1e6 / seconds;
+            let detail = format!(
+                "{} files, {:.1} MB in {:.0} ms: {:.1} MB/s, {:.0} files/s",
+                BENCHMARK_FILES,
+                bytes as f64 / 1e6,
+                seconds * 1000.0,
+                throughput,
+                BENCHMARK_FILES as f64 / seconds
+            );
+            if throughput < MIN_THROUGHPUT {
+                Check::new("Self-benchmark", Status::Warning, detail).with_hint(format!(
+                    "Below {} MB/s: check for a debug build, a slow or network temporary \
+                     directory ({}), or an antivirus scanning every file read",
+                    MIN_THROUGHPUT,
+                    env::temp_dir().display()
+                ))
+            } else {
+                Check::new("Self-benchmark", Status::Ok, detail)
+            }
+        }
+        Err(e) => {
+            Check::new("Self-benchmark", Status::Warning, format!("failed: {}", e)).with_hint(
+                format!("Check that {} is writable", env::temp_dir().display()),
+            )
+        }
+    }
+}
+
+// Writes the benchmark files, then returns the bytes counted and the seconds taken
+async fn run_benchmark(dir: &Path) -> Result<(u64, f64), CliError> {
+    fs::create_dir_all(dir)?;
+    let words = ["lorem", "ipsum", "dolor", "sit", "amet", "consectetur"];
+    let line: String = (0..12)
+        .map(|i| words[i % words.len()])
+        .collect::<Vec<_>>()
+        .join(" ");
+    let content = format!("{}\n", line).repeat(BENCHMARK_WORDS / 12);
+    let paths: Vec<PathBuf> = (0..BENCHMARK_FILES)
+        .map(|i| dir.join(format!("{:03}.txt", i)))
+        .collect();
+    for path in &paths {
+        fs::write(path, &content)?;
+    }
+
+    let start = Instant::now();
+    let mut processor = TextProcessor::new();
+    processor
+        .process_files(paths)
+        .await
+        .map_err(|e| CliError::InputError(e.to_string()))?;
+    let elapsed = start.elapsed().as_secs_f64();
+    let bytes = processor
+        .get_results()
+        .values()
+        .map(|r| r.total_bytes)
+        .sum();
+    Ok((bytes, elapsed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_files() {
+        let check = open_files_status(Some((1024, 4096)), 8);
+        assert_eq!(check.status, Status::Ok);
+        assert_eq!(check.hint, None);
+        let check = open_files_status(Some((64, 512)), 8);
+        assert_eq!(check.status, Status::Warning);
+        assert!(check.hint.unwrap().contains("ulimit -n 512"));
+        assert_eq!(open_files_status(None, 8).status, Status::Info);
+    }
+
+    #[test]
+    fn test_locale() {
+        let check = locale_status(Some(("LANG", "en_US.UTF-8".to_string())));
+        assert_eq!(
+            (check.status, check.detail.as_str()),
+            (Status::Ok, "LANG=en_US.UTF-8")
+        );
+        assert_eq!(
+            locale_status(Some(("LC_ALL", "C.utf8".to_string()))).status,
+            Status::Ok
+        );
+        let check = locale_status(Some(("LC_ALL", "POSIX".to_string())));
+        assert_eq!(check.status, Status::Warning);
+        assert!(check.hint.is_some());
+    }
+
+    #[test]
+    fn test_case_sensitivity() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let check = case_sensitivity_check(temp.path());
+        assert_ne!(check.status, Status::Warning);
+        assert!(check.detail.starts_with(&temp.path().display().to_string()));
+        // The probe file is removed
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 0);
+
+        let check = case_sensitivity_check(&temp.path().join("missing"));
+        assert_eq!(check.status, Status::Info);
+        assert!(check.detail.starts_with("case sensitivity unknown"));
+    }
+
+    #[test]
+    fn test_config() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let valid = temp.path().join("valid.toml");
+        fs::write(&valid, "[runtime]\npin_threads = true\n").unwrap();
+        assert_eq!(config_check(Some(&valid)).status, Status::Ok);
+
+        let invalid = temp.path().join("invalid.toml");
+        fs::write(&invalid, "[runtime]\npin_threads = maybe\n").unwrap();
+        let check = config_check(Some(&invalid));
+        assert_eq!(check.status, Status::Warning);
+        assert!(check.hint.unwrap().starts_with("Fix "));
+    }
+
+    // Test the self-benchmark counts every file it writes
+    #[tokio::test]
+    async fn test_benchmark() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let (bytes, elapsed) = run_benchmark(&temp.path().join("benchmark")).await.unwrap();
+        let line_bytes = "lorem ipsum dolor sit amet consectetur ".len() * 2;
+        assert_eq!(
+            bytes,
+            (BENCHMARK_FILES * (BENCHMARK_WORDS / 12) * line_bytes) as u64
+        );
+        assert!(elapsed > 0.0);
+    }
+}
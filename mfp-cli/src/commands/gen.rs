@@ -0,0 +1,34 @@
+use crate::args::GenArgs;
+use crate::error::CliError;
+use mfp_lib::{CorpusGenerator, CORPUS_MANIFEST_FILE, CORPUS_TEXTS_DIR};
+use tracing::info;
+
+/// Generates a synthetic corpus with its manifest of expected counts
+pub async fn run(args: GenArgs) -> Result<(), CliError> {
+    let manifest = CorpusGenerator::new(args.files, args.lines)
+        .with_seed(args.seed)
+        .with_words_per_line(args.words_per_line)
+        .generate(&args.out_dir)
+        .await
+        .map_err(|e| {
+            CliError::InputError(format!(
+                "Failed to generate the corpus in {}: {}",
+                args.out_dir.display(),
+                e
+            ))
+        })?;
+
+    info!("Generated {} files", manifest.files.len());
+    println!(
+        "{} files, {} lines, {} words in {}",
+        manifest.files.len(),
+        manifest.total_lines,
+        manifest.total_words,
+        args.out_dir.join(CORPUS_TEXTS_DIR).display()
+    );
+    println!(
+        "Expected counts: {}",
+        args.out_dir.join(CORPUS_MANIFEST_FILE).display()
+    );
+    Ok(())
+}
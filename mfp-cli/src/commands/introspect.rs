@@ -0,0 +1,259 @@
+use crate::args::Cli;
+use crate::error::CliError;
+use crate::format::report_schema;
+use clap::{ArgAction, CommandFactory};
+use serde_json::Value;
+
+/// Version of the introspection output, bumped on breaking changes of its layout
+const INTROSPECTION_VERSION: u32 = 1;
+
+#[derive(serde::Serialize)]
+struct Introspection {
+    introspection_version: u32,
+    name: String,
+    version: String,
+    cli: CommandInfo,
+    report_schema: Value,
+}
+
+#[derive(serde::Serialize)]
+struct CommandInfo {
+    name: String,
+    about: Option<String>,
+    arguments: Vec<ArgumentInfo>,
+    subcommands: Vec<CommandInfo>,
+}
+
+#[derive(serde::Serialize)]
+struct ArgumentInfo {
+    id: String,
+    long: Option<String>,
+    short: Option<char>,
+    help: Option<String>,
+    /// 'flag', 'count' or 'value'
+    kind: &'static str,
+    positional: bool,
+    required: bool,
+    multiple: bool,
+    value_names: Vec<String>,
+    defaults: Vec<String>,
+    possible_values: Vec<PossibleValueInfo>,
+    conflicts_with: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+struct PossibleValueInfo {
+    name: String,
+    help: Option<String>,
+}
+
+/// Prints the full CLI surface - subcommands, flags, their values and defaults -
+/// and the JSON schema of the report as one JSON document, for wrapper tools and
+/// GUIs to generate their interface against the installed version
+pub fn run() -> Result<(), CliError> {
+    println!("{}", serde_json::to_string_pretty(&introspection())?);
+    Ok(())
+}
+
+fn introspection() -> Introspection {
+    let mut command = Cli::command();
+    command.build();
+    Introspection {
+        introspection_version: INTROSPECTION_VERSION,
+        name: command.get_name().to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        cli: command_info(&command),
+        report_schema: report_schema(),
+    }
+}
+
+fn command_info(command: &clap::Command) -> CommandInfo {
+    CommandInfo {
+        name: command.get_name().to_string(),
+        about: command.get_about().map(ToString::to_string),
+        arguments: command
+            .get_arguments()
+            .filter(|arg| {
+                !matches!(
+                    arg.get_action(),
+                    ArgAction::Help
+                        | ArgAction::HelpShort
+                        | ArgAction::HelpLong
+                        | ArgAction::Version
+                )
+            })
+            .map(|arg| argument_info(command, arg))
+            .collect(),
+        subcommands: command
+            .get_subcommands()
+            .filter(|subcommand| subcommand.get_name() != "help")
+            .map(command_info)
+            .collect(),
+    }
+}
+
+fn argument_info(command: &clap::Command, arg: &clap::Arg) -> ArgumentInfo {
+    let action = arg.get_action();
+    // Flags and counters take no value
+    let flag = matches!(
+        action,
+        ArgAction::SetTrue | ArgAction::SetFalse | ArgAction::Count
+    );
+    ArgumentInfo {
+        id: arg.get_id().to_string(),
+        long: arg.get_long().map(str::to_string),
+        short: arg.get_short(),
+        help: arg
+            .get_long_help()
+            .or_else(|| arg.get_help())
+            .map(ToString::to_string),
+        kind: match action {
+            ArgAction::SetTrue | ArgAction::SetFalse => "flag",
+            ArgAction::Count => "count",
+            _ => "value",
+        },
+        positional: arg.is_positional(),
+        required: arg.is_required_set(),
+        multiple: matches!(action, ArgAction::Append | ArgAction::Count)
+            || arg
+                .get_num_args()
+                .is_some_and(|range| range.max_values() > 1),
+        value_names: match flag {
+            true => Vec::new(),
+            false => arg
+                .get_value_names()
+                .unwrap_or_default()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        },
+        // Flags default to off, which goes without saying
+        defaults: match flag {
+            true => Vec::new(),
+            false => arg
+                .get_default_values()
+                .iter()
+                .map(|value| value.to_string_lossy().into_owned())
+                .collect(),
+        },
+        possible_values: match flag {
+            true => Vec::new(),
+            false => arg
+                .get_possible_values()
+                .iter()
+                .filter(|value| !value.is_hide_set())
+                .map(|value| PossibleValueInfo {
+                    name: value.get_name().to_string(),
+                    help: value.get_help().map(ToString::to_string),
+                })
+                .collect(),
+        },
+        conflicts_with: command
+            .get_arg_conflicts_with(arg)
+            .iter()
+            .map(|other| other.get_id().to_string())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn argument<'a>(command: &'a Value, long: &str) -> &'a Value {
+        command["arguments"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|arg| arg["long"] == long)
+            .unwrap_or_else(|| panic!("--{} is not listed", long))
+    }
+
+    // Test the options of the command line are listed with their kind, values,
+    // defaults and conflicts, and the subcommands with their own
+    #[test]
+    fn test_introspection() {
+        let introspection = serde_json::to_value(introspection()).unwrap();
+        assert_eq!(
+            introspection["introspection_version"],
+            INTROSPECTION_VERSION
+        );
+        let cli = &introspection["cli"];
+
+        let format = argument(cli, "format");
+        assert_eq!(format["short"], "f");
+        assert_eq!(format["kind"], "value");
+        assert_eq!(format["defaults"], json!(["text"]));
+        let values: Vec<&str> = format["possible_values"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|value| value["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            values,
+            [
+                "text",
+                "json",
+                "frontmatter-patch",
+                "csv",
+                "markdown",
+                "ndjson"
+            ]
+        );
+        assert_eq!(argument(cli, "verbose")["kind"], "count");
+        let watch = argument(cli, "watch");
+        assert_eq!(watch["kind"], "flag");
+        assert_eq!(watch["defaults"], json!([]));
+        assert!(watch["conflicts_with"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("output")));
+        let files = cli["arguments"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|arg| arg["positional"] == true)
+            .unwrap();
+        assert_eq!(files["id"], "files");
+        assert_eq!(files["multiple"], true);
+        assert!(cli["arguments"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|arg| arg["long"] != "help"));
+
+        let subcommands = cli["subcommands"].as_array().unwrap();
+        let names: Vec<&str> = subcommands
+            .iter()
+            .map(|subcommand| subcommand["name"].as_str().unwrap())
+            .collect();
+        assert!(names.contains(&"doctor") && names.contains(&"man"));
+        assert!(!names.contains(&"help"));
+        let doctor = subcommands.iter().find(|s| s["name"] == "doctor").unwrap();
+        assert_eq!(argument(doctor, "no-benchmark")["kind"], "flag");
+        assert!(introspection["report_schema"].is_object());
+    }
+
+    // Test every long option of clap is listed, none being filtered out
+    #[test]
+    fn test_lists_every_option() {
+        let mut command = Cli::command();
+        command.build();
+        let cli = command_info(&command);
+        let listed: Vec<&str> = cli
+            .arguments
+            .iter()
+            .filter_map(|arg| arg.long.as_deref())
+            .collect();
+        for arg in command.get_arguments() {
+            if let Some(long) = arg
+                .get_long()
+                .filter(|&long| long != "help" && long != "version")
+            {
+                assert!(listed.contains(&long), "--{} is not listed", long);
+            }
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use crate::args::Cli;
+use crate::config::Config;
+use crate::error::CliError;
+use mfp_lib::{FileProcessingResult, TextProcessor};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tokio::io::{self, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tracing::{debug, info, warn};
+
+/// Largest message accepted, so that a bogus Content-Length cannot exhaust memory
+const MAX_MESSAGE_LEN: usize = 64 * 1024 * 1024;
+
+/// JSON-RPC error code for messages that are not JSON
+const PARSE_ERROR: i64 = -32700;
+/// JSON-RPC error code for messages that are not requests
+const INVALID_REQUEST: i64 = -32600;
+/// JSON-RPC error code for unknown methods
+const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code for malformed parameters
+const INVALID_PARAMS: i64 = -32602;
+
+/// Open editor buffers and their latest counts, keyed by document URI
+struct Session {
+    processor: TextProcessor,
+    documents: HashMap<String, FileProcessingResult>,
+}
+
+/// Serves word counts of editor buffers over a Content-Length framed JSON-RPC protocol on stdio.
+/// `textDocument/didOpen` and `textDocument/didChange` (full sync) publish an `mfp/counts`
+/// notification; `mfp/counts` can also be requested for an open document. Buffers are
+/// counted with the settings of the command line and the configuration, named after
+/// their URI. Malformed messages get an error response and the server keeps serving.
+pub async fn run(cli: &Cli) -> Result<(), CliError> {
+    let config = Config::discover(cli.config.as_deref(), !cli.no_system_config)?;
+    info!("Listening for JSON-RPC messages on stdin");
+    serve(
+        cli.buffer_processor(&config),
+        &mut BufReader::new(io::stdin()),
+        &mut io::stdout(),
+    )
+    .await
+}
+
+// Answers the messages of the reader on the writer until the reader is closed or
+// the client asks to exit
+async fn serve<R, W>(
+    processor: TextProcessor,
+    stdin: &mut R,
+    stdout: &mut W,
+) -> Result<(), CliError>
+where
+    R: AsyncBufReadExt + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut session = Session {
+        processor,
+        documents: HashMap::new(),
+    };
+    while let Some(message) = read_message(stdin).await? {
+        let message = match message {
+            Ok(message) => message,
+            Err((code, msg)) => {
+                warn!("{}", msg);
+                let response = json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": code, "message": msg }
+                });
+                write_message(stdout, &response).await?;
+                continue;
+            }
+        };
+        let id = message.get("id").cloned();
+        let method = message
+            .get("method")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        debug!("Received {}", method);
+
+        let reply = match method {
+            "initialize" => Ok(json!({
+                "capabilities": { "textDocumentSync": 1 },
+                "serverInfo": { "name": "mfp", "version": env!("CARGO_PKG_VERSION") }
+            })),
+            "shutdown" => Ok(Value::Null),
+            "exit" => break,
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                match session.update(&params) {
+                    Some(notification) => write_message(stdout, &notification).await?,
+                    None => warn!("Ignoring malformed {} notification", method),
+                }
+                continue;
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = document_uri(&params) {
+                    session.documents.remove(uri);
+                }
+                continue;
+            }
+            "mfp/counts" => document_uri(&params)
+                .and_then(|uri| session.documents.get(uri).map(|r| counts(uri, r)))
+                .ok_or((INVALID_PARAMS, "Unknown document")),
+            _ => Err((METHOD_NOT_FOUND, "Method not found")),
+        };
+
+        // Notifications carry no id and never get a response
+        let Some(id) = id else {
+            continue;
+        };
+        let response = match reply {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, msg)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": msg }
+            }),
+        };
+        write_message(stdout, &response).await?;
+    }
+
+    Ok(())
+}
+
+impl Session {
+    // Recounts an opened or changed document, returning the counts notification
+    fn update(&mut self, params: &Value) -> Option<Value> {
+        let uri = document_uri(params)?;
+        let text = params
+            .pointer("/textDocument/text")
+            .or_else(|| {
+                params
+                    .pointer("/contentChanges")?
+                    .as_array()?
+                    .last()?
+                    .get("text")
+            })?
+            .as_str()?;
+
+        // Named after the file of the URI, for the segmentation rules and the
+        // markup of its extension to apply
+        let name = uri.strip_prefix("file://").unwrap_or(uri);
+        let result = self.processor.process_text_as(name, text);
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "mfp/counts",
+            "params": counts(uri, &result)
+        });
+        self.documents.insert(uri.to_string(), result);
+        Some(notification)
+    }
+}
+
+fn document_uri(params: &Value) -> Option<&str> {
+    params.pointer("/textDocument/uri")?.as_str()
+}
+
+fn counts(uri: &str, result: &FileProcessingResult) -> Value {
+    json!({
+        "uri": uri,
+        "totalWords": result.total_words,
+        "lineCounts": result.line_counts
+    })
+}
+
+// Reads one Content-Length framed message, or None once stdin is closed. A message
+// that is too large or not JSON is skipped, and its error returned to be answered.
+async fn read_message<R>(reader: &mut R) -> Result<Option<Result<Value, (i64, String)>>, CliError>
+where
+    R: AsyncBufReadExt + Unpin,
+{
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await.map_err(input_error)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let length = content_length
+        .ok_or_else(|| CliError::InputError("Missing Content-Length header".to_string()))?;
+    if length > MAX_MESSAGE_LEN {
+        let mut body = reader.take(length as u64);
+        io::copy(&mut body, &mut io::sink())
+            .await
+            .map_err(input_error)?;
+        return Ok(Some(Err((
+            INVALID_REQUEST,
+            format!("Message of {length} bytes over the limit of {MAX_MESSAGE_LEN}"),
+        ))));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await.map_err(input_error)?;
+    Ok(Some(serde_json::from_slice(&body).map_err(|e| {
+        (PARSE_ERROR, format!("Invalid JSON-RPC message: {}", e))
+    })))
+}
+
+async fn write_message<W>(stdout: &mut W, message: &Value) -> Result<(), CliError>
+where
+    W: AsyncWrite + Unpin,
+{
+    let body = serde_json::to_string(message)?;
+    let frame = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+    stdout
+        .write_all(frame.as_bytes())
+        .await
+        .map_err(input_error)?;
+    stdout.flush().await.map_err(input_error)
+}
+
+fn input_error(e: io::Error) -> CliError {
+    CliError::InputError(format!("Failed to exchange messages on stdio: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(message: Value) -> String {
+        let body = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    // Messages written by the server, unframed
+    fn messages(mut output: &[u8]) -> Vec<Value> {
+        let mut messages = Vec::new();
+        while !output.is_empty() {
+            let text = std::str::from_utf8(output).unwrap();
+            let (header, rest) = text.split_once("\r\n\r\n").unwrap();
+            let length: usize = header["Content-Length: ".len()..].parse().unwrap();
+            messages.push(serde_json::from_str(&rest[..length]).unwrap());
+            output = &rest.as_bytes()[length..];
+        }
+        messages
+    }
+
+    // Test a session from initialize to exit, counts being published and requested
+    #[tokio::test]
+    async fn test_round_trip() {
+        let uri = "file:///notes/draft.txt";
+        let input = [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didOpen",
+                "params": { "textDocument": { "uri": uri, "text": "one two\nthree\n" } }
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "mfp/counts",
+                "params": { "textDocument": { "uri": uri } }
+            }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "unknown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ]
+        .map(frame)
+        .concat();
+        let mut output = Vec::new();
+        serve(
+            TextProcessor::new(),
+            &mut BufReader::new(input.as_bytes()),
+            &mut output,
+        )
+        .await
+        .unwrap();
+
+        let messages = messages(&output);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0]["id"], 1);
+        assert_eq!(messages[0]["result"]["capabilities"]["textDocumentSync"], 1);
+        let counts = json!({ "uri": uri, "totalWords": 3, "lineCounts": [2, 1] });
+        assert_eq!(
+            messages[1],
+            json!({ "jsonrpc": "2.0", "method": "mfp/counts", "params": counts })
+        );
+        assert_eq!(
+            messages[2],
+            json!({ "jsonrpc": "2.0", "id": 2, "result": counts })
+        );
+        assert_eq!(messages[3]["id"], 3);
+        assert_eq!(messages[3]["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    // Test a message that is not JSON gets an error, and the server keeps serving
+    #[tokio::test]
+    async fn test_parse_error() {
+        let input = format!(
+            "Content-Length: 5\r\n\r\n{{nope{}",
+            frame(json!({ "jsonrpc": "2.0", "id": 1, "method": "shutdown" }))
+        );
+        let mut output = Vec::new();
+        serve(
+            TextProcessor::new(),
+            &mut BufReader::new(input.as_bytes()),
+            &mut output,
+        )
+        .await
+        .unwrap();
+
+        let messages = messages(&output);
+        assert_eq!(messages[0]["id"], Value::Null);
+        assert_eq!(messages[0]["error"]["code"], PARSE_ERROR);
+        assert_eq!(
+            messages[1],
+            json!({ "jsonrpc": "2.0", "id": 1, "result": null })
+        );
+    }
+}
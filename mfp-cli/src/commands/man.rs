@@ -0,0 +1,298 @@
+use crate::args::{Cli, ManArgs};
+use crate::config::{CONFIG_FILE, SYSTEM_CONFIG_FILE};
+use crate::error::CliError;
+use crate::INTERRUPTED_EXIT_CODE;
+use clap::{Arg, ArgAction, CommandFactory};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use tracing::info;
+
+/// Settings of the configuration file, by table, along with what they set
+const CONFIG_KEYS: &[(&str, &str)] = &[
+    (
+        "[segmentation] \"<PATTERN>\"",
+        "Segmentation mode of the files matching the pattern, e.g. \"*.md\" = \"unicode\". \
+         Patterns without a '/' match file names, others whole paths; the last matching \
+         pattern wins over earlier ones and over --segmentation.",
+    ),
+    (
+        "[tokenizer] apostrophes, hyphens, slashes",
+        "Whether contractions, hyphenated compounds and slash-joined terms are one word \
+         (\"join\") or several (\"split\"), overridden by the options of the same name.",
+    ),
+    (
+        "[tokenizer] delimiters",
+        "Characters splitting words besides whitespace, overridden by --delimiters.",
+    ),
+    (
+        "[tokenizer] word_regex",
+        "Regular expression whose matches are the words, overridden by --word-regex.",
+    ),
+    (
+        "[target] words",
+        "Word count to reach, overridden by --target.",
+    ),
+    (
+        "[target] scope",
+        "Whether the target applies to each file (\"file\") or to the whole corpus \
+         (\"corpus\"), overridden by --target-scope.",
+    ),
+];
+
+/// Writes the man page of mfp - its options, subcommands, output formats, configuration
+/// file and exit statuses - as groff to stdout, or as 'mfp.1' in a directory
+pub fn run(args: ManArgs) -> Result<(), CliError> {
+    match args.out_dir {
+        Some(dir) => {
+            fs::create_dir_all(&dir)?;
+            let path = dir.join("mfp.1");
+            let mut out = BufWriter::new(File::create(&path)?);
+            write_page(&mut out)?;
+            out.flush()?;
+            info!("Wrote {}", path.display());
+            Ok(())
+        }
+        None => write_page(&mut io::stdout().lock()),
+    }
+}
+
+// Man page of the whole command line interface
+fn write_page(out: &mut dyn Write) -> Result<(), CliError> {
+    let mut command = Cli::command();
+    command.build();
+    let version = env!("CARGO_PKG_VERSION");
+
+    writeln!(out, ".TH MFP 1 \"\" \"mfp {}\" \"User Commands\"", version)?;
+    writeln!(out, ".SH NAME")?;
+    let about = command.get_about().map(|about| about.to_string());
+    writeln!(out, "mfp \\- {}", escape(about.as_deref().unwrap_or("")))?;
+
+    writeln!(out, ".SH SYNOPSIS")?;
+    writeln!(out, ".B mfp\n[\\fIOPTIONS\\fR] \\fIFILES\\fR...\n.br")?;
+    writeln!(out, ".B mfp\n\\fICOMMAND\\fR [\\fIOPTIONS\\fR]")?;
+
+    writeln!(out, ".SH DESCRIPTION")?;
+    writeln!(
+        out,
+        "Counts the words of each line of text files, directories walked recursively, \
+         and reports them along with corpus-wide figures in one of several output \
+         formats. The subcommands compare, export or track counts in other ways."
+    )?;
+
+    writeln!(out, ".SH OPTIONS")?;
+    write_arguments(out, &command)?;
+
+    writeln!(out, ".SH COMMANDS")?;
+    for subcommand in command
+        .get_subcommands()
+        .filter(|subcommand| subcommand.get_name() != "help")
+    {
+        let usage = subcommand.clone().render_usage().to_string();
+        let usage = usage.trim_start_matches("Usage:").trim();
+        writeln!(out, ".SS \"{}\"", escape(usage))?;
+        if let Some(about) = subcommand.get_about() {
+            writeln!(out, "{}", escape(&about.to_string()))?;
+        }
+        write_arguments(out, subcommand)?;
+    }
+
+    writeln!(out, ".SH CONFIGURATION")?;
+    writeln!(
+        out,
+        "Settings are read from \\fI{}\\fR, then from \\fI$XDG_CONFIG_HOME/mfp/config.toml\\fR, \
+         then from \\fI{}\\fR in the current directory or the file given with \
+         \\fB\\-\\-config\\fR, each file that exists overriding the keys set by the \
+         previous ones. \\fB\\-\\-no\\-system\\-config\\fR skips the first two. \
+         The files support the following keys:",
+        SYSTEM_CONFIG_FILE, CONFIG_FILE
+    )?;
+    for (key, description) in CONFIG_KEYS {
+        writeln!(out, ".TP\n\\fB{}\\fR\n{}", escape(key), escape(description))?;
+    }
+
+    writeln!(out, ".SH EXIT STATUS")?;
+    let statuses = [
+        ("0", "The files were processed and the report written."),
+        (
+            "1",
+            "Some files could not be processed or the report could not be written, \
+             or the arguments or the configuration file are invalid.",
+        ),
+        ("2", "The command line could not be parsed."),
+        (
+            &INTERRUPTED_EXIT_CODE.to_string(),
+            "The run was interrupted with Ctrl-C, after writing the results so far.",
+        ),
+    ];
+    for (status, description) in statuses {
+        writeln!(out, ".TP\n\\fB{}\\fR\n{}", status, escape(description))?;
+    }
+
+    writeln!(out, ".SH FILES")?;
+    writeln!(
+        out,
+        ".TP\n\\fI{}\\fR\nSystem\\-wide configuration file, see \\fBCONFIGURATION\\fR.",
+        SYSTEM_CONFIG_FILE
+    )?;
+    writeln!(
+        out,
+        ".TP\n\\fI$XDG_CONFIG_HOME/mfp/config.toml\\fR\nUser configuration file, in \
+         \\fI~/.config/mfp\\fR when XDG_CONFIG_HOME is unset."
+    )?;
+    writeln!(
+        out,
+        ".TP\n\\fI{}\\fR\nProject configuration file, see \\fBCONFIGURATION\\fR.",
+        CONFIG_FILE
+    )?;
+    writeln!(
+        out,
+        ".TP\n\\fI$XDG_DATA_HOME/mfp/history.tsv\\fR\nWord counts recorded by \
+         \\fBmfp progress\\fR, in \\fI~/.local/share/mfp\\fR when XDG_DATA_HOME is unset."
+    )?;
+    Ok(())
+}
+
+// Options and positional arguments of a command, one tagged paragraph each
+fn write_arguments(out: &mut dyn Write, command: &clap::Command) -> Result<(), CliError> {
+    let arguments = command.get_arguments().filter(|arg| {
+        !matches!(
+            arg.get_action(),
+            ArgAction::Help | ArgAction::HelpShort | ArgAction::HelpLong | ArgAction::Version
+        )
+    });
+    for arg in arguments {
+        writeln!(out, ".TP\n{}", arg_label(arg))?;
+        let help = arg.get_long_help().or_else(|| arg.get_help());
+        if let Some(help) = help {
+            writeln!(out, "{}", escape(&help.to_string()))?;
+        }
+        let takes_value = !matches!(
+            arg.get_action(),
+            ArgAction::SetTrue | ArgAction::SetFalse | ArgAction::Count
+        );
+        let defaults: Vec<_> = arg
+            .get_default_values()
+            .iter()
+            .map(|value| value.to_string_lossy())
+            .collect();
+        if takes_value && !defaults.is_empty() {
+            writeln!(out, ".br\nDefault: {}", escape(&defaults.join(", ")))?;
+        }
+        let values: Vec<_> = arg
+            .get_possible_values()
+            .into_iter()
+            .filter(|value| !value.is_hide_set())
+            .collect();
+        if takes_value && !values.is_empty() {
+            writeln!(out, ".RS")?;
+            for value in values {
+                writeln!(out, ".IP \\fB{}\\fR", escape(value.get_name()))?;
+                if let Some(help) = value.get_help() {
+                    writeln!(out, "{}", escape(&help.to_string()))?;
+                }
+            }
+            writeln!(out, ".RE")?;
+        }
+    }
+    Ok(())
+}
+
+// Flags and value name of an argument, e.g. '-f, --format FORMAT'
+fn arg_label(arg: &Arg) -> String {
+    let value_names: Vec<String> = arg
+        .get_value_names()
+        .unwrap_or_default()
+        .iter()
+        .map(|name| format!("\\fI{}\\fR", escape(name)))
+        .collect();
+    if arg.is_positional() {
+        return value_names.join(" ");
+    }
+    let mut flags = Vec::new();
+    if let Some(short) = arg.get_short() {
+        flags.push(format!("\\fB\\-{}\\fR", short));
+    }
+    if let Some(long) = arg.get_long() {
+        flags.push(format!("\\fB\\-\\-{}\\fR", escape(long)));
+    }
+    let mut label = flags.join(", ");
+    if !matches!(
+        arg.get_action(),
+        ArgAction::SetTrue | ArgAction::SetFalse | ArgAction::Count
+    ) {
+        label.push(' ');
+        label.push_str(&value_names.join(" "));
+    }
+    label
+}
+
+// Text escaped for groff: backslashes and hyphens, and lines that would read as requests
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\e")
+        .replace('-', "\\-")
+        .lines()
+        .map(|line| match line.starts_with(['.', '\'']) {
+            true => format!("\\&{}", line),
+            false => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Long options of a command and its subcommands, with the command they belong to
+    fn long_options(command: &clap::Command) -> Vec<(String, String)> {
+        let mut options: Vec<_> = command
+            .get_arguments()
+            .filter_map(|arg| arg.get_long())
+            .filter(|&long| long != "help" && long != "version")
+            .map(|long| (command.get_name().to_string(), long.to_string()))
+            .collect();
+        for subcommand in command.get_subcommands() {
+            options.extend(long_options(subcommand));
+        }
+        options
+    }
+
+    // Test every option of the command line and of its subcommands is documented,
+    // along with each subcommand and configuration key
+    #[test]
+    fn test_page_mentions_every_option() {
+        let mut page = Vec::new();
+        write_page(&mut page).unwrap();
+        let page = String::from_utf8(page).unwrap();
+
+        let mut command = Cli::command();
+        command.build();
+        let options = long_options(&command);
+        assert!(options.len() > 50);
+        for (name, long) in options {
+            let flag = format!("\\fB\\-\\-{}\\fR", escape(&long));
+            assert!(page.contains(&flag), "--{} of {} is missing", long, name);
+        }
+        for subcommand in command.get_subcommands() {
+            let name = subcommand.get_name();
+            if name != "help" {
+                assert!(
+                    page.contains(&format!(".SS \"mfp {}", escape(name))),
+                    "{}",
+                    name
+                );
+            }
+        }
+        for (key, _) in CONFIG_KEYS {
+            assert!(page.contains(&escape(key)));
+        }
+        assert!(page.contains(&format!(".TP\n\\fB{}\\fR", INTERRUPTED_EXIT_CODE)));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("--max-depth"), "\\-\\-max\\-depth");
+        assert_eq!(escape("a\\b"), "a\\eb");
+        assert_eq!(escape("line\n.TH"), "line\n\\&.TH");
+    }
+}
@@ -0,0 +1,35 @@
+mod book;
+mod cooccurrence;
+mod delta;
+mod doctor;
+mod gen;
+mod introspect;
+mod lsp;
+mod man;
+mod overlap;
+mod pandoc;
+mod progress;
+mod query;
+pub mod quick;
+
+use crate::args::{Cli, Command};
+use crate::error::CliError;
+
+/// Runs a subcommand to completion, some taking the counting settings of the
+/// command line
+pub async fn run(command: Command, cli: &Cli) -> Result<(), CliError> {
+    match command {
+        Command::Book(args) => book::run(args).await,
+        Command::Cooccurrence(args) => cooccurrence::run(args).await,
+        Command::Delta(args) => delta::run(args).await,
+        Command::Doctor(args) => doctor::run(args).await,
+        Command::Gen(args) => gen::run(args).await,
+        Command::Introspect => introspect::run(),
+        Command::Man(args) => man::run(args),
+        Command::Overlap(args) => overlap::run(args).await,
+        Command::Lsp => lsp::run(cli).await,
+        Command::Pandoc(args) => pandoc::run(args),
+        Command::Progress(args) => progress::run(args).await,
+        Command::Query(args) => query::run(args).await,
+    }
+}
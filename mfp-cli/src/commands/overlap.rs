@@ -0,0 +1,131 @@
+use crate::args::OverlapArgs;
+use crate::error::CliError;
+use crate::format::ReportFormat;
+use futures::{stream, StreamExt, TryStreamExt};
+use mfp_lib::{expand_paths, Fingerprint, Overlap, WalkOptions};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::thread;
+use tracing::{debug, info};
+
+#[derive(serde::Serialize)]
+struct PairResult {
+    a: PathBuf,
+    b: PathBuf,
+    shared_ngrams: usize,
+    percent_of_a: f64,
+    percent_of_b: f64,
+    jaccard: f64,
+}
+
+#[derive(serde::Serialize)]
+struct OverlapResult {
+    ngram: usize,
+    threshold: f64,
+    pairs: Vec<PairResult>,
+}
+
+/// Reports shared n-gram overlap between every file pair of two sets
+pub async fn run(args: OverlapArgs) -> Result<(), CliError> {
+    let set_a = fingerprint_set(&args.set_a, args.ngram).await?;
+    let set_b = fingerprint_set(&args.set_b, args.ngram).await?;
+    info!(
+        "Comparing {} files against {} files",
+        set_a.len(),
+        set_b.len()
+    );
+
+    let mut pairs: Vec<(&Path, &Path, Overlap)> = set_a
+        .iter()
+        .flat_map(|(a, fa)| {
+            set_b
+                .iter()
+                .map(move |(b, fb)| (a.as_path(), b.as_path(), fa.overlap(fb)))
+        })
+        .filter(|(.., overlap)| {
+            overlap.shared > 0 && overlap.max_containment() * 100.0 >= args.threshold
+        })
+        .collect();
+    pairs.sort_by(|x, y| y.2.max_containment().total_cmp(&x.2.max_containment()));
+
+    match args.format {
+        ReportFormat::Text => format_text(&pairs, &args),
+        ReportFormat::Json => format_json(&pairs, &args)?,
+    }
+    Ok(())
+}
+
+// Fingerprints every file below a directory, or a single file
+async fn fingerprint_set(
+    root: &Path,
+    ngram: usize,
+) -> Result<Vec<(PathBuf, Fingerprint)>, CliError> {
+    let files = expand_paths(vec![root.to_path_buf()], &WalkOptions::default())
+        .await
+        .map_err(|e| CliError::InputError(format!("Failed to read {}: {}", root.display(), e)))?;
+
+    // Files are read one per core at once, as they are processed
+    let concurrency = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let mut fingerprints: Vec<(PathBuf, Fingerprint)> = stream::iter(files)
+        .map(|path| async move {
+            match Fingerprint::from_file(&path, ngram).await {
+                Ok(fingerprint) => Ok((path, fingerprint)),
+                Err(e) => Err(CliError::InputError(format!(
+                    "Failed to fingerprint {}: {}",
+                    path.display(),
+                    e
+                ))),
+            }
+        })
+        .buffer_unordered(concurrency)
+        .try_collect()
+        .await?;
+    fingerprints.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(fingerprints)
+}
+
+// Helper to format overlapping pairs as text
+fn format_text(pairs: &[(&Path, &Path, Overlap)], args: &OverlapArgs) {
+    debug!("Formatting overlap as text");
+    println!(
+        "\nOverlapping pairs ({}-grams, >= {}%):",
+        args.ngram, args.threshold
+    );
+    println!("------------------");
+
+    if pairs.is_empty() {
+        println!("No overlapping pairs found");
+    }
+    for (a, b, overlap) in pairs {
+        println!(
+            "{} <-> {}: {:.1}% of A, {:.1}% of B ({} shared n-grams)",
+            a.display(),
+            b.display(),
+            overlap.containment_a * 100.0,
+            overlap.containment_b * 100.0,
+            overlap.shared
+        );
+    }
+}
+
+// Helper to format overlapping pairs as JSON
+fn format_json(pairs: &[(&Path, &Path, Overlap)], args: &OverlapArgs) -> Result<(), CliError> {
+    debug!("Formatting overlap as JSON");
+    let output = OverlapResult {
+        ngram: args.ngram,
+        threshold: args.threshold,
+        pairs: pairs
+            .iter()
+            .map(|(a, b, overlap)| PairResult {
+                a: a.to_path_buf(),
+                b: b.to_path_buf(),
+                shared_ngrams: overlap.shared,
+                percent_of_a: overlap.containment_a * 100.0,
+                percent_of_b: overlap.containment_b * 100.0,
+                jaccard: overlap.jaccard,
+            })
+            .collect(),
+    };
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use crate::args::PandocArgs;
+use crate::error::CliError;
+use crate::format::ReportFormat;
+use mfp_lib::TextProcessor;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use tracing::debug;
+
+#[derive(serde::Serialize, Default)]
+struct BlockCounts {
+    blocks: usize,
+    words: usize,
+}
+
+#[derive(serde::Serialize)]
+struct PandocResult {
+    total_words: usize,
+    block_types: BTreeMap<String, BlockCounts>,
+}
+
+/// Counts the words of a Pandoc JSON AST read from stdin, per top-level block type.
+/// In filter mode the AST is passed through to stdout unchanged and the report
+/// goes to stderr, so the command can sit in the middle of a Pandoc pipeline.
+pub fn run(args: PandocArgs) -> Result<(), CliError> {
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .map_err(|e| CliError::InputError(format!("Failed to read stdin: {}", e)))?;
+    let document: Value = serde_json::from_str(&input)
+        .map_err(|e| CliError::InputError(format!("Invalid Pandoc JSON AST: {}", e)))?;
+    let result = count_blocks(&document)?;
+
+    let report = match args.format {
+        ReportFormat::Text => format_text(&result),
+        ReportFormat::Json => serde_json::to_string_pretty(&result)?,
+    };
+    if args.filter {
+        print!("{}", input);
+        eprintln!("{}", report);
+    } else {
+        println!("{}", report);
+    }
+    io::stdout()
+        .flush()
+        .map_err(|e| CliError::FormatError(e.to_string()))
+}
+
+// Counts the words of the top-level blocks of a document, by block type
+fn count_blocks(document: &Value) -> Result<PandocResult, CliError> {
+    let blocks = document
+        .get("blocks")
+        .and_then(Value::as_array)
+        .ok_or_else(|| CliError::InputError("Pandoc JSON AST has no 'blocks' list".to_string()))?;
+
+    let processor = TextProcessor::new();
+    let mut result = PandocResult {
+        total_words: 0,
+        block_types: BTreeMap::new(),
+    };
+    for block in blocks {
+        let block_type = block.get("t").and_then(Value::as_str).unwrap_or("Unknown");
+        let mut text = String::new();
+        collect_text(block, &mut text);
+        let words = processor.process_text(&text).total_words;
+        debug!("{} block with {} words", block_type, words);
+
+        let counts = result
+            .block_types
+            .entry(block_type.to_string())
+            .or_default();
+        counts.blocks += 1;
+        counts.words += words;
+        result.total_words += words;
+    }
+    Ok(result)
+}
+
+// Flattens the readable text of an AST node: 'Str' words, breaks as spaces, and the
+// content of code and math. Attributes and link targets are plain JSON strings,
+// never 'Str' nodes, so they are skipped naturally.
+fn collect_text(node: &Value, text: &mut String) {
+    match node {
+        Value::Array(items) => items.iter().for_each(|item| collect_text(item, text)),
+        Value::Object(object) => {
+            let content = object.get("c");
+            match object.get("t").and_then(Value::as_str) {
+                Some("Str") => {
+                    if let Some(word) = content.and_then(Value::as_str) {
+                        text.push_str(word);
+                    }
+                }
+                Some("Space" | "SoftBreak" | "LineBreak") => text.push(' '),
+                Some("Code" | "CodeBlock" | "Math") => {
+                    if let Some(code) = content.and_then(|c| c.get(1)).and_then(Value::as_str) {
+                        text.push(' ');
+                        text.push_str(code);
+                        text.push(' ');
+                    }
+                }
+                Some("RawInline" | "RawBlock") => {}
+                Some(
+                    "Emph" | "Underline" | "Strong" | "Strikeout" | "Superscript" | "Subscript"
+                    | "SmallCaps" | "Quoted" | "Cite" | "Link" | "Image" | "Span",
+                ) => {
+                    // Inline markup may sit within a word, e.g. 'un*believ*able'
+                    if let Some(content) = content {
+                        collect_text(content, text);
+                    }
+                }
+                _ => {
+                    // Blocks and footnotes - separated so words never merge across them,
+                    // footnotes following a word directly
+                    if let Some(content) = content {
+                        text.push(' ');
+                        collect_text(content, text);
+                        text.push(' ');
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// Helper to format the per block type counts as text
+fn format_text(result: &PandocResult) -> String {
+    let mut report = format!(
+        "\nPandoc document: {} words in total\n------------------",
+        result.total_words
+    );
+    for (block_type, counts) in &result.block_types {
+        report.push_str(&format!(
+            "\n{}: {} words in {} blocks",
+            block_type, counts.words, counts.blocks
+        ));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    fn document() -> Value {
+        let json = fs::read_to_string("tests/pandoc/document.json").unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    // Test the words of a fixture AST, markup within words, footnotes, code and
+    // math counted while link targets, attributes, metadata and raw content are not
+    #[test]
+    fn test_count_blocks() {
+        let result = count_blocks(&document()).unwrap();
+        let counts: Vec<_> = result
+            .block_types
+            .iter()
+            .map(|(block_type, counts)| (block_type.as_str(), counts.blocks, counts.words))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                ("BulletList", 1, 2),
+                ("CodeBlock", 1, 3),
+                ("Header", 1, 2),
+                ("Para", 2, 8),
+                ("RawBlock", 1, 0),
+            ]
+        );
+        assert_eq!(result.total_words, 15);
+        assert!(format_text(&result).contains("\nPara: 8 words in 2 blocks"));
+    }
+
+    // Test documents without a list of blocks are rejected
+    #[test]
+    fn test_invalid_document() {
+        for document in [json!({}), json!({ "blocks": "none" })] {
+            assert!(matches!(
+                count_blocks(&document),
+                Err(CliError::InputError(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use crate::args::ProgressArgs;
+use crate::config::Config;
+use crate::error::CliError;
+use crate::format::ReportFormat;
+use crate::target::{Target, TargetProgress, TargetScope};
+use mfp_lib::{
+    daily_words, format_day, load_snapshots, record_snapshot, DailyWords, Snapshot, TextProcessor,
+};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use tracing::{debug, info};
+
+/// Bars of the sparkline, from the fewest words added to the most
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(serde::Serialize)]
+struct DayRow {
+    date: String,
+    words: usize,
+    added: isize,
+}
+
+#[derive(serde::Serialize)]
+struct TargetRow {
+    words: usize,
+    percent: f64,
+    remaining: usize,
+}
+
+#[derive(serde::Serialize)]
+struct ProgressReport {
+    dir: String,
+    total_words: usize,
+    days: Vec<DayRow>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<TargetRow>,
+}
+
+/// Records the current word count of a directory, then reports the words added per day
+pub async fn run(args: ProgressArgs) -> Result<(), CliError> {
+    let dir = fs::canonicalize(&args.dir).map_err(|e| {
+        CliError::InputError(format!("Failed to read {}: {}", args.dir.display(), e))
+    })?;
+    let history = match args.history {
+        Some(history) => history,
+        None => default_history_path().ok_or_else(|| {
+            CliError::InputError("No home directory to keep the history in, use --history".into())
+        })?,
+    };
+
+    let mut processor = TextProcessor::new();
+    processor
+        .process_files(vec![dir.clone()])
+        .await
+        .map_err(|e| CliError::InputError(format!("Failed to process files: {}", e)))?;
+    let total_words = processor
+        .get_results()
+        .values()
+        .map(|r| r.total_words)
+        .sum();
+
+    let snapshot = Snapshot::now(total_words);
+    let io_error = |e: std::io::Error| {
+        CliError::InputError(format!("Failed to access {}: {}", history.display(), e))
+    };
+    record_snapshot(&history, &dir, snapshot)
+        .await
+        .map_err(io_error)?;
+    info!("Recorded {} words in {}", total_words, history.display());
+    let snapshots = load_snapshots(&history, &dir).await.map_err(io_error)?;
+    let days = daily_words(&snapshots, snapshot.day(), args.days.max(1));
+
+    let target = match args.target {
+        Some(words) => Some(words),
+        None => Config::discover(None, true)?.target_words,
+    }
+    .map(|words| {
+        Target {
+            words,
+            scope: TargetScope::Corpus,
+        }
+        .progress(total_words)
+    });
+
+    let report = ProgressReport {
+        dir: dir.display().to_string(),
+        total_words,
+        days: days
+            .iter()
+            .map(|day| DayRow {
+                date: format_day(day.day),
+                words: day.words,
+                added: day.added,
+            })
+            .collect(),
+        target: target.map(|progress| TargetRow {
+            words: progress.target,
+            percent: progress.percent(),
+            remaining: progress.remaining(),
+        }),
+    };
+    match args.format {
+        ReportFormat::Text if args.plain => format_plain(&report, target),
+        ReportFormat::Text => format_text(&report, &days, target),
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
+
+// History file in the user data directory, following the XDG base directory spec
+fn default_history_path() -> Option<PathBuf> {
+    let data = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+    Some(data.join("mfp").join("history.tsv"))
+}
+
+// Helper to format the report as a table of days, followed by a sparkline
+fn format_text(report: &ProgressReport, days: &[DailyWords], target: Option<TargetProgress>) {
+    debug!("Formatting progress report as text");
+    println!("\n{}: {} words", report.dir, report.total_words);
+    println!("------------------");
+    println!("{:<10}  {:>8}  {:>8}", "Date", "Words", "Added");
+    for row in &report.days {
+        println!("{:<10}  {:>8}  {:>+8}", row.date, row.words, row.added);
+    }
+    println!("\n{}", sparkline(days));
+    if let Some(progress) = target {
+        println!("Target: {}", progress);
+    }
+}
+
+// Helper to format the report as labelled lines, for screen readers
+fn format_plain(report: &ProgressReport, target: Option<TargetProgress>) {
+    debug!("Formatting progress report as plain text");
+    println!("Directory {}: {} words.", report.dir, report.total_words);
+    for row in &report.days {
+        println!(
+            "Date {}: {} words in total, {} added.",
+            row.date, row.words, row.added
+        );
+    }
+    if let Some(progress) = target {
+        println!("Target: {}.", progress);
+    }
+}
+
+// One bar per day, scaled to the day with the most words added
+fn sparkline(days: &[DailyWords]) -> String {
+    let max = days.iter().map(|day| day.added).max().unwrap_or(0).max(1);
+    days.iter()
+        .map(|day| {
+            let level = day.added.max(0) as usize * (SPARKS.len() - 1) / max as usize;
+            SPARKS[level]
+        })
+        .collect()
+}
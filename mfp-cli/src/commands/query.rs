@@ -0,0 +1,93 @@
+use crate::args::QueryArgs;
+use crate::error::CliError;
+use crate::format::ReportFormat;
+use mfp_lib::{bloom_filter_path, expand_paths, read_bloom_filter, WalkOptions, BLOOM_EXTENSION};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use tracing::{debug, info, warn};
+
+#[derive(serde::Serialize)]
+struct QueryResult<'a> {
+    word: &'a str,
+    filters: usize,
+    files: &'a [PathBuf],
+}
+
+/// Reports the files whose Bloom filter sidecars may contain a word
+pub async fn run(args: QueryArgs) -> Result<(), CliError> {
+    let sidecars = find_sidecars(&args).await?;
+    if sidecars.is_empty() {
+        return Err(CliError::InputError(
+            "No Bloom filters found, write them with --bloom-filters".to_string(),
+        ));
+    }
+    info!(
+        "Looking '{}' up in {} Bloom filters",
+        args.word,
+        sidecars.len()
+    );
+
+    let mut files = Vec::new();
+    for sidecar in &sidecars {
+        match read_bloom_filter(sidecar).await {
+            // The sidecar of 'notes.txt' is 'notes.txt.bloom'
+            Ok(filter) if filter.may_contain(&args.word) => files.push(sidecar.with_extension("")),
+            Ok(_) => {}
+            Err(e) => warn!("Skipping {}: {}", sidecar.display(), e),
+        }
+    }
+
+    match args.format {
+        ReportFormat::Text => format_text(&args.word, sidecars.len(), &files),
+        ReportFormat::Json => format_json(&args.word, sidecars.len(), &files)?,
+    }
+    Ok(())
+}
+
+// Sidecars found walking the paths, or next to the files given
+async fn find_sidecars(args: &QueryArgs) -> Result<BTreeSet<PathBuf>, CliError> {
+    let files = expand_paths(args.paths.clone(), &WalkOptions::default())
+        .await
+        .map_err(|e| CliError::InputError(format!("Failed to list the files: {}", e)))?;
+    Ok(files
+        .into_iter()
+        .filter_map(|path| {
+            if path.extension().is_some_and(|ext| ext == BLOOM_EXTENSION) {
+                Some(path)
+            } else {
+                Some(bloom_filter_path(&path)).filter(|sidecar| sidecar.is_file())
+            }
+        })
+        .collect())
+}
+
+// Helper to format the matching files as text
+fn format_text(word: &str, filters: usize, files: &[PathBuf]) {
+    debug!("Formatting query as text");
+    println!(
+        "\nFiles that may contain '{}' ({} of {} filters):",
+        word,
+        files.len(),
+        filters
+    );
+    println!("------------------");
+
+    if files.is_empty() {
+        println!("No file contains it");
+    }
+    for file in files {
+        println!("{}", file.display());
+    }
+}
+
+// Helper to format the matching files as JSON
+fn format_json(word: &str, filters: usize, files: &[PathBuf]) -> Result<(), CliError> {
+    debug!("Formatting query as JSON");
+    let output = QueryResult {
+        word,
+        filters,
+        files,
+    };
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use crate::error::CliError;
+use mfp_lib::TextProcessor;
+use std::io::{self, Read};
+use tracing::debug;
+
+#[derive(serde::Serialize)]
+struct QuickResult<'a> {
+    name: &'a str,
+    total_words: usize,
+    line_counts: Vec<usize>,
+}
+
+/// Counts a single buffer read from stdin and prints one compact JSON object.
+/// Runs synchronously so editor invocations don't pay for an async runtime.
+pub fn run(name: &str, processor: TextProcessor) -> Result<(), CliError> {
+    let mut text = String::new();
+    io::stdin()
+        .read_to_string(&mut text)
+        .map_err(|e| CliError::InputError(format!("Failed to read stdin: {}", e)))?;
+    debug!("Read {} bytes for {}", text.len(), name);
+
+    println!("{}", quick_json(name, &processor, &text)?);
+    Ok(())
+}
+
+// Compact JSON counts of a buffer, counted as a file of the given name
+fn quick_json(name: &str, processor: &TextProcessor, text: &str) -> Result<String, CliError> {
+    let result = processor.process_text_as(name, text);
+    let output = QuickResult {
+        name,
+        total_words: result.total_words,
+        line_counts: result.line_counts,
+    };
+    Ok(serde_json::to_string(&output)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test the buffer is counted as the named file, and printed on a single line
+    #[test]
+    fn test_quick_json() {
+        let processor = TextProcessor::new();
+        assert_eq!(
+            quick_json("draft.txt", &processor, "one two\n\nthree").unwrap(),
+            r#"{"name":"draft.txt","total_words":3,"line_counts":[2,0,1]}"#
+        );
+        assert_eq!(
+            quick_json("empty.txt", &processor, "").unwrap(),
+            r#"{"name":"empty.txt","total_words":0,"line_counts":[]}"#
+        );
+
+        // Markdown settings apply by the name's extension
+        let processor = TextProcessor::new().with_markdown_headings(true);
+        let json = quick_json("notes.md", &processor, "# Title\nBody text").unwrap();
+        assert!(json.contains(r#""total_words":3"#));
+        let json = quick_json("notes.txt", &processor, "# Title\nBody text").unwrap();
+        assert!(json.contains(r#""total_words":4"#));
+    }
+}
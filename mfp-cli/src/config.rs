@@ -0,0 +1,554 @@
+use crate::affinity::CpuSet;
+use crate::error::CliError;
+use crate::target::TargetScope;
+use mfp_lib::{CountingPolicy, Glob, JoinPolicy, Segmentation, TextProcessor, TokenizerConfig};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Configuration file looked up in the current directory
+pub const CONFIG_FILE: &str = "mfp.toml";
+
+/// System-wide configuration file, e.g. holding the defaults of an organization
+/// shipped along with a package
+pub const SYSTEM_CONFIG_FILE: &str = "/etc/mfp/config.toml";
+
+/// Settings read from `mfp.toml`, on top of the system-wide and user configuration files
+#[derive(Debug, Default)]
+pub struct Config {
+    /// Segmentation per file pattern, from the `[segmentation]` table in file order
+    pub segmentation_rules: Vec<(Glob, Segmentation)>,
+    /// Counting of contractions, hyphenated compounds and slash-joined terms,
+    /// from the `[tokenizer]` table
+    pub counting_policy: CountingPolicy,
+    /// Word count to reach, from `words` in the `[target]` table
+    pub target_words: Option<usize>,
+    /// Whether the target applies to each file or the whole corpus,
+    /// from `scope` in the `[target]` table
+    pub target_scope: Option<TargetScope>,
+    /// Characters splitting words, from `delimiters` in the `[tokenizer]` table
+    pub tokenizer_config: TokenizerConfig,
+    /// CPUs the files are processed on, from `cpuset` in the `[runtime]` table
+    pub cpuset: Option<CpuSet>,
+    /// Whether each worker thread is pinned to a CPU, from `pin_threads` in the
+    /// `[runtime]` table
+    pub pin_threads: Option<bool>,
+}
+
+impl Config {
+    /// Loads the given configuration file, or `mfp.toml` when present
+    pub fn load(path: Option<&Path>) -> Result<Self, CliError> {
+        Self::discover(path, false)
+    }
+
+    /// Loads the system-wide and user configuration files that exist, unless `system`
+    /// is unset, then the given configuration file or `mfp.toml` when present, each
+    /// file overriding the settings of the previous ones
+    pub fn discover(path: Option<&Path>, system: bool) -> Result<Self, CliError> {
+        let mut config = Config::default();
+        if system {
+            for path in system_config_paths().iter().filter(|path| path.is_file()) {
+                config.merge_file(path)?;
+            }
+        }
+        match path {
+            Some(path) => config.merge_file(path)?,
+            None if Path::new(CONFIG_FILE).is_file() => {
+                config.merge_file(Path::new(CONFIG_FILE))?
+            }
+            None => {}
+        }
+        Ok(config)
+    }
+
+    fn merge_file(&mut self, path: &Path) -> Result<(), CliError> {
+        let text = fs::read_to_string(path).map_err(|e| {
+            CliError::InputError(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        self.merge(&text)
+            .map_err(|e| CliError::InputError(format!("Invalid {}: {}", path.display(), e)))
+    }
+
+    /// Applies the per-file settings to a processor
+    pub fn apply(&self, mut processor: TextProcessor) -> TextProcessor {
+        for (pattern, segmentation) in &self.segmentation_rules {
+            processor = processor.with_segmentation_rule(pattern.clone(), *segmentation);
+        }
+        processor.with_counting_policy(self.counting_policy)
+    }
+
+    /// Parses the supported subset of TOML into settings, overriding those of the
+    /// previous files: keys set replace their value, segmentation rules and delimiters
+    /// add to the earlier ones
+    pub fn merge(&mut self, text: &str) -> Result<(), String> {
+        for entry in parse_toml(text)? {
+            match (entry.table.as_str(), &entry.value) {
+                ("segmentation", Value::String(mode)) => {
+                    let pattern = entry
+                        .key
+                        .parse()
+                        .map_err(|e| format!("line {}: {}", entry.line, e))?;
+                    let segmentation = mode
+                        .parse()
+                        .map_err(|e| format!("line {}: {}", entry.line, e))?;
+                    self.segmentation_rules.push((pattern, segmentation));
+                }
+                ("tokenizer", Value::String(delimiters)) if entry.key == "delimiters" => {
+                    let delimiters: TokenizerConfig = delimiters
+                        .parse()
+                        .map_err(|e| format!("line {}: {}", entry.line, e))?;
+                    self.tokenizer_config = std::mem::take(&mut self.tokenizer_config)
+                        .with_delimiters(delimiters.delimiters().iter().copied());
+                }
+                ("tokenizer", Value::String(regex)) if entry.key == "word_regex" => {
+                    let regex = regex
+                        .parse()
+                        .map_err(|e| format!("line {}: {}", entry.line, e))?;
+                    self.tokenizer_config =
+                        std::mem::take(&mut self.tokenizer_config).with_word_regex(regex);
+                }
+                ("tokenizer", Value::String(policy)) => {
+                    let policy: JoinPolicy = policy
+                        .parse()
+                        .map_err(|e| format!("line {}: {}", entry.line, e))?;
+                    match entry.key.as_str() {
+                        "apostrophes" => self.counting_policy.apostrophes = policy,
+                        "hyphens" => self.counting_policy.hyphens = policy,
+                        "slashes" => self.counting_policy.slashes = policy,
+                        key => {
+                            return Err(format!(
+                                "line {}: unknown setting 'tokenizer.{}'",
+                                entry.line, key
+                            ))
+                        }
+                    }
+                }
+                ("target", Value::Integer(words)) if entry.key == "words" => {
+                    let words = usize::try_from(*words).map_err(|_| {
+                        format!("line {}: 'target.words' must be positive", entry.line)
+                    })?;
+                    self.target_words = Some(words);
+                }
+                ("target", Value::String(scope)) if entry.key == "scope" => {
+                    let scope = scope
+                        .parse()
+                        .map_err(|e| format!("line {}: {}", entry.line, e))?;
+                    self.target_scope = Some(scope);
+                }
+                ("target", _) if matches!(entry.key.as_str(), "words" | "scope") => {
+                    return Err(format!(
+                        "line {}: 'target.words' must be an integer and 'target.scope' a string",
+                        entry.line
+                    ));
+                }
+                ("runtime", Value::String(cpuset)) if entry.key == "cpuset" => {
+                    let cpuset = cpuset
+                        .parse()
+                        .map_err(|e| format!("line {}: {}", entry.line, e))?;
+                    self.cpuset = Some(cpuset);
+                }
+                ("runtime", Value::Boolean(pin)) if entry.key == "pin_threads" => {
+                    self.pin_threads = Some(*pin);
+                }
+                ("runtime", _) if matches!(entry.key.as_str(), "cpuset" | "pin_threads") => {
+                    return Err(format!(
+                        "line {}: 'runtime.cpuset' must be a string and 'runtime.pin_threads' a boolean",
+                        entry.line
+                    ));
+                }
+                ("segmentation" | "tokenizer", _) => {
+                    return Err(format!(
+                        "line {}: '{}.{}' must be a string",
+                        entry.line, entry.table, entry.key
+                    ));
+                }
+                (table, _) => {
+                    let key = if table.is_empty() {
+                        entry.key.clone()
+                    } else {
+                        format!("{}.{}", table, entry.key)
+                    };
+                    return Err(format!("line {}: unknown setting '{}'", entry.line, key));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// System-wide and user configuration files, by increasing precedence: `/etc/mfp/config.toml`
+/// then `config.toml` in `$XDG_CONFIG_HOME/mfp`, or `~/.config/mfp`
+pub fn system_config_paths() -> Vec<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    let mut paths = vec![PathBuf::from(SYSTEM_CONFIG_FILE)];
+    paths.extend(config_home.map(|dir| dir.join("mfp").join("config.toml")));
+    paths
+}
+
+/// Value of a TOML key
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+/// Key and value, along with the table it belongs to
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    /// Dotted table name, empty for top-level keys
+    table: String,
+    key: String,
+    value: Value,
+    /// 1-based line the key is defined on
+    line: usize,
+}
+
+// Parses the TOML subset the configuration needs: tables, bare or quoted keys,
+// strings, integers, floats, booleans and (possibly multiline) arrays
+fn parse_toml(text: &str) -> Result<Vec<Entry>, String> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    let mut entries = Vec::new();
+    let mut table = String::new();
+
+    loop {
+        parser.skip_blank(true);
+        let Some(c) = parser.peek() else {
+            break;
+        };
+        let line = parser.line;
+        if c == '[' {
+            parser.pos += 1;
+            if parser.peek() == Some('[') {
+                return Err(format!("line {line}: arrays of tables are not supported"));
+            }
+            let mut parts = vec![parser.key()?];
+            parser.skip_blank(false);
+            while parser.peek() == Some('.') {
+                parser.pos += 1;
+                parts.push(parser.key()?);
+                parser.skip_blank(false);
+            }
+            parser.expect(']')?;
+            table = parts.join(".");
+        } else {
+            let key = parser.key()?;
+            parser.skip_blank(false);
+            parser.expect('=')?;
+            parser.skip_blank(false);
+            let value = parser.value()?;
+            if entries
+                .iter()
+                .any(|e: &Entry| e.table == table && e.key == key)
+            {
+                return Err(format!("line {line}: duplicate key '{key}'"));
+            }
+            entries.push(Entry {
+                table: table.clone(),
+                key,
+                value,
+                line,
+            });
+        }
+
+        parser.skip_blank(false);
+        match parser.peek() {
+            None | Some('\n') => {}
+            Some(c) => return Err(format!("line {}: unexpected '{}'", parser.line, c)),
+        }
+    }
+
+    Ok(entries)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!(
+                "line {}: expected '{}', found '{}'",
+                self.line, expected, c
+            )),
+            None => Err(format!("line {}: expected '{}'", self.line, expected)),
+        }
+    }
+
+    // Skips spaces and comments, and line breaks too when `newlines` is set
+    fn skip_blank(&mut self, newlines: bool) {
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\r' => {}
+                '\n' if newlines => {}
+                '#' => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                    continue;
+                }
+                _ => break,
+            }
+            self.next();
+        }
+    }
+
+    fn key(&mut self) -> Result<String, String> {
+        self.skip_blank(false);
+        match self.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                {
+                    self.pos += 1;
+                }
+                if start == self.pos {
+                    return Err(format!("line {}: expected a key", self.line));
+                }
+                Ok(self.chars[start..self.pos].iter().collect())
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => {
+                self.next();
+                let mut items = Vec::new();
+                loop {
+                    self.skip_blank(true);
+                    if self.peek() == Some(']') {
+                        self.next();
+                        return Ok(Value::Array(items));
+                    }
+                    items.push(self.value()?);
+                    self.skip_blank(true);
+                    match self.next() {
+                        Some(',') => {}
+                        Some(']') => return Ok(Value::Array(items)),
+                        _ => return Err(format!("line {}: unterminated array", self.line)),
+                    }
+                }
+            }
+            _ => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || "+-._".contains(c))
+                {
+                    self.pos += 1;
+                }
+                let token: String = self.chars[start..self.pos].iter().collect();
+                let number = token.replace('_', "");
+                match token.as_str() {
+                    "true" => Ok(Value::Boolean(true)),
+                    "false" => Ok(Value::Boolean(false)),
+                    _ => number
+                        .parse()
+                        .map(Value::Integer)
+                        .or_else(|_| number.parse().map(Value::Float))
+                        .map_err(|_| format!("line {}: invalid value '{}'", self.line, token)),
+                }
+            }
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.next() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some(u @ ('u' | 'U')) => {
+                        let len = if u == 'u' { 4 } else { 8 };
+                        let hex: String = (0..len).filter_map(|_| self.next()).collect();
+                        let c = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| {
+                                format!("line {}: invalid escape '\\{}{}'", self.line, u, hex)
+                            })?;
+                        s.push(c);
+                    }
+                    other => {
+                        return Err(format!(
+                            "line {}: invalid escape '\\{}'",
+                            self.line,
+                            other.unwrap_or(' ')
+                        ))
+                    }
+                },
+                Some('\n') | None => {
+                    return Err(format!("line {}: unterminated string", self.line))
+                }
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.expect('\'')?;
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('\'') => return Ok(s),
+                Some('\n') | None => {
+                    return Err(format!("line {}: unterminated string", self.line))
+                }
+                Some(c) => s.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    impl Config {
+        fn parse(text: &str) -> Result<Self, String> {
+            let mut config = Config::default();
+            config.merge(text)?;
+            Ok(config)
+        }
+    }
+
+    #[test]
+    fn test_parse_toml_subset() {
+        let entries = parse_toml(
+            "# comment\ntop = 1_000\n[a.\"b c\"]\nflag = true # trailing\nlist = [\n  'x', \"y\\u00e9\",\n  2.5,\n]\n",
+        )
+        .unwrap();
+
+        assert_eq!(entries[0].key, "top");
+        assert_eq!(entries[0].value, Value::Integer(1000));
+        assert_eq!(entries[1].table, "a.b c");
+        assert_eq!(entries[1].value, Value::Boolean(true));
+        assert_eq!(
+            entries[2].value,
+            Value::Array(vec![
+                Value::String("x".to_string()),
+                Value::String("yé".to_string()),
+                Value::Float(2.5),
+            ])
+        );
+        assert_eq!(entries[2].line, 5);
+
+        assert!(parse_toml("key = \"open").is_err());
+        assert!(parse_toml("key = 1\nkey = 2").is_err());
+        assert!(parse_toml("[[tables]]").is_err());
+    }
+
+    #[test]
+    fn test_segmentation_rules() {
+        let config =
+            Config::parse("[segmentation]\n\".ja.md\" = \"unicode\"\n\"*.txt\" = \"whitespace\"\n")
+                .unwrap();
+        let rules = &config.segmentation_rules;
+        assert_eq!(rules.len(), 2);
+        assert!(rules[0].0.matches(Path::new("docs/intro.ja.md")));
+        assert_eq!(rules[0].1, Segmentation::Unicode);
+
+        assert!(Config::parse("[segmentation]\n\"*.md\" = \"mecab\"").is_err());
+
+        let config = Config::parse("[tokenizer]\nhyphens = \"split\"\n").unwrap();
+        assert_eq!(config.counting_policy.hyphens, JoinPolicy::Split);
+        assert_eq!(config.counting_policy.apostrophes, JoinPolicy::Segmentation);
+        assert!(Config::parse("[tokenizer]\ncommas = \"split\"").is_err());
+        let config = Config::parse("[tokenizer]\ndelimiters = \",;|\"\n").unwrap();
+        assert_eq!(config.tokenizer_config.delimiters(), [',', ';', '|']);
+        let config =
+            Config::parse("[tokenizer]\nword_regex = '#\\w+'\ndelimiters = \"-\"\n").unwrap();
+        let regex = config.tokenizer_config.word_regex().unwrap();
+        assert_eq!(regex.as_str(), "#\\w+");
+        assert_eq!(config.tokenizer_config.delimiters(), ['-']);
+        assert!(Config::parse("[tokenizer]\nword_regex = '(\\w+'\n").is_err());
+        assert!(Config::parse("unknown = 1").is_err());
+    }
+
+    #[test]
+    fn test_target() {
+        let config = Config::parse("[target]\nwords = 50_000\nscope = \"file\"\n").unwrap();
+        assert_eq!(config.target_words, Some(50000));
+        assert_eq!(config.target_scope, Some(TargetScope::File));
+
+        assert!(Config::parse("[target]\nwords = -1").is_err());
+        assert!(Config::parse("[target]\nwords = \"many\"").is_err());
+        assert!(Config::parse("[target]\nscope = \"chapter\"").is_err());
+        assert!(Config::parse("[target]\ndeadline = 30").is_err());
+    }
+
+    #[test]
+    fn test_runtime() {
+        let config = Config::parse("[runtime]\ncpuset = \"0-3,8\"\npin_threads = true\n").unwrap();
+        assert_eq!(config.cpuset.unwrap().cpus(), [0, 1, 2, 3, 8]);
+        assert_eq!(config.pin_threads, Some(true));
+
+        assert!(Config::parse("[runtime]\ncpuset = 4").is_err());
+        assert!(Config::parse("[runtime]\ncpuset = \"4-2\"").is_err());
+        assert!(Config::parse("[runtime]\npin_threads = \"yes\"").is_err());
+        assert!(Config::parse("[runtime]\nnuma = 1").is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut config = Config::parse(
+            "[segmentation]\n\"*.md\" = \"unicode\"\n[tokenizer]\nhyphens = \"split\"\n\
+             [target]\nwords = 1000\n",
+        )
+        .unwrap();
+        config
+            .merge("[segmentation]\n\"*.txt\" = \"unicode\"\n[target]\nwords = 2000\n")
+            .unwrap();
+        assert_eq!(config.segmentation_rules.len(), 2);
+        assert_eq!(config.counting_policy.hyphens, JoinPolicy::Split);
+        assert_eq!(config.target_words, Some(2000));
+        assert!(config.merge("[target]\nwords = \"many\"").is_err());
+
+        let paths = system_config_paths();
+        assert_eq!(paths[0], Path::new(SYSTEM_CONFIG_FILE));
+        assert!(paths
+            .iter()
+            .skip(1)
+            .all(|path| path.ends_with("mfp/config.toml")));
+    }
+}
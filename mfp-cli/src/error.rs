@@ -26,3 +26,9 @@ impl From<serde_json::Error> for CliError {
         CliError::FormatError(err.to_string())
     }
 }
+
+impl From<std::io::Error> for CliError {
+    fn from(err: std::io::Error) -> Self {
+        CliError::FormatError(err.to_string())
+    }
+}
@@ -1,8 +1,23 @@
+use crate::args::STDIN_PATH;
 use crate::error::CliError;
+use crate::frontmatter;
+use crate::i18n::{Lang, Message};
+use crate::metadata::RunMetadata;
+use crate::target::{Target, TargetProgress, TargetScope};
 use clap::ValueEnum;
-use mfp_lib::FileProcessingResult;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use mfp_lib::{
+    entry_archive, line_index_path, CorpusEstimate, CorpusStats, Estimate, FileProcessingResult,
+    FileStatus, LineStats, PatternMatches, Readability, RegionCounts, Warning, ZipfFit,
+};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::time::Duration;
 use tracing::debug;
 
 #[derive(ValueEnum, Clone, Debug)]
@@ -11,18 +26,342 @@ pub enum OutputFormat {
     Text,
     /// Structured JSON format
     Json,
+    /// Markdown front matter with 'wordcount' and 'reading_time' keys set
+    FrontmatterPatch,
+    /// One CSV row per file, for spreadsheets
+    Csv,
+    /// GitHub-flavored Markdown table, for PRs and wikis
+    Markdown,
+    /// One JSON object per line and file, streamed as each file completes
+    Ndjson,
 }
 
-/// Outputs the processing results in the specified format
+/// Output formats of the subcommand reports
+#[derive(ValueEnum, Clone, Debug)]
+pub enum ReportFormat {
+    /// Simple text output
+    Text,
+    /// Structured JSON format
+    Json,
+}
+
+/// Order of the files in the report, ties broken by path
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// Path of the files
+    #[default]
+    Name,
+    /// Total words of the files, fewest first
+    Words,
+    /// Number of lines of the files, fewest first
+    Lines,
+}
+
+/// Detail of the output, each level adding to the previous one in every format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Words per file, with the per-line counts where the format always had them
+    #[default]
+    Normal,
+    /// Total words, lines, characters and bytes of each file, from -v
+    Totals,
+    /// Per-line counts and the time taken to count each file, from -vv
+    Details,
+    /// Analyzer internals: the encoding and byte order mark of each file, from -vvv
+    Internals,
+}
+
+impl From<u8> for Verbosity {
+    /// Verbosity of a number of -v flags, levels past -vvv adding nothing
+    fn from(count: u8) -> Self {
+        match count {
+            0 => Verbosity::Normal,
+            1 => Verbosity::Totals,
+            2 => Verbosity::Details,
+            _ => Verbosity::Internals,
+        }
+    }
+}
+
+/// Earlier output layout reproduced for the parsers written against it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputCompat {
+    /// Layout of mfp 0.1: file names without their directory, the per-line
+    /// counts and, from -v, the total words - nothing else
+    V1,
+}
+
+impl FromStr for OutputCompat {
+    type Err = String;
+
+    /// Parses a layout version, e.g. '1' or 'v1'
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_start_matches(['v', 'V']) {
+            "1" => Ok(OutputCompat::V1),
+            _ => Err(format!("Unknown output layout '{s}', expected '1'")),
+        }
+    }
+}
+
+/// Output settings shared across formats
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    /// Detail of the figures
+    pub verbosity: Verbosity,
+    /// Update files in place where the format supports it
+    pub write: bool,
+    /// Number of most frequent words shown per file, if any
+    pub top_words: Option<usize>,
+    /// Omit the header row, e.g. when appending CSV rows to a file that has one
+    pub skip_header: bool,
+    /// Word count to report progress against, if any
+    pub target: Option<Target>,
+    /// Language of the text and table labels
+    pub lang: Lang,
+    /// Line-oriented text with explicit labels, for screen readers
+    pub plain: bool,
+    /// Circumstances of the run, written with the report if given
+    pub metadata: Option<RunMetadata>,
+    /// Earlier layout of the text and JSON output to stick to, if any
+    pub compat: Option<OutputCompat>,
+    /// Leave out the files, only writing the aggregate summary and corpus-wide figures
+    pub summary_only: bool,
+    /// Order of the files
+    pub sort_by: SortKey,
+    /// List the files in reverse order
+    pub reverse: bool,
+    /// Fewest words of the files listed, if any
+    pub min_words: Option<usize>,
+    /// Most words of the files listed, if any
+    pub max_words: Option<usize>,
+}
+
+impl OutputOptions {
+    /// Whether a file is listed, its words being within the range of the options
+    pub fn lists(&self, result: &FileProcessingResult) -> bool {
+        self.min_words.is_none_or(|min| result.total_words >= min)
+            && self.max_words.is_none_or(|max| result.total_words <= max)
+    }
+}
+
+/// Corpus-wide figures shown after the file results
+#[derive(Debug, Default)]
+pub struct CorpusReport {
+    /// Totals extrapolated from a sample
+    pub estimate: Option<CorpusEstimate>,
+    /// Distribution of the words per file
+    pub stats: Option<CorpusStats>,
+    /// Zipf's law fit of the corpus word frequencies
+    pub zipf: Option<ZipfFit>,
+    /// Most frequent words across all files, most frequent first
+    pub top_words: Option<Vec<(String, usize)>>,
+    /// Matches of the counted pattern across all files
+    pub pattern: Option<PatternMatches>,
+    /// Distinct words across all files, when counted
+    pub unique_words: Option<usize>,
+    /// Policy decisions taken along the run, e.g. skipped files
+    pub warnings: Vec<Warning>,
+    /// Time the run took, written with the run metadata
+    pub elapsed: Option<Duration>,
+}
+
+/// Destination of the results of a run, fed one file at a time: formats that
+/// can stream write each file as it is added, keeping memory flat on large runs,
+/// while the others hold the files until `finish`
+pub trait ReportSink: Send {
+    /// Starts the report, e.g. with a title or a header row
+    fn begin(&mut self, out: &mut dyn Write) -> Result<(), CliError>;
+
+    /// Adds the result of a file
+    fn add_file(
+        &mut self,
+        out: &mut dyn Write,
+        path: &Path,
+        result: &FileProcessingResult,
+    ) -> Result<(), CliError>;
+
+    /// Marks a listed file as done, whether its results were all added or it failed
+    fn file_done(&mut self, _out: &mut dyn Write, _path: &Path) -> Result<(), CliError> {
+        Ok(())
+    }
+
+    /// Ends the report with the totals and corpus-wide figures, where the format has them
+    fn finish(&mut self, out: &mut dyn Write, report: &CorpusReport) -> Result<(), CliError>;
+}
+
+impl OutputFormat {
+    /// Whether the report is written file by file as results arrive - JSON is a
+    /// single document, and plain text starts with the number of files
+    pub fn streams(&self, plain: bool) -> bool {
+        match self {
+            OutputFormat::Json => false,
+            OutputFormat::Text => !plain,
+            _ => true,
+        }
+    }
+}
+
+/// Files, lines and words added to a report so far, for its aggregate summary
+#[derive(Debug, Clone, Copy, Default)]
+struct Totals {
+    files: usize,
+    lines: usize,
+    words: usize,
+}
+
+impl Totals {
+    fn add(&mut self, result: &FileProcessingResult) {
+        self.files += 1;
+        self.lines += result.line_count;
+        self.words += result.total_words;
+    }
+
+    // Average words per file, zero without files
+    fn mean_words(&self) -> f64 {
+        match self.files {
+            0 => 0.0,
+            files => self.words as f64 / files as f64,
+        }
+    }
+
+    // Summary line of the text output
+    fn line(&self, lang: Lang) -> String {
+        lang.message(
+            Message::Summary,
+            &[
+                &lang.files(self.files),
+                &self.lines,
+                &self.words,
+                &format!("{:.1}", self.mean_words()),
+            ],
+        )
+    }
+
+    // What the Markdown totals row leaves out: the files and the average words per file
+    fn mean_line(&self, lang: Lang) -> String {
+        lang.message(
+            Message::SummaryMean,
+            &[
+                &lang.files(self.files),
+                &format!("{:.1}", self.mean_words()),
+            ],
+        )
+    }
+}
+
+#[derive(serde::Serialize)]
+struct SummaryResult {
+    files: usize,
+    lines: usize,
+    words: usize,
+    mean_words: f64,
+}
+
+impl From<&Totals> for SummaryResult {
+    fn from(totals: &Totals) -> Self {
+        Self {
+            files: totals.files,
+            lines: totals.lines,
+            words: totals.words,
+            mean_words: totals.mean_words(),
+        }
+    }
+}
+
+/// Report sink of a format, `paths` being the files expected in the report so
+/// that those sharing a name are told apart from the first one on
+pub fn report_sink(
+    format: &OutputFormat,
+    options: &OutputOptions,
+    paths: &[PathBuf],
+) -> Box<dyn ReportSink> {
+    let options = options.clone();
+    match format {
+        OutputFormat::Text | OutputFormat::Json if options.compat == Some(OutputCompat::V1) => {
+            Box::new(V1Sink {
+                json: matches!(format, OutputFormat::Json),
+                verbose: options.verbosity >= Verbosity::Totals,
+                files: BTreeMap::new(),
+            })
+        }
+        OutputFormat::Text => Box::new(TextSink {
+            options,
+            names: DisplayNames::new(paths),
+            pending: Vec::new(),
+            totals: Totals::default(),
+        }),
+        OutputFormat::Json => Box::new(JsonSink {
+            options,
+            names: DisplayNames::new(paths),
+            files: OrderedFiles::default(),
+            totals: Totals::default(),
+        }),
+        OutputFormat::FrontmatterPatch => Box::new(FrontmatterSink {
+            write: options.write,
+        }),
+        OutputFormat::Csv => Box::new(CsvSink {
+            options,
+            totals: Totals::default(),
+        }),
+        OutputFormat::Markdown => Box::new(MarkdownSink::new(options)),
+        OutputFormat::Ndjson => Box::new(NdjsonSink {
+            options,
+            totals: Totals::default(),
+        }),
+    }
+}
+
+/// Report sink of a format fed results as they complete, holding each file back
+/// until those before it in path order are in, so that the output keeps that order
+pub fn streaming_sink(
+    format: &OutputFormat,
+    options: &OutputOptions,
+    paths: &[PathBuf],
+) -> Box<dyn ReportSink> {
+    let mut order = paths.to_vec();
+    order.sort();
+    Box::new(InOrderSink {
+        inner: report_sink(format, options, paths),
+        options: options.clone(),
+        order,
+        next: 0,
+        held: HashMap::new(),
+        done: HashSet::new(),
+    })
+}
+
+/// Writes the processing results in the specified format, in the order of the options
 pub fn format_output(
+    out: &mut dyn Write,
     results: &HashMap<PathBuf, FileProcessingResult>,
+    report: &CorpusReport,
     format: OutputFormat,
-    verbose: bool,
+    options: &OutputOptions,
 ) -> Result<(), CliError> {
-    match format {
-        OutputFormat::Json => format_json(results, verbose),
-        OutputFormat::Text => format_text(results, verbose),
+    let mut paths: Vec<&PathBuf> = results
+        .iter()
+        .filter(|(_, result)| options.lists(result))
+        .map(|(path, _)| path)
+        .collect();
+    paths.sort_by(|a, b| {
+        let (first, second) = (&results[*a], &results[*b]);
+        match options.sort_by {
+            SortKey::Name => a.cmp(b),
+            SortKey::Words => first.total_words.cmp(&second.total_words).then(a.cmp(b)),
+            SortKey::Lines => first.line_count.cmp(&second.line_count).then(a.cmp(b)),
+        }
+    });
+    if options.reverse {
+        paths.reverse();
+    }
+    let known: Vec<PathBuf> = paths.iter().map(|&path| path.clone()).collect();
+    let mut sink = report_sink(&format, options, &known);
+
+    sink.begin(out)?;
+    for path in paths {
+        sink.add_file(out, path, &results[path])?;
     }
+    sink.finish(out, report)
 }
 
 #[derive(serde::Serialize)]
@@ -30,77 +369,2390 @@ struct FileResult {
     line_counts: Vec<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     total_words: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_chars: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unique_words: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line_stats: Option<LineStatsResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    citations: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    readability: Option<ReadabilityResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    regions: Option<RegionsResult>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    partial: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    had_bom: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line_index: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_words: Option<Vec<WordCount>>,
+}
+
+/// JSON Schema of the JSON report, the fields past `line_counts` being present
+/// depending on the verbosity and options
+pub fn report_schema() -> serde_json::Value {
+    let count = json!({ "type": "integer", "minimum": 0 });
+    let number = json!({ "type": "number" });
+    let string = json!({ "type": "string" });
+    let object = |properties: serde_json::Value, required: &[&str]| {
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false,
+        })
+    };
+    let word_counts = json!({
+        "type": "array",
+        "items": object(json!({ "word": string, "count": count }), &["word", "count"]),
+    });
+    let estimate = object(
+        json!({ "value": number, "lower": number, "upper": { "type": ["number", "null"] } }),
+        &["value", "lower", "upper"],
+    );
+
+    let file = object(
+        json!({
+            "line_counts": { "type": "array", "items": count },
+            "total_words": count,
+            "total_chars": count,
+            "total_bytes": count,
+            "line_count": count,
+            "unique_words": count,
+            "line_stats": object(
+                json!({
+                    "mean_words": number,
+                    "stddev_words": number,
+                    "min_words": count,
+                    "max_words": count,
+                    "longest_line": count,
+                }),
+                &["mean_words", "stddev_words", "min_words", "max_words", "longest_line"],
+            ),
+            "elapsed_ms": number,
+            "score": number,
+            "citations": count,
+            "readability": object(
+                json!({
+                    "sentences": count,
+                    "syllables": count,
+                    "grade_level": number,
+                    "reading_ease": number,
+                }),
+                &["sentences", "syllables"],
+            ),
+            "regions": object(
+                json!({ "footnotes": count, "captions": count, "tables": count, "quotes": count }),
+                &["footnotes", "captions", "tables", "quotes"],
+            ),
+            "partial": { "type": "boolean" },
+            "encoding": string,
+            "had_bom": { "type": "boolean" },
+            "skipped": string,
+            "line_index": string,
+            "top_words": word_counts,
+        }),
+        &["line_counts"],
+    );
+    let stats_fields = [
+        "files",
+        "total_words",
+        "mean",
+        "stddev",
+        "min",
+        "p25",
+        "median",
+        "p75",
+        "p90",
+        "p99",
+        "max",
+        "gini",
+    ];
+    let stats = object(
+        stats_fields
+            .iter()
+            .map(|&field| (field.to_string(), number.clone()))
+            .collect(),
+        &stats_fields,
+    );
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "mfp JSON report",
+        "type": "object",
+        "properties": {
+            "metadata": object(
+                json!({
+                    "version": string,
+                    "timestamp": { "type": "string", "format": "date-time" },
+                    "host": string,
+                    "options": { "type": "object", "additionalProperties": string },
+                    "elapsed_ms": number,
+                }),
+                &[],
+            ),
+            "files": { "type": "object", "additionalProperties": file },
+            "summary": object(
+                json!({ "files": count, "lines": count, "words": count, "mean_words": number }),
+                &["files", "lines", "words", "mean_words"],
+            ),
+            "estimate": object(
+                json!({
+                    "population": count,
+                    "sampled": count,
+                    "total_words": estimate,
+                    "total_lines": estimate,
+                }),
+                &["population", "sampled", "total_words", "total_lines"],
+            ),
+            "stats": stats,
+            "zipf": object(
+                json!({ "slope": number, "intercept": number, "r_squared": number }),
+                &["slope", "intercept", "r_squared"],
+            ),
+            "top_words": word_counts,
+            "pattern": object(
+                json!({ "pattern": string, "matches": count, "lines": count, "files": count }),
+                &["pattern", "matches", "lines", "files"],
+            ),
+            "unique_words": count,
+            "warnings": {
+                "type": "array",
+                "items": object(
+                    json!({
+                        "path": string,
+                        "kind": {
+                            "enum": ["skipped", "encoding_fallback", "truncated", "symlink_not_followed"]
+                        },
+                        "reason": string,
+                        "encoding": string,
+                    }),
+                    &["path", "kind"],
+                ),
+            },
+        },
+        "required": ["summary"],
+        "additionalProperties": false,
+    })
+}
+
+#[derive(serde::Serialize)]
+struct NdjsonRecord {
+    path: String,
+    #[serde(flatten)]
+    result: FileResult,
+}
+
+#[derive(serde::Serialize)]
+struct RegionsResult {
+    footnotes: usize,
+    captions: usize,
+    tables: usize,
+    quotes: usize,
+}
+
+impl From<&RegionCounts> for RegionsResult {
+    fn from(counts: &RegionCounts) -> Self {
+        Self {
+            footnotes: counts.footnotes,
+            captions: counts.captions,
+            tables: counts.tables,
+            quotes: counts.quotes,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct LineStatsResult {
+    mean_words: f64,
+    stddev_words: f64,
+    min_words: usize,
+    max_words: usize,
+    longest_line: usize,
+}
+
+impl From<&LineStats> for LineStatsResult {
+    fn from(stats: &LineStats) -> Self {
+        Self {
+            mean_words: stats.mean,
+            stddev_words: stats.stddev,
+            min_words: stats.min,
+            max_words: stats.max,
+            longest_line: stats.longest_line,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ReadabilityResult {
+    sentences: usize,
+    syllables: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grade_level: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reading_ease: Option<f64>,
+}
+
+impl From<&Readability> for ReadabilityResult {
+    fn from(readability: &Readability) -> Self {
+        Self {
+            sentences: readability.sentences,
+            syllables: readability.syllables,
+            grade_level: readability.grade_level(),
+            reading_ease: readability.reading_ease(),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct WordCount {
+    word: String,
+    count: usize,
+}
+
+#[derive(serde::Serialize)]
+struct EstimateResult {
+    value: f64,
+    lower: f64,
+    /// None when unbounded
+    upper: Option<f64>,
+}
+
+impl From<&Estimate> for EstimateResult {
+    fn from(estimate: &Estimate) -> Self {
+        Self {
+            value: estimate.value.round(),
+            lower: estimate.lower().round(),
+            upper: Some(estimate.upper().round()).filter(|upper| upper.is_finite()),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct SampleResult {
+    population: usize,
+    sampled: usize,
+    total_words: EstimateResult,
+    total_lines: EstimateResult,
+}
+
+#[derive(serde::Serialize)]
+struct StatsResult {
+    files: usize,
+    total_words: usize,
+    mean: f64,
+    stddev: f64,
+    min: usize,
+    p25: f64,
+    median: f64,
+    p75: f64,
+    p90: f64,
+    p99: f64,
+    max: usize,
+    gini: f64,
+}
+
+impl From<&CorpusStats> for StatsResult {
+    fn from(stats: &CorpusStats) -> Self {
+        Self {
+            files: stats.files,
+            total_words: stats.total_words,
+            mean: stats.mean,
+            stddev: stats.stddev,
+            min: stats.min,
+            p25: stats.p25,
+            median: stats.median,
+            p75: stats.p75,
+            p90: stats.p90,
+            p99: stats.p99,
+            max: stats.max,
+            gini: stats.gini,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct MetadataResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed_ms: Option<f64>,
+}
+
+impl MetadataResult {
+    // Metadata known before the files are processed, the elapsed time left out
+    fn new(metadata: &RunMetadata) -> Self {
+        Self {
+            version: Some(metadata.version.clone()),
+            timestamp: Some(metadata.timestamp()),
+            host: Some(metadata.host.clone()),
+            options: Some(metadata.options.iter().cloned().collect()),
+            elapsed_ms: None,
+        }
+    }
+
+    // Time the run took, on its own
+    fn elapsed(elapsed: Duration) -> Self {
+        Self {
+            version: None,
+            timestamp: None,
+            host: None,
+            options: None,
+            elapsed_ms: Some(elapsed_ms(elapsed)),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct NdjsonMetadata {
+    metadata: MetadataResult,
+}
+
+#[derive(serde::Serialize)]
+struct NdjsonSummary {
+    summary: SummaryResult,
+}
+
+// Files by name, serialized as an object whose keys keep the report order
+#[derive(Default)]
+struct OrderedFiles(Vec<(String, FileResult)>);
+
+impl serde::Serialize for OrderedFiles {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(name, file)| (name, file)))
+    }
 }
 
 #[derive(serde::Serialize)]
 struct OutputResult {
-    files: HashMap<String, FileResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<MetadataResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<OrderedFiles>,
+    summary: SummaryResult,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    estimate: Option<SampleResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<StatsResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    zipf: Option<ZipfResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_words: Option<Vec<WordCount>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pattern: Option<PatternResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unique_words: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<WarningResult>,
 }
 
-// Helper to format results as text,
-// optionally including total word counts
-fn format_text(
-    results: &HashMap<PathBuf, FileProcessingResult>,
-    verbose: bool,
-) -> Result<(), CliError> {
-    debug!("Formatting as text");
-    println!("\nProcessing Results:");
-    println!("------------------");
+#[derive(serde::Serialize)]
+struct WarningResult {
+    path: PathBuf,
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<String>,
+}
 
-    for (path, result) in results {
-        let filename = path.file_name().unwrap_or_default().to_string_lossy();
+impl From<&Warning> for WarningResult {
+    fn from(warning: &Warning) -> Self {
+        let (kind, reason, encoding) = match warning {
+            Warning::Skipped { reason, .. } => ("skipped", Some(reason.clone()), None),
+            Warning::EncodingFallback { encoding, .. } => {
+                ("encoding_fallback", None, Some(encoding.to_string()))
+            }
+            Warning::Truncated { .. } => ("truncated", None, None),
+            Warning::SymlinkNotFollowed { .. } => ("symlink_not_followed", None, None),
+        };
+        Self {
+            path: warning.path().to_path_buf(),
+            kind,
+            reason,
+            encoding,
+        }
+    }
+}
 
-        if verbose {
-            println!(
-                "{}: {} words in total\n  Line counts: {:?}",
-                filename, result.total_words, result.line_counts
-            );
-        } else {
-            println!("{}: {:?}", filename, result.line_counts);
+#[derive(serde::Serialize)]
+struct PatternResult {
+    pattern: String,
+    matches: usize,
+    lines: usize,
+    files: usize,
+}
+
+impl From<&PatternMatches> for PatternResult {
+    fn from(summary: &PatternMatches) -> Self {
+        Self {
+            pattern: summary.pattern.clone(),
+            matches: summary.matches,
+            lines: summary.lines,
+            files: summary.files,
         }
     }
+}
 
-    Ok(())
+#[derive(serde::Serialize)]
+struct ZipfResult {
+    slope: f64,
+    intercept: f64,
+    r_squared: f64,
 }
 
-// Helper to formats results as JSON,
-// optionally including total word counts
-fn format_json(
-    results: &HashMap<PathBuf, FileProcessingResult>,
-    verbose: bool,
-) -> Result<(), CliError> {
-    debug!("Formatting as JSON");
-    let files = results
-        .iter()
-        .map(|(path, result)| {
-            let name = path
-                .file_name()
-                .unwrap_or_default()
+impl From<&ZipfFit> for ZipfResult {
+    fn from(fit: &ZipfFit) -> Self {
+        Self {
+            slope: fit.slope,
+            intercept: fit.intercept,
+            r_squared: fit.r_squared,
+        }
+    }
+}
+
+// Names of the files in reports: the file name, or for files sharing it the
+// shortest path suffix telling them apart, e.g. 'a/notes.txt' and 'b/notes.txt'
+struct DisplayNames {
+    // Paths known so far, by file name
+    by_name: HashMap<OsString, Vec<PathBuf>>,
+}
+
+impl DisplayNames {
+    fn new(paths: &[PathBuf]) -> Self {
+        let mut names = DisplayNames {
+            by_name: HashMap::new(),
+        };
+        for path in paths {
+            names.insert(path);
+        }
+        names
+    }
+
+    fn insert(&mut self, path: &Path) -> &[PathBuf] {
+        let same_name = self
+            .by_name
+            .entry(path.file_name().unwrap_or_default().to_os_string())
+            .or_default();
+        if !same_name.iter().any(|other| other == path) {
+            same_name.push(path.to_path_buf());
+        }
+        same_name
+    }
+
+    // Name of a file, remembered so that later files of the same name are told apart
+    fn name(&mut self, path: &Path) -> String {
+        let same_name = self.insert(path);
+        let components: Vec<_> = path.components().rev().collect();
+        let unique = (1..=components.len()).find(|&depth| {
+            same_name
+                .iter()
+                .filter(|&other| other != path)
+                .all(|other| {
+                    !other
+                        .components()
+                        .rev()
+                        .take(depth)
+                        .eq(components[..depth].iter().copied())
+                })
+        });
+        match unique {
+            Some(depth) => components[..depth]
+                .iter()
+                .rev()
+                .collect::<PathBuf>()
                 .to_string_lossy()
-                .to_string();
+                .into_owned(),
+            // A suffix of another path, only told apart by the whole of it
+            None => path.to_string_lossy().into_owned(),
+        }
+    }
+}
 
-            debug!(
-                "Processing file {} with total_words={}",
-                name, result.total_words
-            );
+// Sidecar line index path of a file, when one was written
+fn line_index(path: &Path, result: &FileProcessingResult) -> Option<PathBuf> {
+    result
+        .line_offsets
+        .as_ref()
+        .filter(|_| path.as_os_str() != STDIN_PATH)
+        .map(|_| line_index_path(path))
+}
+
+// Target each file is held to, if any
+fn file_target(options: &OutputOptions) -> Option<&Target> {
+    options
+        .target
+        .as_ref()
+        .filter(|t| t.scope == TargetScope::File)
+}
+
+// Target the files are held to together, if any
+fn corpus_target(options: &OutputOptions) -> Option<&Target> {
+    options
+        .target
+        .as_ref()
+        .filter(|t| t.scope == TargetScope::Corpus)
+}
+
+// Why a file was not counted, if skipped
+fn skip_reason(result: &FileProcessingResult) -> Option<String> {
+    match &result.status {
+        FileStatus::Processed => None,
+        FileStatus::Skipped(reason) => Some(reason.clone()),
+    }
+}
+
+// Time taken to count a file, in milliseconds to the tenth
+fn elapsed_ms(elapsed: Duration) -> f64 {
+    (elapsed.as_secs_f64() * 10_000.0).round() / 10.0
+}
+
+// Most frequent words of a file, when requested and recorded
+fn top_words(result: &FileProcessingResult, n: Option<usize>) -> Option<Vec<(&str, usize)>> {
+    Some(result.frequencies.as_ref()?.top(n?))
+}
+
+// Sink of another format fed in path order, whatever order the files come in, and
+// only the files the options list. Archive entries are held under their archive,
+// which is done once all of them are in.
+struct InOrderSink {
+    inner: Box<dyn ReportSink>,
+    options: OutputOptions,
+    // Expected files in path order, and the index of the next one to write
+    order: Vec<PathBuf>,
+    next: usize,
+    // Listed results come in ahead of their turn, by expected file
+    held: HashMap<PathBuf, Vec<(PathBuf, FileProcessingResult)>>,
+    // Expected files done, with all their results in or failed
+    done: HashSet<PathBuf>,
+}
+
+impl InOrderSink {
+    // Writes the held results of the done files whose turn has come
+    fn release(&mut self, out: &mut dyn Write) -> Result<(), CliError> {
+        while let Some(path) = self.order.get(self.next) {
+            if !self.done.remove(path) {
+                break;
+            }
+            for (path, result) in self.held.remove(path).unwrap_or_default() {
+                self.inner.add_file(out, &path, &result)?;
+            }
+            self.next += 1;
+        }
+        Ok(())
+    }
+
+    fn expects(&self, path: &Path) -> bool {
+        self.order
+            .binary_search_by(|known| known.as_path().cmp(path))
+            .is_ok()
+    }
+}
 
-            let file_result = if verbose {
-                FileResult {
-                    line_counts: result.line_counts.clone(),
-                    total_words: Some(result.total_words),
-                }
-            } else {
-                FileResult {
-                    line_counts: result.line_counts.clone(),
-                    total_words: None,
-                }
+impl ReportSink for InOrderSink {
+    fn begin(&mut self, out: &mut dyn Write) -> Result<(), CliError> {
+        self.inner.begin(out)
+    }
+
+    fn add_file(
+        &mut self,
+        out: &mut dyn Write,
+        path: &Path,
+        result: &FileProcessingResult,
+    ) -> Result<(), CliError> {
+        let listed = self.options.lists(result);
+        let source = entry_archive(path).unwrap_or(path);
+        // Files that were not expected, e.g. stdin, have no turn to wait for
+        if !self.expects(source) {
+            return match listed {
+                true => self.inner.add_file(out, path, result),
+                false => Ok(()),
             };
+        }
+        if listed {
+            self.held
+                .entry(source.to_path_buf())
+                .or_default()
+                .push((path.to_path_buf(), result.clone()));
+        }
+        // A file has a single result, unlike an archive
+        match source == path {
+            true => self.file_done(out, path),
+            false => Ok(()),
+        }
+    }
 
-            (name, file_result)
-        })
-        .collect();
+    fn file_done(&mut self, out: &mut dyn Write, path: &Path) -> Result<(), CliError> {
+        if self.expects(path) {
+            self.done.insert(path.to_path_buf());
+        }
+        self.release(out)
+    }
 
-    let output = OutputResult { files };
-    println!("{}", serde_json::to_string_pretty(&output)?);
-    Ok(())
+    fn finish(&mut self, out: &mut dyn Write, report: &CorpusReport) -> Result<(), CliError> {
+        // Files that never came in, e.g. after an interruption, are skipped
+        for index in self.next..self.order.len() {
+            for (path, result) in self.held.remove(&self.order[index]).unwrap_or_default() {
+                self.inner.add_file(out, &path, &result)?;
+            }
+        }
+        self.inner.finish(out, report)
+    }
+}
+
+// Text lines, written as files are added but in plain text, which starts with
+// the number of files
+struct TextSink {
+    options: OutputOptions,
+    names: DisplayNames,
+    // Files held back until the end in plain text, along with their names
+    pending: Vec<(PathBuf, String, FileProcessingResult)>,
+    totals: Totals,
+}
+
+impl ReportSink for TextSink {
+    fn begin(&mut self, out: &mut dyn Write) -> Result<(), CliError> {
+        debug!("Formatting as text");
+        match self.options.plain {
+            true => Ok(()),
+            false => write_text_header(out, &self.options, 0),
+        }
+    }
+
+    fn add_file(
+        &mut self,
+        out: &mut dyn Write,
+        path: &Path,
+        result: &FileProcessingResult,
+    ) -> Result<(), CliError> {
+        self.totals.add(result);
+        if self.options.summary_only {
+            return Ok(());
+        }
+        let name = self.names.name(path);
+        if self.options.plain {
+            self.pending
+                .push((path.to_path_buf(), name, result.clone()));
+            return Ok(());
+        }
+        write_text_file(out, path, &name, result, &self.options)
+    }
+
+    fn finish(&mut self, out: &mut dyn Write, report: &CorpusReport) -> Result<(), CliError> {
+        if self.options.plain {
+            write_text_header(out, &self.options, self.totals.files)?;
+            for (path, name, result) in self.pending.drain(..) {
+                write_text_file(out, &path, &name, &result, &self.options)?;
+            }
+        }
+        write_text_footer(out, report, &self.options, self.totals)
+    }
+}
+
+// Title of the text output, the number of files only given in plain text
+fn write_text_header(
+    out: &mut dyn Write,
+    options: &OutputOptions,
+    files: usize,
+) -> Result<(), CliError> {
+    let lang = options.lang;
+    if let Some(metadata) = &options.metadata {
+        writeln!(
+            out,
+            "{}",
+            lang.message(
+                Message::RunInfo,
+                &[&metadata.version, &metadata.timestamp(), &metadata.host]
+            )
+        )?;
+        writeln!(
+            out,
+            "{}",
+            lang.message(Message::RunOptions, &[&metadata.options_line()])
+        )?;
+    }
+    if options.plain {
+        writeln!(out, "{}", lang.message(Message::PlainResults, &[&files]))?;
+    } else {
+        writeln!(out, "\n{}", lang.message(Message::ProcessingResults, &[]))?;
+        writeln!(out, "------------------")?;
+    }
+    Ok(())
+}
+
+// Text lines of a file shown under a name, labels in the language of the options,
+// with more figures the higher the verbosity
+fn write_text_file(
+    out: &mut dyn Write,
+    path: &Path,
+    name: &str,
+    result: &FileProcessingResult,
+    options: &OutputOptions,
+) -> Result<(), CliError> {
+    let lang = options.lang;
+    let verbosity = options.verbosity;
+    let filename = if result.partial {
+        lang.message(Message::Partial, &[&name])
+    } else {
+        name.to_string()
+    };
+    if let Some(reason) = skip_reason(result) {
+        writeln!(
+            out,
+            "{}",
+            lang.message(Message::Skipped, &[&filename, &reason])
+        )?;
+        return Ok(());
+    }
+
+    if options.plain {
+        writeln!(
+            out,
+            "{}",
+            lang.message(
+                Message::PlainFile,
+                &[&filename, &result.total_words, &result.line_count]
+            )
+        )?;
+        if verbosity >= Verbosity::Totals {
+            writeln!(
+                out,
+                "  {}",
+                lang.message(
+                    Message::PlainDetails,
+                    &[&result.total_chars, &result.total_bytes]
+                )
+            )?;
+            if let Some(unique) = result.unique_words {
+                writeln!(out, "  {}.", lang.message(Message::UniqueWords, &[&unique]))?;
+            }
+            writeln!(out, "  {}.", line_stats_line(lang, &result.line_stats))?;
+        }
+        if verbosity >= Verbosity::Details {
+            let line_counts: Vec<String> =
+                result.line_counts.iter().map(usize::to_string).collect();
+            writeln!(
+                out,
+                "  {}",
+                lang.message(Message::PlainLineCounts, &[&line_counts.join(", ")])
+            )?;
+        }
+    } else if verbosity >= Verbosity::Totals {
+        writeln!(
+            out,
+            "{}",
+            lang.message(
+                Message::FileDetails,
+                &[
+                    &filename,
+                    &result.total_words,
+                    &result.line_count,
+                    &result.total_chars,
+                    &result.total_bytes
+                ]
+            )
+        )?;
+        if let Some(unique) = result.unique_words {
+            writeln!(out, "  {}", lang.message(Message::UniqueWords, &[&unique]))?;
+        }
+        writeln!(out, "  {}", line_stats_line(lang, &result.line_stats))?;
+        if verbosity >= Verbosity::Details {
+            let line_counts = format!("{:?}", result.line_counts);
+            writeln!(
+                out,
+                "  {}",
+                lang.message(Message::LineCounts, &[&line_counts])
+            )?;
+        }
+    } else {
+        writeln!(out, "{}: {:?}", filename, result.line_counts)?;
+    }
+
+    if verbosity >= Verbosity::Details {
+        writeln!(
+            out,
+            "  {}",
+            lang.message(Message::Elapsed, &[&elapsed_ms(result.elapsed)])
+        )?;
+    }
+
+    if let Some(score) = result.score {
+        writeln!(out, "  {}", lang.message(Message::Score, &[&score]))?;
+    }
+
+    if let Some(citations) = result.citations {
+        writeln!(out, "  {}", lang.message(Message::Citations, &[&citations]))?;
+    }
+
+    if let Some(readability) = &result.readability {
+        let score = |score: Option<f64>| score.map_or("-".to_string(), |s| format!("{:.1}", s));
+        writeln!(
+            out,
+            "  {}",
+            lang.message(
+                Message::Readability,
+                &[
+                    &score(readability.grade_level()),
+                    &score(readability.reading_ease()),
+                    &readability.sentences
+                ]
+            )
+        )?;
+    }
+
+    if verbosity >= Verbosity::Internals {
+        let message = match result.had_bom {
+            true => Message::EncodingBom,
+            false => Message::Encoding,
+        };
+        writeln!(out, "  {}", lang.message(message, &[&result.encoding]))?;
+    }
+
+    if let Some(regions) = &result.regions {
+        writeln!(
+            out,
+            "  {}",
+            lang.message(
+                Message::Regions,
+                &[
+                    &regions.footnotes,
+                    &regions.captions,
+                    &regions.tables,
+                    &regions.quotes
+                ]
+            )
+        )?;
+    }
+
+    if let Some(progress) = file_target(options).map(|t| t.progress(result.total_words)) {
+        writeln!(out, "  {}", target_line(lang, progress))?;
+    }
+
+    if let Some(sidecar) = line_index(path, result) {
+        writeln!(
+            out,
+            "  {}",
+            lang.message(Message::LineIndex, &[&sidecar.display()])
+        )?;
+    }
+
+    if let Some(words) = top_words(result, options.top_words) {
+        let words: Vec<String> = words
+            .iter()
+            .map(|(word, count)| format!("{} ({})", word, count))
+            .collect();
+        writeln!(
+            out,
+            "  {}",
+            lang.message(Message::TopWords, &[&words.join(", ")])
+        )?;
+    }
+
+    Ok(())
+}
+
+// Totals and corpus-wide figures closing the text output
+fn write_text_footer(
+    out: &mut dyn Write,
+    report: &CorpusReport,
+    options: &OutputOptions,
+    totals: Totals,
+) -> Result<(), CliError> {
+    let lang = options.lang;
+    match options.plain {
+        true => writeln!(out, "{}.", totals.line(lang))?,
+        false => writeln!(out, "\n{}", totals.line(lang))?,
+    }
+
+    if let Some(unique) = report.unique_words {
+        if options.verbosity >= Verbosity::Totals {
+            writeln!(
+                out,
+                "\n{}",
+                lang.message(Message::CorpusUniqueWords, &[&unique])
+            )?;
+        }
+    }
+
+    if let Some(target) = corpus_target(options) {
+        let progress = target.progress(totals.words);
+        writeln!(out, "\n{}", target_line(lang, progress))?;
+    }
+
+    if let Some(estimate) = &report.estimate {
+        writeln!(
+            out,
+            "\n{}",
+            lang.message(
+                Message::Estimated,
+                &[&estimate.sampled, &estimate.population]
+            )
+        )?;
+        for (message, value) in [
+            (Message::EstimatedWords, &estimate.total_words),
+            (Message::EstimatedLines, &estimate.total_lines),
+        ] {
+            // A single sampled file leaves the interval unbounded
+            let upper = match value.upper() {
+                upper if upper.is_finite() => format!("{:.0}", upper),
+                _ => "∞".to_string(),
+            };
+            let (value, lower) = (
+                format!("{:.0}", value.value),
+                format!("{:.0}", value.lower()),
+            );
+            writeln!(
+                out,
+                "  {}",
+                lang.message(message, &[&value, &lower, &upper])
+            )?;
+        }
+    }
+
+    if let Some(stats) = &report.stats {
+        let decimal = |value: f64| format!("{:.1}", value);
+        writeln!(
+            out,
+            "\n{}",
+            lang.message(
+                Message::CorpusStatistics,
+                &[&stats.files, &stats.total_words]
+            )
+        )?;
+        writeln!(
+            out,
+            "  {}",
+            lang.message(
+                Message::WordsPerFile,
+                &[&decimal(stats.mean), &decimal(stats.stddev)]
+            )
+        )?;
+        writeln!(
+            out,
+            "  {}",
+            lang.message(
+                Message::Percentiles,
+                &[
+                    &stats.min,
+                    &decimal(stats.p25),
+                    &decimal(stats.median),
+                    &decimal(stats.p75),
+                    &decimal(stats.p90),
+                    &decimal(stats.p99),
+                    &stats.max
+                ]
+            )
+        )?;
+        writeln!(
+            out,
+            "  {}",
+            lang.message(Message::Gini, &[&format!("{:.3}", stats.gini)])
+        )?;
+    }
+
+    if let Some(fit) = &report.zipf {
+        let fixed = |value: f64| format!("{:.3}", value);
+        writeln!(
+            out,
+            "\n{}",
+            lang.message(
+                Message::Zipf,
+                &[
+                    &fixed(fit.slope),
+                    &fixed(fit.intercept),
+                    &fixed(fit.r_squared)
+                ]
+            )
+        )?;
+    }
+
+    if let Some(words) = &report.top_words {
+        writeln!(
+            out,
+            "\n{}",
+            lang.message(Message::CorpusTopWords, &[&words.len()])
+        )?;
+        for (word, count) in words {
+            writeln!(out, "  {}: {}", word, count)?;
+        }
+    }
+
+    if !report.warnings.is_empty() {
+        writeln!(out, "\n{}", lang.message(Message::Warnings, &[]))?;
+        for warning in &report.warnings {
+            writeln!(out, "  {}", warning_line(lang, warning))?;
+        }
+    }
+
+    if let Some(summary) = &report.pattern {
+        writeln!(
+            out,
+            "\n{}",
+            lang.message(
+                Message::PatternMatches,
+                &[
+                    &summary.pattern,
+                    &summary.matches,
+                    &summary.lines,
+                    &summary.files
+                ]
+            )
+        )?;
+    }
+
+    if let Some(elapsed) = report.elapsed.filter(|_| options.metadata.is_some()) {
+        writeln!(
+            out,
+            "\n{}",
+            lang.message(Message::RunElapsed, &[&elapsed_ms(elapsed)])
+        )?;
+    }
+
+    Ok(())
+}
+
+// Warning about a path, as a line naming it
+fn warning_line(lang: Lang, warning: &Warning) -> String {
+    let path = warning.path().display();
+    match warning {
+        Warning::Skipped { reason, .. } => lang.message(Message::Skipped, &[&path, reason]),
+        Warning::EncodingFallback { encoding, .. } => {
+            lang.message(Message::EncodingFallback, &[&path, encoding])
+        }
+        Warning::Truncated { .. } => lang.message(Message::Truncated, &[&path]),
+        Warning::SymlinkNotFollowed { .. } => lang.message(Message::SymlinkNotFollowed, &[&path]),
+    }
+}
+
+// Progress towards a target, as a labelled line
+fn target_line(lang: Lang, progress: TargetProgress) -> String {
+    let percent = format!("{:.1}", progress.percent());
+    let progress = lang.message(
+        Message::TargetProgress,
+        &[&percent, &progress.target, &progress.remaining()],
+    );
+    lang.message(Message::Target, &[&progress])
+}
+
+// Words per line and longest line of a file
+fn line_stats_line(lang: Lang, stats: &LineStats) -> String {
+    lang.message(
+        Message::LineStats,
+        &[
+            &format!("{:.1}", stats.mean),
+            &format!("{:.1}", stats.stddev),
+            &stats.min,
+            &stats.max,
+            &stats.longest_line,
+        ],
+    )
+}
+
+// JSON result of a file, with more figures the higher the verbosity
+fn file_result(path: &Path, result: &FileProcessingResult, options: &OutputOptions) -> FileResult {
+    let top_words = top_words(result, options.top_words).map(|words| {
+        words
+            .into_iter()
+            .map(|(word, count)| WordCount {
+                word: word.to_string(),
+                count,
+            })
+            .collect()
+    });
+    let totals = options.verbosity >= Verbosity::Totals;
+    let internals = options.verbosity >= Verbosity::Internals;
+
+    FileResult {
+        line_counts: result.line_counts.clone(),
+        total_words: totals.then_some(result.total_words),
+        total_chars: totals.then_some(result.total_chars),
+        total_bytes: totals.then_some(result.total_bytes),
+        line_count: totals.then_some(result.line_count),
+        unique_words: result.unique_words.filter(|_| totals),
+        line_stats: totals.then(|| LineStatsResult::from(&result.line_stats)),
+        elapsed_ms: (options.verbosity >= Verbosity::Details).then(|| elapsed_ms(result.elapsed)),
+        score: result.score,
+        citations: result.citations,
+        readability: result.readability.as_ref().map(ReadabilityResult::from),
+        regions: result.regions.as_ref().map(RegionsResult::from),
+        partial: result.partial,
+        encoding: internals.then(|| result.encoding.to_string()),
+        had_bom: internals && result.had_bom,
+        skipped: skip_reason(result),
+        line_index: line_index(path, result),
+        top_words,
+    }
+}
+
+// JSON document, buffered until the end since it holds all the files,
+// with more figures the higher the verbosity
+struct JsonSink {
+    options: OutputOptions,
+    names: DisplayNames,
+    files: OrderedFiles,
+    totals: Totals,
+}
+
+impl ReportSink for JsonSink {
+    fn begin(&mut self, _out: &mut dyn Write) -> Result<(), CliError> {
+        debug!("Formatting as JSON");
+        Ok(())
+    }
+
+    fn add_file(
+        &mut self,
+        _out: &mut dyn Write,
+        path: &Path,
+        result: &FileProcessingResult,
+    ) -> Result<(), CliError> {
+        self.totals.add(result);
+        if self.options.summary_only {
+            return Ok(());
+        }
+        let name = self.names.name(path);
+
+        debug!(
+            "Processing file {} with total_words={}",
+            name, result.total_words
+        );
+
+        let file = file_result(path, result, &self.options);
+        self.files.0.push((name, file));
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut dyn Write, report: &CorpusReport) -> Result<(), CliError> {
+        let estimate = report.estimate.as_ref().map(|estimate| SampleResult {
+            population: estimate.population,
+            sampled: estimate.sampled,
+            total_words: (&estimate.total_words).into(),
+            total_lines: (&estimate.total_lines).into(),
+        });
+
+        let metadata = self
+            .options
+            .metadata
+            .as_ref()
+            .map(|metadata| MetadataResult {
+                elapsed_ms: report.elapsed.map(elapsed_ms),
+                ..MetadataResult::new(metadata)
+            });
+
+        let output = OutputResult {
+            metadata,
+            files: (!self.options.summary_only).then(|| std::mem::take(&mut self.files)),
+            summary: SummaryResult::from(&self.totals),
+            estimate,
+            stats: report.stats.as_ref().map(StatsResult::from),
+            zipf: report.zipf.as_ref().map(ZipfResult::from),
+            top_words: report.top_words.as_ref().map(|words| {
+                words
+                    .iter()
+                    .map(|(word, count)| WordCount {
+                        word: word.clone(),
+                        count: *count,
+                    })
+                    .collect()
+            }),
+            pattern: report.pattern.as_ref().map(PatternResult::from),
+            unique_words: report
+                .unique_words
+                .filter(|_| self.options.verbosity >= Verbosity::Totals),
+            warnings: report.warnings.iter().map(WarningResult::from).collect(),
+        };
+        writeln!(out, "{}", serde_json::to_string_pretty(&output)?)?;
+        Ok(())
+    }
+}
+
+// CSV rows, one per file, with more columns the higher the verbosity, the
+// per-line counts as a quoted list
+struct CsvSink {
+    options: OutputOptions,
+    totals: Totals,
+}
+
+impl ReportSink for CsvSink {
+    fn begin(&mut self, out: &mut dyn Write) -> Result<(), CliError> {
+        debug!("Formatting as CSV");
+        if let Some(metadata) = &self.options.metadata {
+            writeln!(
+                out,
+                "# mfp {}, run at {} on {}",
+                metadata.version,
+                metadata.timestamp(),
+                metadata.host
+            )?;
+            writeln!(out, "# options: {}", metadata.options_line())?;
+        }
+        if self.options.skip_header {
+            return Ok(());
+        }
+        if self.options.summary_only {
+            writeln!(out, "files,lines,words,mean_words")?;
+            return Ok(());
+        }
+        let verbosity = self.options.verbosity;
+        let mut header = String::from("path,line_count,total_words");
+        if verbosity >= Verbosity::Totals {
+            header.push_str(",total_chars,total_bytes,unique_words");
+        }
+        if verbosity >= Verbosity::Details {
+            header.push_str(",line_counts,elapsed_ms");
+        }
+        if verbosity >= Verbosity::Internals {
+            header.push_str(",encoding,had_bom");
+        }
+        writeln!(out, "{}", header)?;
+        Ok(())
+    }
+
+    fn add_file(
+        &mut self,
+        out: &mut dyn Write,
+        path: &Path,
+        result: &FileProcessingResult,
+    ) -> Result<(), CliError> {
+        self.totals.add(result);
+        if self.options.summary_only {
+            return Ok(());
+        }
+        let verbosity = self.options.verbosity;
+        let path = csv_field(&path.to_string_lossy());
+        write!(out, "{},{},{}", path, result.line_count, result.total_words)?;
+        if verbosity >= Verbosity::Totals {
+            let unique = result.unique_words.map_or(String::new(), |n| n.to_string());
+            write!(
+                out,
+                ",{},{},{}",
+                result.total_chars, result.total_bytes, unique
+            )?;
+        }
+        if verbosity >= Verbosity::Details {
+            write!(
+                out,
+                ",\"{:?}\",{}",
+                result.line_counts,
+                elapsed_ms(result.elapsed)
+            )?;
+        }
+        if verbosity >= Verbosity::Internals {
+            write!(out, ",{},{}", result.encoding, result.had_bom)?;
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut dyn Write, report: &CorpusReport) -> Result<(), CliError> {
+        let totals = &self.totals;
+        if self.options.summary_only {
+            writeln!(
+                out,
+                "{},{},{},{}",
+                totals.files,
+                totals.lines,
+                totals.words,
+                totals.mean_words()
+            )?;
+        }
+        if let Some(elapsed) = report.elapsed.filter(|_| self.options.metadata.is_some()) {
+            writeln!(out, "# elapsed_ms: {}", elapsed_ms(elapsed))?;
+        }
+        Ok(())
+    }
+}
+
+// NDJSON lines, one per file, then a summary record - other corpus-wide figures
+// have no place in them
+struct NdjsonSink {
+    options: OutputOptions,
+    totals: Totals,
+}
+
+impl ReportSink for NdjsonSink {
+    fn begin(&mut self, out: &mut dyn Write) -> Result<(), CliError> {
+        debug!("Formatting as NDJSON");
+        if let Some(metadata) = &self.options.metadata {
+            let record = NdjsonMetadata {
+                metadata: MetadataResult::new(metadata),
+            };
+            writeln!(out, "{}", serde_json::to_string(&record)?)?;
+        }
+        Ok(())
+    }
+
+    fn add_file(
+        &mut self,
+        out: &mut dyn Write,
+        path: &Path,
+        result: &FileProcessingResult,
+    ) -> Result<(), CliError> {
+        self.totals.add(result);
+        if self.options.summary_only {
+            return Ok(());
+        }
+        write_ndjson_line(out, path, result, &self.options)
+    }
+
+    fn finish(&mut self, out: &mut dyn Write, report: &CorpusReport) -> Result<(), CliError> {
+        let record = NdjsonSummary {
+            summary: SummaryResult::from(&self.totals),
+        };
+        writeln!(out, "{}", serde_json::to_string(&record)?)?;
+        if let Some(elapsed) = report.elapsed.filter(|_| self.options.metadata.is_some()) {
+            let record = NdjsonMetadata {
+                metadata: MetadataResult::elapsed(elapsed),
+            };
+            writeln!(out, "{}", serde_json::to_string(&record)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the result of a file as a single JSON line, e.g. as soon as it is processed
+pub fn write_ndjson_line(
+    out: &mut dyn Write,
+    path: &Path,
+    result: &FileProcessingResult,
+    options: &OutputOptions,
+) -> Result<(), CliError> {
+    let record = NdjsonRecord {
+        path: path.to_string_lossy().to_string(),
+        result: file_result(path, result, options),
+    };
+    writeln!(out, "{}", serde_json::to_string(&record)?)?;
+    Ok(())
+}
+
+// GitHub-flavored Markdown table, one row per file and a total row, with more
+// columns the higher the verbosity and progress columns when each file has a target
+struct MarkdownSink {
+    options: OutputOptions,
+    columns: Vec<Message>,
+    totals: Totals,
+    total_chars: usize,
+    total_bytes: u64,
+}
+
+impl MarkdownSink {
+    fn new(options: OutputOptions) -> Self {
+        let verbosity = options.verbosity;
+        let mut columns = vec![Message::File, Message::Lines, Message::Words];
+        if verbosity >= Verbosity::Totals {
+            columns.extend([Message::Characters, Message::Bytes, Message::Unique]);
+        }
+        if verbosity >= Verbosity::Details {
+            columns.extend([Message::WordsPerLine, Message::Milliseconds]);
+        }
+        if verbosity >= Verbosity::Internals {
+            columns.push(Message::TextEncoding);
+        }
+        if file_target(&options).is_some() {
+            columns.extend([Message::Progress, Message::Remaining]);
+        }
+        Self {
+            options,
+            columns,
+            totals: Totals::default(),
+            total_chars: 0,
+            total_bytes: 0,
+        }
+    }
+}
+
+impl ReportSink for MarkdownSink {
+    fn begin(&mut self, out: &mut dyn Write) -> Result<(), CliError> {
+        debug!("Formatting as Markdown");
+        let lang = self.options.lang;
+        if let Some(metadata) = &self.options.metadata {
+            writeln!(
+                out,
+                "<!-- mfp {}, run at {} on {}; options: {} -->\n",
+                metadata.version,
+                metadata.timestamp(),
+                metadata.host,
+                metadata.options_line()
+            )?;
+        }
+        let header: Vec<String> = self.columns.iter().map(|&c| lang.message(c, &[])).collect();
+        write_markdown_row(out, &header)?;
+        // Figures are right-aligned, names and lists left-aligned
+        let alignments: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| match column {
+                Message::File | Message::WordsPerLine | Message::TextEncoding => "---",
+                _ => "---:",
+            })
+            .map(String::from)
+            .collect();
+        write_markdown_row(out, &alignments)
+    }
+
+    fn add_file(
+        &mut self,
+        out: &mut dyn Write,
+        path: &Path,
+        result: &FileProcessingResult,
+    ) -> Result<(), CliError> {
+        self.totals.add(result);
+        self.total_chars += result.total_chars;
+        self.total_bytes += result.total_bytes;
+        if self.options.summary_only {
+            return Ok(());
+        }
+
+        let verbosity = self.options.verbosity;
+        let mut row = vec![
+            path.to_string_lossy().replace('|', "\\|"),
+            result.line_count.to_string(),
+            result.total_words.to_string(),
+        ];
+        if verbosity >= Verbosity::Totals {
+            row.extend([
+                result.total_chars.to_string(),
+                result.total_bytes.to_string(),
+                result.unique_words.map_or(String::new(), |n| n.to_string()),
+            ]);
+        }
+        if verbosity >= Verbosity::Details {
+            let line_counts: Vec<String> =
+                result.line_counts.iter().map(usize::to_string).collect();
+            row.extend([
+                line_counts.join(", "),
+                elapsed_ms(result.elapsed).to_string(),
+            ]);
+        }
+        if verbosity >= Verbosity::Internals {
+            row.push(result.encoding.to_string());
+        }
+        if let Some(target) = file_target(&self.options) {
+            let progress = target.progress(result.total_words);
+            row.extend([
+                format!("{:.1}%", progress.percent()),
+                progress.remaining().to_string(),
+            ]);
+        }
+        write_markdown_row(out, &row)
+    }
+
+    fn finish(&mut self, out: &mut dyn Write, report: &CorpusReport) -> Result<(), CliError> {
+        let lang = self.options.lang;
+        let bold = |total: String| format!("**{}**", total);
+        let mut total = vec![
+            bold(lang.message(Message::Total, &[])),
+            bold(self.totals.lines.to_string()),
+            bold(self.totals.words.to_string()),
+        ];
+        if self.options.verbosity >= Verbosity::Totals {
+            total.extend([
+                bold(self.total_chars.to_string()),
+                bold(self.total_bytes.to_string()),
+                report
+                    .unique_words
+                    .map_or(String::new(), |n| bold(n.to_string())),
+            ]);
+        }
+        total.resize(self.columns.len(), String::new());
+        write_markdown_row(out, &total)?;
+        writeln!(out, "\n{}", self.totals.mean_line(lang))?;
+
+        if let Some(elapsed) = report.elapsed.filter(|_| self.options.metadata.is_some()) {
+            writeln!(out, "\n<!-- elapsed_ms: {} -->", elapsed_ms(elapsed))?;
+        }
+
+        if let Some(target) = corpus_target(&self.options) {
+            let progress = target.progress(self.totals.words);
+            writeln!(out, "\n{}", target_line(lang, progress))?;
+        }
+        Ok(())
+    }
+}
+
+// Writes a row of a Markdown table, empty cells left blank
+fn write_markdown_row(out: &mut dyn Write, cells: &[String]) -> Result<(), CliError> {
+    write!(out, "|")?;
+    for cell in cells {
+        match cell.is_empty() {
+            true => write!(out, " |")?,
+            false => write!(out, " {} |", cell)?,
+        }
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+// Patched front matter of Markdown files, or the files updated in place
+// Text and JSON output of mfp 0.1, for parsers yet to move to the current
+// layout: files are named without their directory and only carry their
+// per-line counts, along with their total words when verbose
+struct V1Sink {
+    json: bool,
+    verbose: bool,
+    files: BTreeMap<String, V1FileResult>,
+}
+
+#[derive(serde::Serialize)]
+struct V1FileResult {
+    line_counts: Vec<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_words: Option<usize>,
+}
+
+impl ReportSink for V1Sink {
+    fn begin(&mut self, out: &mut dyn Write) -> Result<(), CliError> {
+        debug!("Formatting in the v1 layout");
+        if !self.json {
+            writeln!(out, "\nProcessing Results:")?;
+            writeln!(out, "------------------")?;
+        }
+        Ok(())
+    }
+
+    fn add_file(
+        &mut self,
+        out: &mut dyn Write,
+        path: &Path,
+        result: &FileProcessingResult,
+    ) -> Result<(), CliError> {
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        match (self.json, self.verbose) {
+            (true, _) => {
+                self.files.insert(
+                    name,
+                    V1FileResult {
+                        line_counts: result.line_counts.clone(),
+                        total_words: self.verbose.then_some(result.total_words),
+                    },
+                );
+            }
+            (false, true) => writeln!(
+                out,
+                "{}: {} words in total\n  Line counts: {:?}",
+                name, result.total_words, result.line_counts
+            )?,
+            (false, false) => writeln!(out, "{}: {:?}", name, result.line_counts)?,
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut dyn Write, _report: &CorpusReport) -> Result<(), CliError> {
+        if self.json {
+            let output = json!({ "files": std::mem::take(&mut self.files) });
+            writeln!(out, "{}", serde_json::to_string_pretty(&output)?)?;
+        }
+        Ok(())
+    }
+}
+
+struct FrontmatterSink {
+    write: bool,
+}
+
+impl ReportSink for FrontmatterSink {
+    fn begin(&mut self, _out: &mut dyn Write) -> Result<(), CliError> {
+        debug!("Formatting as front matter patches");
+        Ok(())
+    }
+
+    fn add_file(
+        &mut self,
+        out: &mut dyn Write,
+        path: &Path,
+        result: &FileProcessingResult,
+    ) -> Result<(), CliError> {
+        if !frontmatter::is_markdown(path) {
+            debug!("Skipping non Markdown file {:?}", path);
+            return Ok(());
+        }
+
+        let io_error = |e: std::io::Error| {
+            CliError::FormatError(format!("Failed to patch {}: {}", path.display(), e))
+        };
+        let text = fs::read_to_string(path).map_err(io_error)?;
+        let body_words = frontmatter::body_words(&text, &result.line_counts);
+        let (patched, block) =
+            frontmatter::patch(&text, body_words, frontmatter::reading_time(body_words));
+
+        if self.write {
+            if patched != text {
+                fs::write(path, patched).map_err(io_error)?;
+            }
+            writeln!(out, "Updated {}: {} words", path.display(), body_words)?;
+        } else {
+            writeln!(out, "# {}\n{}\n", path.display(), block)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _out: &mut dyn Write, _report: &CorpusReport) -> Result<(), CliError> {
+        Ok(())
+    }
+}
+
+/// Writes the formatted results to a file, after its current content when appending.
+/// The content goes to a temporary file next to it first, renamed over it once complete.
+pub fn write_output_file(path: &Path, output: &[u8], append: bool) -> Result<(), CliError> {
+    let io_error = |e: std::io::Error| {
+        CliError::FormatError(format!("Failed to write {}: {}", path.display(), e))
+    };
+
+    let mut content = match fs::read(path) {
+        Ok(content) if append => content,
+        Err(e) if append && e.kind() != ErrorKind::NotFound => return Err(io_error(e)),
+        _ => Vec::new(),
+    };
+    content.extend_from_slice(output);
+
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", process::id()));
+    let temp = path.with_file_name(name);
+    fs::write(&temp, content)
+        .and_then(|_| fs::rename(&temp, path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temp);
+            io_error(e)
+        })
+}
+
+/// Writes the rank-frequency curve of the corpus as CSV, one word per row, from the
+/// words by decreasing frequency as read in memory or from spilled runs
+pub fn write_rank_frequency_csv<W: AsRef<str>>(
+    path: &Path,
+    ranked: impl IntoIterator<Item = io::Result<(W, usize)>>,
+) -> Result<(), CliError> {
+    let write = || -> io::Result<()> {
+        let mut csv = BufWriter::new(fs::File::create(path)?);
+        writeln!(csv, "rank,word,frequency")?;
+        for (rank, entry) in ranked.into_iter().enumerate() {
+            let (word, count) = entry?;
+            writeln!(csv, "{},{},{}", rank + 1, csv_field(word.as_ref()), count)?;
+        }
+        csv.flush()
+    };
+
+    write().map_err(|e| CliError::FormatError(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// Quotes a CSV field when needed - Unicode segmentation keeps separators
+/// such as the comma of "1,000" within words
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mfp_lib::{Encoding, TextProcessor};
+    use std::env;
+
+    /// Fixed corpus the output snapshots are taken over
+    const SNAPSHOT_CORPUS: &str = "tests/corpus";
+    /// Golden files of the outputs, one per format and verbosity
+    const SNAPSHOT_DIR: &str = "tests/snapshots";
+
+    // Compares an output with its golden file, or rewrites the file when
+    // MFP_BLESS is set
+    fn assert_snapshot(name: &str, output: &str) {
+        let path = Path::new(SNAPSHOT_DIR).join(format!("{name}.snap"));
+        if env::var_os("MFP_BLESS").is_some() {
+            fs::create_dir_all(SNAPSHOT_DIR).unwrap();
+            fs::write(&path, output).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "Missing snapshot {}, run with MFP_BLESS=1 to create it",
+                path.display()
+            )
+        });
+        assert!(
+            expected == output,
+            "Output differs from {}, run with MFP_BLESS=1 to update it if intended\n\
+             --- expected\n{expected}\n--- actual\n{output}",
+            path.display()
+        );
+    }
+
+    // Test every format and verbosity against its golden file
+    #[tokio::test]
+    async fn test_output_snapshots() {
+        let paths = ["chapter.md", "notes.txt", "unicode.txt", "empty.txt"]
+            .map(|name| Path::new(SNAPSHOT_CORPUS).join(name))
+            .to_vec();
+        let mut processor = TextProcessor::new().with_unique_words(true);
+        processor.process_files(paths).await.unwrap();
+        let report = CorpusReport {
+            unique_words: Some(processor.get_unique_words()),
+            ..Default::default()
+        };
+        // Timings vary from run to run
+        let mut results = processor.get_results().clone();
+        results
+            .values_mut()
+            .for_each(|result| result.elapsed = Duration::ZERO);
+
+        for format in OutputFormat::value_variants() {
+            for level in 0..=3 {
+                let options = OutputOptions {
+                    verbosity: Verbosity::from(level),
+                    ..Default::default()
+                };
+                let mut out = Vec::new();
+                format_output(&mut out, &results, &report, format.clone(), &options).unwrap();
+
+                let format_name = format.to_possible_value().unwrap();
+                let name = match level {
+                    0 => format_name.get_name().to_string(),
+                    _ => format!("{}-{}", format_name.get_name(), "v".repeat(level.into())),
+                };
+                assert_snapshot(&name, &String::from_utf8(out).unwrap());
+            }
+        }
+    }
+
+    // Test streaming formats write each file as it is added, the others at the end
+    #[test]
+    fn test_report_sink() {
+        let result = {
+            let mut result = FileProcessingResult::default();
+            result.line_counts = vec![2, 3];
+            result.total_words = 5;
+            result.line_count = 2;
+            result
+        };
+        let options = OutputOptions::default();
+
+        let mut out = Vec::new();
+        let mut sink = report_sink(&OutputFormat::Csv, &options, &[]);
+        sink.begin(&mut out).unwrap();
+        sink.add_file(&mut out, Path::new("a.txt"), &result)
+            .unwrap();
+        assert_eq!(out, b"path,line_count,total_words\na.txt,2,5\n");
+        sink.finish(&mut out, &CorpusReport::default()).unwrap();
+
+        let mut out = Vec::new();
+        let mut sink = report_sink(&OutputFormat::Json, &options, &[]);
+        sink.begin(&mut out).unwrap();
+        sink.add_file(&mut out, Path::new("a.txt"), &result)
+            .unwrap();
+        assert!(out.is_empty());
+        sink.finish(&mut out, &CorpusReport::default()).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("\"a.txt\""));
+
+        assert!(OutputFormat::Text.streams(false));
+        assert!(!OutputFormat::Text.streams(true));
+        assert!(!OutputFormat::Json.streams(false));
+    }
+
+    // Test files sharing a name are told apart by their shortest distinct path suffix
+    #[test]
+    fn test_display_names() {
+        let paths: Vec<PathBuf> = [
+            "docs/a/notes.txt",
+            "docs/b/notes.txt",
+            "x/b/notes.txt",
+            "notes.txt",
+            "docs/readme.md",
+        ]
+        .map(PathBuf::from)
+        .to_vec();
+        let mut names = DisplayNames::new(&paths);
+        let shown: Vec<String> = paths.iter().map(|path| names.name(path)).collect();
+        assert_eq!(
+            shown,
+            [
+                "a/notes.txt",
+                "docs/b/notes.txt",
+                "x/b/notes.txt",
+                "notes.txt",
+                "readme.md"
+            ]
+        );
+
+        // Files unknown upfront are told apart from those seen before them
+        let mut names = DisplayNames::new(&[]);
+        assert_eq!(names.name(Path::new("a/notes.txt")), "notes.txt");
+        assert_eq!(names.name(Path::new("b/notes.txt")), "b/notes.txt");
+    }
+
+    // Test colliding names all make it to the text and JSON outputs
+    #[test]
+    fn test_colliding_names() {
+        let result = |words| {
+            let mut result = FileProcessingResult::default();
+            result.line_counts = vec![words];
+            result.total_words = words;
+            result.line_count = 1;
+            result
+        };
+        let results = HashMap::from([
+            (PathBuf::from("a/notes.txt"), result(1)),
+            (PathBuf::from("b/notes.txt"), result(2)),
+        ]);
+        let output = |format| {
+            let mut out = Vec::new();
+            format_output(
+                &mut out,
+                &results,
+                &CorpusReport::default(),
+                format,
+                &OutputOptions::default(),
+            )
+            .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let text = output(OutputFormat::Text);
+        assert!(text.contains("a/notes.txt: [1]\nb/notes.txt: [2]"));
+        let json: serde_json::Value = serde_json::from_str(&output(OutputFormat::Json)).unwrap();
+        assert_eq!(json["files"]["a/notes.txt"]["line_counts"][0], 1);
+        assert_eq!(json["files"]["b/notes.txt"]["line_counts"][0], 2);
+    }
+
+    // Test warnings get a section of their own in text and an array in JSON
+    #[test]
+    fn test_warnings() {
+        let results = HashMap::from([(PathBuf::from("a.txt"), FileProcessingResult::default())]);
+        let report = CorpusReport {
+            warnings: vec![
+                Warning::EncodingFallback {
+                    path: PathBuf::from("a.txt"),
+                    encoding: Encoding::Windows1252,
+                },
+                Warning::SymlinkNotFollowed {
+                    path: PathBuf::from("docs/link"),
+                },
+            ],
+            ..Default::default()
+        };
+        let output = |format| {
+            let mut out = Vec::new();
+            format_output(
+                &mut out,
+                &results,
+                &report,
+                format,
+                &OutputOptions::default(),
+            )
+            .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let text = output(OutputFormat::Text);
+        assert!(text.ends_with(
+            "\nWarnings:\n  a.txt: not valid UTF-8, decoded as windows-1252\n  \
+             docs/link: symlink not followed\n"
+        ));
+        let json: serde_json::Value = serde_json::from_str(&output(OutputFormat::Json)).unwrap();
+        assert_eq!(json["warnings"][0]["kind"], "encoding_fallback");
+        assert_eq!(json["warnings"][0]["encoding"], "windows-1252");
+        assert_eq!(json["warnings"][1]["path"], "docs/link");
+        assert_eq!(json["warnings"][1]["kind"], "symlink_not_followed");
+    }
+
+    // Test the run metadata heads every format but frontmatter patches, the elapsed time ending it
+    #[test]
+    fn test_run_metadata() {
+        let results = HashMap::from([(PathBuf::from("a.txt"), FileProcessingResult::default())]);
+        let report = CorpusReport {
+            elapsed: Some(Duration::from_millis(250)),
+            ..Default::default()
+        };
+        let options = OutputOptions {
+            metadata: Some(RunMetadata {
+                version: "1.2.3".to_string(),
+                started: 1_714_555_800,
+                host: "box".to_string(),
+                options: vec![("jobs".to_string(), "4".to_string())],
+            }),
+            ..Default::default()
+        };
+        let output = |format| {
+            let mut out = Vec::new();
+            format_output(&mut out, &results, &report, format, &options).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let text = output(OutputFormat::Text);
+        assert!(
+            text.starts_with("mfp 1.2.3, run at 2024-05-01T09:30:00Z on box\nOptions: jobs=4\n")
+        );
+        assert!(text.ends_with("\nRun took 250 ms\n"));
+        let csv = output(OutputFormat::Csv);
+        assert!(csv.starts_with(
+            "# mfp 1.2.3, run at 2024-05-01T09:30:00Z on box\n# options: jobs=4\npath,"
+        ));
+        assert!(csv.ends_with("# elapsed_ms: 250\n"));
+        let json: serde_json::Value = serde_json::from_str(&output(OutputFormat::Json)).unwrap();
+        assert_eq!(json["metadata"]["host"], "box");
+        assert_eq!(json["metadata"]["options"]["jobs"], "4");
+        assert_eq!(json["metadata"]["elapsed_ms"], 250.0);
+        let ndjson = output(OutputFormat::Ndjson);
+        let lines: Vec<&str> = ndjson.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("{\"metadata\":{\"version\":\"1.2.3\""));
+        assert_eq!(lines[3], "{\"metadata\":{\"elapsed_ms\":250.0}}");
+        assert!(output(OutputFormat::Markdown).starts_with("<!-- mfp 1.2.3"));
+    }
+
+    // Test the files are listed in the order of the options, and streamed ones in path order
+    #[test]
+    fn test_sort_order() {
+        let result = |total_words, line_count| {
+            let mut result = FileProcessingResult::default();
+            result.total_words = total_words;
+            result.line_count = line_count;
+            result
+        };
+        let results = HashMap::from([
+            (PathBuf::from("a.txt"), result(5, 3)),
+            (PathBuf::from("b.txt"), result(9, 1)),
+            (PathBuf::from("c.txt"), result(5, 2)),
+        ]);
+        let order = |sort_by, reverse| {
+            let options = OutputOptions {
+                sort_by,
+                reverse,
+                ..Default::default()
+            };
+            let mut out = Vec::new();
+            let report = CorpusReport::default();
+            format_output(&mut out, &results, &report, OutputFormat::Json, &options).unwrap();
+            // Parsed JSON objects sort their keys, so the names are found in the text
+            let json = String::from_utf8(out).unwrap();
+            let mut names = ["a.txt", "b.txt", "c.txt"];
+            names.sort_by_key(|name| json.find(&format!("\"{name}\"")));
+            names.join(" ")
+        };
+        assert_eq!(order(SortKey::Name, false), "a.txt b.txt c.txt");
+        assert_eq!(order(SortKey::Words, false), "a.txt c.txt b.txt");
+        assert_eq!(order(SortKey::Words, true), "b.txt c.txt a.txt");
+        assert_eq!(order(SortKey::Lines, false), "b.txt c.txt a.txt");
+
+        let paths: Vec<PathBuf> = ["a.txt", "b.txt", "c.txt"].map(PathBuf::from).into();
+        let options = OutputOptions::default();
+        let mut sink = streaming_sink(&OutputFormat::Csv, &options, &paths);
+        let mut out = Vec::new();
+        sink.begin(&mut out).unwrap();
+        for name in ["c.txt", "-", "a.txt", "b.txt"] {
+            let path = Path::new(name);
+            let result = results.get(path).cloned().unwrap_or_default();
+            sink.add_file(&mut out, path, &result).unwrap();
+            if name == "a.txt" {
+                // a.txt was next, c.txt still waits for b.txt
+                let written = String::from_utf8_lossy(&out).into_owned();
+                assert!(written.ends_with("-,0,0\na.txt,3,5\n"));
+            }
+        }
+        sink.finish(&mut out, &CorpusReport::default()).unwrap();
+        let rows: Vec<&str> = std::str::from_utf8(&out).unwrap().lines().collect();
+        let paths: Vec<&str> = rows[1..5]
+            .iter()
+            .map(|row| &row[..row.find(',').unwrap()])
+            .collect();
+        assert_eq!(paths, ["-", "a.txt", "b.txt", "c.txt"]);
+    }
+
+    // Test files that failed are moved past, and archive entries wait for their archive
+    #[test]
+    fn test_streaming_sink_done() {
+        let paths: Vec<PathBuf> = ["a.txt", "b.txt", "c.zip"].map(PathBuf::from).into();
+        let result = |total_words| {
+            let mut result = FileProcessingResult::default();
+            result.total_words = total_words;
+            result
+        };
+        let options = OutputOptions::default();
+        let mut sink = streaming_sink(&OutputFormat::Csv, &options, &paths);
+        let mut out = Vec::new();
+        sink.begin(&mut out).unwrap();
+        sink.add_file(&mut out, Path::new("c.zip!/x.txt"), &result(3))
+            .unwrap();
+        sink.add_file(&mut out, Path::new("a.txt"), &result(1))
+            .unwrap();
+        sink.file_done(&mut out, Path::new("a.txt")).unwrap();
+        // b.txt failed, without a result
+        sink.file_done(&mut out, Path::new("b.txt")).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&out),
+            "path,line_count,total_words\na.txt,0,1\n"
+        );
+        sink.add_file(&mut out, Path::new("c.zip!/y.txt"), &result(2))
+            .unwrap();
+        sink.file_done(&mut out, Path::new("c.zip")).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&out),
+            "path,line_count,total_words\na.txt,0,1\nc.zip!/x.txt,0,3\nc.zip!/y.txt,0,2\n"
+        );
+    }
+
+    // Test CSV paths with commas, quotes or line breaks are quoted, and the per-line
+    // counts are a single quoted column
+    #[test]
+    fn test_csv_fields() {
+        let result = |line_counts: Vec<usize>| {
+            let mut result = FileProcessingResult::default();
+            result.line_count = line_counts.len();
+            result.total_words = line_counts.iter().sum();
+            result.line_counts = line_counts;
+            result
+        };
+        let results = HashMap::from([
+            (PathBuf::from("a,b.txt"), result(vec![2, 0, 1])),
+            (PathBuf::from("say \"hi\".txt"), result(vec![4])),
+            (PathBuf::from("two\nlines.txt"), result(vec![])),
+            (PathBuf::from("plain.txt"), result(vec![1])),
+        ]);
+        let output = |verbosity| {
+            let options = OutputOptions {
+                verbosity,
+                ..Default::default()
+            };
+            let mut out = Vec::new();
+            let report = CorpusReport::default();
+            format_output(&mut out, &results, &report, OutputFormat::Csv, &options).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(
+            output(Verbosity::Normal),
+            "path,line_count,total_words\n\
+             \"a,b.txt\",3,3\n\
+             plain.txt,1,1\n\
+             \"say \"\"hi\"\".txt\",1,4\n\
+             \"two\nlines.txt\",0,0\n"
+        );
+        let details = output(Verbosity::Details);
+        let rows: Vec<&str> = details.split_terminator('\n').collect();
+        assert_eq!(
+            rows[0],
+            "path,line_count,total_words,total_chars,total_bytes,unique_words,line_counts,elapsed_ms"
+        );
+        assert_eq!(rows[1], "\"a,b.txt\",3,3,0,0,,\"[2, 0, 1]\",0");
+        assert_eq!(rows[2], "plain.txt,1,1,0,0,,\"[1]\",0");
+    }
+
+    // Test pipes in Markdown paths are escaped, and the total and average rows
+    // sum up the table
+    #[test]
+    fn test_markdown_table() {
+        let result = |line_count, total_words| {
+            let mut result = FileProcessingResult::default();
+            result.line_count = line_count;
+            result.total_words = total_words;
+            result
+        };
+        let results = HashMap::from([
+            (PathBuf::from("a|b.txt"), result(2, 5)),
+            (PathBuf::from("c.txt"), result(1, 2)),
+        ]);
+        let mut out = Vec::new();
+        let report = CorpusReport::default();
+        let options = OutputOptions::default();
+        format_output(
+            &mut out,
+            &results,
+            &report,
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "| File | Lines | Words |\n\
+             | --- | ---: | ---: |\n\
+             | a\\|b.txt | 2 | 5 |\n\
+             | c.txt | 1 | 2 |\n\
+             | **Total** | **3** | **7** |\n\
+             \n\
+             2 files, 3.5 words per file on average\n"
+        );
+    }
+
+    // Test only the files within the word range are listed, streamed or not
+    #[test]
+    fn test_word_range() {
+        let result = |total_words| {
+            let mut result = FileProcessingResult::default();
+            result.total_words = total_words;
+            result
+        };
+        let results = HashMap::from([
+            (PathBuf::from("empty.txt"), result(0)),
+            (PathBuf::from("short.txt"), result(12)),
+            (PathBuf::from("long.txt"), result(5000)),
+        ]);
+        let options = OutputOptions {
+            min_words: Some(1),
+            max_words: Some(1000),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        let report = CorpusReport::default();
+        format_output(&mut out, &results, &report, OutputFormat::Csv, &options).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        assert_eq!(csv, "path,line_count,total_words\nshort.txt,0,12\n");
+
+        let paths: Vec<PathBuf> = results.keys().cloned().collect();
+        let mut sink = streaming_sink(&OutputFormat::Csv, &options, &paths);
+        let mut out = Vec::new();
+        sink.begin(&mut out).unwrap();
+        for (path, result) in &results {
+            sink.add_file(&mut out, path, result).unwrap();
+        }
+        sink.finish(&mut out, &report).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), csv);
+    }
+
+    // Test every format sums up the corpus, leaving out the files with --summary-only
+    #[test]
+    fn test_summary() {
+        let result = |line_counts: Vec<usize>| {
+            let mut result = FileProcessingResult::default();
+            result.line_count = line_counts.len();
+            result.total_words = line_counts.iter().sum();
+            result.line_counts = line_counts;
+            result
+        };
+        let results = HashMap::from([
+            (PathBuf::from("a.txt"), result(vec![3, 4])),
+            (PathBuf::from("b.txt"), result(vec![2])),
+        ]);
+        let output = |format, summary_only| {
+            let options = OutputOptions {
+                summary_only,
+                ..Default::default()
+            };
+            let mut out = Vec::new();
+            format_output(
+                &mut out,
+                &results,
+                &CorpusReport::default(),
+                format,
+                &options,
+            )
+            .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let summary = "Total: 2 files, 3 lines, 9 words, 4.5 words per file on average\n";
+        assert!(output(OutputFormat::Text, false).ends_with(summary));
+        let text = output(OutputFormat::Text, true);
+        assert!(!text.contains("a.txt") && text.ends_with(summary));
+        let json: serde_json::Value =
+            serde_json::from_str(&output(OutputFormat::Json, false)).unwrap();
+        assert_eq!(json["summary"]["words"], 9);
+        assert_eq!(json["summary"]["mean_words"], 4.5);
+        let json: serde_json::Value =
+            serde_json::from_str(&output(OutputFormat::Json, true)).unwrap();
+        assert!(json.get("files").is_none());
+        assert_eq!(json["summary"]["files"], 2);
+        // Only rows of the header's columns, for CSV readers to load
+        assert_eq!(
+            output(OutputFormat::Csv, false),
+            "path,line_count,total_words\na.txt,2,7\nb.txt,1,2\n"
+        );
+        assert_eq!(
+            output(OutputFormat::Csv, true),
+            "files,lines,words,mean_words\n2,3,9,4.5\n"
+        );
+        assert_eq!(
+            output(OutputFormat::Ndjson, true),
+            "{\"summary\":{\"files\":2,\"lines\":3,\"words\":9,\"mean_words\":4.5}}\n"
+        );
+        // The totals row once, then what it leaves out
+        let markdown = output(OutputFormat::Markdown, true);
+        assert!(!markdown.contains("a.txt"));
+        assert_eq!(markdown.matches("**Total**").count(), 1);
+        assert!(markdown.contains("| **Total** | **3** | **9** |"));
+        assert!(markdown.ends_with("\n2 files, 4.5 words per file on average\n"));
+
+        let mut totals = Totals::default();
+        totals.add(&result(vec![5]));
+        assert_eq!(
+            totals.line(Lang::En),
+            "Total: 1 file, 1 lines, 5 words, 5.0 words per file on average"
+        );
+    }
+    // Checks a JSON value against the parts of JSON Schema the report schema uses
+    fn assert_matches_schema(value: &serde_json::Value, schema: &serde_json::Value, at: &str) {
+        if let Some(object) = value.as_object() {
+            let properties = schema["properties"].as_object();
+            for (key, field) in object {
+                let field_schema = match properties.and_then(|properties| properties.get(key)) {
+                    Some(field_schema) => field_schema,
+                    None => {
+                        assert!(
+                            schema["additionalProperties"].is_object(),
+                            "{at}.{key} missing from the schema"
+                        );
+                        &schema["additionalProperties"]
+                    }
+                };
+                assert_matches_schema(field, field_schema, &format!("{at}.{key}"));
+            }
+            for required in schema["required"].as_array().into_iter().flatten() {
+                assert!(
+                    object.contains_key(required.as_str().unwrap()),
+                    "{at}.{required} required"
+                );
+            }
+        } else if let Some(items) = value.as_array() {
+            for (i, item) in items.iter().enumerate() {
+                assert_matches_schema(item, &schema["items"], &format!("{at}[{i}]"));
+            }
+        }
+    }
+
+    // Test a JSON report with every optional part matches the published schema
+    #[tokio::test]
+    async fn test_report_schema() {
+        let paths = ["chapter.md", "notes.txt"]
+            .map(|name| Path::new(SNAPSHOT_CORPUS).join(name))
+            .to_vec();
+        let mut processor = TextProcessor::new().with_unique_words(true);
+        processor.process_files(paths).await.unwrap();
+        let report = CorpusReport {
+            unique_words: Some(processor.get_unique_words()),
+            warnings: vec![Warning::Truncated {
+                path: PathBuf::from("notes.txt"),
+            }],
+            elapsed: Some(Duration::from_millis(5)),
+            ..Default::default()
+        };
+        let options = OutputOptions {
+            verbosity: Verbosity::from(3),
+            metadata: Some(RunMetadata::new(vec![(
+                "jobs".to_string(),
+                "auto".to_string(),
+            )])),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        format_output(
+            &mut out,
+            processor.get_results(),
+            &report,
+            OutputFormat::Json,
+            &options,
+        )
+        .unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        for part in ["metadata", "files", "unique_words", "warnings"] {
+            assert!(json.get(part).is_some(), "No {part} in the report");
+        }
+        assert_matches_schema(&json, &report_schema(), "report");
+    }
+    // Test the v1 layout reproduces the output of mfp 0.1, for text and JSON alike
+    #[test]
+    fn test_output_compat() {
+        let results = HashMap::from([
+            (PathBuf::from("docs/a.txt"), {
+                let mut result = FileProcessingResult::default();
+                result.line_counts = vec![2, 3];
+                result.total_words = 5;
+                result.line_count = 2;
+                result
+            }),
+            (PathBuf::from("b.txt"), {
+                let mut result = FileProcessingResult::default();
+                result.line_counts = vec![1];
+                result.total_words = 1;
+                result.line_count = 1;
+                result
+            }),
+        ]);
+        let report = CorpusReport {
+            unique_words: Some(4),
+            ..Default::default()
+        };
+        let output = |format, level| {
+            let options = OutputOptions {
+                verbosity: Verbosity::from(level),
+                compat: Some(OutputCompat::V1),
+                ..Default::default()
+            };
+            let mut out = Vec::new();
+            format_output(&mut out, &results, &report, format, &options).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(
+            output(OutputFormat::Text, 0),
+            "\nProcessing Results:\n------------------\nb.txt: [1]\na.txt: [2, 3]\n"
+        );
+        assert_eq!(
+            output(OutputFormat::Text, 2),
+            "\nProcessing Results:\n------------------\n\
+             b.txt: 1 words in total\n  Line counts: [1]\n\
+             a.txt: 5 words in total\n  Line counts: [2, 3]\n"
+        );
+        assert_eq!(
+            output(OutputFormat::Json, 0),
+            "{\n  \"files\": {\n    \"a.txt\": {\n      \"line_counts\": [\n        2,\n        3\n      ]\n    },\n    \"b.txt\": {\n      \"line_counts\": [\n        1\n      ]\n    }\n  }\n}\n"
+        );
+        let json: serde_json::Value = serde_json::from_str(&output(OutputFormat::Json, 1)).unwrap();
+        assert_eq!(json["files"]["a.txt"]["total_words"], 5);
+        assert_eq!(json.as_object().unwrap().len(), 1);
+        assert_eq!("v1".parse(), Ok(OutputCompat::V1));
+        assert!("2".parse::<OutputCompat>().is_err());
+    }
+
+    // Test the output file is replaced or appended to, and left intact when the
+    // write fails
+    #[test]
+    fn test_write_output_file() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("report.csv");
+        write_output_file(&path, b"path,total_words\na.txt,1\n", true).unwrap();
+        write_output_file(&path, b"b.txt,2\n", true).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "path,total_words\na.txt,1\nb.txt,2\n"
+        );
+        write_output_file(&path, b"path,total_words\nc.txt,3\n", false).unwrap();
+        let previous = fs::read_to_string(&path).unwrap();
+        assert_eq!(previous, "path,total_words\nc.txt,3\n");
+
+        // A directory in the way of the temporary file makes the write fail
+        let blocker = temp
+            .path()
+            .join(format!(".report.csv.{}.tmp", process::id()));
+        fs::create_dir(&blocker).unwrap();
+        for append in [false, true] {
+            assert!(write_output_file(&path, b"d.txt,4\n", append).is_err());
+            assert_eq!(fs::read_to_string(&path).unwrap(), previous);
+        }
+    }
 }
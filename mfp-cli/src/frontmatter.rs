@@ -0,0 +1,140 @@
+use std::path::Path;
+
+/// Reading speed used to estimate reading times
+pub const WORDS_PER_MINUTE: usize = 200;
+
+/// Key holding the body word count
+const WORDCOUNT_KEY: &str = "wordcount";
+/// Key holding the reading time in minutes
+const READING_TIME_KEY: &str = "reading_time";
+
+/// Whether the file is a Markdown document
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "md" | "markdown" | "mdx"))
+}
+
+/// Reading time in whole minutes, at least one for a non-empty document
+pub fn reading_time(words: usize) -> usize {
+    words.div_ceil(WORDS_PER_MINUTE)
+}
+
+/// Number of lines taken by the front matter block, delimiters included
+pub fn front_matter_lines(text: &str) -> usize {
+    split(text).map_or(0, |(_, lines, _)| lines.len() + 2)
+}
+
+/// Words of the document body, those of its front matter left out
+pub fn body_words(text: &str, line_counts: &[usize]) -> usize {
+    line_counts.iter().skip(front_matter_lines(text)).sum()
+}
+
+/// Document with its front matter keys set, along with the new front matter block.
+/// YAML ('---') and TOML ('+++') front matter are updated in place, other keys
+/// untouched; documents without front matter get a new YAML block.
+pub fn patch(text: &str, wordcount: usize, reading_time: usize) -> (String, String) {
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let (delimiter, lines, body) = split(text).unwrap_or(("---", Vec::new(), text));
+    let separator = if delimiter == "+++" { " = " } else { ": " };
+
+    let mut missing = vec![
+        (WORDCOUNT_KEY, wordcount.to_string()),
+        (READING_TIME_KEY, reading_time.to_string()),
+    ];
+    let mut block = vec![delimiter.to_string()];
+    for line in lines {
+        let key = line.split([':', '=']).next().unwrap_or_default().trim();
+        match missing.iter().position(|(k, _)| *k == key) {
+            Some(idx) => {
+                let (key, value) = missing.remove(idx);
+                block.push(format!("{key}{separator}{value}"));
+            }
+            None => block.push(line.to_string()),
+        }
+    }
+    block.extend(
+        missing
+            .into_iter()
+            .map(|(key, value)| format!("{key}{separator}{value}")),
+    );
+    block.push(delimiter.to_string());
+
+    let block = block.join(newline);
+    (format!("{block}{newline}{body}"), block)
+}
+
+// Splits the front matter delimiter, its inner lines and the remaining body
+fn split(text: &str) -> Option<(&'static str, Vec<&str>, &str)> {
+    let first = text.lines().next()?;
+    let delimiter = ["---", "+++"]
+        .into_iter()
+        .find(|d| first.trim_end() == *d)?;
+
+    let mut inner = Vec::new();
+    let mut consumed = 0;
+    for raw in text.split_inclusive('\n') {
+        consumed += raw.len();
+        let line = raw.trim_end_matches(['\n', '\r']);
+        if consumed == raw.len() {
+            continue;
+        }
+        if line.trim_end() == delimiter {
+            return Some((delimiter, inner, &text[consumed..]));
+        }
+        inner.push(line);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mfp_lib::TextProcessor;
+
+    #[test]
+    fn test_patch_updates_existing_yaml_keys() {
+        let text = "---\ntitle: Post\nwordcount: 1\n---\nBody text here\n";
+        let (patched, block) = patch(text, 3, 1);
+
+        assert_eq!(
+            block,
+            "---\ntitle: Post\nwordcount: 3\nreading_time: 1\n---"
+        );
+        assert_eq!(patched, format!("{block}\nBody text here\n"));
+        assert_eq!(front_matter_lines(text), 4);
+    }
+
+    #[test]
+    fn test_patch_toml_and_missing_front_matter() {
+        let (patched, _) = patch("+++\ntitle = \"x\"\n+++\nbody", 1, 1);
+        assert_eq!(
+            patched,
+            "+++\ntitle = \"x\"\nwordcount = 1\nreading_time = 1\n+++\nbody"
+        );
+
+        let (patched, _) = patch("# Title\n", 1, 1);
+        assert_eq!(
+            patched,
+            "---\nwordcount: 1\nreading_time: 1\n---\n# Title\n"
+        );
+        assert_eq!(front_matter_lines("# Title\n---\n"), 0);
+    }
+
+    // Test the front matter and heading markup are not counted
+    #[test]
+    fn test_body_words() {
+        let text = "---\ntitle: Some post\n---\n# Title\n\nBody text here\n## More ##\n";
+        let result = TextProcessor::new()
+            .with_markdown_headings(true)
+            .process_text_as("post.md", text);
+        assert_eq!(body_words(text, &result.line_counts), 5);
+    }
+
+    #[test]
+    fn test_reading_time() {
+        assert_eq!(reading_time(0), 0);
+        assert_eq!(reading_time(1), 1);
+        assert_eq!(reading_time(401), 3);
+    }
+}
@@ -0,0 +1,389 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Language of the human-readable output labels; JSON keys stay English
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    En,
+    De,
+    Fr,
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "en" => Ok(Lang::En),
+            "de" => Ok(Lang::De),
+            "fr" => Ok(Lang::Fr),
+            _ => Err(format!(
+                "unsupported language '{}', expected 'en', 'de' or 'fr'",
+                s
+            )),
+        }
+    }
+}
+
+/// Messages of the text and table outputs, their `{N}` placeholders standing
+/// for the Nth argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    ProcessingResults,
+    Partial,
+    Skipped,
+    FileDetails,
+    LineCounts,
+    Elapsed,
+    Score,
+    UniqueWords,
+    LineStats,
+    Citations,
+    Readability,
+    Encoding,
+    EncodingBom,
+    Regions,
+    Target,
+    TargetProgress,
+    LineIndex,
+    TopWords,
+    Estimated,
+    EstimatedWords,
+    EstimatedLines,
+    CorpusStatistics,
+    WordsPerFile,
+    Percentiles,
+    Gini,
+    Zipf,
+    PatternMatches,
+    CorpusTopWords,
+    CorpusUniqueWords,
+    Warnings,
+    RunInfo,
+    RunOptions,
+    RunElapsed,
+    EncodingFallback,
+    Truncated,
+    SymlinkNotFollowed,
+    PlainResults,
+    PlainFile,
+    PlainDetails,
+    PlainLineCounts,
+    Summary,
+    SummaryMean,
+    Files,
+    OneFile,
+    WatchWords,
+    WatchChanged,
+    WatchRemoved,
+    File,
+    Lines,
+    Words,
+    Characters,
+    Bytes,
+    Unique,
+    WordsPerLine,
+    Milliseconds,
+    TextEncoding,
+    Progress,
+    Remaining,
+    Total,
+}
+
+impl Lang {
+    /// Message in this language, its placeholders filled with the arguments
+    pub fn message(self, message: Message, args: &[&dyn Display]) -> String {
+        let template = match self {
+            Lang::En => en(message),
+            Lang::De => de(message),
+            Lang::Fr => fr(message),
+        };
+        fill(template, args)
+    }
+
+    /// Count of files, singular for a single one, e.g. '1 file' or '3 files'
+    pub fn files(self, count: usize) -> String {
+        match count {
+            1 => self.message(Message::OneFile, &[]),
+            _ => self.message(Message::Files, &[&count]),
+        }
+    }
+}
+
+// Replaces the '{N}' placeholders of a template, leaving unknown ones as is
+fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let placeholder = rest[start + 1..]
+            .split_once('}')
+            .and_then(|(index, after)| {
+                let arg = args.get(index.parse::<usize>().ok()?)?;
+                Some((arg, after))
+            });
+        match placeholder {
+            Some((arg, after)) => {
+                out.push_str(&arg.to_string());
+                rest = after;
+            }
+            None => {
+                out.push('{');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn en(message: Message) -> &'static str {
+    match message {
+        Message::ProcessingResults => "Processing Results:",
+        Message::Partial => "{0} (partial)",
+        Message::Skipped => "{0}: skipped, {1}",
+        Message::FileDetails => {
+            "{0}: {1} words in total\n  Lines: {2}, characters: {3}, bytes: {4}"
+        }
+        Message::LineCounts => "Line counts: {0}",
+        Message::Elapsed => "Counted in {0} ms",
+        Message::Score => "Score: {0}",
+        Message::UniqueWords => "Unique words: {0}",
+        Message::LineStats => {
+            "Words per line: mean {0}, standard deviation {1}, min {2}, max {3}; longest line: {4} characters"
+        }
+        Message::Citations => "Citations: {0}",
+        Message::Readability => "Readability: grade {0}, reading ease {1} ({2} sentences)",
+        Message::Encoding => "Encoding: {0}",
+        Message::EncodingBom => "Encoding: {0}, with a byte order mark",
+        Message::Regions => "Words in footnotes: {0}, captions: {1}, tables: {2}, quotes: {3}",
+        Message::Target => "Target: {0}",
+        Message::TargetProgress => "{0}% of {1} words, {2} remaining",
+        Message::LineIndex => "Line index: {0}",
+        Message::TopWords => "Top words: {0}",
+        Message::Estimated => "Estimated from {0} of {1} files (95% confidence):",
+        Message::EstimatedWords => "Total words: ~{0} ({1} - {2})",
+        Message::EstimatedLines => "Total lines: ~{0} ({1} - {2})",
+        Message::CorpusStatistics => "Corpus statistics ({0} files, {1} words):",
+        Message::WordsPerFile => "Words per file: mean {0}, stddev {1}",
+        Message::Percentiles => {
+            "Percentiles: min {0}, p25 {1}, median {2}, p75 {3}, p90 {4}, p99 {5}, max {6}"
+        }
+        Message::Gini => "Gini coefficient: {0}",
+        Message::Zipf => "Zipf fit: slope {0}, intercept {1}, R² {2}",
+        Message::PatternMatches => "Pattern '{0}': {1} matches on {2} lines in {3} files",
+        Message::CorpusTopWords => "Top {0} words:",
+        Message::CorpusUniqueWords => "Unique words across all files: {0}",
+        Message::Warnings => "Warnings:",
+        Message::RunInfo => "mfp {0}, run at {1} on {2}",
+        Message::RunOptions => "Options: {0}",
+        Message::RunElapsed => "Run took {0} ms",
+        Message::EncodingFallback => "{0}: not valid UTF-8, decoded as {1}",
+        Message::Truncated => "{0}: truncated, only partly counted",
+        Message::SymlinkNotFollowed => "{0}: symlink not followed",
+        Message::PlainResults => "Results for {0} files.",
+        Message::PlainFile => "File {0}: {1} words, {2} lines.",
+        Message::PlainDetails => "Characters: {0}, bytes: {1}.",
+        Message::PlainLineCounts => "Words per line: {0}.",
+        Message::Summary => {
+            "Total: {0}, {1} lines, {2} words, {3} words per file on average"
+        }
+        Message::SummaryMean => "{0}, {1} words per file on average",
+        Message::Files => "{0} files",
+        Message::OneFile => "1 file",
+        Message::WatchWords => "{0}: {1} words",
+        Message::WatchChanged => "{0}: {1} words ({2})",
+        Message::WatchRemoved => "{0}: removed",
+        Message::File => "File",
+        Message::Lines => "Lines",
+        Message::Words => "Words",
+        Message::Characters => "Characters",
+        Message::Bytes => "Bytes",
+        Message::Unique => "Unique words",
+        Message::WordsPerLine => "Words per line",
+        Message::Milliseconds => "ms",
+        Message::TextEncoding => "Encoding",
+        Message::Progress => "Progress",
+        Message::Remaining => "Remaining",
+        Message::Total => "Total",
+    }
+}
+
+fn de(message: Message) -> &'static str {
+    match message {
+        Message::ProcessingResults => "Verarbeitungsergebnisse:",
+        Message::Partial => "{0} (teilweise)",
+        Message::Skipped => "{0}: übersprungen, {1}",
+        Message::FileDetails => {
+            "{0}: {1} Wörter insgesamt\n  Zeilen: {2}, Zeichen: {3}, Bytes: {4}"
+        }
+        Message::LineCounts => "Wörter pro Zeile: {0}",
+        Message::Elapsed => "Gezählt in {0} ms",
+        Message::Score => "Bewertung: {0}",
+        Message::UniqueWords => "Verschiedene Wörter: {0}",
+        Message::LineStats => {
+            "Wörter pro Zeile: Mittelwert {0}, Standardabweichung {1}, Minimum {2}, Maximum {3}; längste Zeile: {4} Zeichen"
+        }
+        Message::Citations => "Literaturverweise: {0}",
+        Message::Readability => "Lesbarkeit: Klassenstufe {0}, Lesefreundlichkeit {1} ({2} Sätze)",
+        Message::Encoding => "Kodierung: {0}",
+        Message::EncodingBom => "Kodierung: {0}, mit Byte-Order-Mark",
+        Message::Regions => {
+            "Wörter in Fußnoten: {0}, Bildunterschriften: {1}, Tabellen: {2}, Zitaten: {3}"
+        }
+        Message::Target => "Ziel: {0}",
+        Message::TargetProgress => "{0} % von {1} Wörtern, {2} verbleibend",
+        Message::LineIndex => "Zeilenindex: {0}",
+        Message::TopWords => "Häufigste Wörter: {0}",
+        Message::Estimated => "Hochgerechnet aus {0} von {1} Dateien (95 % Konfidenz):",
+        Message::EstimatedWords => "Wörter insgesamt: ~{0} ({1} - {2})",
+        Message::EstimatedLines => "Zeilen insgesamt: ~{0} ({1} - {2})",
+        Message::CorpusStatistics => "Korpusstatistik ({0} Dateien, {1} Wörter):",
+        Message::WordsPerFile => "Wörter pro Datei: Mittelwert {0}, Standardabweichung {1}",
+        Message::Percentiles => {
+            "Perzentile: Min. {0}, p25 {1}, Median {2}, p75 {3}, p90 {4}, p99 {5}, Max. {6}"
+        }
+        Message::Gini => "Gini-Koeffizient: {0}",
+        Message::Zipf => "Zipf-Anpassung: Steigung {0}, Achsenabschnitt {1}, R² {2}",
+        Message::PatternMatches => "Muster '{0}': {1} Treffer in {2} Zeilen und {3} Dateien",
+        Message::CorpusTopWords => "Die {0} häufigsten Wörter:",
+        Message::CorpusUniqueWords => "Verschiedene Wörter in allen Dateien: {0}",
+        Message::Warnings => "Warnungen:",
+        Message::RunInfo => "mfp {0}, gestartet am {1} auf {2}",
+        Message::RunOptions => "Optionen: {0}",
+        Message::RunElapsed => "Laufzeit: {0} ms",
+        Message::EncodingFallback => "{0}: kein gültiges UTF-8, als {1} dekodiert",
+        Message::Truncated => "{0}: abgeschnitten, nur teilweise gezählt",
+        Message::SymlinkNotFollowed => "{0}: symbolischer Link nicht verfolgt",
+        Message::PlainResults => "Ergebnisse für {0} Dateien.",
+        Message::PlainFile => "Datei {0}: {1} Wörter, {2} Zeilen.",
+        Message::PlainDetails => "Zeichen: {0}, Bytes: {1}.",
+        Message::PlainLineCounts => "Wörter pro Zeile: {0}.",
+        Message::Summary => {
+            "Gesamt: {0}, {1} Zeilen, {2} Wörter, durchschnittlich {3} Wörter pro Datei"
+        }
+        Message::SummaryMean => "{0}, durchschnittlich {1} Wörter pro Datei",
+        Message::Files => "{0} Dateien",
+        Message::OneFile => "1 Datei",
+        Message::WatchWords => "{0}: {1} Wörter",
+        Message::WatchChanged => "{0}: {1} Wörter ({2})",
+        Message::WatchRemoved => "{0}: entfernt",
+        Message::File => "Datei",
+        Message::Lines => "Zeilen",
+        Message::Words => "Wörter",
+        Message::Characters => "Zeichen",
+        Message::Bytes => "Bytes",
+        Message::Unique => "Verschiedene Wörter",
+        Message::WordsPerLine => "Wörter pro Zeile",
+        Message::Milliseconds => "ms",
+        Message::TextEncoding => "Kodierung",
+        Message::Progress => "Fortschritt",
+        Message::Remaining => "Verbleibend",
+        Message::Total => "Gesamt",
+    }
+}
+
+fn fr(message: Message) -> &'static str {
+    match message {
+        Message::ProcessingResults => "Résultats du traitement :",
+        Message::Partial => "{0} (partiel)",
+        Message::Skipped => "{0} : ignoré, {1}",
+        Message::FileDetails => {
+            "{0} : {1} mots au total\n  Lignes : {2}, caractères : {3}, octets : {4}"
+        }
+        Message::LineCounts => "Mots par ligne : {0}",
+        Message::Elapsed => "Compté en {0} ms",
+        Message::Score => "Score : {0}",
+        Message::UniqueWords => "Mots distincts : {0}",
+        Message::LineStats => {
+            "Mots par ligne : moyenne {0}, écart type {1}, minimum {2}, maximum {3} ; ligne la plus longue : {4} caractères"
+        }
+        Message::Citations => "Références citées : {0}",
+        Message::Readability => {
+            "Lisibilité : niveau scolaire {0}, facilité de lecture {1} ({2} phrases)"
+        }
+        Message::Encoding => "Encodage : {0}",
+        Message::EncodingBom => "Encodage : {0}, avec indicateur d'ordre des octets",
+        Message::Regions => {
+            "Mots dans les notes de bas de page : {0}, légendes : {1}, tableaux : {2}, passages cités : {3}"
+        }
+        Message::Target => "Objectif : {0}",
+        Message::TargetProgress => "{0} % de {1} mots, {2} restants",
+        Message::LineIndex => "Index des lignes : {0}",
+        Message::TopWords => "Mots les plus fréquents : {0}",
+        Message::Estimated => "Estimé à partir de {0} fichiers sur {1} (confiance à 95 %) :",
+        Message::EstimatedWords => "Total des mots : ~{0} ({1} - {2})",
+        Message::EstimatedLines => "Total des lignes : ~{0} ({1} - {2})",
+        Message::CorpusStatistics => "Statistiques du corpus ({0} fichiers, {1} mots) :",
+        Message::WordsPerFile => "Mots par fichier : moyenne {0}, écart type {1}",
+        Message::Percentiles => {
+            "Percentiles : min {0}, p25 {1}, médiane {2}, p75 {3}, p90 {4}, p99 {5}, max {6}"
+        }
+        Message::Gini => "Coefficient de Gini : {0}",
+        Message::Zipf => "Ajustement de Zipf : pente {0}, ordonnée à l'origine {1}, R² {2}",
+        Message::PatternMatches => {
+            "Motif '{0}' : {1} occurrences sur {2} lignes dans {3} fichiers"
+        }
+        Message::CorpusTopWords => "Les {0} mots les plus fréquents :",
+        Message::CorpusUniqueWords => "Mots distincts dans tous les fichiers : {0}",
+        Message::Warnings => "Avertissements :",
+        Message::RunInfo => "mfp {0}, lancé le {1} sur {2}",
+        Message::RunOptions => "Options : {0}",
+        Message::RunElapsed => "Durée de l'exécution : {0} ms",
+        Message::EncodingFallback => "{0} : UTF-8 invalide, décodé en {1}",
+        Message::Truncated => "{0} : tronqué, compté en partie seulement",
+        Message::SymlinkNotFollowed => "{0} : lien symbolique non suivi",
+        Message::PlainResults => "Résultats pour {0} fichiers.",
+        Message::PlainFile => "Fichier {0} : {1} mots, {2} lignes.",
+        Message::PlainDetails => "Caractères : {0}, octets : {1}.",
+        Message::PlainLineCounts => "Mots par ligne : {0}.",
+        Message::Summary => {
+            "Total : {0}, {1} lignes, {2} mots, {3} mots par fichier en moyenne"
+        }
+        Message::SummaryMean => "{0}, {1} mots par fichier en moyenne",
+        Message::Files => "{0} fichiers",
+        Message::OneFile => "1 fichier",
+        Message::WatchWords => "{0} : {1} mots",
+        Message::WatchChanged => "{0} : {1} mots ({2})",
+        Message::WatchRemoved => "{0} : supprimé",
+        Message::File => "Fichier",
+        Message::Lines => "Lignes",
+        Message::Words => "Mots",
+        Message::Characters => "Caractères",
+        Message::Bytes => "Octets",
+        Message::Unique => "Mots distincts",
+        Message::WordsPerLine => "Mots par ligne",
+        Message::Milliseconds => "ms",
+        Message::TextEncoding => "Encodage",
+        Message::Progress => "Progression",
+        Message::Remaining => "Restants",
+        Message::Total => "Total",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message() {
+        assert_eq!(
+            Lang::En.message(Message::Estimated, &[&5, &100]),
+            "Estimated from 5 of 100 files (95% confidence):"
+        );
+        assert_eq!(
+            Lang::De.message(Message::TargetProgress, &[&"25.0", &50000, &37500]),
+            "25.0 % von 50000 Wörtern, 37500 verbleibend"
+        );
+        assert_eq!(Lang::Fr.message(Message::File, &[]), "Fichier");
+        assert_eq!(Lang::En.files(1), "1 file");
+        assert_eq!(Lang::De.files(0), "0 Dateien");
+
+        // Unknown placeholders are kept
+        assert_eq!(fill("{0} {x} {3}", &[&1]), "1 {x} {3}");
+
+        assert_eq!("DE".parse(), Ok(Lang::De));
+        assert!("tlh".parse::<Lang>().is_err());
+    }
+}
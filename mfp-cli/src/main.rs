@@ -1,40 +1,437 @@
+mod affinity;
 mod args;
+mod clipboard;
+mod commands;
+mod config;
 mod error;
 mod format;
+mod frontmatter;
+mod i18n;
+mod metadata;
+mod progress;
+mod target;
+mod watch;
 
-use args::Cli;
+use args::{Cli, Command, STDIN_PATH};
 use clap::Parser;
+use config::Config;
 use error::CliError;
-use format::format_output;
-use mfp_lib::TextProcessor;
+use format::{
+    format_output, streaming_sink, write_output_file, write_rank_frequency_csv, CorpusReport,
+    OutputFormat,
+};
+use metadata::RunMetadata;
+use mfp_lib::{
+    entry_archive, expand_paths_with_warnings, sample_paths, write_bloom_filter, write_line_index,
+    CancellationToken, CorpusEstimate, CorpusStats, Dictionary, InputManifest, PartialRead,
+    PatternMatches, SpillingFrequency, StopWordFilter, StopWordList, StreamedResult,
+    TextProcessorError,
+};
+use std::future::Future;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process;
-use tracing::{error, info};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::signal;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn, Level};
 use tracing_subscriber::fmt;
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    fmt::init();
+/// Exit status of a run stopped by Ctrl-C, as shells report for SIGINT
+pub(crate) const INTERRUPTED_EXIT_CODE: i32 = 130;
 
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse and validate command line arguments
-    let args = Cli::parse();
+    let mut args = Cli::parse();
 
+    // Initialize logging - kept off stdout when it carries a protocol, a table, JSON for an editor
+    // or a streamed report, and without colors in plain mode
+    let ansi = !args.plain && !matches!(&args.command, Some(Command::Progress(p)) if p.plain);
+    if matches!(args.command, Some(Command::Doctor(_))) {
+        // The self-benchmark would drown the report in per-file logs
+        fmt()
+            .with_max_level(Level::WARN)
+            .with_writer(std::io::stderr)
+            .init();
+    } else if matches!(
+        args.command,
+        Some(
+            Command::Lsp
+                | Command::Cooccurrence(_)
+                | Command::Progress(_)
+                | Command::Query(_)
+                | Command::Introspect
+                | Command::Man(_)
+        )
+    ) || matches!(
+        args.format,
+        OutputFormat::Csv | OutputFormat::Markdown | OutputFormat::Ndjson
+    ) || args.streams()
+        || args.stdin_name.is_some()
+    {
+        fmt().with_ansi(ansi).with_writer(std::io::stderr).init();
+    } else {
+        fmt().with_ansi(ansi).init();
+    }
+
+    // Editor quick mode answers before any async runtime is spun up
+    if let Some(name) = &args.stdin_name {
+        if args.files.len() != 1 || args.files[0].as_os_str() != STDIN_PATH {
+            error!("--stdin-name expects '-' as the only file");
+            process::exit(1);
+        }
+        let config = Config::discover(args.config.as_deref(), !args.no_system_config)?;
+        let processor = args.buffer_processor(&config);
+        commands::quick::run(name, processor)?;
+        return Ok(());
+    }
+
+    if let Some(command) = args.command.take() {
+        let runtime = tokio::runtime::Runtime::new()?;
+        return Ok(block_on(runtime, commands::run(command, &args))?);
+    }
+    // Read once, for both the CPU affinity of the runtime and the run
+    let config = Config::discover(args.config.as_deref(), !args.no_system_config)?;
+    let cpus = args.affinity(&config);
+    let runtime = affinity::runtime(&cpus)
+        .map_err(|e| CliError::InputError(format!("Failed to set the CPU affinity: {}", e)))?;
+    block_on(runtime, run(args, config))
+}
+
+// Runs a future to completion, then leaves the blocking tasks behind
+fn block_on<F: Future>(runtime: tokio::runtime::Runtime, future: F) -> F::Output {
+    let result = runtime.block_on(future);
+    // Reads of timed out files may still be blocked, e.g. on a FIFO without writer
+    runtime.shutdown_background();
+    result
+}
+
+async fn run(args: Cli, config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    let start = Instant::now();
     if let Err(e) = args.validate() {
         error!("{}", e);
         process::exit(1);
     }
 
-    info!("Starting to process {} files", args.files.len());
+    let mut options = args.output_options(&config);
+    let inputs = args.inputs();
+    let mut files: Vec<_> = inputs.iter().map(|(path, _)| path.clone()).collect();
+    let read_stdin = files.iter().any(|path| path.as_os_str() == STDIN_PATH);
+    files.retain(|path| path.as_os_str() != STDIN_PATH);
+    // Directories are watched as such, so that files added later are picked up
+    let watched = files.clone();
+    let (files, walk_warnings) = expand_paths_with_warnings(files, &args.walk_options())
+        .await
+        .map_err(|e| CliError::InputError(format!("Failed to list files: {}", e)))?;
+    let population = files.len();
+    let files = match args.sample_size() {
+        Some(size) => {
+            let sample = sample_paths(files, size, args.seed);
+            info!("Sampled {} out of {} files", sample.len(), population);
+            sample
+        }
+        None => files,
+    };
+
+    info!("Starting to process {} files", files.len());
 
     // Process files
-    let mut processor = TextProcessor::new();
-    processor
-        .process_files(args.files)
-        .await
-        .map_err(|e| CliError::InputError(format!("Failed to process files: {}", e)))?;
+    let processor = args
+        .processor()
+        .with_frequencies(args.zipf.is_some() || args.frequencies.is_some() || args.top.is_some());
+    let processor = match args.max_frequency_words {
+        Some(max_words) => processor.with_frequency_spill(SpillingFrequency::new(max_words)),
+        None => processor,
+    };
+    let mut processor = config
+        .apply(processor)
+        .with_counting_policy(args.counting_policy(config.counting_policy))
+        .with_tokenizer_config(args.tokenizer_config(&config.tokenizer_config));
+    for (script, path) in &args.dictionary {
+        let dictionary = Dictionary::from_file(path).await.map_err(|e| {
+            CliError::InputError(format!(
+                "Failed to load dictionary {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        info!("Loaded {} {:?} words", dictionary.len(), script);
+        processor = processor.with_dictionary(*script, Arc::new(dictionary));
+    }
+    if !args.stopwords.is_empty() {
+        let mut stop_words = StopWordFilter::new();
+        for source in &args.stopwords {
+            stop_words = match source.parse::<StopWordList>() {
+                Ok(list) => stop_words.with_list(list),
+                Err(_) => stop_words.with_file(Path::new(source)).await.map_err(|e| {
+                    CliError::InputError(format!("Failed to load stop words {}: {}", source, e))
+                })?,
+            };
+        }
+        info!("Loaded {} stop words", stop_words.len());
+        processor = processor.with_stop_words(Arc::new(stop_words));
+    }
+    if let Some(max_depth) = args.max_depth {
+        processor = processor.with_max_depth(max_depth);
+    }
+    for pattern in &args.exclude {
+        processor = processor.with_exclude(pattern.clone());
+    }
+    processor = processor.with_ignore_files(!args.no_ignore);
+    if let Some(max_concurrent) = args.max_concurrent {
+        processor = processor.with_concurrency(max_concurrent);
+    }
+    if let Some(timeout) = args.timeout {
+        processor = processor.with_timeout(timeout);
+    }
+    if let Some(scorer) = args.scorer() {
+        processor = processor.with_scorer_concurrency(scorer, args.scorer_jobs);
+    }
+    if let Some(partial) = args.partial_read() {
+        processor = processor.with_partial_read(partial);
+    }
+    processor = processor.with_line_offsets(args.line_index);
+    for (path, range) in inputs {
+        if let Some(range) = range {
+            processor = processor.with_file_partial_read(path, PartialRead::Lines(range));
+        }
+    }
+    if args.metadata {
+        options.metadata = Some(RunMetadata::new(args.effective_options(&processor)));
+    }
+    if args.watch {
+        return Ok(watch::run(processor, watched, &args.format, &options).await?);
+    }
+    // Formats that can stream write each file as it completes, in path order, unless written
+    // to a file at once or sorted otherwise
+    let printer = if args.streams() {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        processor = processor.with_result_sender(sender);
+        let mut sink = streaming_sink(&args.format, &options, &files);
+        Some(tokio::spawn(async move {
+            sink.begin(&mut io::stdout().lock())?;
+            while let Some(streamed) = receiver.recv().await {
+                match streamed {
+                    StreamedResult::File { path, result } => {
+                        sink.add_file(&mut io::stdout().lock(), &path, &result)?
+                    }
+                    StreamedResult::Done { path } => {
+                        sink.file_done(&mut io::stdout().lock(), &path)?
+                    }
+                }
+            }
+            Ok::<_, CliError>(sink)
+        }))
+    } else {
+        None
+    };
+    if read_stdin {
+        processor
+            .process_reader(STDIN_PATH, tokio::io::stdin())
+            .await
+            .map_err(|e| CliError::InputError(format!("Failed to process stdin: {}", e)))?;
+    }
+    let mut interrupted = false;
+    if !files.is_empty() || !read_stdin {
+        let progress = if !args.no_progress && !args.plain && io::stderr().is_terminal() {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            processor = processor.with_progress_sender(sender);
+            Some(tokio::spawn(progress::draw(receiver)))
+        } else {
+            None
+        };
+        let processed = processor
+            .process_listed_files_with_cancel(files, cancel_on_ctrl_c())
+            .await;
+        processor.close_progress_sender();
+        if let Some(progress) = progress {
+            progress.await?;
+        }
+        match processed {
+            // An interrupted run still reports the files it got through
+            Err(e @ TextProcessorError::Cancelled { .. }) => {
+                warn!("{}", e);
+                interrupted = true;
+            }
+            processed => processed
+                .map_err(|e| CliError::InputError(format!("Failed to process files: {}", e)))?,
+        }
+    }
+    processor.close_result_sender();
+    let sink = match printer {
+        Some(printer) => Some(printer.await??),
+        None => None,
+    };
+
+    // Stdin and archive entries have no location to write a sidecar next to
+    let indexed = processor
+        .get_results()
+        .iter()
+        .filter(|(path, _)| path.as_os_str() != STDIN_PATH && entry_archive(path).is_none());
+    for (path, result) in indexed {
+        if let Some(offsets) = &result.line_offsets {
+            let sidecar = write_line_index(path, offsets).await.map_err(|e| {
+                CliError::InputError(format!(
+                    "Failed to write line index for {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            info!("Wrote line index {:?}", sidecar);
+        }
+        if let Some(filter) = &result.bloom_filter {
+            let sidecar = write_bloom_filter(path, filter).await.map_err(|e| {
+                CliError::InputError(format!(
+                    "Failed to write Bloom filter for {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            info!("Wrote Bloom filter {:?}", sidecar);
+        }
+    }
+
+    if let Some(manifest_path) = &args.emit_manifest {
+        let paths = processor
+            .get_results()
+            .keys()
+            .filter(|path| path.as_os_str() != STDIN_PATH)
+            .map(PathBuf::as_path);
+        let manifest = InputManifest::from_paths(paths)
+            .await
+            .map_err(|e| CliError::InputError(format!("Failed to hash inputs: {}", e)))?;
+        manifest.write(manifest_path).await.map_err(|e| {
+            CliError::InputError(format!(
+                "Failed to write manifest {}: {}",
+                manifest_path.display(),
+                e
+            ))
+        })?;
+        info!(
+            "Wrote manifest of {} files to {:?}",
+            manifest.files.len(),
+            manifest_path
+        );
+    }
+
+    let mut report = CorpusReport {
+        estimate: args
+            .sample_size()
+            .map(|_| CorpusEstimate::from_sample(population, processor.get_results().values())),
+        stats: args
+            .stats
+            .then(|| CorpusStats::from_results(processor.get_results().values())),
+        unique_words: (args.verbose > 0).then(|| processor.get_unique_words()),
+        elapsed: Some(start.elapsed()),
+        warnings: walk_warnings
+            .into_iter()
+            .chain(processor.get_warnings())
+            .collect(),
+        pattern: args
+            .count_pattern
+            .as_ref()
+            .map(|pattern| PatternMatches::from_results(pattern, processor.get_results().values())),
+        ..Default::default()
+    };
+
+    if let Some(frequencies) = processor.take_spilled_frequencies() {
+        let spill_error = |e| {
+            CliError::InputError(format!(
+                "Failed to read the spilled word frequencies: {}",
+                e
+            ))
+        };
+        if let Some(csv_path) = &args.zipf {
+            write_rank_frequency_csv(csv_path, frequencies.ranked().map_err(spill_error)?)?;
+            info!("Wrote rank-frequency curve to {:?}", csv_path);
+            report.zipf = frequencies.zipf_fit().map_err(spill_error)?;
+        }
+        report.top_words = args
+            .top
+            .map(|n| frequencies.top(n))
+            .transpose()
+            .map_err(spill_error)?;
+    } else if args.zipf.is_some() || args.top.is_some() {
+        let frequencies = processor.get_frequencies();
+        if let Some(csv_path) = &args.zipf {
+            write_rank_frequency_csv(csv_path, frequencies.ranked().into_iter().map(Ok))?;
+            info!("Wrote rank-frequency curve to {:?}", csv_path);
+            report.zipf = frequencies.zipf_fit();
+        }
+        report.top_words = args.top.map(|n| {
+            frequencies
+                .top(n)
+                .into_iter()
+                .map(|(word, count)| (word.to_string(), count))
+                .collect()
+        });
+    }
 
-    format_output(processor.get_results(), args.format, args.verbose)
+    if let Some(format) = &args.copy {
+        let mut copied = Vec::new();
+        format_output(
+            &mut copied,
+            processor.get_results(),
+            &report,
+            format.clone(),
+            &options,
+        )
         .map_err(|e| CliError::FormatError(format!("Failed to format output: {}", e)))?;
+        clipboard::copy(&copied)?;
+        info!("Copied the {:?} report to the clipboard", format);
+    }
+
+    // A streamed report only has its totals and corpus-wide figures left to write
+    if let Some(mut sink) = sink {
+        sink.finish(&mut io::stdout().lock(), &report)
+            .map_err(|e| CliError::FormatError(format!("Failed to format output: {}", e)))?;
+        return finish(interrupted);
+    }
+
+    let mut output = Vec::new();
+    let out: &mut dyn Write = match args.output {
+        Some(_) => &mut output,
+        None => &mut io::stdout().lock(),
+    };
+    format_output(
+        out,
+        processor.get_results(),
+        &report,
+        args.format.clone(),
+        &options,
+    )
+    .map_err(|e| CliError::FormatError(format!("Failed to format output: {}", e)))?;
+    if let Some(path) = &args.output {
+        write_output_file(path, &output, args.append)?;
+        info!("Wrote results to {:?}", path);
+    }
 
+    finish(interrupted)
+}
+
+/// Returns a token cancelled on the first Ctrl-C; a second one exits at once.
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let token = CancellationToken::new();
+    tokio::spawn({
+        let token = token.clone();
+        async move {
+            if signal::ctrl_c().await.is_ok() {
+                warn!("Interrupted, reporting the files processed so far");
+                token.cancel();
+            }
+            if signal::ctrl_c().await.is_ok() {
+                process::exit(INTERRUPTED_EXIT_CODE);
+            }
+        }
+    });
+    token
+}
+
+/// Exits with the conventional SIGINT status once partial output is written
+fn finish(interrupted: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if interrupted {
+        process::exit(INTERRUPTED_EXIT_CODE);
+    }
     Ok(())
 }
@@ -0,0 +1,80 @@
+use mfp_lib::format_timestamp;
+use std::env;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Circumstances of a run, written with the report so that its results can be
+/// reproduced and audited later
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunMetadata {
+    /// Version of mfp
+    pub version: String,
+    /// Seconds since the Unix epoch the run started at
+    pub started: u64,
+    /// Name of the machine the run took place on
+    pub host: String,
+    /// Effective options by name, as given or defaulted
+    pub options: Vec<(String, String)>,
+}
+
+impl RunMetadata {
+    /// Metadata of a run starting now on this machine
+    pub fn new(options: Vec<(String, String)>) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            started: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            host: hostname(),
+            options,
+        }
+    }
+
+    /// Start of the run in UTC, as RFC 3339, e.g. '2024-05-01T09:30:00Z'
+    pub fn timestamp(&self) -> String {
+        format_timestamp(self.started)
+    }
+
+    /// Effective options as 'name=value' pairs separated by commas
+    pub fn options_line(&self) -> String {
+        let options: Vec<String> = self
+            .options
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        options.join(", ")
+    }
+}
+
+// Name of this machine, from the environment or the kernel, else 'unknown'
+fn hostname() -> String {
+    env::var("HOSTNAME")
+        .or_else(|_| env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_lines() {
+        let metadata = RunMetadata {
+            version: "1.2.3".to_string(),
+            started: 1_714_555_800 + 7,
+            host: "box".to_string(),
+            options: vec![
+                ("jobs".to_string(), "auto".to_string()),
+                ("segmentation".to_string(), "unicode".to_string()),
+            ],
+        };
+        assert_eq!(metadata.timestamp(), "2024-05-01T09:30:07Z");
+        assert_eq!(metadata.options_line(), "jobs=auto, segmentation=unicode");
+        assert!(!RunMetadata::new(Vec::new()).host.is_empty());
+    }
+}
@@ -0,0 +1,67 @@
+use mfp_lib::Progress;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// Width of the bar itself, in characters
+const BAR_WIDTH: usize = 30;
+
+/// Minimum delay between two redraws
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Draws a progress bar on stderr from the processor's progress events
+/// until the channel closes, then clears it
+pub async fn draw(mut receiver: UnboundedReceiver<Progress>) {
+    let start = Instant::now();
+    let mut last_draw: Option<Instant> = None;
+    let (mut total, mut done, mut bytes) = (0, 0, 0);
+
+    while let Some(progress) = receiver.recv().await {
+        match progress {
+            Progress::Started { total: files } => total += files,
+            Progress::FileDone { bytes: read, .. } => {
+                done += 1;
+                bytes += read;
+            }
+        }
+        if done < total && last_draw.is_some_and(|last| last.elapsed() < REDRAW_INTERVAL) {
+            continue;
+        }
+        last_draw = Some(Instant::now());
+        eprint!("\r{}", line(done, total, bytes, start.elapsed()));
+        let _ = io::stderr().flush();
+    }
+
+    if last_draw.is_some() {
+        eprint!("\r\x1b[2K");
+    }
+}
+
+// Progress line, e.g. '[=========>      ] 120/400 files, 2.1 MB/s, ETA 14s'
+fn line(done: usize, total: usize, bytes: u64, elapsed: Duration) -> String {
+    let filled = (done * BAR_WIDTH).checked_div(total).unwrap_or(0);
+    let bar = match filled {
+        BAR_WIDTH => "=".repeat(BAR_WIDTH),
+        _ => format!(
+            "{}>{}",
+            "=".repeat(filled),
+            " ".repeat(BAR_WIDTH - filled - 1)
+        ),
+    };
+
+    let seconds = elapsed.as_secs_f64();
+    let throughput = if seconds > 0.0 {
+        bytes as f64 / seconds / 1_000_000.0
+    } else {
+        0.0
+    };
+    let eta = match done {
+        0 => "?".to_string(),
+        _ => format!(
+            "{:.0}s",
+            seconds / done as f64 * total.saturating_sub(done) as f64
+        ),
+    };
+
+    format!("[{bar}] {done}/{total} files, {throughput:.1} MB/s, ETA {eta}")
+}
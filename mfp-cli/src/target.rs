@@ -0,0 +1,107 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// What a word count target applies to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TargetScope {
+    /// Each file has to reach the target on its own
+    File,
+    /// The words of all files add up towards the target
+    #[default]
+    Corpus,
+}
+
+impl FromStr for TargetScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "file" => Ok(TargetScope::File),
+            "corpus" => Ok(TargetScope::Corpus),
+            _ => Err(format!(
+                "unknown target scope '{}', expected 'file' or 'corpus'",
+                s
+            )),
+        }
+    }
+}
+
+/// Word count to reach, e.g. 50000 words for a novel draft
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    pub words: usize,
+    pub scope: TargetScope,
+}
+
+impl Target {
+    /// Progress of a word count towards the target
+    pub fn progress(&self, words: usize) -> TargetProgress {
+        TargetProgress {
+            words,
+            target: self.words,
+        }
+    }
+}
+
+/// Words written against a target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetProgress {
+    pub words: usize,
+    pub target: usize,
+}
+
+impl TargetProgress {
+    /// Share of the target reached, past 100 once exceeded
+    pub fn percent(&self) -> f64 {
+        if self.target == 0 {
+            return 100.0;
+        }
+        self.words as f64 * 100.0 / self.target as f64
+    }
+
+    /// Words still missing, zero once the target is reached
+    pub fn remaining(&self) -> usize {
+        self.target.saturating_sub(self.words)
+    }
+}
+
+impl fmt::Display for TargetProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1}% of {} words, {} remaining",
+            self.percent(),
+            self.target,
+            self.remaining()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_progress() {
+        let target = Target {
+            words: 50000,
+            scope: TargetScope::Corpus,
+        };
+
+        let progress = target.progress(12500);
+        assert_eq!(progress.percent(), 25.0);
+        assert_eq!(progress.remaining(), 37500);
+        assert_eq!(
+            progress.to_string(),
+            "25.0% of 50000 words, 37500 remaining"
+        );
+
+        // Exceeding the target leaves nothing remaining
+        let progress = target.progress(55000);
+        assert_eq!(progress.percent(), 110.0);
+        assert_eq!(progress.remaining(), 0);
+
+        assert_eq!("file".parse(), Ok(TargetScope::File));
+        assert!("chapter".parse::<TargetScope>().is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::error::CliError;
+use crate::format::{write_ndjson_line, OutputFormat, OutputOptions};
+use crate::i18n::Message;
+use futures::StreamExt;
+use mfp_lib::{TextProcessor, WatchEvent, WatchingProcessor};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use tokio::signal;
+use tracing::{error, info};
+
+/// Prints the results of the watched files, then again each time one changes,
+/// until Ctrl-C. NDJSON lines are written for JSON formats, text lines otherwise.
+pub async fn run(
+    processor: TextProcessor,
+    paths: Vec<PathBuf>,
+    format: &OutputFormat,
+    options: &OutputOptions,
+) -> Result<(), CliError> {
+    info!("Watching {} paths, press Ctrl-C to stop", paths.len());
+    let mut events = Box::pin(WatchingProcessor::new(processor, paths).watch());
+    let json = matches!(format, OutputFormat::Json | OutputFormat::Ndjson);
+    let mut words: HashMap<PathBuf, usize> = HashMap::new();
+
+    loop {
+        let event = tokio::select! {
+            event = events.next() => match event {
+                Some(event) => event,
+                None => return Ok(()),
+            },
+            _ = signal::ctrl_c() => return Ok(()),
+        };
+
+        let mut out = io::stdout().lock();
+        match event {
+            WatchEvent::Updated { path, result } => {
+                let previous = words.insert(path.clone(), result.total_words);
+                if json {
+                    write_ndjson_line(&mut out, &path, &result, options)?;
+                } else {
+                    let line = match previous {
+                        Some(previous) => {
+                            let delta =
+                                format!("{:+}", result.total_words as isize - previous as isize);
+                            options.lang.message(
+                                Message::WatchChanged,
+                                &[&path.display(), &result.total_words, &delta],
+                            )
+                        }
+                        None => options
+                            .lang
+                            .message(Message::WatchWords, &[&path.display(), &result.total_words]),
+                    };
+                    writeln!(out, "{}", line)?;
+                }
+            }
+            WatchEvent::Removed { path } => {
+                words.remove(&path);
+                if json {
+                    let record =
+                        serde_json::json!({ "path": path.to_string_lossy(), "removed": true });
+                    writeln!(out, "{}", record)?;
+                } else {
+                    let line = options
+                        .lang
+                        .message(Message::WatchRemoved, &[&path.display()]);
+                    writeln!(out, "{}", line)?;
+                }
+            }
+            WatchEvent::Failed { path, error } => {
+                error!("Failed to process {}: {}", path.display(), error);
+            }
+        }
+        out.flush()?;
+    }
+}
@@ -0,0 +1,47 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Figures an analyzer reports for a file by name, e.g. `{"todos": 3.0}`
+pub type Metrics = BTreeMap<String, f64>;
+
+/// Custom analysis of the lines of a file, run alongside word counting in the same
+/// pass. The processor makes a fresh analyzer for each file, and stores what it
+/// finishes with in the file result under the name it was registered with.
+pub trait Analyzer: Send {
+    /// Looks at the next line counted, stripped of its terminator
+    fn on_line(&mut self, line: &str);
+
+    /// Figures of the file, once all its lines are seen
+    fn finish(self: Box<Self>) -> Metrics;
+}
+
+/// Named maker of an analyzer per file, shared by the file tasks
+#[derive(Clone)]
+pub(crate) struct AnalyzerFactory {
+    pub(crate) name: String,
+    make: Arc<dyn Fn() -> Box<dyn Analyzer> + Send + Sync>,
+}
+
+impl AnalyzerFactory {
+    pub(crate) fn new<A: Analyzer + 'static>(
+        name: String,
+        make: impl Fn() -> A + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name,
+            make: Arc::new(move || Box::new(make())),
+        }
+    }
+
+    /// Analyzer for a new file
+    pub(crate) fn make(&self) -> Box<dyn Analyzer> {
+        (self.make)()
+    }
+}
+
+impl fmt::Debug for AnalyzerFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AnalyzerFactory").field(&self.name).finish()
+    }
+}
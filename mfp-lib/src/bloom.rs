@@ -0,0 +1,198 @@
+use crate::sources::entry_archive;
+use crate::utils::stable_hash;
+use std::collections::HashSet;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Extension appended to a file name to name its Bloom filter sidecar
+pub const BLOOM_EXTENSION: &str = "bloom";
+/// False positive rate of the filters by default
+pub const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Leading bytes of a Bloom filter sidecar, the last one being the format version
+const MAGIC: &[u8; 8] = b"MFPBLOM1";
+
+/// Bloom filter of the vocabulary of a file, telling whether the file may contain a
+/// word: a word the file contains is always found, one it does not is reported at
+/// the false positive rate the filter was sized for. About 1.2 bytes per distinct
+/// word at 1%. Words are trimmed of surrounding punctuation and folded to lower case,
+/// both when added and when looked up.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BloomFilter {
+    hashes: u32,
+    bit_count: u64,
+    bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    /// Filter of a vocabulary, sized for a false positive rate within `0..1`
+    pub fn from_words<'a>(
+        words: impl IntoIterator<Item = &'a str>,
+        false_positive_rate: f64,
+    ) -> Self {
+        let words: HashSet<String> = words.into_iter().filter_map(key).collect();
+        let mut filter = Self::with_capacity(words.len(), false_positive_rate);
+        for word in &words {
+            filter.insert_key(word);
+        }
+        filter
+    }
+
+    // Empty filter of the optimal size for a number of words and a false positive rate
+    fn with_capacity(words: usize, false_positive_rate: f64) -> Self {
+        let rate = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = (-(words.max(1) as f64) * rate.ln() / (ln2 * ln2)).ceil() as u64;
+        let bit_count = bit_count.max(64);
+        let hashes = ((bit_count as f64 / words.max(1) as f64) * ln2).round() as u32;
+        Self {
+            hashes: hashes.clamp(1, 32),
+            bit_count,
+            bits: vec![0; bit_count.div_ceil(64) as usize],
+        }
+    }
+
+    /// Whether the file may contain a word, never wrong when it is not
+    pub fn may_contain(&self, word: &str) -> bool {
+        match key(word) {
+            Some(word) => self
+                .positions(&word)
+                .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0),
+            None => false,
+        }
+    }
+
+    /// Serializes the filter for its sidecar
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 12 + self.bits.len() * 8);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.hashes.to_le_bytes());
+        bytes.extend_from_slice(&self.bit_count.to_le_bytes());
+        for word in &self.bits {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Reads a filter serialized by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let invalid = || Error::new(ErrorKind::InvalidData, "not an mfp Bloom filter");
+        let rest = bytes.strip_prefix(MAGIC).ok_or_else(invalid)?;
+        if rest.len() < 12 || (rest.len() - 12) % 8 != 0 {
+            return Err(invalid());
+        }
+        let (hashes, rest) = rest.split_at(4);
+        let (bit_count, rest) = rest.split_at(8);
+        let hashes = u32::from_le_bytes(hashes.try_into().map_err(|_| invalid())?);
+        let bit_count = u64::from_le_bytes(bit_count.try_into().map_err(|_| invalid())?);
+        let bits: Vec<u64> = rest
+            .chunks_exact(8)
+            .map(|word| u64::from_le_bytes(word.try_into().unwrap_or_default()))
+            .collect();
+        if hashes == 0 || bit_count == 0 || bit_count.div_ceil(64) != bits.len() as u64 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            hashes,
+            bit_count,
+            bits,
+        })
+    }
+
+    fn insert_key(&mut self, word: &str) {
+        for bit in self.positions(word).collect::<Vec<_>>() {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    // Bits of a word, from two hashes combined as many times as the filter hashes
+    fn positions(&self, word: &str) -> impl Iterator<Item = usize> + '_ {
+        let first = stable_hash(word.as_bytes());
+        let second = stable_hash(&first.to_le_bytes()) | 1;
+        (0..u64::from(self.hashes))
+            .map(move |i| (first.wrapping_add(i.wrapping_mul(second)) % self.bit_count) as usize)
+    }
+}
+
+// Word as filtered, None when nothing is left once trimmed
+fn key(word: &str) -> Option<String> {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+    (!word.is_empty()).then(|| word.to_lowercase())
+}
+
+/// Path of the Bloom filter sidecar of a file, e.g. 'notes.txt.bloom'
+pub fn bloom_filter_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(BLOOM_EXTENSION);
+    path.with_file_name(name)
+}
+
+/// Writes the Bloom filter sidecar next to a file. Archive entries have no location
+/// of their own to write it next to, and are refused.
+pub async fn write_bloom_filter(path: &Path, filter: &BloomFilter) -> Result<PathBuf, Error> {
+    if entry_archive(path).is_some() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "archive entries have no Bloom filter",
+        ));
+    }
+    let sidecar = bloom_filter_path(path);
+    fs::write(&sidecar, filter.to_bytes()).await?;
+    Ok(sidecar)
+}
+
+/// Reads a Bloom filter sidecar
+pub async fn read_bloom_filter(sidecar: &Path) -> Result<BloomFilter, Error> {
+    BloomFilter::from_bytes(&fs::read(sidecar).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_membership() {
+        let words: Vec<String> = (0..10_000).map(|i| format!("word{i}")).collect();
+        let filter = BloomFilter::from_words(
+            words.iter().map(String::as_str).chain(["Café,", "---"]),
+            DEFAULT_FALSE_POSITIVE_RATE,
+        );
+        assert!(words.iter().all(|word| filter.may_contain(word)));
+        assert!(filter.may_contain("CAFÉ"));
+        assert!(filter.may_contain("\"café\""));
+        assert!(!filter.may_contain("---"));
+
+        let false_positives = (0..10_000)
+            .filter(|i| filter.may_contain(&format!("other{i}")))
+            .count();
+        assert!(false_positives < 200, "{false_positives}");
+
+        let empty = BloomFilter::from_words([], DEFAULT_FALSE_POSITIVE_RATE);
+        assert!(!empty.may_contain("word"));
+    }
+
+    #[tokio::test]
+    async fn test_sidecar_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("a.txt");
+        let filter = BloomFilter::from_words(["alpha", "beta"], 0.001);
+
+        let sidecar = write_bloom_filter(&file, &filter).await.unwrap();
+        assert_eq!(sidecar, temp.path().join("a.txt.bloom"));
+        assert_eq!(read_bloom_filter(&sidecar).await.unwrap(), filter);
+
+        assert!(BloomFilter::from_bytes(b"MFPBLOM1").is_err());
+        assert!(BloomFilter::from_bytes(b"0\n12\n40\n").is_err());
+        let mut truncated = filter.to_bytes();
+        truncated.truncate(truncated.len() - 8);
+        assert!(BloomFilter::from_bytes(&truncated).is_err());
+
+        let entry = PathBuf::from(format!("{}!/b.txt", temp.path().join("a.zip").display()));
+        let error = write_bloom_filter(&entry, &filter).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert_eq!(std::fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::PathBuf;
+
+/// Chapter listed in an mdBook SUMMARY.md, in reading order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SummaryChapter {
+    /// Title shown in the table of contents
+    pub title: String,
+    /// Source file relative to the book's src directory, `None` for draft chapters
+    pub path: Option<PathBuf>,
+    /// Nesting level, 0 for top-level chapters
+    pub depth: usize,
+    /// Title of the part the chapter belongs to, if any
+    pub part: Option<String>,
+}
+
+/// Parses the chapters of an mdBook SUMMARY.md in reading order.
+/// The leading title heading is skipped, later headings start new parts,
+/// and separators are ignored.
+pub fn parse_summary(text: &str) -> Vec<SummaryChapter> {
+    let mut chapters = Vec::new();
+    let mut part = None;
+    let mut seen_title = false;
+    // Indentation widths of the enclosing list items
+    let mut indents: Vec<usize> = Vec::new();
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            let heading = heading.trim_start_matches('#').trim();
+            if seen_title || !chapters.is_empty() {
+                part = Some(heading.to_string());
+            }
+            seen_title = true;
+            indents.clear();
+            continue;
+        }
+
+        let indent: usize = line[..line.len() - trimmed.len()]
+            .chars()
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum();
+        let (is_item, rest) = strip_list_marker(trimmed);
+        let Some((title, path)) = parse_link(rest) else {
+            continue;
+        };
+
+        let depth = if is_item {
+            while indents.last().is_some_and(|&last| last >= indent) {
+                indents.pop();
+            }
+            indents.push(indent);
+            indents.len() - 1
+        } else {
+            indents.clear();
+            0
+        };
+
+        chapters.push(SummaryChapter {
+            title: title.to_string(),
+            path: (!path.is_empty()).then(|| PathBuf::from(path)),
+            depth,
+            part: part.clone(),
+        });
+    }
+
+    chapters
+}
+
+// Strips a '-', '*' or numbered list marker, telling whether there was one
+fn strip_list_marker(line: &str) -> (bool, &str) {
+    if let Some(rest) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        return (true, rest.trim_start());
+    }
+
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    match line[digits..].strip_prefix(". ") {
+        Some(rest) if digits > 0 => (true, rest.trim_start()),
+        _ => (false, line),
+    }
+}
+
+// Parses '[title](path)', the path being empty for draft chapters
+fn parse_link(text: &str) -> Option<(&str, &str)> {
+    let rest = text.strip_prefix('[')?;
+    let (title, rest) = rest.split_once("](")?;
+    let (path, _) = rest.split_once(')')?;
+    Some((title.trim(), path.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUMMARY: &str = "# Summary
+
+[Introduction](README.md)
+
+# User Guide
+
+- [Installation](guide/installation.md)
+    - [Linux](guide/linux.md)
+        - [Arch](guide/arch.md)
+    - [Windows](guide/windows.md)
+- [Draft chapter]()
+
+---
+
+# Reference
+
+1. [CLI](reference/cli.md)
+
+[Contributors](misc/contributors.md)
+";
+
+    #[test]
+    fn test_parse_summary_structure() {
+        let chapters = parse_summary(SUMMARY);
+        let outline: Vec<(&str, usize, Option<&str>)> = chapters
+            .iter()
+            .map(|c| (c.title.as_str(), c.depth, c.part.as_deref()))
+            .collect();
+
+        assert_eq!(
+            outline,
+            vec![
+                ("Introduction", 0, None),
+                ("Installation", 0, Some("User Guide")),
+                ("Linux", 1, Some("User Guide")),
+                ("Arch", 2, Some("User Guide")),
+                ("Windows", 1, Some("User Guide")),
+                ("Draft chapter", 0, Some("User Guide")),
+                ("CLI", 0, Some("Reference")),
+                ("Contributors", 0, Some("Reference")),
+            ]
+        );
+        assert_eq!(chapters[0].path, Some(PathBuf::from("README.md")));
+        assert_eq!(chapters[5].path, None);
+    }
+
+    #[test]
+    fn test_parse_summary_without_title() {
+        let chapters = parse_summary("- [One](one.md)\n# Part\n- [Two](two.md)\n");
+        assert_eq!(chapters[0].part, None);
+        assert_eq!(chapters[1].part.as_deref(), Some("Part"));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::types::FileProcessingResult;
+use std::collections::HashMap;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+use tracing::{debug, warn};
+
+/// Size and modification time of a file, in nanoseconds since the Unix epoch
+type Stamp = (u64, u128);
+
+/// Most cache files kept, one per set of counting settings
+const MAX_CACHE_FILES: usize = 8;
+/// Most bytes taken by the cache files together
+const MAX_CACHE_BYTES: u64 = 64 * 1024 * 1024;
+/// Most results of a cache file, beyond which only those of this run are kept
+const MAX_CACHE_ENTRIES: usize = 100_000;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CacheEntry {
+    path: PathBuf,
+    size: u64,
+    modified: u128,
+    result: FileProcessingResult,
+}
+
+/// Results of previous runs, reused for files whose size and modification time
+/// did not change. Each set of counting settings has its own file in the cache
+/// directory, so that results counted differently are never mixed up. The least
+/// recently used files are removed once there are more than `MAX_CACHE_FILES` of
+/// them or they take more than `MAX_CACHE_BYTES`.
+#[derive(Debug)]
+pub(crate) struct ResultCache {
+    file: PathBuf,
+    entries: HashMap<PathBuf, (Stamp, FileProcessingResult)>,
+    /// Stamps of the files looked up in this run, by given path
+    stamps: HashMap<PathBuf, (PathBuf, Stamp)>,
+    dirty: bool,
+}
+
+impl ResultCache {
+    /// Loads the cached results of the given settings, starting afresh when
+    /// there are none or they cannot be read
+    pub(crate) async fn load(dir: &Path, fingerprint: u64) -> Self {
+        let file = dir.join(format!("results-{fingerprint:016x}.json"));
+        let entries = match fs::read(&file).await {
+            Ok(content) => match serde_json::from_slice::<Vec<CacheEntry>>(&content) {
+                Ok(entries) => entries
+                    .into_iter()
+                    .map(|e| (e.path, ((e.size, e.modified), e.result)))
+                    .collect(),
+                Err(e) => {
+                    warn!("Ignoring unreadable cache {:?}: {}", file, e);
+                    HashMap::new()
+                }
+            },
+            Err(_) => HashMap::new(),
+        };
+        debug!("Loaded {} cached results from {:?}", entries.len(), file);
+
+        Self {
+            file,
+            entries,
+            stamps: HashMap::new(),
+            dirty: false,
+        }
+    }
+
+    /// Cached result of a file, if it did not change since
+    pub(crate) async fn get(&mut self, path: &Path) -> Option<FileProcessingResult> {
+        let key = fs::canonicalize(path).await.ok()?;
+        let metadata = fs::metadata(&key).await.ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        let stamp = (metadata.len(), modified.as_nanos());
+        self.stamps.insert(path.to_path_buf(), (key.clone(), stamp));
+
+        let (cached, result) = self.entries.get(&key)?;
+        (*cached == stamp).then(|| result.clone())
+    }
+
+    /// Caches the result of a file looked up with `get`, under the stamp it had
+    /// before being processed
+    pub(crate) fn insert(&mut self, path: &Path, result: &FileProcessingResult) {
+        if let Some((key, stamp)) = self.stamps.get(path) {
+            self.entries.insert(key.clone(), (*stamp, result.clone()));
+            self.dirty = true;
+        }
+    }
+
+    /// Writes the cache back if it changed, through a temporary file renamed over it,
+    /// then prunes the least recently used cache files of other settings
+    pub(crate) async fn save(&self) -> Result<(), Error> {
+        if self.dirty {
+            self.write().await?;
+        } else if let Ok(file) = fs::OpenOptions::new().write(true).open(&self.file).await {
+            // Marks the unchanged cache as used
+            file.into_std().await.set_modified(SystemTime::now())?;
+        }
+        match self.file.parent() {
+            Some(dir) => prune(dir, &self.file, MAX_CACHE_FILES, MAX_CACHE_BYTES).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn write(&self) -> Result<(), Error> {
+        // Results of files not seen for a while go first once there are too many
+        let entries: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(path, _)| {
+                self.entries.len() <= MAX_CACHE_ENTRIES
+                    || self.stamps.values().any(|(key, _)| key == *path)
+            })
+            .map(|(path, ((size, modified), result))| CacheEntry {
+                path: path.clone(),
+                size: *size,
+                modified: *modified,
+                result: result.clone(),
+            })
+            .collect();
+
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        let temp = self.file.with_extension(format!("{}.tmp", process::id()));
+        fs::write(&temp, serde_json::to_vec(&entries)?).await?;
+        if let Err(e) = fs::rename(&temp, &self.file).await {
+            let _ = fs::remove_file(&temp).await;
+            return Err(e);
+        }
+        debug!("Saved {} cached results to {:?}", entries.len(), self.file);
+        Ok(())
+    }
+}
+
+// Removes the least recently used cache files beyond a number of files or bytes,
+// always keeping the given one
+async fn prune(dir: &Path, keep: &Path, max_files: usize, max_bytes: u64) -> Result<(), Error> {
+    let mut files = Vec::new();
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(_) => return Ok(()),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !(name.starts_with("results-") && name.ends_with(".json")) {
+            continue;
+        }
+        let metadata = entry.metadata().await?;
+        files.push((metadata.modified()?, metadata.len(), entry.path()));
+    }
+    // Most recently used first, the kept file ahead of all
+    files.sort_by_key(|(modified, _, path)| (path != keep, std::cmp::Reverse(*modified)));
+
+    let mut bytes = 0;
+    for (index, (_, len, path)) in files.into_iter().enumerate() {
+        bytes += len;
+        if index > 0 && (index >= max_files || bytes > max_bytes) {
+            debug!("Pruning the cache file {:?}", path);
+            fs::remove_file(&path).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    // Test the least recently used cache files go first, the current one staying
+    #[tokio::test]
+    async fn test_prune_least_recently_used() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let now = SystemTime::now();
+        for i in 0..5u64 {
+            let file = dir.join(format!("results-{i:016x}.json"));
+            std::fs::write(&file, "[]").unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&file)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(60 * (i + 1)))
+                .unwrap();
+        }
+        std::fs::write(dir.join("unrelated.txt"), "kept").unwrap();
+        let remaining = || {
+            let mut names: Vec<String> = std::fs::read_dir(dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        };
+
+        let oldest = dir.join(format!("results-{:016x}.json", 4));
+        prune(dir, &oldest, 3, MAX_CACHE_BYTES).await.unwrap();
+        assert_eq!(
+            remaining(),
+            [
+                "results-0000000000000000.json",
+                "results-0000000000000001.json",
+                "results-0000000000000004.json",
+                "unrelated.txt",
+            ]
+        );
+
+        // Over the size budget, only the current file is left
+        prune(dir, &oldest, 3, 2).await.unwrap();
+        assert_eq!(
+            remaining(),
+            ["results-0000000000000004.json", "unrelated.txt"]
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// Cancels a `TextProcessor::process_files_with_cancel` run, e.g. on Ctrl-C.
+/// Clones share the same state: cancelling one cancels them all.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token, waking every task waiting on it
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Whether the token was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Completes once the token is cancelled
+    pub async fn cancelled(&self) {
+        loop {
+            // Registered before checking, so that a cancellation in between is not missed
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_wakes_waiters() {
+        let token = CancellationToken::new();
+        let waiter = tokio::spawn({
+            let token = token.clone();
+            async move { token.cancelled().await }
+        });
+
+        assert!(!token.is_cancelled());
+        token.cancel();
+        waiter.await.unwrap();
+        assert!(token.is_cancelled());
+
+        // Already cancelled tokens complete at once
+        token.cancelled().await;
+    }
+}
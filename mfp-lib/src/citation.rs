@@ -0,0 +1,197 @@
+use std::path::Path;
+
+/// Section headings of Markdown bibliographies, lowercase
+const BIBLIOGRAPHY_HEADINGS: &[&str] = &[
+    "references",
+    "bibliography",
+    "works cited",
+    "literature cited",
+];
+
+/// Whether a path names a Markdown file, whose Pandoc citations are recognized
+pub(crate) fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "md" | "markdown" | "mdx"))
+}
+
+/// Strips Pandoc citations and bibliography sections from Markdown lines,
+/// counting the cited keys. Code blocks and sections span lines, so the filter is stateful.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MarkdownCitations {
+    /// Number of citation keys seen so far
+    pub(crate) citations: usize,
+    /// Fence of the code block being read, if any
+    fence: Option<String>,
+    /// Heading level of the bibliography section being skipped, if any
+    bibliography: Option<usize>,
+    /// Within a Pandoc '::: {#refs}' div
+    in_refs_div: bool,
+}
+
+impl MarkdownCitations {
+    /// Text of a line to count, citations removed
+    pub(crate) fn apply(&mut self, line: &str) -> String {
+        let trimmed = line.trim_start();
+
+        if let Some(fence) = &self.fence {
+            if trimmed.starts_with(fence.as_str()) {
+                self.fence = None;
+            }
+            return line.to_string();
+        }
+        if self.in_refs_div {
+            self.in_refs_div = !trimmed.starts_with(":::");
+            return String::new();
+        }
+
+        if let Some(level) = heading_level(trimmed) {
+            let title = trimmed.trim_start_matches('#').trim().to_lowercase();
+            match self.bibliography {
+                Some(bibliography) if level > bibliography => return String::new(),
+                _ => self.bibliography = None,
+            }
+            if BIBLIOGRAPHY_HEADINGS.contains(&title.as_str()) {
+                self.bibliography = Some(level);
+                return String::new();
+            }
+        }
+        if self.bibliography.is_some() {
+            return String::new();
+        }
+
+        if trimmed.starts_with(":::") && trimmed.contains("#refs") {
+            self.in_refs_div = true;
+            return String::new();
+        }
+        for fence in ["```", "~~~"] {
+            if trimmed.starts_with(fence) {
+                self.fence = Some(fence.to_string());
+                return line.to_string();
+            }
+        }
+
+        self.strip_citations(line)
+    }
+
+    // Removes bracketed citations, e.g. '[see @doe99, p. 3]', and in-text ones, e.g. '@doe99'
+    fn strip_citations(&mut self, line: &str) -> String {
+        let mut text = String::with_capacity(line.len());
+        let mut rest = line;
+
+        while let Some(start) = rest.find(['[', '@']) {
+            let (before, from) = rest.split_at(start);
+            text.push_str(before);
+
+            if let Some(after) = from.strip_prefix('[') {
+                let group = from.find(']').map(|end| &from[..=end]);
+                let keys = group.map_or(0, count_keys);
+                if let (Some(group), true) = (group, keys > 0) {
+                    self.citations += keys;
+                    text.push(' ');
+                    rest = &from[group.len()..];
+                } else {
+                    text.push('[');
+                    rest = after;
+                }
+                continue;
+            }
+
+            // An '@' within a word, e.g. of an email address, is no citation
+            let key_len = citation_key_len(&from[1..]);
+            if key_len > 0 && !before.ends_with(|c: char| c.is_alphanumeric()) {
+                self.citations += 1;
+                text.push(' ');
+                rest = &from[1 + key_len..];
+            } else {
+                text.push('@');
+                rest = &from[1..];
+            }
+        }
+
+        text.push_str(rest);
+        text
+    }
+}
+
+// Level of an ATX heading, e.g. 2 for '## Title'
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let after = &line[level..];
+    ((1..=6).contains(&level) && (after.is_empty() || after.starts_with(' '))).then_some(level)
+}
+
+// Number of citation keys in a bracketed group, e.g. 2 for '[@doe99; -@smith04]'
+fn count_keys(group: &str) -> usize {
+    group
+        .match_indices('@')
+        .filter(|&(i, _)| {
+            let before = group[..i].chars().next_back();
+            before.is_none_or(|c| !c.is_alphanumeric()) && citation_key_len(&group[i + 1..]) > 0
+        })
+        .count()
+}
+
+// Length of the Pandoc citation key at the start of a text, trailing punctuation excluded
+fn citation_key_len(text: &str) -> usize {
+    if !text.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+        return 0;
+    }
+    let len = text
+        .find(|c: char| !(c.is_alphanumeric() || "_:.#$%&-+?<>~/".contains(c)))
+        .unwrap_or(text.len());
+    text[..len]
+        .trim_end_matches(|c: char| !c.is_alphanumeric())
+        .len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pandoc_citations_are_counted_and_stripped() {
+        let mut filter = MarkdownCitations::default();
+        let text =
+            filter.apply("As @doe99 shows [see @smith04, p. 3; @lee10], mail me@example.com.");
+        assert_eq!(
+            text.split_whitespace().collect::<Vec<_>>(),
+            ["As", "shows", ",", "mail", "me@example.com."]
+        );
+        assert_eq!(filter.citations, 3);
+    }
+
+    #[test]
+    fn test_bibliography_sections_and_code_are_skipped() {
+        let mut filter = MarkdownCitations::default();
+        let lines = [
+            "## Method",
+            "```python",
+            "@dataclass",
+            "```",
+            "## References",
+            "Doe, J. (1999). A book.",
+            "### Primary sources",
+            "Smith (2004).",
+            "## Appendix",
+            "More text.",
+        ];
+        let counted: Vec<String> = lines.iter().map(|line| filter.apply(line)).collect();
+        assert_eq!(
+            counted,
+            [
+                "## Method",
+                "```python",
+                "@dataclass",
+                "```",
+                "",
+                "",
+                "",
+                "",
+                "## Appendix",
+                "More text."
+            ]
+        );
+        assert_eq!(filter.citations, 0);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::frequency::normalize_word;
+use crate::segmentation::Segmentation;
+use std::collections::{HashMap, VecDeque};
+
+/// Default number of following words a word co-occurs with
+pub const DEFAULT_WINDOW: usize = 5;
+
+/// Sparse symmetric matrix counting how often two vocabulary words occur
+/// within a window of each other
+#[derive(Debug, Clone, Default)]
+pub struct CooccurrenceMatrix {
+    window: usize,
+    /// Index of each vocabulary word in `words`
+    ids: HashMap<String, usize>,
+    words: Vec<String>,
+    /// Counts keyed by ordered word index pairs
+    counts: HashMap<(usize, usize), usize>,
+}
+
+impl CooccurrenceMatrix {
+    /// Creates an empty matrix restricted to a vocabulary - other words are skipped,
+    /// still taking their place in the window - which bounds its size
+    pub fn new(vocabulary: impl IntoIterator<Item = String>, window: usize) -> Self {
+        let words: Vec<String> = vocabulary.into_iter().collect();
+        let ids = words
+            .iter()
+            .enumerate()
+            .map(|(id, word)| (word.clone(), id))
+            .collect();
+
+        Self {
+            window,
+            ids,
+            words,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Counts the pairs of distinct vocabulary words at most `window` words apart.
+    /// Windows span lines, words being normalized as in `WordFrequency`.
+    pub fn add_text(&mut self, text: &str, segmentation: Segmentation) {
+        let mut recent: VecDeque<Option<usize>> = VecDeque::with_capacity(self.window);
+
+        for line in text.lines() {
+            for word in segmentation.words(line).filter_map(normalize_word) {
+                let id = self.ids.get(&word).copied();
+                if let Some(id) = id {
+                    for &other in recent.iter().flatten() {
+                        if other != id {
+                            *self
+                                .counts
+                                .entry((id.min(other), id.max(other)))
+                                .or_default() += 1;
+                        }
+                    }
+                }
+
+                if self.window > 0 {
+                    if recent.len() == self.window {
+                        recent.pop_front();
+                    }
+                    recent.push_back(id);
+                }
+            }
+        }
+    }
+
+    /// Number of following words a word co-occurs with
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Number of vocabulary words
+    pub fn vocabulary_len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Number of non-zero entries, each pair counted once
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Whether no pair was counted
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Co-occurrences of two words, in either order
+    pub fn get(&self, a: &str, b: &str) -> usize {
+        match (self.ids.get(a), self.ids.get(b)) {
+            (Some(&a), Some(&b)) => self.counts.get(&(a.min(b), a.max(b))).copied().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /// Non-zero entries as alphabetically ordered word pairs, most frequent first
+    pub fn entries(&self) -> Vec<(&str, &str, usize)> {
+        let mut entries: Vec<(&str, &str, usize)> = self
+            .counts
+            .iter()
+            .map(|(&(a, b), &count)| {
+                let (a, b) = (self.words[a].as_str(), self.words[b].as_str());
+                (a.min(b), a.max(b), count)
+            })
+            .collect();
+        entries.sort_unstable_by(|x, y| y.2.cmp(&x.2).then((x.0, x.1).cmp(&(y.0, y.1))));
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vocabulary(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_window_counts() {
+        let mut matrix = CooccurrenceMatrix::new(vocabulary(&["a", "b", "c"]), 2);
+        matrix.add_text("a b c\nA x b", Segmentation::Whitespace);
+
+        // Tokens "a b c a x b": a-b at 0-1, 1-3 and 3-5, a-c at 0-2 and 2-3, b-c at 1-2
+        assert_eq!(matrix.get("a", "b"), 3);
+        assert_eq!(matrix.get("b", "a"), 3);
+        assert_eq!(matrix.get("a", "c"), 2);
+        assert_eq!(matrix.get("b", "c"), 1);
+        assert_eq!(matrix.get("a", "a"), 0);
+        assert_eq!(matrix.entries()[0], ("a", "b", 3));
+    }
+
+    #[test]
+    fn test_pruned_words_keep_their_place() {
+        let mut matrix = CooccurrenceMatrix::new(vocabulary(&["a", "b"]), 1);
+        matrix.add_text("a pruned b", Segmentation::Whitespace);
+        assert!(matrix.is_empty());
+
+        let mut matrix = CooccurrenceMatrix::new(vocabulary(&["a", "b"]), 2);
+        matrix.add_text("a pruned b", Segmentation::Whitespace);
+        assert_eq!(matrix.len(), 1);
+        assert_eq!(matrix.get("a", "unknown"), 0);
+    }
+}
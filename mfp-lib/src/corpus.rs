@@ -0,0 +1,170 @@
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::debug;
+
+/// Directory of a generated corpus holding the text files
+pub const CORPUS_TEXTS_DIR: &str = "texts";
+/// File of a generated corpus listing the expected counts, next to the texts
+pub const CORPUS_MANIFEST_FILE: &str = "manifest.json";
+
+/// Average number of words per line by default
+const DEFAULT_WORDS_PER_LINE: usize = 12;
+/// Mixes the file index into the seed, so that each file has its own sequence
+const SEED_MIX: u64 = 0x9e37_79b9_7f4a_7c15;
+
+// Words of the generated text, the first ones drawn the most often
+const VOCABULARY: &str = "\
+    the of and to a in is it that for was on with as by at from this not but \
+    text file word line count corpus process result number value report sample \
+    river mountain garden window library morning evening silence journey harbor \
+    lantern orchard meadow thunder velvet compass marble whisper \
+    café naïve über façade résumé jalapeño smörgåsbord fjord déjà piñata";
+const PUNCTUATION: &[&str] = &["", "", "", "", "", "", ",", ",", ".", ";", "!", "?"];
+
+/// Expected counts of a generated file, as the processor reports them by default
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct GeneratedFile {
+    /// Path relative to the texts directory of the corpus
+    pub path: PathBuf,
+    pub line_counts: Vec<usize>,
+    pub total_words: usize,
+    pub total_chars: usize,
+    pub total_bytes: u64,
+}
+
+/// Settings and expected counts of a generated corpus
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CorpusManifest {
+    pub seed: u64,
+    pub words_per_line: usize,
+    pub total_words: usize,
+    pub total_lines: usize,
+    pub files: Vec<GeneratedFile>,
+}
+
+/// Generates synthetic corpora with known counts, e.g. for load tests and
+/// benchmarks. The same settings and seed always yield the same files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CorpusGenerator {
+    files: usize,
+    lines: usize,
+    seed: u64,
+    words_per_line: usize,
+}
+
+impl CorpusGenerator {
+    /// Generator of `files` files of `lines` lines each
+    pub fn new(files: usize, lines: usize) -> Self {
+        Self {
+            files,
+            lines,
+            seed: 0,
+            words_per_line: DEFAULT_WORDS_PER_LINE,
+        }
+    }
+
+    /// Sets the seed of the generated words, 0 by default
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the average number of words per line, 12 by default - the actual
+    /// counts vary from none to twice as many
+    pub fn with_words_per_line(mut self, words_per_line: usize) -> Self {
+        self.words_per_line = words_per_line;
+        self
+    }
+
+    /// Content of the file at `index` along with the words of each line
+    pub fn file_content(&self, index: usize) -> (String, Vec<usize>) {
+        let mut rng = fastrand::Rng::with_seed(self.seed ^ (index as u64).wrapping_mul(SEED_MIX));
+        let vocabulary: Vec<&str> = VOCABULARY.split_whitespace().collect();
+        let mut content = String::new();
+        let mut line_counts = Vec::with_capacity(self.lines);
+
+        for _ in 0..self.lines {
+            let words = rng.usize(0..=2 * self.words_per_line);
+            for i in 0..words {
+                if i > 0 {
+                    content.push(' ');
+                }
+                // Cubing skews the draws towards the start of the vocabulary, Zipf-like
+                let rank = (rng.f64().powi(3) * vocabulary.len() as f64) as usize;
+                let word = vocabulary[rank.min(vocabulary.len() - 1)];
+                if i == 0 {
+                    let mut chars = word.chars();
+                    if let Some(first) = chars.next() {
+                        content.extend(first.to_uppercase());
+                        content.push_str(chars.as_str());
+                    }
+                } else {
+                    content.push_str(word);
+                }
+                content.push_str(PUNCTUATION[rng.usize(..PUNCTUATION.len())]);
+            }
+            content.push('\n');
+            line_counts.push(words);
+        }
+        (content, line_counts)
+    }
+
+    /// Writes the corpus in `dir`: the text files in its `texts` directory and
+    /// the expected counts in `manifest.json`, also returned
+    pub async fn generate(&self, dir: &Path) -> Result<CorpusManifest, Error> {
+        let texts = dir.join(CORPUS_TEXTS_DIR);
+        fs::create_dir_all(&texts).await?;
+        let width = self.files.saturating_sub(1).to_string().len().max(4);
+
+        let mut files = Vec::with_capacity(self.files);
+        for index in 0..self.files {
+            let (content, line_counts) = self.file_content(index);
+            let path = PathBuf::from(format!("{index:0width$}.txt"));
+            fs::write(texts.join(&path), &content).await?;
+            files.push(GeneratedFile {
+                path,
+                total_words: line_counts.iter().sum(),
+                line_counts,
+                total_chars: content.chars().count(),
+                total_bytes: content.len() as u64,
+            });
+        }
+
+        let manifest = CorpusManifest {
+            seed: self.seed,
+            words_per_line: self.words_per_line,
+            total_words: files.iter().map(|file| file.total_words).sum(),
+            total_lines: self.files * self.lines,
+            files,
+        };
+        fs::write(
+            dir.join(CORPUS_MANIFEST_FILE),
+            serde_json::to_vec_pretty(&manifest)?,
+        )
+        .await?;
+        debug!("Generated {} files in {:?}", self.files, texts);
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_content() {
+        let generator = CorpusGenerator::new(3, 50).with_seed(42);
+        let (content, line_counts) = generator.file_content(1);
+        assert_eq!(line_counts.len(), 50);
+        assert_eq!(content.lines().count(), 50);
+        for (line, &count) in content.lines().zip(&line_counts) {
+            assert_eq!(line.split_whitespace().count(), count);
+        }
+
+        // Deterministic for a seed, different across files and seeds
+        assert_eq!(generator.file_content(1), (content.clone(), line_counts));
+        assert_ne!(generator.file_content(2).0, content);
+        assert_ne!(generator.with_seed(7).file_content(1).0, content);
+    }
+}
@@ -0,0 +1,338 @@
+use crate::error::TextProcessorError;
+use crate::utils::{count_words, validate_file_path};
+use std::path::Path;
+use tokio::fs;
+
+/// Line level edit turning the old file into the new one, holding line indices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOp {
+    /// Line kept as is
+    Equal { old: usize, new: usize },
+    /// Line only present in the new file
+    Insert { new: usize },
+    /// Line only present in the old file
+    Delete { old: usize },
+}
+
+/// Word count changes between two versions of a file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileDelta {
+    /// Number of inserted lines
+    pub lines_added: usize,
+    /// Number of deleted lines
+    pub lines_removed: usize,
+    /// Words carried by the inserted lines
+    pub words_added: usize,
+    /// Words carried by the deleted lines
+    pub words_removed: usize,
+    /// Total words of the old file
+    pub old_total_words: usize,
+    /// Total words of the new file
+    pub new_total_words: usize,
+}
+
+impl FileDelta {
+    /// Net change of the total word count
+    pub fn net_words(&self) -> isize {
+        self.new_total_words as isize - self.old_total_words as isize
+    }
+
+    /// Summarizes the edit script between two sets of lines
+    pub fn from_lines(old: &[&str], new: &[&str]) -> Self {
+        let mut delta = FileDelta {
+            old_total_words: old.iter().map(|line| count_words(line)).sum(),
+            new_total_words: new.iter().map(|line| count_words(line)).sum(),
+            ..Default::default()
+        };
+
+        for op in diff_lines(old, new) {
+            match op {
+                DiffOp::Equal { .. } => {}
+                DiffOp::Insert { new: idx } => {
+                    delta.lines_added += 1;
+                    delta.words_added += count_words(new[idx]);
+                }
+                DiffOp::Delete { old: idx } => {
+                    delta.lines_removed += 1;
+                    delta.words_removed += count_words(old[idx]);
+                }
+            }
+        }
+
+        delta
+    }
+}
+
+/// Computes the word count delta between an old and a new version of a file
+pub async fn compute_delta(old: &Path, new: &Path) -> Result<FileDelta, TextProcessorError> {
+    let old_text = read_text(old).await?;
+    let new_text = read_text(new).await?;
+    let old_lines: Vec<&str> = old_text.lines().collect();
+    let new_lines: Vec<&str> = new_text.lines().collect();
+
+    Ok(FileDelta::from_lines(&old_lines, &new_lines))
+}
+
+async fn read_text(path: &Path) -> Result<String, TextProcessorError> {
+    validate_file_path(path)
+        .await
+        .map_err(|_| TextProcessorError::FileNotFound(path.to_path_buf()))?;
+    Ok(fs::read_to_string(path).await?)
+}
+
+/// Shortest line edit script between two sets of lines, using Myers' O(ND) algorithm
+/// in linear space
+pub fn diff_lines<T: PartialEq>(old: &[T], new: &[T]) -> Vec<DiffOp> {
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    myers(old, new, 0, 0, &mut ops);
+    ops
+}
+
+// Appends the edit script of two sequences starting at the given lines. Common prefix
+// and suffix never take part in it, and what is left is split around the middle snake
+// of a shortest path, each side being diffed in turn: memory stays linear in the
+// number of lines whatever the number of edits.
+fn myers<T: PartialEq>(
+    old: &[T],
+    new: &[T],
+    old_start: usize,
+    new_start: usize,
+    ops: &mut Vec<DiffOp>,
+) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    ops.extend((0..prefix).map(|i| DiffOp::Equal {
+        old: old_start + i,
+        new: new_start + i,
+    }));
+
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+    let (old_first, new_first) = (old_start + prefix, new_start + prefix);
+    if old_middle.is_empty() {
+        ops.extend((0..new_middle.len()).map(|i| DiffOp::Insert { new: new_first + i }));
+    } else if new_middle.is_empty() {
+        ops.extend((0..old_middle.len()).map(|i| DiffOp::Delete { old: old_first + i }));
+    } else {
+        let (x, y, u, v) = middle_snake(old_middle, new_middle);
+        myers(
+            &old_middle[..x],
+            &new_middle[..y],
+            old_first,
+            new_first,
+            ops,
+        );
+        ops.extend((0..u - x).map(|i| DiffOp::Equal {
+            old: old_first + x + i,
+            new: new_first + y + i,
+        }));
+        myers(
+            &old_middle[u..],
+            &new_middle[v..],
+            old_first + u,
+            new_first + v,
+            ops,
+        );
+    }
+
+    ops.extend((0..suffix).map(|i| DiffOp::Equal {
+        old: old_start + old.len() - suffix + i,
+        new: new_start + new.len() - suffix + i,
+    }));
+}
+
+// Start and end points of the snake found halfway along a shortest path between two
+// non-empty sequences, by running the search forward from the start and backward
+// from the end until both meet on a diagonal
+fn middle_snake<T: PartialEq>(old: &[T], new: &[T]) -> (usize, usize, usize, usize) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+    // Furthest x reached on each diagonal k = x - y, the backward one counting from the
+    // ends of both sequences
+    let mut forward = vec![0isize; 2 * offset as usize + 1];
+    let mut backward = vec![0isize; 2 * offset as usize + 1];
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && forward[i - 1] < forward[i + 1]) {
+                forward[i + 1]
+            } else {
+                forward[i - 1] + 1
+            };
+            let mut y = x - k;
+            let (x0, y0) = (x, y);
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[i] = x;
+
+            // The backward search went d - 1 rounds on the same diagonal
+            let reverse = delta - k;
+            if odd
+                && (1 - d..d).contains(&reverse)
+                && x + backward[(reverse + offset) as usize] >= n
+            {
+                return (x0 as usize, y0 as usize, x as usize, y as usize);
+            }
+        }
+
+        for k in (-d..=d).step_by(2) {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && backward[i - 1] < backward[i + 1]) {
+                backward[i + 1]
+            } else {
+                backward[i - 1] + 1
+            };
+            let mut y = x - k;
+            let (x0, y0) = (x, y);
+            while x < n && y < m && old[(n - x - 1) as usize] == new[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[i] = x;
+
+            let reverse = delta - k;
+            if !odd && (-d..=d).contains(&reverse) && x + forward[(reverse + offset) as usize] >= n
+            {
+                return (
+                    (n - x) as usize,
+                    (m - y) as usize,
+                    (n - x0) as usize,
+                    (m - y0) as usize,
+                );
+            }
+        }
+    }
+
+    unreachable!("the searches meet within (n + m) / 2 rounds")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Rebuilds the new sequence from the old one and the edit script
+    fn apply<'a>(old: &[&'a str], new: &[&'a str], ops: &[DiffOp]) -> Vec<&'a str> {
+        ops.iter()
+            .filter_map(|op| match *op {
+                DiffOp::Equal { old: i, .. } => Some(old[i]),
+                DiffOp::Insert { new: i } => Some(new[i]),
+                DiffOp::Delete { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_is_minimal_and_consistent() {
+        let old = ["a", "b", "c", "a", "b", "b", "a"];
+        let new = ["c", "b", "a", "b", "a", "c"];
+        let ops = diff_lines(&old, &new);
+
+        let edits = ops
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Equal { .. }))
+            .count();
+        assert_eq!(edits, 5);
+        assert_eq!(apply(&old, &new, &ops), new);
+    }
+
+    #[test]
+    fn test_diff_edge_cases() {
+        let empty: [&str; 0] = [];
+        assert!(diff_lines(&empty, &empty).is_empty());
+        assert_eq!(diff_lines(&empty, &["x"]), vec![DiffOp::Insert { new: 0 }]);
+        assert_eq!(diff_lines(&["x"], &empty), vec![DiffOp::Delete { old: 0 }]);
+        assert_eq!(
+            diff_lines(&["same"], &["same"]),
+            vec![DiffOp::Equal { old: 0, new: 0 }]
+        );
+    }
+
+    #[test]
+    fn test_word_deltas() {
+        let old = ["intro line here", "to be removed", "kept as is"];
+        let new = ["intro line here", "kept as is", "brand new closing words"];
+        let delta = FileDelta::from_lines(&old, &new);
+
+        assert_eq!(delta.lines_added, 1);
+        assert_eq!(delta.lines_removed, 1);
+        assert_eq!(delta.words_added, 4);
+        assert_eq!(delta.words_removed, 3);
+        assert_eq!(delta.net_words(), 1);
+    }
+
+    #[test]
+    fn test_diff_large_dissimilar_inputs() {
+        // Every 100th line is shared, the others differ, so nearly 8000 edits
+        let lines = |prefix: &str| -> Vec<String> {
+            (0..4000)
+                .map(|i| match i % 100 {
+                    0 => format!("shared {i}"),
+                    _ => format!("{prefix} {i}"),
+                })
+                .collect()
+        };
+        let (old, new) = (lines("old"), lines("new"));
+        let old: Vec<&str> = old.iter().map(String::as_str).collect();
+        let new: Vec<&str> = new.iter().map(String::as_str).collect();
+        let ops = diff_lines(&old, &new);
+
+        let equal = ops
+            .iter()
+            .filter(|op| matches!(op, DiffOp::Equal { .. }))
+            .count();
+        assert_eq!(equal, 40);
+        assert_eq!(ops.len() - equal, 2 * (4000 - 40));
+        assert_eq!(apply(&old, &new, &ops), new);
+    }
+
+    #[test]
+    fn test_diff_matches_longest_common_subsequence() {
+        let mut rng = fastrand::Rng::with_seed(7);
+        for _ in 0..300 {
+            let old: Vec<u8> = (0..rng.usize(0..20)).map(|_| rng.u8(0..4)).collect();
+            let new: Vec<u8> = (0..rng.usize(0..20)).map(|_| rng.u8(0..4)).collect();
+
+            // Length of the longest common subsequence, by dynamic programming
+            let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+            for i in 0..old.len() {
+                for j in 0..new.len() {
+                    lcs[i + 1][j + 1] = if old[i] == new[j] {
+                        lcs[i][j] + 1
+                    } else {
+                        lcs[i][j + 1].max(lcs[i + 1][j])
+                    };
+                }
+            }
+
+            let ops = diff_lines(&old, &new);
+            let rebuilt: Vec<u8> = ops
+                .iter()
+                .filter_map(|op| match *op {
+                    DiffOp::Equal { old: i, new: j } => {
+                        assert_eq!(old[i], new[j]);
+                        Some(old[i])
+                    }
+                    DiffOp::Insert { new: j } => Some(new[j]),
+                    DiffOp::Delete { .. } => None,
+                })
+                .collect();
+            assert_eq!(rebuilt, new);
+            let equal = ops
+                .iter()
+                .filter(|op| matches!(op, DiffOp::Equal { .. }))
+                .count();
+            assert_eq!(equal, lcs[old.len()][new.len()], "{old:?} {new:?}");
+        }
+    }
+}
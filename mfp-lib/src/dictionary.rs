@@ -0,0 +1,208 @@
+use crate::error::TextProcessorError;
+use crate::utils::validate_file_path;
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+use tokio::fs;
+
+/// Script written without spaces between words, segmented with a dictionary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Script {
+    Thai,
+    Lao,
+    Khmer,
+    Myanmar,
+}
+
+impl FromStr for Script {
+    type Err = String;
+
+    /// Parses a language code ('th', 'lo', 'km', 'my') or a script name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "th" | "thai" => Ok(Script::Thai),
+            "lo" | "lao" => Ok(Script::Lao),
+            "km" | "khmer" => Ok(Script::Khmer),
+            "my" | "myanmar" | "burmese" => Ok(Script::Myanmar),
+            _ => Err(format!(
+                "Unsupported language '{s}', expected 'th', 'lo', 'km' or 'my'"
+            )),
+        }
+    }
+}
+
+impl Script {
+    /// Whether the character belongs to the script's Unicode blocks
+    pub fn contains(&self, c: char) -> bool {
+        match self {
+            Script::Thai => ('\u{0E00}'..='\u{0E7F}').contains(&c),
+            Script::Lao => ('\u{0E80}'..='\u{0EFF}').contains(&c),
+            Script::Khmer => {
+                ('\u{1780}'..='\u{17FF}').contains(&c) || ('\u{19E0}'..='\u{19FF}').contains(&c)
+            }
+            Script::Myanmar => ('\u{1000}'..='\u{109F}').contains(&c),
+        }
+    }
+}
+
+/// Word list splitting runs of a spaceless script into words by maximal matching
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dictionary {
+    words: HashSet<String>,
+    /// Length in characters of the longest word
+    max_len: usize,
+}
+
+impl Dictionary {
+    /// Builds a dictionary from its words
+    pub fn from_words<S: Into<String>>(words: impl IntoIterator<Item = S>) -> Self {
+        let words: HashSet<String> = words
+            .into_iter()
+            .map(Into::into)
+            .filter(|word: &String| !word.is_empty())
+            .collect();
+        let max_len = words.iter().map(|w| w.chars().count()).max().unwrap_or(0);
+        Self { words, max_len }
+    }
+
+    /// Loads a word list with one word per line, skipping blank lines and '#' comments
+    pub async fn from_file(path: &Path) -> Result<Self, TextProcessorError> {
+        validate_file_path(path)
+            .await
+            .map_err(|_| TextProcessorError::FileNotFound(path.to_path_buf()))?;
+        let text = fs::read_to_string(path).await?;
+        Ok(Self::from_words(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#')),
+        ))
+    }
+
+    /// Number of words
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    // Words in a stable order, e.g. to fingerprint the dictionary
+    pub(crate) fn sorted_words(&self) -> Vec<&str> {
+        let mut words: Vec<&str> = self.words.iter().map(String::as_str).collect();
+        words.sort_unstable();
+        words
+    }
+
+    /// Whether the dictionary has no words
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Splits spaceless text into the fewest dictionary words, leaving as few characters
+    /// as possible unknown. Consecutive unknown characters make up a single word.
+    pub fn segment<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let bounds: Vec<usize> = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([text.len()])
+            .collect();
+        let n = bounds.len() - 1;
+
+        // Best segmentation of the first i characters
+        let mut best: Vec<Option<Step>> = vec![None; n + 1];
+        best[0] = Some(Step {
+            cost: (0, 0),
+            start: 0,
+            known: true,
+        });
+        for end in 1..=n {
+            let mut candidates = Vec::new();
+            for start in end.saturating_sub(self.max_len)..end {
+                if let Some(Step {
+                    cost: (unknown, words),
+                    ..
+                }) = best[start]
+                {
+                    if self.words.contains(&text[bounds[start]..bounds[end]]) {
+                        candidates.push(Step {
+                            cost: (unknown, words + 1),
+                            start,
+                            known: true,
+                        });
+                    }
+                }
+            }
+            if let Some(Step {
+                cost: (unknown, words),
+                ..
+            }) = best[end - 1]
+            {
+                candidates.push(Step {
+                    cost: (unknown + 1, words + 1),
+                    start: end - 1,
+                    known: false,
+                });
+            }
+            best[end] = candidates.into_iter().min_by_key(|step| step.cost);
+        }
+
+        let mut tokens = Vec::new();
+        let mut end = n;
+        let mut unknown_end = None;
+        while end > 0 {
+            let Some(Step { start, known, .. }) = best[end] else {
+                break;
+            };
+            if known {
+                if let Some(unknown_end) = unknown_end.take() {
+                    tokens.push(&text[bounds[end]..bounds[unknown_end]]);
+                }
+                tokens.push(&text[bounds[start]..bounds[end]]);
+            } else if unknown_end.is_none() {
+                unknown_end = Some(end);
+            }
+            end = start;
+        }
+        if let Some(unknown_end) = unknown_end {
+            tokens.push(&text[..bounds[unknown_end]]);
+        }
+
+        tokens.reverse();
+        tokens
+    }
+}
+
+/// Last token of the best segmentation of a text prefix
+#[derive(Debug, Clone, Copy)]
+struct Step {
+    /// Unknown characters and words, compared in that order
+    cost: (usize, usize),
+    /// Character index the token starts at
+    start: usize,
+    /// Whether the token is a dictionary word
+    known: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maximal_matching() {
+        let dictionary = Dictionary::from_words(["ไป", "ไปเที่ยว", "เที่ยว", "ทะเล", "ฉัน"]);
+
+        // "I go to the sea" - the longer "ไปเที่ยว" yields fewer words
+        assert_eq!(
+            dictionary.segment("ฉันไปเที่ยวทะเล"),
+            vec!["ฉัน", "ไปเที่ยว", "ทะเล"]
+        );
+        // Unknown characters are grouped
+        assert_eq!(dictionary.segment("ฉันกกกทะเล"), vec!["ฉัน", "กกก", "ทะเล"]);
+        assert!(dictionary.segment("").is_empty());
+    }
+
+    #[test]
+    fn test_script_parsing() {
+        assert_eq!("th".parse(), Ok(Script::Thai));
+        assert_eq!("Khmer".parse(), Ok(Script::Khmer));
+        assert!("ja".parse::<Script>().is_err());
+        assert!(Script::Thai.contains('ก') && !Script::Thai.contains('a'));
+    }
+}
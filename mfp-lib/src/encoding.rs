@@ -0,0 +1,284 @@
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
+const UTF8_BOM: &[u8] = &[0xef, 0xbb, 0xbf];
+const UTF16LE_BOM: &[u8] = &[0xff, 0xfe];
+const UTF16BE_BOM: &[u8] = &[0xfe, 0xff];
+
+/// Leading bytes of common binary formats, some of which hold no NUL byte early on
+const BINARY_SIGNATURES: &[(&[u8], &str)] = &[
+    (b"%PDF-", "PDF document"),
+    (b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (b"\xff\xd8\xff", "JPEG image"),
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"PK\x03\x04", "zip archive"),
+    (b"\x1f\x8b", "gzip data"),
+    (b"\x7fELF", "ELF executable"),
+    (
+        b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1",
+        "legacy Office document",
+    ),
+    (b"SQLite format 3\0", "SQLite database"),
+];
+
+/// Characters of Windows-1252 in the 0x80-0x9F range, where it departs from
+/// Latin-1. Unassigned bytes map to the control characters of the same value.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}', '\u{8f}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+];
+
+/// Character encoding of a file, detected from its first bytes unless given
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum Encoding {
+    #[default]
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "iso-8859-1")]
+    Latin1,
+    #[serde(rename = "windows-1252")]
+    Windows1252,
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    /// Parses an encoding label such as 'utf-8', 'utf-16le', 'latin1' or 'cp1252'
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "utf-16le" | "utf16le" | "utf-16" | "utf16" => Ok(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Encoding::Utf16Be),
+            "iso-8859-1" | "iso8859-1" | "latin1" | "latin-1" => Ok(Encoding::Latin1),
+            "windows-1252" | "cp1252" => Ok(Encoding::Windows1252),
+            _ => Err(format!(
+                "Unknown encoding '{s}', expected utf-8, utf-16le, utf-16be, latin1 or windows-1252"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Latin1 => "iso-8859-1",
+            Encoding::Windows1252 => "windows-1252",
+        };
+        f.write_str(label)
+    }
+}
+
+impl Encoding {
+    /// Guesses the encoding of a file from its first bytes: its byte order mark
+    /// if any, else UTF-16 when every other byte is NUL, else UTF-8 when valid,
+    /// else Windows-1252, the usual encoding of legacy Western text
+    pub fn detect(head: &[u8]) -> Self {
+        if head.starts_with(UTF8_BOM) {
+            return Encoding::Utf8;
+        }
+        if head.starts_with(UTF16LE_BOM) {
+            return Encoding::Utf16Le;
+        }
+        if head.starts_with(UTF16BE_BOM) {
+            return Encoding::Utf16Be;
+        }
+
+        // ASCII text in UTF-16 has a NUL byte in every unit
+        let units = head.len() / 2;
+        if units > 0 {
+            let even_nuls = head.iter().step_by(2).filter(|&&b| b == 0).count();
+            let odd_nuls = head.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+            if odd_nuls * 10 >= units * 3 && even_nuls * 10 < units {
+                return Encoding::Utf16Le;
+            }
+            if even_nuls * 10 >= units * 3 && odd_nuls * 10 < units {
+                return Encoding::Utf16Be;
+            }
+        }
+
+        match std::str::from_utf8(head) {
+            Ok(_) => Encoding::Utf8,
+            // The head may end in the middle of a character
+            Err(e) if e.error_len().is_none() => Encoding::Utf8,
+            Err(_) => Encoding::Windows1252,
+        }
+    }
+
+    /// Kind of binary content the first bytes of a file hold, if any: a known
+    /// format, else data with NUL bytes, which text only holds in UTF-16
+    pub(crate) fn binary_kind(head: &[u8], encoding: Option<Encoding>) -> Option<&'static str> {
+        if let Some((_, kind)) = BINARY_SIGNATURES
+            .iter()
+            .find(|(signature, _)| head.starts_with(signature))
+        {
+            return Some(kind);
+        }
+        let utf16 = matches!(
+            encoding.unwrap_or_else(|| Encoding::detect(head)),
+            Encoding::Utf16Le | Encoding::Utf16Be
+        );
+        (!utf16 && head.contains(&0)).then_some("binary data")
+    }
+
+    /// Byte order mark of the encoding, empty for single-byte encodings
+    pub(crate) fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => UTF8_BOM,
+            Encoding::Utf16Le => UTF16LE_BOM,
+            Encoding::Utf16Be => UTF16BE_BOM,
+            Encoding::Latin1 | Encoding::Windows1252 => &[],
+        }
+    }
+
+    /// Line feed and carriage return as encoded, the line feed ending a line
+    pub(crate) fn line_feed(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf16Le => &[b'\n', 0],
+            Encoding::Utf16Be => &[0, b'\n'],
+            _ => b"\n",
+        }
+    }
+
+    pub(crate) fn carriage_return(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf16Le => &[b'\r', 0],
+            Encoding::Utf16Be => &[0, b'\r'],
+            _ => b"\r",
+        }
+    }
+
+    /// Decodes a line. An incomplete character at the very end, as left by a
+    /// byte limit, is dropped.
+    pub(crate) fn decode(self, bytes: &[u8]) -> Result<String, Error> {
+        match self {
+            Encoding::Utf8 => match std::str::from_utf8(bytes) {
+                Ok(line) => Ok(line.to_string()),
+                Err(e) if e.error_len().is_none() => {
+                    Ok(String::from_utf8_lossy(&bytes[..e.valid_up_to()]).into_owned())
+                }
+                Err(_) => Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "stream did not contain valid UTF-8",
+                )),
+            },
+            Encoding::Latin1 => Ok(bytes.iter().map(|&b| b as char).collect()),
+            Encoding::Windows1252 => Ok(bytes
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9f => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+                    _ => b as char,
+                })
+                .collect()),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|pair| match self {
+                        Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                        _ => u16::from_be_bytes([pair[0], pair[1]]),
+                    })
+                    .collect();
+                let mut line = String::with_capacity(units.len());
+                let mut chars = char::decode_utf16(units.iter().copied()).peekable();
+                while let Some(c) = chars.next() {
+                    match c {
+                        Ok(c) => line.push(c),
+                        // A lone high surrogate at the end is half a character cut off
+                        Err(_) if chars.peek().is_none() => {}
+                        Err(_) => {
+                            return Err(Error::new(
+                                ErrorKind::InvalidData,
+                                format!("stream did not contain valid {}", self),
+                            ))
+                        }
+                    }
+                }
+                Ok(line)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, little_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| match little_endian {
+                true => unit.to_le_bytes(),
+                false => unit.to_be_bytes(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Encoding::detect(b"plain text"), Encoding::Utf8);
+        assert_eq!(Encoding::detect("déjà vu".as_bytes()), Encoding::Utf8);
+        // Cut in the middle of 'é'
+        assert_eq!(Encoding::detect(&"dé".as_bytes()[..2]), Encoding::Utf8);
+        assert_eq!(Encoding::detect(b"d\xe9j\xe0 vu"), Encoding::Windows1252);
+        assert_eq!(Encoding::detect(&utf16("déjà vu", true)), Encoding::Utf16Le);
+        assert_eq!(
+            Encoding::detect(&utf16("déjà vu", false)),
+            Encoding::Utf16Be
+        );
+        assert_eq!(Encoding::detect(b"\xff\xfe"), Encoding::Utf16Le);
+        assert_eq!(Encoding::detect(b""), Encoding::Utf8);
+    }
+
+    #[test]
+    fn test_binary_kind() {
+        assert_eq!(
+            Encoding::binary_kind(b"%PDF-1.7\n", None),
+            Some("PDF document")
+        );
+        assert_eq!(
+            Encoding::binary_kind(b"abc\x00\x00\x00\x00def", None),
+            Some("binary data")
+        );
+        assert_eq!(Encoding::binary_kind(b"plain text", None), None);
+        assert_eq!(Encoding::binary_kind(b"d\xe9j\xe0 vu", None), None);
+        let text = utf16("déjà vu", true);
+        assert_eq!(Encoding::binary_kind(&text, None), None);
+        assert_eq!(
+            Encoding::binary_kind(&text, Some(Encoding::Utf8)),
+            Some("binary data")
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(Encoding::Latin1.decode(b"d\xe9j\xe0").unwrap(), "déjà");
+        assert_eq!(
+            Encoding::Windows1252
+                .decode(b"\x93quoted\x94 \x80")
+                .unwrap(),
+            "\u{201c}quoted\u{201d} €"
+        );
+        let emoji = utf16("a 😀", false);
+        assert_eq!(Encoding::Utf16Be.decode(&emoji).unwrap(), "a 😀");
+        // Half of the emoji, as cut by a byte limit
+        assert_eq!(Encoding::Utf16Be.decode(&emoji[..6]).unwrap(), "a ");
+        assert!(Encoding::Utf16Le.decode(&[0x00, 0xd8, b'a', 0]).is_err());
+        assert!(Encoding::Utf8.decode(b"\xe9t\xe9").is_err());
+
+        assert_eq!("CP1252".parse(), Ok(Encoding::Windows1252));
+        assert_eq!("utf_16be".parse(), Ok(Encoding::Utf16Be));
+        assert!("ebcdic".parse::<Encoding>().is_err());
+        assert_eq!(Encoding::Latin1.to_string(), "iso-8859-1");
+    }
+}
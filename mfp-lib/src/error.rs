@@ -1,5 +1,6 @@
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Errors that can occur during text processing
@@ -19,4 +20,29 @@ pub enum TextProcessorError {
         failed_count: usize,
         total_count: usize,
     },
+
+    #[error("Cancelled after processing {processed_count} out of {total_count} files")]
+    Cancelled {
+        processed_count: usize,
+        total_count: usize,
+    },
+
+    #[error("File too large: {path} is {size} bytes, over the limit of {limit} bytes")]
+    FileTooLarge {
+        path: PathBuf,
+        size: u64,
+        limit: u64,
+    },
+
+    #[error("Decompressed content too large: {path} is over the limit of {limit} bytes")]
+    DecompressedTooLarge { path: PathBuf, limit: u64 },
+
+    #[error("Unsupported archive {path}: {reason}")]
+    UnsupportedArchive { path: PathBuf, reason: String },
+
+    #[error("Timed out after {timeout:?} processing {path}")]
+    Timeout { path: PathBuf, timeout: Duration },
+
+    #[error("Scorer failed for {path}: {reason}")]
+    ScorerFailed { path: PathBuf, reason: String },
 }
@@ -0,0 +1,175 @@
+use crate::glob::Glob;
+use std::future::Future;
+use std::io::{self, Error};
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::time::Sleep;
+
+/// Fault injected into the reads of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Waits before the first byte is read, e.g. to run into a timeout
+    Delay(Duration),
+    /// Fails with an I/O error, as EIO from a failing disk, once this many
+    /// bytes have been read
+    IoError { after: u64 },
+    /// Returns at most this many bytes per read, as pipes and network
+    /// filesystems may
+    PartialReads(usize),
+}
+
+/// Injects faults into the reads of the files matching path patterns, so that
+/// timeouts, retries and partial failures can be tested deterministically.
+/// Only available with the `testing` feature.
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjector {
+    rules: Vec<(Glob, Fault)>,
+}
+
+impl FaultInjector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Injects a fault into the reads of the files matching a pattern.
+    /// The faults of all the matching rules apply.
+    pub fn with_fault(mut self, pattern: Glob, fault: Fault) -> Self {
+        self.rules.push((pattern, fault));
+        self
+    }
+
+    /// Faults applying to a path
+    pub(crate) fn faults_for(&self, path: &Path) -> Vec<Fault> {
+        self.rules
+            .iter()
+            .filter(|(pattern, _)| pattern.matches(path))
+            .map(|&(_, fault)| fault)
+            .collect()
+    }
+}
+
+/// Reader applying faults to the reads of another: delays add up, the
+/// earliest error and the smallest reads win
+pub(crate) struct FaultyReader<R> {
+    inner: R,
+    delay: Option<Pin<Box<Sleep>>>,
+    fail_after: Option<u64>,
+    max_read: Option<usize>,
+    read: u64,
+}
+
+impl<R> FaultyReader<R> {
+    pub(crate) fn new(inner: R, faults: &[Fault]) -> Self {
+        let mut delay = Duration::ZERO;
+        let mut fail_after = None;
+        let mut max_read = None;
+        for fault in faults {
+            match *fault {
+                Fault::Delay(duration) => delay += duration,
+                Fault::IoError { after } => {
+                    fail_after = Some(fail_after.map_or(after, |other: u64| other.min(after)))
+                }
+                // Reads of no bytes would look like the end of the file
+                Fault::PartialReads(max) => {
+                    max_read = Some(max_read.map_or(max, |other: usize| other.min(max)).max(1))
+                }
+            }
+        }
+        Self {
+            inner,
+            delay: (!delay.is_zero()).then(|| Box::pin(tokio::time::sleep(delay))),
+            fail_after,
+            max_read,
+            read: 0,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for FaultyReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if let Some(delay) = self.delay.as_mut() {
+            ready!(delay.as_mut().poll(cx));
+            self.delay = None;
+        }
+
+        let mut limit = buf.remaining();
+        if let Some(max_read) = self.max_read {
+            limit = limit.min(max_read);
+        }
+        if let Some(after) = self.fail_after {
+            if self.read >= after {
+                return Poll::Ready(Err(Error::other("Input/output error (injected)")));
+            }
+            limit = limit.min((after - self.read) as usize);
+        }
+
+        let mut chunk = vec![0; limit];
+        let mut chunk_buf = ReadBuf::new(&mut chunk);
+        ready!(Pin::new(&mut self.inner).poll_read(cx, &mut chunk_buf))?;
+        let filled = chunk_buf.filled();
+        buf.put_slice(filled);
+        self.read += filled.len() as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn test_faults_for() {
+        let injector = FaultInjector::new()
+            .with_fault(".log".parse().unwrap(), Fault::PartialReads(3))
+            .with_fault("slow/**".parse().unwrap(), Fault::IoError { after: 0 });
+        assert_eq!(
+            injector.faults_for(Path::new("slow/app.log")),
+            vec![Fault::PartialReads(3), Fault::IoError { after: 0 }]
+        );
+        assert!(injector.faults_for(Path::new("app.txt")).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_faulty_reads() {
+        let content = b"twelve bytes".as_slice();
+
+        let mut reader = FaultyReader::new(content, &[Fault::PartialReads(5)]);
+        let mut buf = [0; 64];
+        assert_eq!(reader.read(&mut buf).await.unwrap(), 5);
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, b"e bytes");
+
+        let mut reader = FaultyReader::new(
+            content,
+            &[Fault::IoError { after: 8 }, Fault::IoError { after: 4 }],
+        );
+        let mut read = Vec::new();
+        assert!(reader.read_to_end(&mut read).await.is_err());
+        assert_eq!(read, b"twel");
+    }
+
+    #[tokio::test]
+    async fn test_delay() {
+        let start = std::time::Instant::now();
+        let mut reader = FaultyReader::new(
+            b"text".as_slice(),
+            &[
+                Fault::Delay(Duration::from_millis(20)),
+                Fault::Delay(Duration::from_millis(30)),
+            ],
+        );
+        let mut read = String::new();
+        reader.read_to_string(&mut read).await.unwrap();
+        assert_eq!(read, "text");
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::error::TextProcessorError;
+use crate::utils::validate_file_path;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use tokio::fs;
+
+/// Default number of words per shingle
+pub const DEFAULT_NGRAM: usize = 5;
+
+/// Set of hashed word n-grams (shingles) characterizing a text's content
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fingerprint {
+    shingles: HashSet<u64>,
+}
+
+/// Shared content between two fingerprints
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Overlap {
+    /// Number of shingles found in both texts
+    pub shared: usize,
+    /// Fraction of the first text's shingles found in the second one
+    pub containment_a: f64,
+    /// Fraction of the second text's shingles found in the first one
+    pub containment_b: f64,
+    /// Shared shingles over all distinct shingles of both texts
+    pub jaccard: f64,
+}
+
+impl Overlap {
+    /// Highest of both containments, i.e. how much of either text was copied
+    pub fn max_containment(&self) -> f64 {
+        self.containment_a.max(self.containment_b)
+    }
+}
+
+impl Fingerprint {
+    /// Fingerprints a text by hashing every run of `ngram` consecutive normalized words.
+    /// Texts shorter than `ngram` words yield a single shingle.
+    pub fn from_text(text: &str, ngram: usize) -> Self {
+        let words: Vec<String> = text.split_whitespace().filter_map(normalize).collect();
+        let ngram = ngram.clamp(1, words.len().max(1));
+
+        let shingles = words
+            .windows(ngram)
+            .map(|window| {
+                let mut hasher = DefaultHasher::new();
+                window.hash(&mut hasher);
+                hasher.finish()
+            })
+            .collect();
+
+        Self { shingles }
+    }
+
+    /// Fingerprints a file's content, invalid UTF-8 being replaced rather than failing
+    pub async fn from_file(path: &Path, ngram: usize) -> Result<Self, TextProcessorError> {
+        validate_file_path(path)
+            .await
+            .map_err(|_| TextProcessorError::FileNotFound(path.to_path_buf()))?;
+        let bytes = fs::read(path).await?;
+        Ok(Self::from_text(&String::from_utf8_lossy(&bytes), ngram))
+    }
+
+    /// Number of distinct shingles
+    pub fn len(&self) -> usize {
+        self.shingles.len()
+    }
+
+    /// Whether the text had no words at all
+    pub fn is_empty(&self) -> bool {
+        self.shingles.is_empty()
+    }
+
+    /// Measures the shared content with another fingerprint
+    pub fn overlap(&self, other: &Fingerprint) -> Overlap {
+        let shared = self.shingles.intersection(&other.shingles).count();
+        let ratio = |part: usize, whole: usize| {
+            if whole == 0 {
+                0.0
+            } else {
+                part as f64 / whole as f64
+            }
+        };
+
+        Overlap {
+            shared,
+            containment_a: ratio(shared, self.len()),
+            containment_b: ratio(shared, other.len()),
+            jaccard: ratio(shared, self.len() + other.len() - shared),
+        }
+    }
+}
+
+// Lowercases a word and trims surrounding punctuation, dropping punctuation-only tokens
+fn normalize(word: &str) -> Option<String> {
+    let trimmed = word.trim_matches(|c: char| !c.is_alphanumeric());
+    (!trimmed.is_empty()).then(|| trimmed.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_from_file_reads_invalid_utf8() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("latin1.txt");
+        std::fs::write(&path, b"caf\xe9 au lait tous les matins").unwrap();
+
+        let fingerprint = Fingerprint::from_file(&path, 3).await.unwrap();
+        assert_eq!(
+            fingerprint,
+            Fingerprint::from_text("caf\u{fffd} au lait tous les matins", 3)
+        );
+        assert_eq!(fingerprint.len(), 4);
+    }
+
+    #[test]
+    fn test_identical_texts_fully_overlap() {
+        let a = Fingerprint::from_text("The quick brown fox jumps over the lazy dog", 3);
+        let b = Fingerprint::from_text("the QUICK brown fox, jumps over the lazy dog!", 3);
+
+        assert_eq!(a.len(), 7);
+        let overlap = a.overlap(&b);
+        assert_eq!(overlap.shared, 7);
+        assert_eq!(overlap.jaccard, 1.0);
+    }
+
+    #[test]
+    fn test_partial_copy_containment() {
+        let source = "one two three four five six seven eight nine ten";
+        let copy = "intro words then three four five six and more";
+        let a = Fingerprint::from_text(source, 3);
+        let b = Fingerprint::from_text(copy, 3);
+
+        let overlap = a.overlap(&b);
+        // "three four five" and "four five six" are shared
+        assert_eq!(overlap.shared, 2);
+        assert_eq!(overlap.containment_a, 2.0 / 8.0);
+        assert_eq!(overlap.containment_b, 2.0 / 7.0);
+        assert_eq!(overlap.max_containment(), 2.0 / 7.0);
+    }
+
+    #[test]
+    fn test_short_and_empty_texts() {
+        assert_eq!(Fingerprint::from_text("two words", 5).len(), 1);
+
+        let empty = Fingerprint::from_text(" ... ", 5);
+        assert!(empty.is_empty());
+        let overlap = empty.overlap(&Fingerprint::from_text("some text", 5));
+        assert_eq!(overlap.shared, 0);
+        assert_eq!(overlap.max_containment(), 0.0);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::segmentation::Segmentation;
+use std::collections::HashMap;
+
+/// Number of occurrences of each word, case-insensitively
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WordFrequency {
+    counts: HashMap<String, usize>,
+}
+
+/// Least squares fit of Zipf's law, `log(frequency) = intercept + slope * log(rank)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZipfFit {
+    /// Slope of the log-log rank-frequency line, close to -1 for natural language
+    pub slope: f64,
+    /// Base 10 logarithm of the frequency the fit predicts for rank 1
+    pub intercept: f64,
+    /// Coefficient of determination of the fit
+    pub r_squared: f64,
+}
+
+impl WordFrequency {
+    /// Creates an empty frequency table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts the words of a line, lowercased and trimmed of surrounding punctuation
+    pub fn add_line(&mut self, line: &str, segmentation: Segmentation) {
+        self.add_words(segmentation.words(line));
+    }
+
+    /// Counts already segmented words, lowercased and trimmed of surrounding punctuation
+    pub(crate) fn add_words<'a>(&mut self, words: impl IntoIterator<Item = &'a str>) {
+        for word in words.into_iter().filter_map(normalize_word) {
+            *self.counts.entry(word).or_default() += 1;
+        }
+    }
+
+    /// Adds the occurrences of another table, e.g. to build corpus-wide frequencies
+    pub fn merge(&mut self, other: &WordFrequency) {
+        for (word, count) in &other.counts {
+            *self.counts.entry(word.clone()).or_default() += count;
+        }
+    }
+
+    /// Occurrences of a word, given in lowercase
+    pub fn get(&self, word: &str) -> usize {
+        self.counts.get(word).copied().unwrap_or(0)
+    }
+
+    /// Number of distinct words
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Whether no word was counted
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Words by decreasing frequency, ties in alphabetical order
+    pub fn ranked(&self) -> Vec<(&str, usize)> {
+        let mut ranked: Vec<(&str, usize)> = self
+            .counts
+            .iter()
+            .map(|(word, &count)| (word.as_str(), count))
+            .collect();
+        ranked.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        ranked
+    }
+
+    /// The `n` most frequent words with their occurrences
+    pub fn top(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut ranked = self.ranked();
+        ranked.truncate(n);
+        ranked
+    }
+
+    /// Most frequent words occurring at least `min_count` times, at most `max_size` of them
+    pub fn vocabulary(&self, min_count: usize, max_size: Option<usize>) -> Vec<String> {
+        self.ranked()
+            .into_iter()
+            .take_while(|&(_, count)| count >= min_count)
+            .take(max_size.unwrap_or(usize::MAX))
+            .map(|(word, _)| word.to_string())
+            .collect()
+    }
+
+    /// Fits Zipf's law to the rank-frequency curve, needing at least two distinct words
+    pub fn zipf_fit(&self) -> Option<ZipfFit> {
+        ZipfFit::from_ranked_counts(self.ranked().into_iter().map(|(_, count)| count))
+    }
+
+    /// Words with their occurrences in alphabetical order
+    pub(crate) fn sorted(&self) -> Vec<(&str, usize)> {
+        let mut sorted: Vec<(&str, usize)> = self
+            .counts
+            .iter()
+            .map(|(word, &count)| (word.as_str(), count))
+            .collect();
+        sorted.sort_unstable();
+        sorted
+    }
+}
+
+impl ZipfFit {
+    /// Fits Zipf's law to the occurrences of the words by decreasing frequency,
+    /// needing at least two of them
+    pub fn from_ranked_counts(counts: impl IntoIterator<Item = usize>) -> Option<Self> {
+        let points: Vec<(f64, f64)> = counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| (((i + 1) as f64).log10(), (count as f64).log10()))
+            .collect();
+        if points.len() < 2 {
+            return None;
+        }
+
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+        let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+        let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+        let syy: f64 = points.iter().map(|p| (p.1 - mean_y).powi(2)).sum();
+
+        let slope = sxy / sxx;
+        Some(Self {
+            slope,
+            intercept: mean_y - slope * mean_x,
+            // All words equally frequent: the flat fit is exact
+            r_squared: if syy == 0.0 {
+                1.0
+            } else {
+                sxy * sxy / (sxx * syy)
+            },
+        })
+    }
+}
+
+/// Lowercases a word and trims surrounding punctuation, dropping punctuation-only tokens
+pub(crate) fn normalize_word(word: &str) -> Option<String> {
+    let trimmed = word.trim_matches(|c: char| !c.is_alphanumeric());
+    (!trimmed.is_empty()).then(|| trimmed.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_are_case_insensitive() {
+        let mut frequency = WordFrequency::new();
+        frequency.add_line("The cat and the hat.", Segmentation::Whitespace);
+        frequency.add_line("THE end -- ", Segmentation::Whitespace);
+
+        assert_eq!(frequency.get("the"), 3);
+        assert_eq!(frequency.get("hat"), 1);
+        assert_eq!(frequency.len(), 5);
+        assert_eq!(frequency.top(2), vec![("the", 3), ("and", 1)]);
+        assert_eq!(frequency.vocabulary(2, None), vec!["the"]);
+        assert_eq!(frequency.vocabulary(1, Some(2)), vec!["the", "and"]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut a = WordFrequency::new();
+        a.add_line("one two", Segmentation::Whitespace);
+        let mut b = WordFrequency::new();
+        b.add_line("two,three", Segmentation::Unicode);
+
+        a.merge(&b);
+        assert_eq!(a.get("two"), 2);
+        assert_eq!(a.get("three"), 1);
+    }
+
+    #[test]
+    fn test_zipf_fit() {
+        // Frequencies exactly inversely proportional to rank
+        let mut frequency = WordFrequency::new();
+        for (word, count) in [("a", 12), ("b", 6), ("c", 4), ("d", 3)] {
+            frequency.add_line(&format!("{word} ").repeat(count), Segmentation::Whitespace);
+        }
+
+        let fit = frequency.zipf_fit().unwrap();
+        assert!((fit.slope + 1.0).abs() < 1e-9);
+        assert!((fit.intercept - 12f64.log10()).abs() < 1e-9);
+        assert!((fit.r_squared - 1.0).abs() < 1e-9);
+
+        assert_eq!(WordFrequency::new().zipf_fit(), None);
+    }
+}
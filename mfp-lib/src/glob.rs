@@ -0,0 +1,144 @@
+use std::path::Path;
+use std::str::FromStr;
+
+/// Shell-style path pattern: `*` and `?` match within a path component, `**` across
+/// components and `[a-z]` / `[!a-z]` match character classes.
+/// Patterns without a `/` match file names only, a leading `.` being shorthand for
+/// an extension, e.g. `.ja.md` for `*.ja.md`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    pattern: String,
+    /// Whether the pattern applies to the whole path rather than to the file name
+    anchored: bool,
+}
+
+impl FromStr for Glob {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("Empty glob pattern".to_string());
+        }
+        if s.matches('[').count() != s.matches(']').count() {
+            return Err(format!("Invalid glob pattern '{s}', unbalanced brackets"));
+        }
+
+        let anchored = s.contains('/');
+        let pattern = match s.strip_prefix("./") {
+            Some(rest) => rest.to_string(),
+            None if s.starts_with('.') && !anchored => format!("*{s}"),
+            None => s.to_string(),
+        };
+        Ok(Glob { pattern, anchored })
+    }
+}
+
+impl Glob {
+    /// Whether the path matches the pattern
+    pub fn matches(&self, path: &Path) -> bool {
+        let text = if self.anchored {
+            let path = path.to_string_lossy();
+            path.strip_prefix("./").unwrap_or(&path).replace('\\', "/")
+        } else {
+            match path.file_name() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => return false,
+            }
+        };
+
+        let pattern: Vec<char> = self.pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        match_from(&pattern, &text)
+    }
+
+    /// Pattern as written, extension shorthand expanded
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+}
+
+// Backtracking matcher over characters
+fn match_from(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // '**/' also matches no directory at all
+            match_from(rest, text)
+                || (0..text.len())
+                    .filter(|&i| text[i] == '/')
+                    .any(|i| match_from(rest, &text[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| match_from(rest, &text[i..])),
+        ['*', rest @ ..] => {
+            let component = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+            (0..=component).any(|i| match_from(rest, &text[i..]))
+        }
+        ['?', rest @ ..] => {
+            matches!(text.first(), Some(&c) if c != '/') && match_from(rest, &text[1..])
+        }
+        ['[', rest @ ..] => {
+            let Some(end) = rest.iter().skip(1).position(|&c| c == ']').map(|i| i + 1) else {
+                return text.first() == Some(&'[') && match_from(rest, &text[1..]);
+            };
+            let Some(&c) = text.first() else {
+                return false;
+            };
+            let (negated, class) = match &rest[..end] {
+                ['!' | '^', class @ ..] => (true, class),
+                class => (false, class),
+            };
+            class_contains(class, c) != negated
+                && c != '/'
+                && match_from(&rest[end + 1..], &text[1..])
+        }
+        [p, rest @ ..] => text.first() == Some(p) && match_from(rest, &text[1..]),
+    }
+}
+
+// Whether a bracket class such as 'a-z0-9_' contains a character
+fn class_contains(class: &[char], c: char) -> bool {
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            if (class[i]..=class[i + 2]).contains(&c) {
+                return true;
+            }
+            i += 3;
+        } else {
+            if class[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        pattern.parse::<Glob>().unwrap().matches(Path::new(path))
+    }
+
+    #[test]
+    fn test_file_name_patterns() {
+        assert!(matches(".ja.md", "docs/intro.ja.md"));
+        assert!(!matches(".ja.md", "docs/intro.md"));
+        assert!(matches("*.txt", "a/b/notes.txt"));
+        assert!(matches("chapter-?.md", "chapter-1.md"));
+        assert!(matches("[a-c]*.md", "book/beta.md"));
+        assert!(!matches("[!a-c]*.md", "book/beta.md"));
+    }
+
+    #[test]
+    fn test_anchored_patterns() {
+        assert!(matches("docs/*.md", "./docs/intro.md"));
+        assert!(!matches("docs/*.md", "docs/ja/intro.md"));
+        assert!(matches("docs/**/*.md", "docs/ja/intro.md"));
+        assert!(matches("docs/**/*.md", "docs/intro.md"));
+        assert!(matches("**/ja/*", "site/docs/ja/intro.md"));
+        assert!("[oops".parse::<Glob>().is_err());
+    }
+}
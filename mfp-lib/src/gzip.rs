@@ -0,0 +1,638 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Error, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Compression method of every gzip file in use
+const DEFLATE: u8 = 8;
+/// Distance a DEFLATE back-reference can reach
+const WINDOW_SIZE: usize = 32 * 1024;
+const MAX_CODE_BITS: usize = 15;
+/// Decompressed bytes sent at once from the decoding thread
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Chunks decoded ahead of the reader
+const CHUNKS_AHEAD: usize = 4;
+
+const FLAG_HEADER_CRC: u8 = 0x02;
+const FLAG_EXTRA: u8 = 0x04;
+const FLAG_NAME: u8 = 0x08;
+const FLAG_COMMENT: u8 = 0x10;
+const FLAGS_RESERVED: u8 = 0xe0;
+
+// Base lengths and extra bits of the length symbols 257 to 285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+// Base distances and extra bits of the distance symbols 0 to 29
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// Order in which the code length code lengths of a dynamic block are stored
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut crc = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            k += 1;
+        }
+        table[n] = crc;
+        n += 1;
+    }
+    table
+}
+
+pub(crate) fn update_crc(crc: u32, bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!crc, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid gzip data: {}", message),
+    )
+}
+
+/// Whether a path names a gzip file by its `.gz` extension
+pub(crate) fn has_gzip_extension(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gz"))
+}
+
+/// Whether a file starts with the gzip magic bytes, whatever its name
+pub(crate) async fn has_gzip_magic(path: &Path) -> Result<bool, Error> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut magic = [0u8; 2];
+    let mut read = 0;
+    while read < magic.len() {
+        match file.read(&mut magic[read..]).await? {
+            0 => return Ok(false),
+            n => read += n,
+        }
+    }
+    Ok(magic == GZIP_MAGIC)
+}
+
+/// Decompresses a gzip file on a blocking thread, streaming its content back.
+/// Decoding stops as soon as the reader is dropped, or with an error of kind
+/// `ErrorKind::FileTooLarge` once more than `limit` bytes are decompressed.
+pub(crate) fn decompress_file(
+    path: PathBuf,
+    limit: Option<u64>,
+) -> impl AsyncRead + Unpin + Send + 'static {
+    let (sender, receiver) = mpsc::channel(CHUNKS_AHEAD);
+    tokio::task::spawn_blocking(move || {
+        let limit = limit.unwrap_or(u64::MAX);
+        // One byte past the limit tells a stream over it from one just at it
+        let mut decoder = match File::open(&path) {
+            Ok(file) => GzipDecoder::new(BufReader::new(file)).take(limit.saturating_add(1)),
+            Err(e) => {
+                let _ = sender.blocking_send(Err(e));
+                return;
+            }
+        };
+        let mut decompressed = 0;
+        loop {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let chunk = match decoder.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) if decompressed + read as u64 > limit => Err(Error::new(
+                    ErrorKind::FileTooLarge,
+                    format!("decompressed content over {} bytes", limit),
+                )),
+                Ok(read) => {
+                    decompressed += read as u64;
+                    chunk.truncate(read);
+                    Ok(chunk)
+                }
+                Err(e) => Err(e),
+            };
+            let failed = chunk.is_err();
+            if sender.blocking_send(chunk).is_err() || failed {
+                break;
+            }
+        }
+        debug!("Decompressed {:?}", path);
+    });
+
+    ChannelReader {
+        receiver,
+        chunk: Vec::new(),
+        position: 0,
+    }
+}
+
+// Async reader over the chunks sent by the decoding thread
+struct ChannelReader {
+    receiver: mpsc::Receiver<Result<Vec<u8>, Error>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl AsyncRead for ChannelReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        while self.position == self.chunk.len() {
+            match ready!(self.receiver.poll_recv(cx)) {
+                Some(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                Some(Err(e)) => return Poll::Ready(Err(e)),
+                // The decoder is done: end of stream
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+        let len = buf.remaining().min(self.chunk.len() - self.position);
+        buf.put_slice(&self.chunk[self.position..self.position + len]);
+        self.position += len;
+        Poll::Ready(Ok(()))
+    }
+}
+
+// Canonical Huffman code, as counts of codes per length and symbols by code
+struct Huffman {
+    counts: [u16; MAX_CODE_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, Error> {
+        let mut counts = [0u16; MAX_CODE_BITS + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Incomplete codes are allowed, over-subscribed ones are not
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(invalid("over-subscribed Huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; MAX_CODE_BITS + 1];
+        for len in 1..MAX_CODE_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    fn fixed() -> (Self, Self) {
+        let mut lengths = [0u8; 288];
+        lengths[..144].fill(8);
+        lengths[144..256].fill(9);
+        lengths[256..280].fill(7);
+        lengths[280..].fill(8);
+        let literals = Self::new(&lengths).expect("valid fixed literal code");
+        let distances = Self::new(&[5; 30]).expect("valid fixed distance code");
+        (literals, distances)
+    }
+}
+
+// Reads the input least significant bit first, as DEFLATE packs it
+struct BitReader<R> {
+    inner: R,
+    bits: u64,
+    count: u32,
+}
+
+impl<R: BufRead> BitReader<R> {
+    fn bits(&mut self, n: u32) -> Result<u32, Error> {
+        while self.count < n {
+            let byte = match self.inner.fill_buf()?.first() {
+                Some(&byte) => byte,
+                None => return Err(Error::new(ErrorKind::UnexpectedEof, "truncated gzip data")),
+            };
+            self.inner.consume(1);
+            self.bits |= (byte as u64) << self.count;
+            self.count += 8;
+        }
+        let value = (self.bits & ((1u64 << n) - 1)) as u32;
+        self.bits >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        self.bits(8).map(|byte| byte as u8)
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        self.bits(16).map(|value| value as u16)
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(self.u16()? as u32 | (self.u16()? as u32) << 16)
+    }
+
+    // Drops the bits left in the current byte
+    fn align(&mut self) {
+        let skipped = self.count % 8;
+        self.bits >>= skipped;
+        self.count -= skipped;
+    }
+
+    fn at_end(&mut self) -> Result<bool, Error> {
+        Ok(self.count < 8 && self.inner.fill_buf()?.is_empty())
+    }
+
+    // Decodes a symbol one bit at a time, codes being stored most significant bit first
+    fn symbol(&mut self, huffman: &Huffman) -> Result<u16, Error> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &huffman.counts[1..] {
+            code |= self.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(huffman.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("unknown Huffman code"))
+    }
+}
+
+enum State {
+    Header,
+    Block,
+    Stored(usize),
+    Compressed {
+        literals: Huffman,
+        distances: Huffman,
+    },
+    Trailer,
+    Done,
+}
+
+/// Streaming gzip decoder, reading one or more concatenated members, or a
+/// single raw DEFLATE stream
+pub(crate) struct GzipDecoder<R> {
+    input: BitReader<R>,
+    state: State,
+    /// Whether the stream has gzip headers and trailers
+    framed: bool,
+    last_block: bool,
+    members: usize,
+    /// Output of the current member, trimmed to the last `WINDOW_SIZE` bytes
+    window: Vec<u8>,
+    /// Bytes of the window not yet read
+    pending: usize,
+    crc: u32,
+    size: u32,
+}
+
+impl<R: BufRead> GzipDecoder<R> {
+    pub(crate) fn new(input: R) -> Self {
+        Self {
+            input: BitReader {
+                inner: input,
+                bits: 0,
+                count: 0,
+            },
+            state: State::Header,
+            framed: true,
+            last_block: false,
+            members: 0,
+            window: Vec::with_capacity(2 * WINDOW_SIZE),
+            pending: 0,
+            crc: 0,
+            size: 0,
+        }
+    }
+
+    /// Decoder of a raw DEFLATE stream, as stored in zip archives
+    pub(crate) fn deflate(input: R) -> Self {
+        Self {
+            state: State::Block,
+            framed: false,
+            ..Self::new(input)
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.window.push(byte);
+        self.pending += 1;
+    }
+
+    // Decodes until some output is pending or the stream ends
+    fn decode(&mut self) -> Result<(), Error> {
+        while self.pending == 0 {
+            self.state = match std::mem::replace(&mut self.state, State::Done) {
+                State::Header => {
+                    if self.members > 0 && !self.next_member()? {
+                        State::Done
+                    } else {
+                        self.header()?;
+                        State::Block
+                    }
+                }
+                State::Block if self.last_block && !self.framed => State::Done,
+                State::Block if self.last_block => State::Trailer,
+                State::Block => self.block()?,
+                State::Stored(len) => {
+                    let chunk = len.min(CHUNK_SIZE);
+                    for _ in 0..chunk {
+                        let byte = self.input.byte()?;
+                        self.push(byte);
+                    }
+                    match len - chunk {
+                        0 => State::Block,
+                        len => State::Stored(len),
+                    }
+                }
+                State::Compressed {
+                    literals,
+                    distances,
+                } => {
+                    if self.codes(&literals, &distances)? {
+                        State::Block
+                    } else {
+                        State::Compressed {
+                            literals,
+                            distances,
+                        }
+                    }
+                }
+                State::Trailer => {
+                    self.input.align();
+                    let crc = self.input.u32()?;
+                    let size = self.input.u32()?;
+                    if crc != self.crc {
+                        return Err(invalid("CRC mismatch"));
+                    }
+                    if size != self.size {
+                        return Err(invalid("length mismatch"));
+                    }
+                    self.members += 1;
+                    State::Header
+                }
+                State::Done => return Ok(()),
+            };
+        }
+        Ok(())
+    }
+
+    // Whether another member follows; trailing bytes other than a member are ignored
+    fn next_member(&mut self) -> Result<bool, Error> {
+        if self.input.at_end()? {
+            return Ok(false);
+        }
+        let magic = self.input.inner.fill_buf()?;
+        if magic.first() != Some(&GZIP_MAGIC[0]) {
+            debug!("Ignoring trailing bytes after gzip data");
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn header(&mut self) -> Result<(), Error> {
+        if [self.input.byte()?, self.input.byte()?] != GZIP_MAGIC {
+            return Err(invalid("not in gzip format"));
+        }
+        if self.input.byte()? != DEFLATE {
+            return Err(invalid("unknown compression method"));
+        }
+        let flags = self.input.byte()?;
+        if flags & FLAGS_RESERVED != 0 {
+            return Err(invalid("reserved header flags set"));
+        }
+        // Modification time, extra flags and operating system
+        for _ in 0..6 {
+            self.input.byte()?;
+        }
+        if flags & FLAG_EXTRA != 0 {
+            for _ in 0..self.input.u16()? {
+                self.input.byte()?;
+            }
+        }
+        for flag in [FLAG_NAME, FLAG_COMMENT] {
+            if flags & flag != 0 {
+                while self.input.byte()? != 0 {}
+            }
+        }
+        if flags & FLAG_HEADER_CRC != 0 {
+            self.input.u16()?;
+        }
+
+        self.last_block = false;
+        self.window.clear();
+        self.crc = 0;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn block(&mut self) -> Result<State, Error> {
+        self.last_block = self.input.bits(1)? == 1;
+        match self.input.bits(2)? {
+            0 => {
+                self.input.align();
+                let len = self.input.u16()?;
+                if self.input.u16()? != !len {
+                    return Err(invalid("stored block length mismatch"));
+                }
+                Ok(State::Stored(len as usize))
+            }
+            1 => {
+                let (literals, distances) = Huffman::fixed();
+                Ok(State::Compressed {
+                    literals,
+                    distances,
+                })
+            }
+            2 => self.dynamic_codes(),
+            _ => Err(invalid("unknown block type")),
+        }
+    }
+
+    fn dynamic_codes(&mut self) -> Result<State, Error> {
+        let literal_count = self.input.bits(5)? as usize + 257;
+        let distance_count = self.input.bits(5)? as usize + 1;
+        let code_length_count = self.input.bits(4)? as usize + 4;
+        if literal_count > 286 || distance_count > 30 {
+            return Err(invalid("too many codes"));
+        }
+
+        let mut code_lengths = [0u8; 19];
+        for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+            code_lengths[index] = self.input.bits(3)? as u8;
+        }
+        let code_lengths = Huffman::new(&code_lengths)?;
+
+        let mut lengths = vec![0u8; literal_count + distance_count];
+        let mut index = 0;
+        while index < lengths.len() {
+            let (len, repeat) = match self.input.symbol(&code_lengths)? {
+                symbol @ 0..=15 => (symbol as u8, 1),
+                16 if index == 0 => return Err(invalid("repeated length without a first one")),
+                16 => (lengths[index - 1], 3 + self.input.bits(2)? as usize),
+                17 => (0, 3 + self.input.bits(3)? as usize),
+                _ => (0, 11 + self.input.bits(7)? as usize),
+            };
+            if index + repeat > lengths.len() {
+                return Err(invalid("too many code lengths"));
+            }
+            lengths[index..index + repeat].fill(len);
+            index += repeat;
+        }
+        if lengths[256] == 0 {
+            return Err(invalid("missing end-of-block code"));
+        }
+
+        Ok(State::Compressed {
+            literals: Huffman::new(&lengths[..literal_count])?,
+            distances: Huffman::new(&lengths[literal_count..])?,
+        })
+    }
+
+    // Decodes a chunk of a compressed block, returning whether the block ended
+    fn codes(&mut self, literals: &Huffman, distances: &Huffman) -> Result<bool, Error> {
+        while self.pending < CHUNK_SIZE {
+            let symbol = self.input.symbol(literals)? as usize;
+            if symbol < 256 {
+                self.push(symbol as u8);
+                continue;
+            }
+            if symbol == 256 {
+                return Ok(true);
+            }
+
+            let symbol = symbol - 257;
+            if symbol >= LENGTH_BASE.len() {
+                return Err(invalid("bad length symbol"));
+            }
+            let len = LENGTH_BASE[symbol] as usize
+                + self.input.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+            let symbol = self.input.symbol(distances)? as usize;
+            if symbol >= DISTANCE_BASE.len() {
+                return Err(invalid("bad distance symbol"));
+            }
+            let distance = DISTANCE_BASE[symbol] as usize
+                + self.input.bits(DISTANCE_EXTRA[symbol] as u32)? as usize;
+            if distance > self.window.len() {
+                return Err(invalid("distance too far back"));
+            }
+            // Byte by byte, as the copy may overlap its own output
+            let start = self.window.len() - distance;
+            for i in 0..len {
+                let byte = self.window[start + i];
+                self.push(byte);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl<R: BufRead> Read for GzipDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.decode()?;
+        let start = self.window.len() - self.pending;
+        let len = buf.len().min(self.pending);
+        buf[..len].copy_from_slice(&self.window[start..start + len]);
+        self.crc = update_crc(self.crc, &buf[..len]);
+        self.size = self.size.wrapping_add(len as u32);
+        self.pending -= len;
+
+        // Only the last window of read bytes stays around for back-references
+        if self.pending == 0 && self.window.len() > 2 * WINDOW_SIZE {
+            self.window.drain(..self.window.len() - WINDOW_SIZE);
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "Hello, hello, hello!\n" compressed with fixed Huffman codes
+    const FIXED: &[u8] = &[
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xf3, 0x48, 0xcd, 0xc9, 0xc9,
+        0xd7, 0x51, 0xc8, 0x40, 0xa2, 0x14, 0xb9, 0x00, 0xbb, 0xc7, 0x53, 0xed, 0x15, 0x00, 0x00,
+        0x00,
+    ];
+    // "stored\n" in a stored block
+    const STORED: &[u8] = &[
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x03, 0x01, 0x07, 0x00, 0xf8, 0xff,
+        0x73, 0x74, 0x6f, 0x72, 0x65, 0x64, 0x0a, 0xe2, 0x9c, 0x53, 0xa5, 0x07, 0x00, 0x00, 0x00,
+    ];
+
+    fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+        GzipDecoder::new(data).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_decompress() {
+        assert_eq!(decompress(FIXED).unwrap(), b"Hello, hello, hello!\n");
+        assert_eq!(decompress(STORED).unwrap(), b"stored\n");
+
+        // Dynamic Huffman codes, with the file name in the header
+        let compressed = std::fs::read("tests/files/longer.txt.gz").unwrap();
+        let original = std::fs::read("tests/files/longer.txt").unwrap();
+        assert_eq!(decompress(&compressed).unwrap(), original);
+
+        // Concatenated members read as one stream
+        let twice = [FIXED, FIXED].concat();
+        assert_eq!(
+            decompress(&twice).unwrap(),
+            b"Hello, hello, hello!\nHello, hello, hello!\n"
+        );
+
+        let mut corrupted = FIXED.to_vec();
+        let crc = corrupted.len() - 8;
+        corrupted[crc] ^= 1;
+        assert!(decompress(&corrupted).is_err());
+        assert!(decompress(&FIXED[..20]).is_err());
+        assert!(decompress(b"plain text").is_err());
+
+        // Raw DEFLATE data, without the gzip header and trailer
+        let mut out = Vec::new();
+        GzipDecoder::deflate(&FIXED[10..FIXED.len() - 8])
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, b"Hello, hello, hello!\n");
+    }
+}
@@ -0,0 +1,224 @@
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+/// Seconds in a (UTC) day
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Word count of a directory at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub words: usize,
+}
+
+impl Snapshot {
+    /// Snapshot of a word count taken now
+    pub fn now(words: usize) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Self { timestamp, words }
+    }
+
+    /// Day of the snapshot, counted in UTC days since the Unix epoch
+    pub fn day(&self) -> u64 {
+        self.timestamp / SECONDS_PER_DAY
+    }
+}
+
+/// Words written on a day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DailyWords {
+    /// UTC days since the Unix epoch
+    pub day: u64,
+    /// Words at the end of the day, as of its last snapshot or an earlier one
+    pub words: usize,
+    /// Words added since the day before, negative when more were cut than written
+    pub added: isize,
+}
+
+/// Appends a snapshot of a directory to the history file, created when missing.
+/// Each line holds the timestamp, the word count and the directory, tab-separated.
+pub async fn record_snapshot(history: &Path, root: &Path, snapshot: Snapshot) -> Result<(), Error> {
+    if let Some(parent) = history.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).await?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history)
+        .await?;
+    let line = format!(
+        "{}\t{}\t{}\n",
+        snapshot.timestamp,
+        snapshot.words,
+        root.display()
+    );
+    file.write_all(line.as_bytes()).await
+}
+
+/// Snapshots of a directory recorded in the history file, oldest first;
+/// none when the file does not exist yet
+pub async fn load_snapshots(history: &Path, root: &Path) -> Result<Vec<Snapshot>, Error> {
+    let text = match fs::read_to_string(history).await {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let root = root.display().to_string();
+    let mut snapshots = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let mut fields = line.splitn(3, '\t');
+        let (Some(timestamp), Some(words), Some(dir)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        if dir != root {
+            continue;
+        }
+        let (Ok(timestamp), Ok(words)) = (timestamp.parse(), words.parse()) else {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{}:{}: invalid snapshot", history.display(), number + 1),
+            ));
+        };
+        snapshots.push(Snapshot { timestamp, words });
+    }
+    snapshots.sort_by_key(|snapshot| snapshot.timestamp);
+    Ok(snapshots)
+}
+
+/// Words added on each of the `days` days up to `today`, oldest first.
+/// A day's count is its last snapshot, or the previous one carried over;
+/// the first day recorded only counts the words added after its first snapshot.
+pub fn daily_words(snapshots: &[Snapshot], today: u64, days: usize) -> Vec<DailyWords> {
+    let first = today.saturating_sub(days.saturating_sub(1) as u64);
+    // Words as of the end of a day, if anything was recorded by then
+    let words_at = |day: u64| {
+        snapshots
+            .iter()
+            .take_while(|snapshot| snapshot.day() <= day)
+            .last()
+            .map(|snapshot| snapshot.words)
+    };
+
+    (first..=today)
+        .map(|day| {
+            let words = words_at(day);
+            let before = day.checked_sub(1).and_then(words_at).or_else(|| {
+                snapshots
+                    .first()
+                    .filter(|s| s.day() == day)
+                    .map(|s| s.words)
+            });
+            DailyWords {
+                day,
+                words: words.unwrap_or(0),
+                added: match (words, before) {
+                    (Some(words), Some(before)) => words as isize - before as isize,
+                    _ => 0,
+                },
+            }
+        })
+        .collect()
+}
+
+/// Calendar date of a UTC day since the Unix epoch, as 'YYYY-MM-DD'
+pub fn format_day(day: u64) -> String {
+    // Civil from days, after Howard Hinnant's algorithm
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// UTC time of a number of seconds since the Unix epoch, as RFC 3339, e.g.
+/// '2024-05-01T09:30:00Z'
+pub fn format_timestamp(seconds: u64) -> String {
+    let time = seconds % SECONDS_PER_DAY;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        format_day(seconds / SECONDS_PER_DAY),
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn at(day: u64, hour: u64, words: usize) -> Snapshot {
+        Snapshot {
+            timestamp: day * SECONDS_PER_DAY + hour * 3600,
+            words,
+        }
+    }
+
+    #[test]
+    fn test_daily_words() {
+        let snapshots = [
+            at(10, 9, 1000),
+            at(10, 20, 1500),
+            at(12, 8, 2100),
+            at(13, 8, 2000),
+        ];
+        let days = daily_words(&snapshots, 14, 6);
+
+        let added: Vec<_> = days.iter().map(|d| (d.day, d.words, d.added)).collect();
+        assert_eq!(
+            added,
+            vec![
+                (9, 0, 0),
+                (10, 1500, 500),
+                (11, 1500, 0),
+                (12, 2100, 600),
+                (13, 2000, -100),
+                (14, 2000, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_day() {
+        assert_eq!(format_day(0), "1970-01-01");
+        assert_eq!(format_day(19_782), "2024-02-29");
+        assert_eq!(format_day(20_741), "2026-10-15");
+        assert_eq!(format_timestamp(1_714_555_807), "2024-05-01T09:30:07Z");
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_history() {
+        let temp = TempDir::new().unwrap();
+        let history = temp.path().join("mfp").join("history.tsv");
+        let (novel, notes) = (Path::new("/home/me/novel"), Path::new("/home/me/notes"));
+
+        assert!(load_snapshots(&history, novel).await.unwrap().is_empty());
+        record_snapshot(&history, novel, at(2, 0, 200))
+            .await
+            .unwrap();
+        record_snapshot(&history, notes, at(1, 0, 50))
+            .await
+            .unwrap();
+        record_snapshot(&history, novel, at(1, 0, 100))
+            .await
+            .unwrap();
+
+        let snapshots = load_snapshots(&history, novel).await.unwrap();
+        assert_eq!(snapshots, vec![at(1, 0, 100), at(2, 0, 200)]);
+    }
+}
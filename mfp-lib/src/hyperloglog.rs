@@ -0,0 +1,149 @@
+use crate::utils::stable_hash;
+
+/// Smallest precision of a sketch, 16 registers
+pub const MIN_SKETCH_PRECISION: u8 = 4;
+/// Largest precision of a sketch, 262144 registers
+pub const MAX_SKETCH_PRECISION: u8 = 18;
+/// Precision used by default, 16384 registers for a standard error of 0.81%
+pub const DEFAULT_SKETCH_PRECISION: u8 = 14;
+
+/// HyperLogLog sketch estimating the number of distinct words in constant memory:
+/// one byte per register, 2^precision registers. The estimate has a relative
+/// standard error of about 1.04 / sqrt(2^precision) - 0.81% at the default
+/// precision of 14, so within 2.4% of the exact count 99% of the time - whatever
+/// the number of words. Sketches of the same precision merge into the sketch of
+/// the union of their words, so that corpus counts do not hold any word.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// Empty sketch of 2^precision registers, the precision being brought within
+    /// `MIN_SKETCH_PRECISION..=MAX_SKETCH_PRECISION`
+    pub fn new(precision: u8) -> Self {
+        let precision = precision.clamp(MIN_SKETCH_PRECISION, MAX_SKETCH_PRECISION);
+        Self {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    /// Number of hash bits choosing the register of a word
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Relative standard error of the estimates
+    pub fn standard_error(&self) -> f64 {
+        1.04 / (self.registers.len() as f64).sqrt()
+    }
+
+    /// Adds a word, which is counted once however often it is added
+    pub fn insert(&mut self, word: &str) {
+        let hash = stable_hash(word.as_bytes());
+        let index = (hash >> (64 - self.precision)) as usize;
+        // Position of the first set bit among the remaining ones, bounded by a
+        // sentinel bit for hashes whose remaining bits are all clear
+        let rank = ((hash << self.precision) | (1 << (self.precision - 1))).leading_zeros() + 1;
+        self.registers[index] = self.registers[index].max(rank as u8);
+    }
+
+    /// Adds the words of another sketch, ignored unless of the same precision
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        if other.precision != self.precision {
+            return;
+        }
+        for (register, &theirs) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(theirs);
+        }
+    }
+
+    /// Estimated number of distinct words added
+    pub fn estimate(&self) -> usize {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&register| 2f64.powi(-i32::from(register)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+
+        // Small cardinalities are counted more precisely from the empty registers
+        let empty = self
+            .registers
+            .iter()
+            .filter(|&&register| register == 0)
+            .count();
+        if estimate <= 2.5 * m && empty > 0 {
+            (m * (m / empty as f64).ln()).round() as usize
+        } else {
+            estimate.round() as usize
+        }
+    }
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_SKETCH_PRECISION)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_within_error_bound() {
+        let mut sketch = HyperLogLog::default();
+        assert_eq!(sketch.estimate(), 0);
+        for word in ["a", "b", "a", "c"] {
+            sketch.insert(word);
+        }
+        assert_eq!(sketch.estimate(), 3);
+
+        for count in [1_000, 100_000] {
+            let mut sketch = HyperLogLog::default();
+            for i in 0..count {
+                sketch.insert(&format!("word{i}"));
+                sketch.insert(&format!("word{}", i / 2));
+            }
+            let error = (sketch.estimate() as f64 - count as f64).abs() / count as f64;
+            assert!(error < 3.0 * sketch.standard_error(), "{count}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut first = HyperLogLog::new(12);
+        let mut second = HyperLogLog::new(12);
+        for i in 0..20_000 {
+            first.insert(&format!("word{i}"));
+            second.insert(&format!("word{}", i + 10_000));
+        }
+        first.merge(&second);
+        let error = (first.estimate() as f64 - 30_000.0).abs() / 30_000.0;
+        assert!(error < 3.0 * first.standard_error(), "{error}");
+
+        // Sketches of another precision are left out
+        let mut other = HyperLogLog::new(10);
+        other.insert("extra");
+        let before = first.clone();
+        first.merge(&other);
+        assert_eq!(first, before);
+    }
+
+    #[test]
+    fn test_precision_bounds() {
+        assert_eq!(HyperLogLog::new(0).precision(), MIN_SKETCH_PRECISION);
+        assert_eq!(HyperLogLog::new(30).precision(), MAX_SKETCH_PRECISION);
+        assert!((HyperLogLog::default().standard_error() - 0.008125).abs() < 1e-6);
+    }
+}
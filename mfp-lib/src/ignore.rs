@@ -0,0 +1,137 @@
+use crate::glob::Glob;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs;
+
+/// Files whose rules leave paths out of directory walks, in order of precedence
+pub(crate) const IGNORE_FILE_NAMES: [&str; 2] = [".gitignore", ".mfpignore"];
+
+/// Rules of an ignore file in the `.gitignore` syntax, applying below its directory:
+/// patterns without a `/` match names at any depth, others paths relative to the
+/// directory, a trailing `/` restricts a pattern to directories and a leading `!`
+/// includes again what an earlier pattern left out
+#[derive(Debug)]
+pub(crate) struct IgnoreFile {
+    dir: PathBuf,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    glob: Glob,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl IgnoreFile {
+    /// Reads an ignore file of a directory, if there is one
+    pub(crate) async fn read(dir: &Path, name: &str) -> Option<Self> {
+        let content = fs::read_to_string(dir.join(name)).await.ok()?;
+        Some(Self::parse(dir, &content))
+    }
+
+    fn parse(dir: &Path, content: &str) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            rules: content.lines().filter_map(parse_rule).collect(),
+        }
+    }
+
+    // Whether the last rule matching a path ignores it or includes it again, if any matches
+    fn decides(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.dir).ok()?;
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.dir_only) && rule.glob.matches(relative))
+            .map(|rule| !rule.negated)
+    }
+}
+
+/// Whether a path is ignored by the ignore files of the directories above it, the
+/// files being given from the outermost, as the deeper ones take precedence
+pub(crate) fn is_ignored(files: &[Arc<IgnoreFile>], path: &Path, is_dir: bool) -> bool {
+    files
+        .iter()
+        .rev()
+        .find_map(|file| file.decides(path, is_dir))
+        .unwrap_or(false)
+}
+
+// Parses a line of an ignore file, None for blank lines, comments and invalid patterns
+fn parse_rule(line: &str) -> Option<Rule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negated, pattern) = match line.strip_prefix('!') {
+        Some(pattern) => (true, pattern),
+        // '\#' and '\!' stand for patterns starting with these characters
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+    let (dir_only, pattern) = match pattern.strip_suffix('/') {
+        Some(pattern) => (true, pattern),
+        None => (false, pattern),
+    };
+    if pattern.is_empty() {
+        return None;
+    }
+    // Globs without a '/' match file names, so those are spelled relative to the
+    // directory, './' anchoring a pattern whose only '/' is the leading one
+    let glob = match pattern.strip_prefix('/') {
+        Some(pattern) => format!("./{pattern}"),
+        None if pattern.contains('/') => format!("./{pattern}"),
+        None => format!("**/{pattern}"),
+    };
+    Some(Rule {
+        glob: glob.parse().ok()?,
+        negated,
+        dir_only,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_rules() {
+        let root = Path::new("/repo");
+        let file = IgnoreFile::parse(
+            root,
+            "# build output\n\n*.log\n!keep.log\n/target\ndocs/*.pdf\nnode_modules/\n.env\n",
+        );
+        let ignored = |path: &str, is_dir| is_ignored(&[], Path::new(path), is_dir);
+        assert!(!ignored("/repo/a.log", false));
+
+        let files = [Arc::new(file)];
+        let ignored = |path: &str, is_dir| is_ignored(&files, Path::new(path), is_dir);
+        assert!(ignored("/repo/a.log", false));
+        assert!(ignored("/repo/src/deep/b.log", false));
+        assert!(!ignored("/repo/src/keep.log", false));
+        assert!(ignored("/repo/target", true));
+        assert!(!ignored("/repo/src/target", true));
+        assert!(ignored("/repo/docs/guide.pdf", false));
+        assert!(!ignored("/repo/docs/old/guide.pdf", false));
+        assert!(ignored("/repo/web/node_modules", true));
+        assert!(!ignored("/repo/node_modules", false));
+        assert!(ignored("/repo/.env", false));
+        assert!(!ignored("/repo/prod.env", false));
+        assert!(!ignored("/elsewhere/a.log", false));
+    }
+
+    #[test]
+    fn test_deeper_files_take_precedence() {
+        let files = [
+            Arc::new(IgnoreFile::parse(Path::new("/repo"), "*.md\n")),
+            Arc::new(IgnoreFile::parse(
+                Path::new("/repo/docs"),
+                "!*.md\n\\#notes.md\n",
+            )),
+        ];
+        let ignored = |path: &str| is_ignored(&files, Path::new(path), false);
+        assert!(ignored("/repo/README.md"));
+        assert!(!ignored("/repo/docs/intro.md"));
+        assert!(ignored("/repo/docs/#notes.md"));
+    }
+}
@@ -0,0 +1,60 @@
+use crate::sources::entry_archive;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Extension appended to a file name to name its line index sidecar
+pub const LINE_INDEX_EXTENSION: &str = "lineidx";
+
+/// Path of the line index sidecar of a file, e.g. 'notes.txt.lineidx'
+pub fn line_index_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(LINE_INDEX_EXTENSION);
+    path.with_file_name(name)
+}
+
+/// Writes the line index sidecar next to a file: one decimal byte offset per line,
+/// the Nth entry being the start of the Nth counted line. Archive entries have no
+/// location of their own to write it next to, and are refused.
+pub async fn write_line_index(path: &Path, offsets: &[u64]) -> Result<PathBuf, Error> {
+    if entry_archive(path).is_some() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "archive entries have no line index",
+        ));
+    }
+    let sidecar = line_index_path(path);
+    let content: String = offsets.iter().map(|offset| format!("{offset}\n")).collect();
+    fs::write(&sidecar, content).await?;
+    Ok(sidecar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_line_index_path() {
+        assert_eq!(
+            line_index_path(Path::new("dir/notes.txt")),
+            PathBuf::from("dir/notes.txt.lineidx")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_line_index() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("a.txt");
+
+        let sidecar = write_line_index(&file, &[0, 12, 40]).await.unwrap();
+        assert_eq!(sidecar, temp.path().join("a.txt.lineidx"));
+        assert_eq!(std::fs::read_to_string(sidecar).unwrap(), "0\n12\n40\n");
+
+        let entry = PathBuf::from(format!("{}!/b.txt", temp.path().join("a.zip").display()));
+        let error = write_line_index(&entry, &[0]).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert_eq!(std::fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,314 @@
+use crate::bloom::BLOOM_EXTENSION;
+use crate::error::TextProcessorError;
+use crate::glob::Glob;
+use crate::ignore::{is_ignored, IgnoreFile, IGNORE_FILE_NAMES};
+use crate::index::LINE_INDEX_EXTENSION;
+use crate::types::Warning;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs;
+
+/// Repository directory of git, never walked when honouring ignore files
+const GIT_DIR: &str = ".git";
+
+/// How directories are walked into the files they contain
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Depth directories are walked to, files directly inside a directory being
+    /// at depth 1, like `find -maxdepth`
+    pub max_depth: Option<usize>,
+    /// Files and directories skipped, e.g. `**/node_modules/**`
+    pub excludes: Vec<Glob>,
+    /// Whether the `.gitignore` and `.mfpignore` files met along the walk leave
+    /// paths out as well, along with the `.git` directories
+    pub ignore_files: bool,
+}
+
+/// Expands directories into the files they contain, walking them recursively.
+/// Files, and paths which don't exist, are kept as given, even when they match
+/// an exclude pattern or an ignore file.
+pub async fn expand_paths(
+    paths: Vec<PathBuf>,
+    options: &WalkOptions,
+) -> Result<Vec<PathBuf>, TextProcessorError> {
+    let (files, _) = expand_paths_with_warnings(paths, options).await?;
+    Ok(files)
+}
+
+/// Expands directories like `expand_paths`, also returning a warning for each
+/// symlink left out of the walk
+pub async fn expand_paths_with_warnings(
+    paths: Vec<PathBuf>,
+    options: &WalkOptions,
+) -> Result<(Vec<PathBuf>, Vec<Warning>), TextProcessorError> {
+    let mut files = Vec::new();
+    let mut warnings = Vec::new();
+    for path in paths {
+        match fs::metadata(&path).await {
+            Ok(metadata) if metadata.is_dir() => {
+                walk_dir(&path, options, &mut files, &mut warnings).await?
+            }
+            _ => files.push(path),
+        }
+    }
+    Ok((files, warnings))
+}
+
+// Lists the files below a directory in a stable order, leaving out Bloom filter sidecars.
+// Symlinked files are included but symlinked directories are not followed, avoiding cycles.
+// Excluded and ignored directories are not entered at all.
+async fn walk_dir(
+    root: &Path,
+    options: &WalkOptions,
+    all_files: &mut Vec<PathBuf>,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), TextProcessorError> {
+    let mut files = Vec::new();
+    let mut unfollowed = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0, Vec::new())];
+
+    while let Some((dir, depth, mut ignore_files)) = pending.pop() {
+        if options.max_depth.is_some_and(|max| depth >= max) {
+            continue;
+        }
+        if options.ignore_files {
+            for name in IGNORE_FILE_NAMES {
+                if let Some(file) = IgnoreFile::read(&dir, name).await {
+                    ignore_files.push(Arc::new(file));
+                }
+            }
+        }
+
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if options.excludes.iter().any(|glob| glob.matches(&path)) {
+                continue;
+            }
+            let file_type = entry.file_type().await?;
+            if options.ignore_files
+                && ((file_type.is_dir() && entry.file_name() == GIT_DIR)
+                    || is_ignored(&ignore_files, &path, file_type.is_dir()))
+            {
+                continue;
+            }
+            if file_type.is_dir() {
+                pending.push((path, depth + 1, ignore_files.clone()));
+            } else if file_type.is_file() {
+                if !is_sidecar(&path).await {
+                    files.push(path);
+                }
+            } else if file_type.is_symlink() {
+                match fs::metadata(&path).await {
+                    Ok(metadata) if metadata.is_file() => files.push(path),
+                    _ => unfollowed.push(path),
+                }
+            }
+        }
+    }
+
+    files.sort();
+    unfollowed.sort();
+    all_files.extend(files);
+    warnings.extend(
+        unfollowed
+            .into_iter()
+            .map(|path| Warning::SymlinkNotFollowed { path }),
+    );
+    Ok(())
+}
+
+// Whether a file is the line index or Bloom filter sidecar of a file next to it,
+// e.g. 'a.txt.lineidx' or 'a.txt.bloom'
+async fn is_sidecar(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == LINE_INDEX_EXTENSION || ext == BLOOM_EXTENSION)
+        && fs::try_exists(path.with_extension(""))
+            .await
+            .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs as std_fs;
+    use tempfile::TempDir;
+
+    fn create_tree() -> TempDir {
+        let temp = TempDir::new().unwrap();
+        std_fs::create_dir_all(temp.path().join("a/b")).unwrap();
+        for file in ["top.txt", "a/mid.txt", "a/b/deep.txt"] {
+            std_fs::write(temp.path().join(file), "content").unwrap();
+        }
+        temp
+    }
+
+    fn max_depth(depth: usize) -> WalkOptions {
+        WalkOptions {
+            max_depth: Some(depth),
+            ..WalkOptions::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expand_walks_directories_recursively() {
+        let temp = create_tree();
+        let root = temp.path().to_path_buf();
+        let missing = PathBuf::from("missing.txt");
+
+        let files = expand_paths(vec![root.clone(), missing.clone()], &WalkOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            files,
+            vec![
+                root.join("a/b/deep.txt"),
+                root.join("a/mid.txt"),
+                root.join("top.txt"),
+                missing
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expand_respects_max_depth() {
+        let temp = create_tree();
+        let root = temp.path().to_path_buf();
+
+        let files = expand_paths(vec![root.clone()], &max_depth(1))
+            .await
+            .unwrap();
+        assert_eq!(files, vec![root.join("top.txt")]);
+
+        let files = expand_paths(vec![root.clone()], &max_depth(2))
+            .await
+            .unwrap();
+        assert_eq!(files, vec![root.join("a/mid.txt"), root.join("top.txt")]);
+
+        assert!(expand_paths(vec![root], &max_depth(0))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expand_skips_excluded_paths() {
+        let temp = create_tree();
+        let root = temp.path().to_path_buf();
+        let glob = |pattern: &str| pattern.parse::<Glob>().unwrap();
+
+        let options = WalkOptions {
+            excludes: vec![glob("**/a/b/**")],
+            ..WalkOptions::default()
+        };
+        let files = expand_paths(vec![root.clone()], &options).await.unwrap();
+        assert_eq!(files, vec![root.join("a/mid.txt"), root.join("top.txt")]);
+
+        // A directory matching by name is not entered, a file given as such is kept
+        let options = WalkOptions {
+            excludes: vec![glob("a"), glob("top.*")],
+            ..WalkOptions::default()
+        };
+        let files = expand_paths(vec![root.clone(), root.join("a/b/deep.txt")], &options)
+            .await
+            .unwrap();
+        assert_eq!(files, vec![root.join("a/b/deep.txt")]);
+    }
+
+    #[tokio::test]
+    async fn test_expand_honours_ignore_files() {
+        let temp = create_tree();
+        let root = temp.path().to_path_buf();
+        std_fs::create_dir_all(root.join(".git")).unwrap();
+        std_fs::write(root.join(".git/HEAD"), "ref: refs/heads/main").unwrap();
+        std_fs::write(root.join(".gitignore"), "b/\n*.log\n").unwrap();
+        std_fs::write(root.join("a/.mfpignore"), "!*.log\n").unwrap();
+        for file in ["debug.log", "a/kept.log"] {
+            std_fs::write(root.join(file), "content").unwrap();
+        }
+
+        let options = WalkOptions {
+            ignore_files: true,
+            ..WalkOptions::default()
+        };
+        let files = expand_paths(vec![root.clone()], &options).await.unwrap();
+        assert_eq!(
+            files,
+            vec![
+                root.join(".gitignore"),
+                root.join("a/.mfpignore"),
+                root.join("a/kept.log"),
+                root.join("a/mid.txt"),
+                root.join("top.txt"),
+            ]
+        );
+
+        let files = expand_paths(vec![root.clone()], &WalkOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(files.len(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_expand_skips_bloom_sidecars() {
+        let temp = create_tree();
+        let root = temp.path().to_path_buf();
+        for file in ["top.txt.bloom", "orphan.bloom"] {
+            std_fs::write(root.join(file), "filter").unwrap();
+        }
+
+        let files = expand_paths(vec![root.clone()], &max_depth(1))
+            .await
+            .unwrap();
+        assert_eq!(files, vec![root.join("orphan.bloom"), root.join("top.txt")]);
+
+        let sidecar = root.join("top.txt.bloom");
+        let files = expand_paths(vec![sidecar.clone()], &WalkOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(files, vec![sidecar]);
+    }
+
+    #[tokio::test]
+    async fn test_expand_skips_line_index_sidecars() {
+        let temp = create_tree();
+        let root = temp.path().to_path_buf();
+        for file in ["top.txt.lineidx", "orphan.lineidx"] {
+            std_fs::write(root.join(file), "0\n").unwrap();
+        }
+
+        let files = expand_paths(vec![root.clone()], &max_depth(1))
+            .await
+            .unwrap();
+        assert_eq!(
+            files,
+            vec![root.join("orphan.lineidx"), root.join("top.txt")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinks_not_followed() {
+        let temp = create_tree();
+        let root = temp.path().to_path_buf();
+        std::os::unix::fs::symlink(root.join("a"), root.join("link-dir")).unwrap();
+        std::os::unix::fs::symlink(root.join("top.txt"), root.join("link.txt")).unwrap();
+        std::os::unix::fs::symlink(root.join("gone.txt"), root.join("broken.txt")).unwrap();
+
+        let (files, warnings) = expand_paths_with_warnings(vec![root.clone()], &max_depth(1))
+            .await
+            .unwrap();
+        assert_eq!(files, vec![root.join("link.txt"), root.join("top.txt")]);
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::SymlinkNotFollowed {
+                    path: root.join("broken.txt")
+                },
+                Warning::SymlinkNotFollowed {
+                    path: root.join("link-dir")
+                },
+            ]
+        );
+    }
+}
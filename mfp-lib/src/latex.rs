@@ -0,0 +1,459 @@
+use crate::region::{LineParts, Region};
+
+/// Environments whose content is math, not text
+const MATH_ENVIRONMENTS: &[&str] = &[
+    "equation",
+    "align",
+    "alignat",
+    "gather",
+    "multline",
+    "flalign",
+    "eqnarray",
+    "math",
+    "displaymath",
+];
+
+/// Commands whose arguments are references or markup, not text
+const EXCLUDED_ARGUMENT_COMMANDS: &[&str] = &[
+    "label",
+    "ref",
+    "eqref",
+    "pageref",
+    "autoref",
+    "cref",
+    "Cref",
+    "cite",
+    "citep",
+    "citet",
+    "autocite",
+    "parencite",
+    "textcite",
+    "footcite",
+    "nocite",
+    "bibitem",
+    "usepackage",
+    "documentclass",
+    "input",
+    "include",
+    "includegraphics",
+    "bibliography",
+    "bibliographystyle",
+    "url",
+    "href",
+    "begin",
+    "end",
+];
+
+/// Environments whose content is table cells
+const TABLE_ENVIRONMENTS: &[&str] = &["tabular", "tabularx", "tabulary", "longtable", "tabu"];
+
+/// Environments whose content is quoted
+const QUOTE_ENVIRONMENTS: &[&str] = &["quote", "quotation", "displayquote"];
+
+/// Commands citing the keys of their argument
+const CITE_COMMANDS: &[&str] = &[
+    "cite",
+    "citep",
+    "citet",
+    "autocite",
+    "parencite",
+    "textcite",
+    "footcite",
+];
+
+/// End of the bibliography environment
+const BIBLIOGRAPHY_END: &str = "\\end{thebibliography}";
+
+/// Parts of LaTeX sources counted besides the text. Command names, the arguments
+/// of references and markup commands, and the preamble are never counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatexOptions {
+    /// Count the words of '%' comments
+    pub comments: bool,
+    /// Count the symbols of inline and display math, rather than skipping it
+    pub math: bool,
+}
+
+/// Strips LaTeX markup from lines, keeping the text that texcount would count.
+/// Math, regions and the preamble may span lines, so the filter is stateful.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LatexFilter {
+    options: LatexOptions,
+    /// Whether the bibliography environment is skipped
+    skip_bibliography: bool,
+    /// Number of cited keys seen so far
+    pub(crate) citations: usize,
+    /// Within a skipped bibliography environment
+    in_bibliography: bool,
+    /// Between '\documentclass' and '\begin{document}'
+    in_preamble: bool,
+    /// Closing delimiter of the math being read, if any
+    math_end: Option<String>,
+    /// Number of braces currently open
+    depth: usize,
+    /// Footnotes, captions, tables and quotes being read, innermost last
+    regions: Vec<OpenRegion>,
+}
+
+/// Region being read, and what closes it
+#[derive(Debug, Clone)]
+struct OpenRegion {
+    region: Region,
+    end: RegionEnd,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum RegionEnd {
+    /// Closing brace back to this depth, e.g. of '\footnote{..}'
+    Brace(usize),
+    /// End of this environment, e.g. 'tabular'
+    Environment(String),
+}
+
+impl LatexFilter {
+    pub(crate) fn new(options: LatexOptions, skip_bibliography: bool) -> Self {
+        Self {
+            options,
+            skip_bibliography,
+            ..Default::default()
+        }
+    }
+
+    /// Text of a line to count, markup replaced by spaces and split between
+    /// the body and regions
+    pub(crate) fn apply(&mut self, line: &str) -> LineParts {
+        let chars: Vec<char> = line.chars().collect();
+        let mut parts = LineParts::default();
+        let mut i = 0;
+
+        while i < chars.len() {
+            if self.in_bibliography {
+                let end: Vec<char> = BIBLIOGRAPHY_END.chars().collect();
+                if chars[i..].starts_with(&end) {
+                    self.in_bibliography = false;
+                    i += end.len();
+                } else {
+                    i += 1;
+                }
+                continue;
+            }
+            if let Some(end) = &self.math_end {
+                let end: Vec<char> = end.chars().collect();
+                if chars[i..].starts_with(&end) {
+                    parts.push(self.region(), ' ');
+                    i += end.len();
+                    self.math_end = None;
+                    continue;
+                }
+                if !self.options.math {
+                    i += 1;
+                    continue;
+                }
+            }
+
+            match chars[i] {
+                '%' if self.options.comments && !self.in_preamble => {
+                    parts.push(self.region(), ' ');
+                    parts.push_str(self.region(), &chars[i + 1..].iter().collect::<String>());
+                    break;
+                }
+                '%' => break,
+                '$' if chars.get(i + 1) == Some(&'$') => {
+                    self.math_end = Some("$$".to_string());
+                    i += 2;
+                }
+                '$' => {
+                    self.math_end = Some("$".to_string());
+                    i += 1;
+                }
+                '\\' => i = self.command(&chars, i, &mut parts),
+                '{' => {
+                    // Math groups such as '\frac{1}{2}' hold separate symbols
+                    if self.math_end.is_some() {
+                        parts.push(self.region(), ' ');
+                    }
+                    self.depth += 1;
+                    i += 1;
+                }
+                '}' => {
+                    self.depth = self.depth.saturating_sub(1);
+                    if self.math_end.is_some() {
+                        parts.push(self.region(), ' ');
+                    }
+                    if self.regions.last().map(|open| &open.end)
+                        == Some(&RegionEnd::Brace(self.depth))
+                    {
+                        self.regions.pop();
+                        parts.push(self.region(), ' ');
+                    }
+                    i += 1;
+                }
+                // Non-breaking spaces and table cell separators
+                '~' | '&' => {
+                    parts.push(self.region(), ' ');
+                    i += 1;
+                }
+                c => {
+                    if !self.in_preamble {
+                        parts.push(self.region(), c);
+                    }
+                    i += 1;
+                }
+            }
+        }
+
+        parts
+    }
+
+    // Region the text being read belongs to, if any
+    fn region(&self) -> Option<Region> {
+        self.regions.last().map(|open| open.region)
+    }
+
+    // Handles the command starting at a backslash, returning the index past it
+    fn command(&mut self, chars: &[char], start: usize, parts: &mut LineParts) -> usize {
+        let mut i = start + 1;
+        let Some(&next) = chars.get(i) else {
+            return i;
+        };
+        if !next.is_ascii_alphabetic() {
+            match next {
+                '[' => self.math_end = Some("\\]".to_string()),
+                '(' => self.math_end = Some("\\)".to_string()),
+                // Escaped characters such as '\%' are text
+                '%' | '$' | '&' | '#' | '_' | '{' | '}' if !self.in_preamble => {
+                    parts.push(self.region(), next)
+                }
+                _ => parts.push(self.region(), ' '),
+            }
+            return i + 1;
+        }
+
+        while chars.get(i).is_some_and(char::is_ascii_alphabetic) {
+            i += 1;
+        }
+        let name: String = chars[start + 1..i].iter().collect();
+        if chars.get(i) == Some(&'*') {
+            i += 1;
+        }
+        parts.push(self.region(), ' ');
+
+        let region = match name.as_str() {
+            "footnote" | "footnotetext" => Some(Region::Footnote),
+            "caption" => Some(Region::Caption),
+            "enquote" => Some(Region::Quote),
+            _ => None,
+        };
+        if let Some(region) = region {
+            // The short caption of '\caption[short]{long}' is not counted twice
+            while chars.get(i) == Some(&'[') {
+                i = skip_group(chars, i, '[', ']').0;
+            }
+            if chars.get(i) == Some(&'{') {
+                self.regions.push(OpenRegion {
+                    region,
+                    end: RegionEnd::Brace(self.depth),
+                });
+                self.depth += 1;
+                i += 1;
+            }
+            return i;
+        }
+
+        if !EXCLUDED_ARGUMENT_COMMANDS.contains(&name.as_str()) {
+            return i;
+        }
+
+        // Optional arguments, then the first mandatory one
+        while chars.get(i) == Some(&'[') {
+            i = skip_group(chars, i, '[', ']').0;
+        }
+        let (mut end, argument) = if chars.get(i) == Some(&'{') {
+            skip_group(chars, i, '{', '}')
+        } else {
+            (i, String::new())
+        };
+
+        match name.as_str() {
+            "documentclass" => self.in_preamble = true,
+            "begin" if argument == "document" => self.in_preamble = false,
+            "begin" if argument == "thebibliography" => {
+                self.in_bibliography = self.skip_bibliography;
+            }
+            name if CITE_COMMANDS.contains(&name) => {
+                self.citations += argument
+                    .split(',')
+                    .filter(|key| !key.trim().is_empty())
+                    .count();
+            }
+            "begin" => {
+                let environment = argument.trim_end_matches('*');
+                if MATH_ENVIRONMENTS.contains(&environment) {
+                    self.math_end = Some(format!("\\end{{{argument}}}"));
+                } else if TABLE_ENVIRONMENTS.contains(&environment) {
+                    // Position and column specifications, e.g. '{|l|c|}'
+                    while matches!(chars.get(end), Some('[' | '{')) {
+                        end = match chars[end] {
+                            '[' => skip_group(chars, end, '[', ']').0,
+                            _ => skip_group(chars, end, '{', '}').0,
+                        };
+                    }
+                    self.regions.push(OpenRegion {
+                        region: Region::Table,
+                        end: RegionEnd::Environment(argument),
+                    });
+                } else if QUOTE_ENVIRONMENTS.contains(&environment) {
+                    self.regions.push(OpenRegion {
+                        region: Region::Quote,
+                        end: RegionEnd::Environment(argument),
+                    });
+                }
+            }
+            "end"
+                if self.regions.last().map(|open| &open.end)
+                    == Some(&RegionEnd::Environment(argument)) =>
+            {
+                self.regions.pop();
+            }
+            _ => {}
+        }
+        end
+    }
+}
+
+// Skips a bracketed group, nesting included, returning the index past it and its content.
+// An unterminated group runs to the end of the line.
+fn skip_group(chars: &[char], start: usize, open: char, close: char) -> (usize, String) {
+    let mut depth = 0;
+    for (i, &c) in chars.iter().enumerate().skip(start) {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return (i + 1, chars[start + 1..i].iter().collect());
+            }
+        }
+    }
+    (chars.len(), chars[start + 1..].iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(options: LatexOptions, lines: &[&str]) -> Vec<String> {
+        let mut filter = LatexFilter::new(options, false);
+        lines
+            .iter()
+            .map(|line| {
+                filter
+                    .apply(line)
+                    .text
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_latex_markup_is_stripped() {
+        assert_eq!(
+            filter(
+                LatexOptions::default(),
+                &[
+                    "\\section{Intro} Some \\textbf{bold} text~here % a comment",
+                    "See \\ref{fig:one} and $x^2 + y$ or \\cite[p.~3]{knuth} 50\\% done.",
+                ]
+            ),
+            vec!["Intro Some bold text here", "See and or 50% done."]
+        );
+    }
+
+    #[test]
+    fn test_math_and_preamble_span_lines() {
+        assert_eq!(
+            filter(
+                LatexOptions::default(),
+                &[
+                    "\\documentclass{article}",
+                    "\\title{Not counted}",
+                    "\\begin{document}",
+                    "Before \\begin{equation}",
+                    "a = b",
+                    "\\end{equation} after \\[ x \\] end",
+                ]
+            ),
+            vec!["", "", "", "Before", "", "after end"]
+        );
+    }
+
+    #[test]
+    fn test_comments_and_math_can_be_counted() {
+        let options = LatexOptions {
+            comments: true,
+            math: true,
+        };
+        assert_eq!(
+            filter(
+                options,
+                &[
+                    "Let $x = \\frac{1}{2}$ hold. % TODO cite",
+                    "\\begin{align*} a &= b \\end{align*}",
+                ]
+            ),
+            vec!["Let x = 1 2 hold. TODO cite", "a = b"]
+        );
+    }
+
+    #[test]
+    fn test_citations_are_counted_and_bibliography_skipped() {
+        let mut filter = LatexFilter::new(LatexOptions::default(), true);
+        let lines = [
+            "As shown \\citep[p.~2]{knuth84, lamport94} and \\cite{doe}.",
+            "\\begin{thebibliography}{9}",
+            "\\bibitem{knuth84} D. Knuth, The TeXbook.",
+            "\\end{thebibliography} Done",
+        ];
+        let counted: Vec<String> = lines
+            .iter()
+            .map(|line| {
+                filter
+                    .apply(line)
+                    .text
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        assert_eq!(counted, ["As shown and .", "", "", "Done"]);
+        assert_eq!(filter.citations, 3);
+    }
+
+    #[test]
+    fn test_regions_are_split_from_the_text() {
+        let mut filter = LatexFilter::new(LatexOptions::default(), false);
+        let body = filter.apply("Body\\footnote{A \\emph{nested} note} text.");
+        assert_eq!(
+            body.text.split_whitespace().collect::<Vec<_>>(),
+            ["Body", "text."]
+        );
+        assert_eq!(body.regions[0].0, Region::Footnote);
+        assert_eq!(body.regions[0].1.split_whitespace().count(), 3);
+
+        let lines = [
+            "\\begin{table}\\caption[Short]{Long caption}",
+            "\\begin{tabular}{|l|c|}",
+            "Name & Value \\\\ \\hline",
+            "\\end{tabular}\\end{table} After",
+        ];
+        let parts: Vec<LineParts> = lines.iter().map(|line| filter.apply(line)).collect();
+        assert_eq!(parts[0].regions[0].0, Region::Caption);
+        assert_eq!(parts[0].regions[0].1.split_whitespace().count(), 2);
+        assert_eq!(parts[2].regions[0].0, Region::Table);
+        assert_eq!(parts[2].regions[0].1.split_whitespace().count(), 2);
+        assert_eq!(parts[3].text.trim(), "After");
+        assert!(parts.iter().take(3).all(|part| part.text.trim().is_empty()));
+    }
+}
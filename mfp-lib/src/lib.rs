@@ -1,8 +1,95 @@
+mod analyzer;
+mod bloom;
+mod book;
+mod cache;
+mod cancel;
+mod citation;
+mod cooccurrence;
+mod corpus;
+mod delta;
+mod dictionary;
+mod encoding;
 mod error;
+#[cfg(any(test, feature = "testing"))]
+mod fault;
+mod fingerprint;
+mod frequency;
+mod glob;
+mod gzip;
+mod history;
+mod hyperloglog;
+mod ignore;
+mod index;
+mod input;
+mod latex;
+mod manifest;
+mod markdown;
+mod preset;
 mod processor;
+mod progress;
+mod quote;
+mod readability;
+mod reader;
+mod region;
+mod sampling;
+mod scorer;
+mod segmentation;
+mod sha256;
+mod sources;
+mod spill;
+mod stopwords;
+mod summary;
+#[cfg(any(test, feature = "testing"))]
+pub mod test_utils;
 mod types;
 mod utils;
+mod watch;
+mod word_regex;
 
+pub use analyzer::{Analyzer, Metrics};
+pub use bloom::{
+    bloom_filter_path, read_bloom_filter, write_bloom_filter, BloomFilter, BLOOM_EXTENSION,
+    DEFAULT_FALSE_POSITIVE_RATE,
+};
+pub use book::{parse_summary, SummaryChapter};
+pub use cancel::CancellationToken;
+pub use cooccurrence::{CooccurrenceMatrix, DEFAULT_WINDOW};
+pub use corpus::{
+    CorpusGenerator, CorpusManifest, GeneratedFile, CORPUS_MANIFEST_FILE, CORPUS_TEXTS_DIR,
+};
+pub use delta::{compute_delta, diff_lines, DiffOp, FileDelta};
+pub use dictionary::{Dictionary, Script};
+pub use encoding::Encoding;
 pub use error::TextProcessorError;
+#[cfg(any(test, feature = "testing"))]
+pub use fault::{Fault, FaultInjector};
+pub use fingerprint::{Fingerprint, Overlap, DEFAULT_NGRAM};
+pub use frequency::{WordFrequency, ZipfFit};
+pub use glob::Glob;
+pub use history::{
+    daily_words, format_day, format_timestamp, load_snapshots, record_snapshot, DailyWords,
+    Snapshot,
+};
+pub use hyperloglog::{
+    HyperLogLog, DEFAULT_SKETCH_PRECISION, MAX_SKETCH_PRECISION, MIN_SKETCH_PRECISION,
+};
+pub use index::{line_index_path, write_line_index, LINE_INDEX_EXTENSION};
+pub use input::{expand_paths, expand_paths_with_warnings, WalkOptions};
+pub use latex::LatexOptions;
+pub use manifest::{InputFile, InputManifest};
+pub use preset::Preset;
 pub use processor::TextProcessor;
-pub use types::FileProcessingResult;
+pub use progress::{FileEvent, Progress, StreamedResult};
+pub use readability::Readability;
+pub use reader::{LineRange, PartialRead};
+pub use region::{Region, RegionCounts};
+pub use sampling::{sample_paths, CorpusEstimate, Estimate, SampleSize};
+pub use scorer::{CommandScorer, Scorer};
+pub use segmentation::{CountingPolicy, JoinPolicy, Segmentation, TokenizerConfig};
+pub use sources::entry_archive;
+pub use spill::{MergedFrequency, SpillingFrequency};
+pub use stopwords::{StopWordFilter, StopWordList};
+pub use summary::{CorpusStats, LineStats, PatternMatches};
+pub use types::{FileProcessingResult, FileStatus, Warning};
+pub use watch::{WatchEvent, WatchingProcessor};
+pub use word_regex::WordRegex;
@@ -0,0 +1,142 @@
+use crate::history::format_timestamp;
+use crate::sha256::Sha256;
+use crate::sources::entry_archive;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs::{self, File};
+use tokio::io::AsyncReadExt;
+
+/// Bytes read at once while hashing a file
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Size, modification time and content hash of an input file
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct InputFile {
+    pub path: PathBuf,
+    /// Size in bytes
+    pub size: u64,
+    /// Last modification, as RFC 3339 in UTC
+    pub modified: String,
+    /// SHA-256 of the content, in lowercase hexadecimal
+    pub sha256: String,
+}
+
+/// Input files a report was made from, so that it can later be verified against
+/// the exact inputs it describes
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct InputManifest {
+    /// Creation of the manifest, as RFC 3339 in UTC
+    pub created: String,
+    /// Files in path order, archive entries being recorded as their archive
+    pub files: Vec<InputFile>,
+}
+
+impl InputManifest {
+    /// Manifest of the given files, each read and hashed once. The entries of an
+    /// archive, such as `archive.zip!/inner/path.txt`, are covered by the archive.
+    pub async fn from_paths<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Result<Self, Error> {
+        let mut paths: Vec<&Path> = paths
+            .into_iter()
+            .map(|path| entry_archive(path).unwrap_or(path))
+            .collect();
+        paths.sort_unstable();
+        paths.dedup();
+
+        let mut files = Vec::with_capacity(paths.len());
+        for path in paths {
+            files.push(input_file(path).await?);
+        }
+        Ok(Self {
+            created: format_timestamp(seconds_since_epoch(SystemTime::now())),
+            files,
+        })
+    }
+
+    /// Writes the manifest as pretty-printed JSON
+    pub async fn write(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, serde_json::to_vec_pretty(self)?).await
+    }
+}
+
+async fn input_file(path: &Path) -> Result<InputFile, Error> {
+    let mut file = File::open(path).await?;
+    let metadata = file.metadata().await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; READ_BUFFER_SIZE];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(InputFile {
+        path: path.to_path_buf(),
+        size: metadata.len(),
+        modified: format_timestamp(seconds_since_epoch(metadata.modified()?)),
+        sha256: hasher.finish_hex(),
+    })
+}
+
+fn seconds_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_input_manifest() {
+        let temp = TempDir::new().unwrap();
+        let (a, b) = (temp.path().join("a.txt"), temp.path().join("b.txt"));
+        std::fs::write(&a, "abc").unwrap();
+        std::fs::write(&b, "").unwrap();
+
+        let manifest = InputManifest::from_paths([b.as_path(), a.as_path(), b.as_path()])
+            .await
+            .unwrap();
+        assert_eq!(manifest.files.len(), 2);
+        assert_eq!(manifest.files[0].path, a);
+        assert_eq!(manifest.files[0].size, 3);
+        assert_eq!(
+            manifest.files[0].sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(manifest.files[1].size, 0);
+        assert!(manifest.files[1].modified.ends_with('Z'));
+
+        let path = temp.path().join("manifest.json");
+        manifest.write(&path).await.unwrap();
+        let read: InputManifest =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(read, manifest);
+        assert!(
+            InputManifest::from_paths([temp.path().join("missing").as_path()])
+                .await
+                .is_err()
+        );
+    }
+
+    // Test the entries of an archive are recorded as the archive itself
+    #[tokio::test]
+    async fn test_archive_entries_manifest() {
+        let archive = Path::new("tests/files/archives/texts.zip");
+        let entries = [
+            PathBuf::from("tests/files/archives/texts.zip!/inner/first.txt"),
+            PathBuf::from("tests/files/archives/texts.zip!/second.txt"),
+        ];
+        let manifest = InputManifest::from_paths(entries.iter().map(PathBuf::as_path))
+            .await
+            .unwrap();
+        assert_eq!(manifest.files.len(), 1);
+        assert_eq!(manifest.files[0].path, archive);
+        assert_eq!(
+            manifest.files[0].size,
+            std::fs::metadata(archive).unwrap().len()
+        );
+    }
+}
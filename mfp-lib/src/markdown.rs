@@ -0,0 +1,229 @@
+use crate::region::{LineParts, Region};
+
+/// Splits Markdown lines between the body text and footnotes, image and table
+/// captions, pipe tables and block quotes. Code blocks and footnotes span lines, so the filter is stateful.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MarkdownRegions {
+    /// Fence of the code block being read, if any
+    fence: Option<String>,
+    /// Within a footnote definition, continued by indented lines
+    in_footnote: bool,
+}
+
+impl MarkdownRegions {
+    /// Text of a line to count, split between the body and regions
+    pub(crate) fn apply(&mut self, line: &str) -> LineParts {
+        let mut parts = LineParts::default();
+        let trimmed = line.trim_start();
+
+        if let Some(fence) = &self.fence {
+            if trimmed.starts_with(fence.as_str()) {
+                self.fence = None;
+            }
+            parts.push_str(None, line);
+            return parts;
+        }
+        for fence in ["```", "~~~"] {
+            if trimmed.starts_with(fence) {
+                self.fence = Some(fence.to_string());
+                parts.push_str(None, line);
+                return parts;
+            }
+        }
+
+        if let Some(definition) = footnote_definition(trimmed) {
+            self.in_footnote = true;
+            push_inline(&mut parts, Some(Region::Footnote), definition);
+        } else if self.in_footnote && (trimmed.is_empty() || line.starts_with(['\t', ' '])) {
+            push_inline(&mut parts, Some(Region::Footnote), line);
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            self.in_footnote = false;
+            // Nested block quotes, e.g. '> > ..'
+            let quote = quote.trim_start_matches(['>', ' ']);
+            push_inline(&mut parts, Some(Region::Quote), quote);
+        } else if trimmed.starts_with('|') {
+            self.in_footnote = false;
+            // Delimiter rows such as '|---|:--:|' hold no words
+            if !trimmed.chars().all(|c| matches!(c, '|' | '-' | ':' | ' ')) {
+                push_inline(&mut parts, Some(Region::Table), &trimmed.replace('|', " "));
+            }
+        } else if let Some(caption) = trimmed.strip_prefix("Table:") {
+            // Pandoc table caption
+            self.in_footnote = false;
+            push_inline(&mut parts, Some(Region::Caption), caption);
+        } else {
+            self.in_footnote = false;
+            push_inline(&mut parts, None, line);
+        }
+        parts
+    }
+}
+
+/// Strips the markup of ATX headings, e.g. '## Title ##', so that the '#'
+/// are not counted as words. Lines of fenced code blocks are left as is.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MarkdownHeadings {
+    /// Fence of the code block being read, if any
+    fence: Option<&'static str>,
+}
+
+impl MarkdownHeadings {
+    /// Line without its heading markup, if a heading
+    pub(crate) fn apply<'a>(&mut self, line: &'a str) -> &'a str {
+        let trimmed = line.trim_start();
+        if let Some(fence) = self.fence {
+            if trimmed.starts_with(fence) {
+                self.fence = None;
+            }
+            return line;
+        }
+        if let Some(fence) = ["```", "~~~"].into_iter().find(|f| trimmed.starts_with(f)) {
+            self.fence = Some(fence);
+            return line;
+        }
+        heading_text(line).unwrap_or(line)
+    }
+}
+
+// Text of an ATX heading: up to three spaces of indentation, one to six '#'
+// followed by a space or nothing, and an optional closing sequence of '#'
+fn heading_text(line: &str) -> Option<&str> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let marked = &line[indent..];
+    let level = marked.len() - marked.trim_start_matches('#').len();
+    let text = &marked[level..];
+    if !(1..=6).contains(&level) || !(text.is_empty() || text.starts_with([' ', '\t'])) {
+        return None;
+    }
+
+    let text = text.trim_end();
+    let closed = text.trim_end_matches('#');
+    // The closing sequence must follow a space, e.g. not 'C#'
+    match closed.is_empty() || closed.ends_with([' ', '\t']) {
+        true => Some(closed),
+        false => Some(text),
+    }
+}
+
+// Text of a footnote definition, e.g. 'Some note.' of '[^1]: Some note.'
+fn footnote_definition(line: &str) -> Option<&str> {
+    let label = line.strip_prefix("[^")?;
+    let end = label.find("]:")?;
+    Some(&label[end + 2..])
+}
+
+// Appends a line to a region, moving image alt texts to captions and inline
+// footnotes to footnotes, and dropping footnote references and image targets
+fn push_inline(parts: &mut LineParts, region: Option<Region>, text: &str) {
+    let mut rest = text;
+    while let Some(start) = rest.find(['!', '^', '[']) {
+        let (before, from) = rest.split_at(start);
+        parts.push_str(region, before);
+
+        let inline = if let Some(image) = from.strip_prefix("![") {
+            image.find("](").and_then(|alt_end| {
+                let target = &image[alt_end + 2..];
+                let target_end = target.find(')')?;
+                let caption = &image[..alt_end];
+                Some((Some(Region::Caption), caption, alt_end + target_end + 5))
+            })
+        } else if let Some(note) = from.strip_prefix("^[") {
+            note.find(']')
+                .map(|end| (Some(Region::Footnote), &note[..end], end + 3))
+        } else if let Some(label) = from.strip_prefix("[^") {
+            label.find(']').map(|end| (None, "", end + 3))
+        } else {
+            None
+        };
+
+        match inline {
+            // References are dropped, keeping the punctuation following them attached
+            Some((None, _, len)) => rest = &from[len..],
+            Some((Some(inline_region), inline_text, len)) => {
+                parts.push_str(region, " ");
+                parts.push_str(Some(inline_region), inline_text);
+                parts.push_str(region, " ");
+                rest = &from[len..];
+            }
+            None => {
+                parts.push_str(region, &from[..1]);
+                rest = &from[1..];
+            }
+        }
+    }
+    parts.push_str(region, rest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(parts: &LineParts, region: Option<Region>) -> Vec<&str> {
+        let text = match region {
+            None => Some(parts.text.as_str()),
+            Some(region) => parts
+                .regions
+                .iter()
+                .find(|(r, _)| *r == region)
+                .map(|(_, text)| text.as_str()),
+        };
+        text.map_or(Vec::new(), |text| text.split_whitespace().collect())
+    }
+
+    #[test]
+    fn test_headings() {
+        let mut filter = MarkdownHeadings::default();
+        assert_eq!(filter.apply("# Title").split_whitespace().count(), 1);
+        assert_eq!(filter.apply("  ## Learning C# ##").trim(), "Learning C#");
+        assert_eq!(filter.apply("###"), "");
+        // Not headings
+        assert_eq!(filter.apply("#hashtag"), "#hashtag");
+        assert_eq!(filter.apply("####### Seven"), "####### Seven");
+        assert_eq!(filter.apply("    # Indented code"), "    # Indented code");
+        // Comments of code blocks are kept
+        assert_eq!(filter.apply("```sh"), "```sh");
+        assert_eq!(filter.apply("# a comment"), "# a comment");
+        assert_eq!(filter.apply("```"), "```");
+        assert_eq!(filter.apply("# Back"), " Back");
+    }
+
+    #[test]
+    fn test_inline_regions() {
+        let mut filter = MarkdownRegions::default();
+        let parts = filter.apply("A cat![A black cat](cat.png) sleeps[^1] here^[Inline note].");
+        assert_eq!(words(&parts, None), ["A", "cat", "sleeps", "here", "."]);
+        assert_eq!(words(&parts, Some(Region::Caption)), ["A", "black", "cat"]);
+        assert_eq!(words(&parts, Some(Region::Footnote)), ["Inline", "note"]);
+    }
+
+    #[test]
+    fn test_block_regions() {
+        let mut filter = MarkdownRegions::default();
+        let lines = [
+            "| Name | Value |",
+            "|------|:-----:|",
+            "| pi   | 3.14  |",
+            "",
+            "Table: Some constants",
+            "[^1]: A footnote",
+            "    continued here.",
+            "Back to the body.",
+        ];
+        let parts: Vec<LineParts> = lines.iter().map(|line| filter.apply(line)).collect();
+        assert_eq!(words(&parts[0], Some(Region::Table)), ["Name", "Value"]);
+        assert!(parts[1].regions.is_empty());
+        assert_eq!(
+            words(&parts[4], Some(Region::Caption)),
+            ["Some", "constants"]
+        );
+        assert_eq!(words(&parts[5], Some(Region::Footnote)), ["A", "footnote"]);
+        assert_eq!(
+            words(&parts[6], Some(Region::Footnote)),
+            ["continued", "here."]
+        );
+        assert_eq!(words(&parts[7], None), ["Back", "to", "the", "body."]);
+    }
+}
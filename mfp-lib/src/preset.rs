@@ -0,0 +1,75 @@
+use crate::latex::LatexOptions;
+use crate::segmentation::{CountingPolicy, JoinPolicy, Segmentation};
+use std::str::FromStr;
+
+/// Named bundle of tokenizer settings and exclusion rules approximating
+/// the counts of a well-known tool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Microsoft Word: any run of non-whitespace characters is a word,
+    /// contractions, hyphenated compounds and slash-joined terms included
+    MsWord,
+    /// TeXcount: words of the LaTeX text only, skipping the preamble, comments,
+    /// math, command names and the arguments of references and markup commands
+    TexCount,
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "msword" => Ok(Preset::MsWord),
+            "texcount" => Ok(Preset::TexCount),
+            _ => Err(format!(
+                "Invalid preset '{s}', expected 'msword' or 'texcount'"
+            )),
+        }
+    }
+}
+
+impl Preset {
+    /// How the preset splits lines into words
+    pub fn segmentation(&self) -> Segmentation {
+        match self {
+            Preset::MsWord => Segmentation::Whitespace,
+            Preset::TexCount => Segmentation::Unicode,
+        }
+    }
+
+    /// How the preset counts contractions, hyphenated compounds and slash-joined terms
+    pub fn counting_policy(&self) -> CountingPolicy {
+        match self {
+            Preset::MsWord => CountingPolicy {
+                apostrophes: JoinPolicy::Join,
+                hyphens: JoinPolicy::Join,
+                slashes: JoinPolicy::Join,
+            },
+            Preset::TexCount => CountingPolicy {
+                apostrophes: JoinPolicy::Join,
+                hyphens: JoinPolicy::Join,
+                slashes: JoinPolicy::Split,
+            },
+        }
+    }
+
+    /// Which LaTeX markup the preset excludes, if it reads LaTeX sources
+    pub fn latex(&self) -> Option<LatexOptions> {
+        match self {
+            Preset::MsWord => None,
+            Preset::TexCount => Some(LatexOptions::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preset_parsing() {
+        assert_eq!("msword".parse(), Ok(Preset::MsWord));
+        assert_eq!(Preset::TexCount.segmentation(), Segmentation::Unicode);
+        assert!("word".parse::<Preset>().is_err());
+    }
+}
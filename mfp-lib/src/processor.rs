@@ -1,17 +1,111 @@
+use crate::analyzer::{Analyzer, AnalyzerFactory, Metrics};
+use crate::bloom::BloomFilter;
+use crate::cache::ResultCache;
+use crate::cancel::CancellationToken;
+use crate::citation::{is_markdown, MarkdownCitations};
+use crate::dictionary::{Dictionary, Script};
+use crate::encoding::Encoding;
 use crate::error::TextProcessorError;
-use crate::types::FileProcessingResult;
-use crate::utils::{count_words, validate_file_path};
+#[cfg(any(test, feature = "testing"))]
+use crate::fault::{FaultInjector, FaultyReader};
+use crate::frequency::WordFrequency;
+use crate::glob::Glob;
+use crate::gzip::{decompress_file, has_gzip_extension, has_gzip_magic};
+use crate::hyperloglog::HyperLogLog;
+use crate::input::{expand_paths, WalkOptions};
+use crate::latex::{LatexFilter, LatexOptions};
+use crate::markdown::{MarkdownHeadings, MarkdownRegions};
+use crate::preset::Preset;
+use crate::progress::{FileEvent, Progress, StreamedResult};
+use crate::quote::QuotedSpans;
+use crate::readability::Readability;
+use crate::reader::{open_file, open_reader, LineReader, PartialRead};
+use crate::region::{LineParts, Region, RegionCounts};
+use crate::scorer::Scorer;
+use crate::segmentation::{CountingPolicy, Segmentation, Tokenizer, TokenizerConfig};
+use crate::sources::{archive_kind, read_archive, ArchiveKind, Source};
+use crate::spill::{MergedFrequency, SpillingFrequency};
+use crate::stopwords::StopWordFilter;
+use crate::summary::LineStats;
+use crate::types::{FileProcessingResult, FileStatus, Warning};
+use crate::utils::{stable_hash, validate_file_path};
+use crate::word_regex::WordRegex;
 use futures::future;
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::time::Instant;
-use tokio::fs::File;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tracing::{debug, error, info, instrument, trace};
+use futures::stream::{self, Stream, StreamExt};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::io::{self, Cursor, ErrorKind};
+use std::num::NonZeroUsize;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncRead;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::Semaphore;
+use tracing::{debug, error, info, instrument, trace, warn, Instrument};
+
+/// Default number of concurrent external scorer invocations
+const DEFAULT_SCORER_CONCURRENCY: usize = 4;
+/// Byte order mark, as left at the start of decoded text
+const BOM: char = '\u{feff}';
 
 #[derive(Debug, Default)]
 pub struct TextProcessor {
     results: HashMap<PathBuf, FileProcessingResult>,
+    scorer: Option<Arc<ScorerHandle>>,
+    partial: Option<PartialRead>,
+    file_partials: HashMap<PathBuf, PartialRead>,
+    line_offsets: bool,
+    walk: WalkOptions,
+    concurrency: Option<usize>,
+    timeout: Option<Duration>,
+    max_file_size: Option<u64>,
+    cache_dir: Option<PathBuf>,
+    decompress: bool,
+    encoding: Option<Encoding>,
+    include_binary: bool,
+    #[cfg(any(test, feature = "testing"))]
+    fault_injector: FaultInjector,
+    counting: Arc<CountingOptions>,
+    frequency_spill: Option<SpillingFrequency>,
+    result_sender: Option<UnboundedSender<StreamedResult>>,
+    progress_sender: Option<UnboundedSender<Progress>>,
+    event_sender: Option<UnboundedSender<FileEvent>>,
+}
+
+/// Settings of how the words of a file are counted and what is recorded of them,
+/// shared by the worker of each file
+#[derive(Debug, Clone, Default)]
+struct CountingOptions {
+    segmentation: Segmentation,
+    segmentation_rules: Vec<(Glob, Segmentation)>,
+    counting_policy: CountingPolicy,
+    tokenizer_config: TokenizerConfig,
+    count_pattern: Option<WordRegex>,
+    dictionaries: Vec<(Script, Arc<Dictionary>)>,
+    stop_words: Option<Arc<StopWordFilter>>,
+    frequencies: bool,
+    unique_words: bool,
+    unique_words_precision: Option<u8>,
+    bloom_filters: Option<f64>,
+    // Set when no word text may be kept, so that the derived default keeps it
+    no_content_retention: bool,
+    case_insensitive: bool,
+    readability: bool,
+    latex: Option<LatexOptions>,
+    citations: bool,
+    markdown_headings: bool,
+    regions: Option<Vec<Region>>,
+    analyzers: Vec<AnalyzerFactory>,
+}
+
+/// External scorer together with the permits bounding its concurrent invocations
+#[derive(Debug)]
+struct ScorerHandle {
+    scorer: Arc<dyn Scorer>,
+    permits: Semaphore,
 }
 
 impl TextProcessor {
@@ -19,14 +113,407 @@ impl TextProcessor {
     pub fn new() -> Self {
         Self {
             results: HashMap::new(),
+            scorer: None,
+            partial: None,
+            file_partials: HashMap::new(),
+            line_offsets: false,
+            walk: WalkOptions::default(),
+            concurrency: None,
+            timeout: None,
+            max_file_size: None,
+            cache_dir: None,
+            decompress: false,
+            encoding: None,
+            include_binary: false,
+            #[cfg(any(test, feature = "testing"))]
+            fault_injector: FaultInjector::default(),
+            counting: Arc::default(),
+            frequency_spill: None,
+            result_sender: None,
+            progress_sender: None,
+            event_sender: None,
         }
     }
 
-    /// Processes multiple files concurrently
-    #[instrument(skip(self, file_paths), fields(count = file_paths.len()))]
+    /// Records the byte offset of each counted line start in the file results.
+    /// Gzip files get none, their offsets not pointing into the file on disk.
+    pub fn with_line_offsets(mut self, enabled: bool) -> Self {
+        self.line_offsets = enabled;
+        self
+    }
+
+    /// Splits lines into words along the given boundaries, whitespace by default
+    pub fn with_segmentation(mut self, segmentation: Segmentation) -> Self {
+        self.counting_mut().segmentation = segmentation;
+        self
+    }
+
+    /// Splits the lines of files matching a pattern along other boundaries.
+    /// The last matching rule wins over earlier ones and over `with_segmentation`.
+    pub fn with_segmentation_rule(mut self, pattern: Glob, segmentation: Segmentation) -> Self {
+        self.counting_mut()
+            .segmentation_rules
+            .push((pattern, segmentation));
+        self
+    }
+
+    /// Counts contractions, hyphenated compounds and slash-joined terms as one
+    /// word or several, rather than as the segmentation mode splits them
+    pub fn with_counting_policy(mut self, policy: CountingPolicy) -> Self {
+        self.counting_mut().counting_policy = policy;
+        self
+    }
+
+    /// Also splits words on custom delimiters, e.g. the separators of log lines,
+    /// or defines words with a regex
+    pub fn with_tokenizer_config(mut self, config: TokenizerConfig) -> Self {
+        self.counting_mut().tokenizer_config = config;
+        self
+    }
+
+    /// Counts the occurrences of a regex instead of the words, per line and per
+    /// file, as `grep -o` finds them: the segmentation mode, delimiters, counting
+    /// policy and dictionaries do not apply
+    pub fn with_count_pattern(mut self, pattern: WordRegex) -> Self {
+        self.counting_mut().count_pattern = Some(pattern);
+        self
+    }
+
+    /// Counts like a well-known tool, setting the segmentation mode and counting
+    /// policy of the preset and excluding what that tool does not count, e.g. LaTeX markup.
+    /// Later `with_segmentation` and `with_counting_policy` calls override the preset's.
+    pub fn with_preset(mut self, preset: Preset) -> Self {
+        let counting = self.counting_mut();
+        counting.segmentation = preset.segmentation();
+        counting.counting_policy = preset.counting_policy();
+        counting.latex = preset.latex();
+        self
+    }
+
+    /// Reads files as LaTeX sources, counting the words of the text only: command
+    /// names and the preamble are skipped, and so are comments and math unless
+    /// the options count them
+    pub fn with_latex(mut self, options: LatexOptions) -> Self {
+        self.counting_mut().latex = Some(options);
+        self
+    }
+
+    /// Excludes citation keys and bibliography sections of LaTeX sources, with `with_latex`,
+    /// and of Markdown files from the counts, recording the citations of each file instead
+    pub fn with_citations(mut self, enabled: bool) -> Self {
+        self.counting_mut().citations = enabled;
+        self
+    }
+
+    /// Strips the `#` markup of Markdown headings before counting, which otherwise
+    /// counts as words like other symbols
+    pub fn with_markdown_headings(mut self, enabled: bool) -> Self {
+        self.counting_mut().markdown_headings = enabled;
+        self
+    }
+
+    /// Counts the words of footnotes, captions, tables and block quotes of LaTeX sources,
+    /// with `with_latex`, and of Markdown files separately, as well as double-quoted spans
+    /// of any file, leaving the excluded regions out of the totals
+    pub fn with_regions(mut self, excluded: impl IntoIterator<Item = Region>) -> Self {
+        self.counting_mut().regions = Some(excluded.into_iter().collect());
+        self
+    }
+
+    /// Runs a custom analysis over the lines of each file alongside word counting, its
+    /// figures landing in the `analyses` of the file results under `name`. `make` is
+    /// called once per file for a fresh analyzer. Cached results are told apart by
+    /// the names of the analyzers, which should change along with what they report.
+    pub fn with_analyzer<A: Analyzer + 'static>(
+        mut self,
+        name: impl Into<String>,
+        make: impl Fn() -> A + Send + Sync + 'static,
+    ) -> Self {
+        let name = name.into();
+        let analyzers = &mut self.counting_mut().analyzers;
+        analyzers.retain(|analyzer| analyzer.name != name);
+        analyzers.push(AnalyzerFactory::new(name, make));
+        self
+    }
+
+    /// Splits runs of a script written without spaces, e.g. Thai, into dictionary words.
+    /// Otherwise a whole sentence of such a script counts as a single word.
+    pub fn with_dictionary(mut self, script: Script, dictionary: Arc<Dictionary>) -> Self {
+        let dictionaries = &mut self.counting_mut().dictionaries;
+        dictionaries.retain(|(s, _)| *s != script);
+        dictionaries.push((script, dictionary));
+        self
+    }
+
+    /// Leaves common words out of the word frequencies and unique words, the word
+    /// counts being unaffected
+    pub fn with_stop_words(mut self, stop_words: Arc<StopWordFilter>) -> Self {
+        self.counting_mut().stop_words = Some(stop_words);
+        self
+    }
+
+    /// Records how often each word occurs in the file results, unless content
+    /// retention is disabled
+    pub fn with_frequencies(mut self, enabled: bool) -> Self {
+        let counting = self.counting_mut();
+        counting.frequencies = enabled && !counting.no_content_retention;
+        self
+    }
+
+    /// Counts the distinct words of each file, keeping them in the file results so that
+    /// `get_unique_words` can count them across the corpus. Words are trimmed of
+    /// surrounding punctuation and, unless `with_case_insensitive` is set, told apart by case.
+    /// The vocabularies being left out of the result cache, it is not used meanwhile
+    /// unless the words are counted with `with_approximate_unique_words`.
+    pub fn with_unique_words(mut self, enabled: bool) -> Self {
+        let counting = self.counting_mut();
+        counting.unique_words = enabled && !counting.no_content_retention;
+        self
+    }
+
+    /// Counts the distinct words enabled with `with_unique_words` with a HyperLogLog
+    /// sketch of 2^precision registers rather than exact sets, in constant memory
+    /// whatever the vocabulary. Counts are then estimates with a relative standard
+    /// error of about 1.04 / sqrt(2^precision), 0.81% at `DEFAULT_SKETCH_PRECISION`;
+    /// the precision is brought within `MIN_SKETCH_PRECISION..=MAX_SKETCH_PRECISION`.
+    /// The file results keep the sketch of their words instead of the words.
+    pub fn with_approximate_unique_words(mut self, precision: u8) -> Self {
+        self.counting_mut().unique_words_precision = Some(precision);
+        self
+    }
+
+    /// Keeps a Bloom filter of the vocabulary of each file in its result, sized for
+    /// the given false positive rate, to tell cheaply whether a file may contain a
+    /// word. Words are trimmed and folded to lower case. Not kept unless content
+    /// retention is enabled, as a filter tells which words a file holds.
+    pub fn with_bloom_filters(mut self, false_positive_rate: f64) -> Self {
+        let counting = self.counting_mut();
+        counting.bloom_filters =
+            Some(false_positive_rate).filter(|_| !counting.no_content_retention);
+        self
+    }
+
+    /// Whether any word text may be kept. Disabled, the processor holds, caches and
+    /// returns numeric aggregates only: word frequencies, unique words and Bloom
+    /// filters are never recorded, whenever they are requested.
+    pub fn with_content_retention(mut self, enabled: bool) -> Self {
+        let counting = self.counting_mut();
+        counting.no_content_retention = !enabled;
+        counting.frequencies &= enabled;
+        counting.unique_words &= enabled;
+        counting.bloom_filters = counting.bloom_filters.filter(|_| enabled);
+        self
+    }
+
+    /// Aggregates the word frequencies enabled with `with_frequencies` through a
+    /// spilling aggregator rather than in the file results, which are left without
+    /// them, so that a corpus whose vocabulary does not fit in memory can be counted.
+    /// The result cache is not used meanwhile. Read the corpus frequencies with
+    /// `take_spilled_frequencies`.
+    pub fn with_frequency_spill(mut self, spill: SpillingFrequency) -> Self {
+        self.frequency_spill = Some(spill);
+        self
+    }
+
+    /// Folds the case of the words before counting the distinct ones, so that "The"
+    /// and "the" are the same word
+    pub fn with_case_insensitive(mut self, enabled: bool) -> Self {
+        self.counting_mut().case_insensitive = enabled;
+        self
+    }
+
+    /// Records the sentences, words and syllables of each file, from which its
+    /// Flesch-Kincaid grade level and Flesch reading ease follow
+    pub fn with_readability(mut self, enabled: bool) -> Self {
+        self.counting_mut().readability = enabled;
+        self
+    }
+
+    /// Sends each file result through the channel as soon as the file is processed,
+    /// besides storing it for `get_results` once all files are done, followed by the
+    /// file being done - also sent for files that failed, so that consumers waiting
+    /// for a file in particular do not wait forever
+    pub fn with_result_sender(mut self, sender: UnboundedSender<StreamedResult>) -> Self {
+        self.result_sender = Some(sender);
+        self
+    }
+
+    /// Stops sending file results, so that the channel closes once its other senders are gone
+    pub fn close_result_sender(&mut self) {
+        self.result_sender = None;
+    }
+
+    /// Sends progress events through the channel while files are processed
+    pub fn with_progress_sender(mut self, sender: UnboundedSender<Progress>) -> Self {
+        self.progress_sender = Some(sender);
+        self
+    }
+
+    /// Stops sending progress events, so that the channel closes once its other senders are gone
+    pub fn close_progress_sender(&mut self) {
+        self.progress_sender = None;
+    }
+
+    /// Sends an event through the channel as each file starts, completes or fails,
+    /// with its timing, from `process_files` and `process_files_stream`. Files
+    /// cancelled in flight report no end.
+    pub fn with_event_sender(mut self, sender: UnboundedSender<FileEvent>) -> Self {
+        self.event_sender = Some(sender);
+        self
+    }
+
+    /// Stops sending file events, so that the channel closes once its other senders are gone
+    pub fn close_event_sender(&mut self) {
+        self.event_sender = None;
+    }
+
+    /// Only processes the beginning or the end of each file,
+    /// flagging truncated files as partial in their results
+    pub fn with_partial_read(mut self, partial: PartialRead) -> Self {
+        self.partial = Some(partial);
+        self
+    }
+
+    /// Restricts processing of a single file, taking precedence over `with_partial_read`
+    pub fn with_file_partial_read(
+        mut self,
+        path: impl Into<PathBuf>,
+        partial: PartialRead,
+    ) -> Self {
+        self.file_partials.insert(path.into(), partial);
+        self
+    }
+
+    /// Attaches an external scorer whose score is stored in each file result
+    pub fn with_scorer(self, scorer: Arc<dyn Scorer>) -> Self {
+        self.with_scorer_concurrency(scorer, DEFAULT_SCORER_CONCURRENCY)
+    }
+
+    /// Attaches an external scorer, allowing at most `max_concurrent` invocations at once
+    pub fn with_scorer_concurrency(
+        mut self,
+        scorer: Arc<dyn Scorer>,
+        max_concurrent: usize,
+    ) -> Self {
+        self.scorer = Some(Arc::new(ScorerHandle {
+            scorer,
+            permits: Semaphore::new(max_concurrent.max(1)),
+        }));
+        self
+    }
+
+    /// Limits how deep directories are walked - files directly inside
+    /// a given directory are at depth 1
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.walk.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Skips the files and directories matching a pattern while walking directories,
+    /// e.g. `**/node_modules/**` - files given as such are processed regardless
+    pub fn with_exclude(mut self, pattern: Glob) -> Self {
+        self.walk.excludes.push(pattern);
+        self
+    }
+
+    /// Leaves out of directory walks the paths ignored by the `.gitignore` and
+    /// `.mfpignore` files met along the way, and the `.git` directories
+    pub fn with_ignore_files(mut self, enabled: bool) -> Self {
+        self.walk.ignore_files = enabled;
+        self
+    }
+
+    /// Limits how many files are processed at once, the number of CPUs by default
+    pub fn with_concurrency(mut self, max_concurrent: usize) -> Self {
+        self.concurrency = Some(max_concurrent.max(1));
+        self
+    }
+
+    /// Gives up on a file taking longer than `timeout` to process, e.g. on a hung
+    /// network mount or FIFO, reporting it as `TextProcessorError::Timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Rejects files larger than `max_file_size` bytes with `TextProcessorError::FileTooLarge`
+    /// instead of reading them, as well as archive entries by their decompressed size.
+    /// Compressed files decompressing to more fail with `TextProcessorError::DecompressedTooLarge`.
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = Some(max_file_size);
+        self
+    }
+
+    /// Keeps the results in the given cache directory, so that later runs only
+    /// process the files whose size or modification time changed
+    pub fn with_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Decompresses gzip files whatever their name - files ending in `.gz`
+    /// always are
+    pub fn with_decompress(mut self, decompress: bool) -> Self {
+        self.decompress = decompress;
+        self
+    }
+
+    /// Counts files that look binary - NUL bytes or the signature of a binary
+    /// format among their first bytes - instead of skipping them
+    pub fn with_include_binary(mut self, include_binary: bool) -> Self {
+        self.include_binary = include_binary;
+        self
+    }
+
+    /// Injects faults into the reads of the matching files, which are then read
+    /// as plain files. Only available with the `testing` feature.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn with_fault_injector(mut self, fault_injector: FaultInjector) -> Self {
+        self.fault_injector = fault_injector;
+        self
+    }
+
+    /// Decodes all files with the given encoding instead of detecting it from
+    /// their first bytes
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Processes multiple files in parallel, each on its own task, at most
+    /// `with_concurrency` at once.
+    /// Directories are walked recursively and each contained file is processed.
+    /// The text entries of `.zip`, `.tar` and `.tar.gz` archives are processed
+    /// like files, their results stored under `archive.zip!/inner/path.txt`.
     pub async fn process_files(
         &mut self,
         file_paths: Vec<PathBuf>,
+    ) -> Result<(), TextProcessorError> {
+        self.process_files_with_cancel(file_paths, CancellationToken::new())
+            .await
+    }
+
+    /// Like `process_files`, but stops once `token` is cancelled: in-flight
+    /// reads are dropped, pending files are skipped, and the files finished so
+    /// far stay in `get_results`, with `TextProcessorError::Cancelled` returned.
+    pub async fn process_files_with_cancel(
+        &mut self,
+        file_paths: Vec<PathBuf>,
+        token: CancellationToken,
+    ) -> Result<(), TextProcessorError> {
+        let file_paths = expand_paths(file_paths, &self.walk).await?;
+        self.process_listed_files_with_cancel(file_paths, token)
+            .await
+    }
+
+    /// Like `process_files_with_cancel`, for files already listed, e.g. by
+    /// `expand_paths_with_warnings`: the paths are processed as given, without
+    /// walking directories nor skipping excluded paths again.
+    #[instrument(skip(self, file_paths, token), fields(count = file_paths.len()))]
+    pub async fn process_listed_files_with_cancel(
+        &mut self,
+        file_paths: Vec<PathBuf>,
+        token: CancellationToken,
     ) -> Result<(), TextProcessorError> {
         let start = Instant::now();
 
@@ -35,16 +522,108 @@ impl TextProcessor {
         }
 
         info!("Starting to process {} files", file_paths.len());
+        self.send_progress(Progress::Started {
+            total: file_paths.len(),
+        });
+
+        let concurrency = self.concurrency_limit();
+        // Each file is counted on its own task, so that large batches use every core
+        let worker = Arc::new(self.worker());
+        let file_count = file_paths.len();
+        let mut results = Vec::with_capacity(file_count);
 
-        let tasks: Vec<_> = file_paths
-            .into_iter()
-            .map(|path| async {
-                let result = self.process_single_file(path.clone()).await;
-                (path, result)
+        // Files unchanged since a previous run are taken from the cache
+        let mut cache = match &self.cache_dir {
+            // Cached results would keep the frequencies spilling is meant to drop
+            Some(_) if self.frequency_spill.is_some() => None,
+            // Vocabularies are not cached, while the corpus count needs every one of them
+            Some(_)
+                if self.counting.unique_words && self.counting.unique_words_precision.is_none() =>
+            {
+                None
+            }
+            Some(dir) => Some(ResultCache::load(dir, self.settings_fingerprint()).await),
+            None => None,
+        };
+        let mut spill_error = None;
+        let mut changed = Vec::with_capacity(file_count);
+        for path in file_paths {
+            // Archives hold many results, and are always read again
+            let Some(cache) = cache.as_mut().filter(|_| archive_kind(&path).is_none()) else {
+                changed.push(path);
+                continue;
+            };
+            let start = Instant::now();
+            match cache.get(&path).await {
+                Some(result) => {
+                    debug!("Reusing cached result of {:?}", path);
+                    self.send_event(FileEvent::Started { path: path.clone() });
+                    self.send_event(FileEvent::Completed {
+                        path: path.clone(),
+                        bytes: result.total_bytes,
+                        elapsed: start.elapsed(),
+                        cached: true,
+                    });
+                    self.send_result(&path, &result);
+                    self.send_done(&path);
+                    self.send_progress(Progress::FileDone {
+                        path: path.clone(),
+                        bytes: result.total_bytes,
+                    });
+                    results.push((path, Ok(self.spill_frequencies(result, &mut spill_error))));
+                }
+                None => changed.push(path),
+            }
+        }
+
+        let pending = token.clone();
+        let mut tasks = stream::iter(changed)
+            .take_while(move |_| future::ready(!pending.is_cancelled()))
+            .map(|path| {
+                let worker = Arc::clone(&worker);
+                let token = token.clone();
+                tokio::spawn(
+                    async move {
+                        tokio::select! {
+                            outcomes = worker.process_source_timed(path.clone()) => Some((path, outcomes)),
+                            _ = token.cancelled() => None,
+                        }
+                    }
+                    .in_current_span(),
+                )
             })
-            .collect();
+            .buffer_unordered(concurrency)
+            .map(|joined| joined.unwrap_or_else(|e| panic::resume_unwind(e.into_panic())))
+            .filter_map(future::ready);
+
+        while let Some((path, outcomes)) = tasks.next().await {
+            let mut bytes = 0;
+            for (result_path, result) in outcomes {
+                if let Ok(file_result) = &result {
+                    self.send_result(&result_path, file_result);
+                    if let Some(cache) = cache.as_mut() {
+                        cache.insert(&result_path, file_result);
+                    }
+                    bytes += file_result.total_bytes;
+                }
+                let result =
+                    result.map(|file_result| self.spill_frequencies(file_result, &mut spill_error));
+                results.push((result_path, result));
+            }
+            self.send_done(&path);
+            self.send_progress(Progress::FileDone { path, bytes });
+        }
+
+        if let Some(cache) = &cache {
+            if let Err(e) = cache.save().await {
+                warn!("Failed to save the result cache: {}", e);
+            }
+        }
+        if let Some(e) = spill_error {
+            error!("Failed to spill the word frequencies: {}", e);
+            return Err(e.into());
+        }
 
-        let results = future::join_all(tasks).await;
         let total_count = results.len();
         let mut failed_count = 0;
 
@@ -61,6 +640,17 @@ impl TextProcessor {
             }
         }
 
+        if token.is_cancelled() {
+            info!(
+                "Cancelled after processing {} out of {} files",
+                self.results.len(),
+                file_count
+            );
+            return Err(TextProcessorError::Cancelled {
+                processed_count: self.results.len(),
+                total_count: file_count,
+            });
+        }
         if failed_count > 0 {
             error!(
                 "Failed to process {} out of {} files",
@@ -78,6 +668,155 @@ impl TextProcessor {
         Ok(())
     }
 
+    /// Processes multiple files in parallel like `process_files`, yielding each file
+    /// result as soon as the file completes, in completion order, so that consumers
+    /// can act on it - or stop early by dropping the stream, the files in flight
+    /// finishing in the background. The results are not stored for `get_results`,
+    /// and the cache and result sender are not used. Fails upfront when no files
+    /// are found.
+    pub async fn process_files_stream(
+        &self,
+        file_paths: Vec<PathBuf>,
+    ) -> Result<
+        impl Stream<Item = (PathBuf, Result<FileProcessingResult, TextProcessorError>)> + Send + 'static,
+        TextProcessorError,
+    > {
+        let file_paths = expand_paths(file_paths, &self.walk).await?;
+        if file_paths.is_empty() {
+            return Err(TextProcessorError::EmptyFileList);
+        }
+
+        let worker = Arc::new(self.worker());
+        Ok(stream::iter(file_paths)
+            .map(move |path| {
+                let worker = Arc::clone(&worker);
+                tokio::spawn(
+                    async move { worker.process_source_timed(path).await }.in_current_span(),
+                )
+            })
+            .buffer_unordered(self.concurrency_limit())
+            .flat_map(|joined| {
+                stream::iter(joined.unwrap_or_else(|e| panic::resume_unwind(e.into_panic())))
+            }))
+    }
+
+    // Number of files processed at once, one per core by default
+    fn concurrency_limit(&self) -> usize {
+        self.concurrency
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, NonZeroUsize::get))
+    }
+
+    // Processes an input like `process_source`, sending events as it starts and as
+    // each of its results completes or fails
+    async fn process_source_timed(
+        &self,
+        path: PathBuf,
+    ) -> Vec<(PathBuf, Result<FileProcessingResult, TextProcessorError>)> {
+        self.send_event(FileEvent::Started { path: path.clone() });
+        let start = Instant::now();
+        let outcomes = self.process_source(path).await;
+        for (path, result) in &outcomes {
+            let path = path.clone();
+            let elapsed = start.elapsed();
+            self.send_event(match result {
+                Ok(result) => FileEvent::Completed {
+                    path,
+                    bytes: result.total_bytes,
+                    elapsed,
+                    cached: false,
+                },
+                Err(e) => FileEvent::Failed {
+                    path,
+                    error: e.to_string(),
+                    elapsed,
+                },
+            });
+        }
+        outcomes
+    }
+
+    // Processes an input within the timeout, if any: a file gives its own
+    // result, an archive one per text entry
+    pub(crate) async fn process_source(
+        &self,
+        path: PathBuf,
+    ) -> Vec<(PathBuf, Result<FileProcessingResult, TextProcessorError>)> {
+        let (path, kind) = match Source::new(path) {
+            Source::File(path) => {
+                let result = self.process_single_file_in_time(path.clone()).await;
+                return vec![(path, result)];
+            }
+            Source::Archive(path, kind) => (path, kind),
+        };
+        if let Err(e) = self.check_size(&path).await {
+            return vec![(path, Err(e))];
+        }
+        let Some(timeout) = self.timeout else {
+            return self.process_archive(path, kind).await;
+        };
+        match tokio::time::timeout(timeout, self.process_archive(path.clone(), kind)).await {
+            Ok(outcomes) => outcomes,
+            Err(_) => vec![(
+                path.clone(),
+                Err(TextProcessorError::Timeout { path, timeout }),
+            )],
+        }
+    }
+
+    // Processes the text entries of an archive, in the order they are stored
+    async fn process_archive(
+        &self,
+        path: PathBuf,
+        kind: ArchiveKind,
+    ) -> Vec<(PathBuf, Result<FileProcessingResult, TextProcessorError>)> {
+        let mut entries = read_archive(path.clone(), kind, self.max_file_size);
+        let mut outcomes = Vec::new();
+        while let Some(entry) = entries.recv().await {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    outcomes.push((path.clone(), Err(e)));
+                    break;
+                }
+            };
+            let content = match entry.content {
+                Ok(content) => content,
+                Err(e) => {
+                    outcomes.push((entry.path, Err(e)));
+                    continue;
+                }
+            };
+            let partial_read = self.partial_read_for(&entry.path);
+            let result = match open_reader(Cursor::new(content), partial_read, self.encoding).await
+            {
+                Ok((reader, partial)) => {
+                    self.process_lines(&entry.path, reader, partial, partial_read)
+                        .await
+                }
+                Err(e) => Err(TextProcessorError::IoError(e)),
+            };
+            outcomes.push((entry.path, result));
+        }
+        debug!("Processed {} entries of {:?}", outcomes.len(), path);
+        outcomes
+    }
+
+    // Processes a single file within the timeout, if any
+    async fn process_single_file_in_time(
+        &self,
+        file_path: PathBuf,
+    ) -> Result<FileProcessingResult, TextProcessorError> {
+        let Some(timeout) = self.timeout else {
+            return self.process_single_file(file_path).await;
+        };
+        tokio::time::timeout(timeout, self.process_single_file(file_path.clone()))
+            .await
+            .unwrap_or(Err(TextProcessorError::Timeout {
+                path: file_path,
+                timeout,
+            }))
+    }
+
     /// Processes a single file
     #[instrument(skip(self), fields(
         path = ?file_path.display(),
@@ -90,108 +829,961 @@ impl TextProcessor {
         validate_file_path(&file_path)
             .await
             .map_err(|_| TextProcessorError::FileNotFound(file_path.clone()))?;
+        self.check_size(&file_path).await?;
+
+        let partial_read = self.partial_read_for(&file_path);
+        #[cfg(any(test, feature = "testing"))]
+        {
+            let faults = self.fault_injector.faults_for(&file_path);
+            if !faults.is_empty() {
+                debug!("Injecting {:?} into {:?}", faults, file_path);
+                let file = tokio::fs::File::open(&file_path)
+                    .await
+                    .map_err(TextProcessorError::IoError)?;
+                let (reader, partial) = open_reader(
+                    FaultyReader::new(file, &faults),
+                    partial_read,
+                    self.encoding,
+                )
+                .await
+                .map_err(TextProcessorError::IoError)?;
+                return self
+                    .process_lines(&file_path, reader, partial, partial_read)
+                    .await;
+            }
+        }
+        if !self.is_compressed(&file_path).await {
+            let (reader, partial) = open_file(&file_path, partial_read, self.encoding)
+                .await
+                .map_err(TextProcessorError::IoError)?;
+            return self
+                .process_lines(&file_path, reader, partial, partial_read)
+                .await;
+        }
+
+        debug!("Decompressing {:?}", file_path);
+        let too_large = |e| match (e, self.max_file_size) {
+            (TextProcessorError::IoError(e), Some(limit))
+                if e.kind() == ErrorKind::FileTooLarge =>
+            {
+                TextProcessorError::DecompressedTooLarge {
+                    path: file_path.clone(),
+                    limit,
+                }
+            }
+            (e, _) => e,
+        };
+        let (reader, partial) = open_reader(
+            decompress_file(file_path.clone(), self.max_file_size),
+            partial_read,
+            self.encoding,
+        )
+        .await
+        .map_err(|e| too_large(TextProcessorError::IoError(e)))?;
+        let mut result = self
+            .process_lines(&file_path, reader, partial, partial_read)
+            .await
+            .map_err(too_large)?;
+        // Offsets into the decompressed stream would not locate lines in the file
+        result.line_offsets = None;
+        Ok(result)
+    }
 
-        let file = File::open(&file_path)
+    /// Processes any async reader - stdin, a socket, a pipe - like a file,
+    /// storing its result under the given name
+    pub async fn process_reader<R>(
+        &mut self,
+        name: impl Into<PathBuf>,
+        reader: R,
+    ) -> Result<(), TextProcessorError>
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let name = name.into();
+        let partial_read = self.partial_read_for(&name);
+        let (reader, partial) = open_reader(reader, partial_read, self.encoding)
             .await
             .map_err(TextProcessorError::IoError)?;
 
-        let reader = BufReader::new(file);
-        let mut lines = reader.lines();
-        let mut line_counts = Vec::new();
-        let mut total_words = 0;
+        let result = self
+            .process_lines(&name, reader, partial, partial_read)
+            .await?;
+        info!("Successfully processed reader: {:?}", name);
+        self.send_result(&name, &result);
+        self.results.insert(name, result);
+        Ok(())
+    }
 
-        debug!("Starting file processing");
-        while let Some(line) = lines.next_line().await? {
-            let word_count = count_words(&line);
-            trace!(line_number = line_counts.len(), words = word_count);
-            total_words += word_count;
-            line_counts.push(word_count);
+    // Streams a file result to the channel, if any
+    fn send_result(&self, path: &Path, result: &FileProcessingResult) {
+        if let Some(sender) = &self.result_sender {
+            // A dropped receiver only stops the streaming
+            let _ = sender.send(StreamedResult::File {
+                path: path.to_path_buf(),
+                result: Box::new(result.clone()),
+            });
         }
-
-        Ok(FileProcessingResult {
-            line_counts,
-            total_words,
-        })
     }
 
-    /// Returns files results
-    pub fn get_results(&self) -> &HashMap<PathBuf, FileProcessingResult> {
-        &self.results
+    // Streams the end of a listed file to the channel, if any
+    fn send_done(&self, path: &Path) {
+        if let Some(sender) = &self.result_sender {
+            let _ = sender.send(StreamedResult::Done {
+                path: path.to_path_buf(),
+            });
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use std::io::Write;
-    use tempfile::TempDir;
+    // Moves the frequencies of a result to the spilling aggregator, if any. The first
+    // failure to spill is kept for the end of the run, the frequencies being
+    // incomplete from then on.
+    fn spill_frequencies(
+        &mut self,
+        mut result: FileProcessingResult,
+        error: &mut Option<io::Error>,
+    ) -> FileProcessingResult {
+        if let Some(spill) = self.frequency_spill.as_mut() {
+            if let Some(frequencies) = result.frequencies.take() {
+                if let Err(e) = spill.add(&frequencies) {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        result
+    }
 
-    // Helper function to create temporary test files with given content
-    async fn create_test_file(dir: &tempfile::TempDir, filename: &str, content: &str) -> PathBuf {
-        let file_path = dir.path().join(filename);
-        let mut file = fs::File::create(&file_path).unwrap();
-        file.write_all(content.as_bytes()).unwrap();
-        file_path
+    // Reports progress to the channel, if any
+    fn send_progress(&self, progress: Progress) {
+        if let Some(sender) = &self.progress_sender {
+            // A dropped receiver only stops the reporting
+            let _ = sender.send(progress);
+        }
     }
 
-    // Verify new processor starts with empty results
-    #[tokio::test]
-    async fn test_new_processor_creates_empty_results() {
-        let processor = TextProcessor::new();
-        assert!(processor.get_results().is_empty());
+    // Reports a file event to the channel, if any
+    fn send_event(&self, event: FileEvent) {
+        if let Some(sender) = &self.event_sender {
+            let _ = sender.send(event);
+        }
     }
 
-    // Test empty file handling
-    #[tokio::test]
-    async fn test_process_empty_file_returns_zero_counts() {
-        let temp = TempDir::new().unwrap();
-        let file_path = create_test_file(&temp, "empty.txt", "").await;
+    // Settings of the processor without its results, shared by the file tasks
+    fn worker(&self) -> Self {
+        Self {
+            results: HashMap::new(),
+            scorer: self.scorer.clone(),
+            partial: self.partial,
+            file_partials: self.file_partials.clone(),
+            line_offsets: self.line_offsets,
+            walk: self.walk.clone(),
+            concurrency: self.concurrency,
+            timeout: self.timeout,
+            max_file_size: self.max_file_size,
+            cache_dir: None,
+            decompress: self.decompress,
+            encoding: self.encoding,
+            include_binary: self.include_binary,
+            #[cfg(any(test, feature = "testing"))]
+            fault_injector: self.fault_injector.clone(),
+            counting: self.counting.clone(),
+            frequency_spill: None,
+            // Results and progress are sent as the tasks complete, not from the tasks,
+            // unlike the file events timing each task
+            result_sender: None,
+            progress_sender: None,
+            event_sender: self.event_sender.clone(),
+        }
+    }
 
-        let processor = TextProcessor::new();
-        let result = processor
-            .process_single_file(file_path.clone())
-            .await
-            .unwrap();
+    // Counting settings to change, copied first if shared with workers
+    fn counting_mut(&mut self) -> &mut CountingOptions {
+        Arc::make_mut(&mut self.counting)
+    }
 
-        assert_eq!(result.line_counts.len(), 0);
-        assert_eq!(result.total_words, 0);
+    /// Settings the file results depend on, by name, e.g. to record how a run
+    /// counted. The result cache is keyed by the same settings.
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        let mut file_partials: Vec<_> = self.file_partials.iter().collect();
+        file_partials.sort_by_key(|(path, _)| *path);
+        let dictionaries: Vec<String> = self
+            .counting
+            .dictionaries
+            .iter()
+            .map(|(script, dictionary)| format!("{:?}: {} words", script, dictionary.len()))
+            .collect();
+        vec![
+            ("partial_read", format!("{:?}", self.partial)),
+            ("file_partial_reads", format!("{:?}", file_partials)),
+            ("line_offsets", self.line_offsets.to_string()),
+            // Files over the limit must not be taken from the cache
+            ("max_file_size", format!("{:?}", self.max_file_size)),
+            ("decompress", self.decompress.to_string()),
+            ("encoding", format!("{:?}", self.encoding)),
+            ("include_binary", self.include_binary.to_string()),
+            ("segmentation", format!("{:?}", self.counting.segmentation)),
+            (
+                "segmentation_rules",
+                format!("{:?}", self.counting.segmentation_rules),
+            ),
+            (
+                "counting_policy",
+                format!("{:?}", self.counting.counting_policy),
+            ),
+            ("tokenizer", format!("{:?}", self.counting.tokenizer_config)),
+            (
+                "count_pattern",
+                format!("{:?}", self.counting.count_pattern),
+            ),
+            ("dictionaries", format!("{:?}", dictionaries)),
+            (
+                "stop_words",
+                format!(
+                    "{:?}",
+                    self.counting.stop_words.as_ref().map(|words| words.len())
+                ),
+            ),
+            ("frequencies", self.counting.frequencies.to_string()),
+            ("unique_words", self.counting.unique_words.to_string()),
+            (
+                "unique_words_precision",
+                format!("{:?}", self.counting.unique_words_precision),
+            ),
+            (
+                "bloom_filters",
+                format!("{:?}", self.counting.bloom_filters),
+            ),
+            (
+                "content_retention",
+                (!self.counting.no_content_retention).to_string(),
+            ),
+            (
+                "case_insensitive",
+                self.counting.case_insensitive.to_string(),
+            ),
+            ("readability", self.counting.readability.to_string()),
+            ("latex", format!("{:?}", self.counting.latex)),
+            ("citations", self.counting.citations.to_string()),
+            (
+                "markdown_headings",
+                self.counting.markdown_headings.to_string(),
+            ),
+            ("regions", format!("{:?}", self.counting.regions)),
+            (
+                "scorer",
+                format!("{:?}", self.scorer.as_ref().map(|handle| &handle.scorer)),
+            ),
+            ("analyzers", format!("{:?}", self.counting.analyzers)),
+        ]
     }
 
-    // Test word counting across multiple lines
-    #[tokio::test]
-    async fn test_process_multiline_file_counts_correctly() {
-        let temp = TempDir::new().unwrap();
-        let content = "one two\nthree four five\nsix";
-        let file_path = create_test_file(&temp, "multi.txt", content).await;
+    // Hash of the settings a file result depends on, naming its cache
+    fn settings_fingerprint(&self) -> u64 {
+        let mut settings = env!("CARGO_PKG_VERSION").to_string();
+        for (name, value) in self.settings() {
+            settings.push_str(&format!("\n{name}={value}"));
+        }
 
-        let processor = TextProcessor::new();
-        let result = processor
-            .process_single_file(file_path.clone())
-            .await
-            .unwrap();
+        // Hashed stably, as the name of the cache file must not change across builds.
+        // Words never hold a line break, which separates them unambiguously.
+        let mut bytes = settings.into_bytes();
+        for (script, dictionary) in &self.counting.dictionaries {
+            bytes.extend_from_slice(format!("\ndictionary {:?}", script).as_bytes());
+            for word in dictionary.sorted_words() {
+                bytes.push(b'\n');
+                bytes.extend_from_slice(word.as_bytes());
+            }
+        }
+        if let Some(stop_words) = &self.counting.stop_words {
+            bytes.extend_from_slice(b"\nstop words");
+            for word in stop_words.sorted_words() {
+                bytes.push(b'\n');
+                bytes.extend_from_slice(word.as_bytes());
+            }
+        }
+        stable_hash(&bytes)
+    }
 
-        assert_eq!(result.line_counts, vec![2, 3, 1]);
-        assert_eq!(result.total_words, 6);
+    // How directories given as input are walked
+    pub(crate) fn walk_options(&self) -> &WalkOptions {
+        &self.walk
     }
 
-    // Verify error handling for non-existent files
-    #[tokio::test]
-    async fn test_nonexistent_file_returns_error() {
-        let processor = TextProcessor::new();
-        let result = processor
-            .process_single_file(PathBuf::from("nonexistent.txt"))
-            .await;
+    // Rejects a file over the size limit, if any
+    async fn check_size(&self, path: &Path) -> Result<(), TextProcessorError> {
+        let Some(limit) = self.max_file_size else {
+            return Ok(());
+        };
+        let size = tokio::fs::metadata(path).await?.len();
+        if size > limit {
+            return Err(TextProcessorError::FileTooLarge {
+                path: path.to_path_buf(),
+                size,
+                limit,
+            });
+        }
+        Ok(())
+    }
 
-        assert!(matches!(result, Err(TextProcessorError::FileNotFound(_))));
+    // Whether a file is gzip-compressed, by its extension or its content if asked to
+    async fn is_compressed(&self, path: &Path) -> bool {
+        has_gzip_extension(path) || (self.decompress && has_gzip_magic(path).await.unwrap_or(false))
     }
 
-    // Test handling of empty input file list
-    #[tokio::test]
-    async fn test_empty_input_returns_error() {
-        let mut processor = TextProcessor::new();
-        let result = processor.process_files(vec![]).await;
-        assert!(matches!(result, Err(TextProcessorError::EmptyFileList)));
+    // Partial read applying to a path, per-file settings first
+    fn partial_read_for(&self, path: &Path) -> Option<PartialRead> {
+        self.file_partials
+            .get(path)
+            .or(self.partial.as_ref())
+            .copied()
+    }
+
+    // Tokenizer applying to a path, per-pattern segmentation rules first
+    fn tokenizer_for(&self, path: &Path) -> Tokenizer {
+        let segmentation = self
+            .counting
+            .segmentation_rules
+            .iter()
+            .rev()
+            .find(|(pattern, _)| pattern.matches(path))
+            .map_or(self.counting.segmentation, |&(_, segmentation)| {
+                segmentation
+            });
+        Tokenizer::new(
+            segmentation,
+            self.counting.counting_policy,
+            &self.counting.dictionaries,
+        )
+        .with_config(&self.counting.tokenizer_config)
+        .with_count_pattern(self.counting.count_pattern.clone())
+        .with_stop_words(self.counting.stop_words.clone())
+    }
+
+    // Markup stripping applying to a path, fresh for each file
+    fn line_filter(&self, path: &Path) -> Option<LineFilter> {
+        match self.counting.latex {
+            Some(options) => Some(LineFilter::Latex(LatexFilter::new(
+                options,
+                self.counting.citations,
+            ))),
+            None if (self.counting.citations
+                || self.counting.markdown_headings
+                || self.counting.regions.is_some())
+                && is_markdown(path) =>
+            {
+                Some(LineFilter::Markdown {
+                    headings: self
+                        .counting
+                        .markdown_headings
+                        .then(MarkdownHeadings::default),
+                    citations: self.counting.citations.then(MarkdownCitations::default),
+                    regions: self
+                        .counting
+                        .regions
+                        .is_some()
+                        .then(MarkdownRegions::default),
+                })
+            }
+            None => None,
+        }
+    }
+
+    // Bloom filter of the words of a file, when kept
+    fn bloom_filter(&self, words: Option<HashSet<String>>) -> Option<BloomFilter> {
+        let rate = self.counting.bloom_filters?;
+        Some(BloomFilter::from_words(
+            words?.iter().map(String::as_str),
+            rate,
+        ))
+    }
+
+    // Number of citations of a file, when counted
+    fn citations(&self, filter: Option<&LineFilter>) -> Option<usize> {
+        self.counting
+            .citations
+            .then(|| filter.map_or(0, LineFilter::citations))
+    }
+
+    // Counts the lines of an opened source, applying line based partial reads
+    async fn process_lines(
+        &self,
+        path: &Path,
+        mut reader: LineReader,
+        mut partial: bool,
+        partial_read: Option<PartialRead>,
+    ) -> Result<FileProcessingResult, TextProcessorError> {
+        let start = Instant::now();
+        if !self.include_binary {
+            if let Some(kind) = Encoding::binary_kind(reader.head().await?, self.encoding) {
+                debug!("Skipping {:?}: {}", path, kind);
+                return Ok(FileProcessingResult {
+                    status: FileStatus::Skipped(format!("binary file ({})", kind)),
+                    ..Default::default()
+                });
+            }
+        }
+
+        // Full text is only kept around when a scorer needs it
+        let mut tally = LineTally::new(
+            self.tokenizer_for(path),
+            self.line_filter(path),
+            self.scorer.is_some(),
+            self,
+        );
+        let mut tail = VecDeque::new();
+        let mut line_number = 0;
+
+        debug!("Starting file processing");
+        loop {
+            let offset = reader.offset();
+            let Some((line, terminator)) = reader.next_line().await? else {
+                break;
+            };
+            let len = reader.offset() - offset;
+            line_number += 1;
+            match partial_read {
+                Some(PartialRead::HeadLines(limit)) if tally.line_counts.len() >= limit => {
+                    partial = true;
+                    break;
+                }
+                Some(PartialRead::TailLines(limit)) => {
+                    if tail.len() >= limit {
+                        tail.pop_front();
+                        partial = true;
+                    }
+                    if limit > 0 {
+                        tail.push_back((offset, len, line, terminator));
+                    }
+                }
+                Some(PartialRead::Lines(range)) if !range.contains(line_number) => {
+                    partial = true;
+                    if range.ends_before(line_number) {
+                        break;
+                    }
+                }
+                _ => tally.push(offset, len, &line, terminator),
+            }
+        }
+        tail.iter().for_each(|(offset, len, line, terminator)| {
+            tally.push(*offset, *len, line, *terminator)
+        });
+
+        let analyses = tally.analyses();
+        let LineTally {
+            line_counts,
+            total_words,
+            total_chars,
+            total_bytes,
+            line_stats,
+            text,
+            line_offsets,
+            frequencies,
+            vocabulary,
+            vocabulary_sketch,
+            bloom_words,
+            readability,
+            filter,
+            region_counts,
+            ..
+        } = tally;
+
+        let score = match (&self.scorer, text) {
+            (Some(handle), Some(text)) => Some(handle.score(path, &text).await?),
+            _ => None,
+        };
+
+        Ok(FileProcessingResult {
+            line_count: line_counts.len(),
+            line_counts,
+            total_words,
+            total_chars,
+            total_bytes,
+            line_stats: line_stats.finished(),
+            score,
+            partial,
+            line_offsets,
+            frequencies,
+            unique_words: unique_words(vocabulary.as_ref(), vocabulary_sketch.as_ref()),
+            vocabulary,
+            vocabulary_sketch,
+            bloom_filter: self.bloom_filter(bloom_words),
+            readability: readability.map(Readability::finished),
+            citations: self.citations(filter.as_ref()),
+            regions: region_counts,
+            analyses,
+            encoding: reader.encoding().unwrap_or_default(),
+            had_bom: reader.had_bom(),
+            status: FileStatus::Processed,
+            elapsed: start.elapsed(),
+        })
+    }
+
+    /// Processes an in-memory text buffer, e.g. an editor buffer, the same way as a file.
+    /// Partial reads and the external scorer only apply to files.
+    pub fn process_text(&self, text: &str) -> FileProcessingResult {
+        let tokenizer = Tokenizer::new(
+            self.counting.segmentation,
+            self.counting.counting_policy,
+            &self.counting.dictionaries,
+        )
+        .with_config(&self.counting.tokenizer_config)
+        .with_count_pattern(self.counting.count_pattern.clone())
+        .with_stop_words(self.counting.stop_words.clone());
+        self.count_text(tokenizer, self.line_filter(Path::new("")), text)
+    }
+
+    /// Processes an in-memory text buffer named like a file, so that the segmentation
+    /// rules matching the name apply
+    pub fn process_text_as(&self, name: impl AsRef<Path>, text: &str) -> FileProcessingResult {
+        let name = name.as_ref();
+        self.count_text(self.tokenizer_for(name), self.line_filter(name), text)
+    }
+
+    fn count_text(
+        &self,
+        tokenizer: Tokenizer,
+        filter: Option<LineFilter>,
+        text: &str,
+    ) -> FileProcessingResult {
+        let start = Instant::now();
+        let mut tally = LineTally::new(tokenizer, filter, false, self);
+        let mut offset = 0;
+
+        for raw_line in text.split_inclusive('\n') {
+            let line = raw_line.strip_suffix('\n').unwrap_or(raw_line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let terminator = raw_line.len() - line.len();
+            let line = match offset {
+                0 => line.strip_prefix(BOM).unwrap_or(line),
+                _ => line,
+            };
+            tally.push(offset as u64, raw_line.len() as u64, line, terminator);
+            offset += raw_line.len();
+        }
+
+        let analyses = tally.analyses();
+        FileProcessingResult {
+            line_count: tally.line_counts.len(),
+            line_counts: tally.line_counts,
+            total_words: tally.total_words,
+            total_chars: tally.total_chars,
+            total_bytes: tally.total_bytes,
+            line_stats: tally.line_stats.finished(),
+            line_offsets: tally.line_offsets,
+            frequencies: tally.frequencies,
+            unique_words: unique_words(tally.vocabulary.as_ref(), tally.vocabulary_sketch.as_ref()),
+            vocabulary: tally.vocabulary,
+            vocabulary_sketch: tally.vocabulary_sketch,
+            bloom_filter: self.bloom_filter(tally.bloom_words),
+            readability: tally.readability.map(Readability::finished),
+            citations: self.citations(tally.filter.as_ref()),
+            regions: tally.region_counts,
+            analyses,
+            had_bom: text.starts_with(BOM),
+            elapsed: start.elapsed(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns files results
+    pub fn get_results(&self) -> &HashMap<PathBuf, FileProcessingResult> {
+        &self.results
+    }
+
+    /// Returns the warnings of the file results by path: skipped files, files decoded
+    /// with a fallback encoding and files only partly counted
+    pub fn get_warnings(&self) -> Vec<Warning> {
+        let mut paths: Vec<&PathBuf> = self.results.keys().collect();
+        paths.sort();
+        let mut warnings = Vec::new();
+        for path in paths {
+            let result = &self.results[path];
+            if let FileStatus::Skipped(reason) = &result.status {
+                warnings.push(Warning::Skipped {
+                    path: path.clone(),
+                    reason: reason.clone(),
+                });
+            }
+            // Detection only settles on Windows-1252 when the file is not valid UTF-8
+            if self.encoding.is_none() && result.encoding == Encoding::Windows1252 {
+                warnings.push(Warning::EncodingFallback {
+                    path: path.clone(),
+                    encoding: result.encoding,
+                });
+            }
+            if result.partial {
+                warnings.push(Warning::Truncated { path: path.clone() });
+            }
+        }
+        warnings
+    }
+
+    /// Returns the number of distinct words across all files, from the vocabularies of
+    /// the file results. Zero unless enabled with `with_unique_words`, and estimated
+    /// from the merged sketches of the files with `with_approximate_unique_words`.
+    pub fn get_unique_words(&self) -> usize {
+        let mut sketches = self
+            .results
+            .values()
+            .filter_map(|r| r.vocabulary_sketch.as_ref());
+        if let Some(first) = sketches.next() {
+            let mut corpus = first.clone();
+            sketches.for_each(|sketch| corpus.merge(sketch));
+            return corpus.estimate();
+        }
+        let mut corpus: HashSet<&str> = HashSet::new();
+        for vocabulary in self.results.values().filter_map(|r| r.vocabulary.as_ref()) {
+            corpus.extend(vocabulary.iter().map(String::as_str));
+        }
+        corpus.len()
+    }
+
+    /// Takes the corpus-wide word frequencies aggregated since `with_frequency_spill`,
+    /// the processor no longer spilling them afterwards
+    pub fn take_spilled_frequencies(&mut self) -> Option<MergedFrequency> {
+        self.frequency_spill.take().map(SpillingFrequency::finish)
+    }
+
+    /// Returns the corpus-wide word frequencies, merged from the file results.
+    /// Empty unless enabled with `with_frequencies`, or when spilled.
+    pub fn get_frequencies(&self) -> WordFrequency {
+        let mut corpus = WordFrequency::new();
+        for frequencies in self.results.values().filter_map(|r| r.frequencies.as_ref()) {
+            corpus.merge(frequencies);
+        }
+        corpus
+    }
+}
+
+/// Markup stripped from lines before counting
+#[derive(Debug)]
+enum LineFilter {
+    Latex(LatexFilter),
+    Markdown {
+        headings: Option<MarkdownHeadings>,
+        citations: Option<MarkdownCitations>,
+        regions: Option<MarkdownRegions>,
+    },
+}
+
+impl LineFilter {
+    fn apply(&mut self, line: &str) -> LineParts {
+        match self {
+            LineFilter::Latex(filter) => filter.apply(line),
+            LineFilter::Markdown {
+                headings,
+                citations,
+                regions,
+            } => {
+                let line = match headings.as_mut() {
+                    Some(headings) => headings.apply(line),
+                    None => line,
+                };
+                let line = match citations.as_mut() {
+                    Some(citations) => citations.apply(line),
+                    None => line.to_string(),
+                };
+                match regions.as_mut() {
+                    Some(regions) => regions.apply(&line),
+                    None => LineParts {
+                        text: line,
+                        ..Default::default()
+                    },
+                }
+            }
+        }
+    }
+
+    fn citations(&self) -> usize {
+        match self {
+            LineFilter::Latex(filter) => filter.citations,
+            LineFilter::Markdown { citations, .. } => citations
+                .as_ref()
+                .map_or(0, |citations| citations.citations),
+        }
+    }
+}
+
+/// Running word counts of the lines read so far
+struct LineTally {
+    tokenizer: Tokenizer,
+    filter: Option<LineFilter>,
+    line_counts: Vec<usize>,
+    total_words: usize,
+    total_chars: usize,
+    total_bytes: u64,
+    line_stats: LineStats,
+    text: Option<String>,
+    line_offsets: Option<Vec<u64>>,
+    frequencies: Option<WordFrequency>,
+    vocabulary: Option<HashSet<String>>,
+    vocabulary_sketch: Option<HyperLogLog>,
+    bloom_words: Option<HashSet<String>>,
+    fold_case: bool,
+    readability: Option<Readability>,
+    region_counts: Option<RegionCounts>,
+    excluded_regions: Vec<Region>,
+    quotes: Option<QuotedSpans>,
+    analyzers: Vec<(String, Box<dyn Analyzer>)>,
+}
+
+impl LineTally {
+    /// Tally keeping what the settings of the processor ask for, and the full text
+    /// if `keep_text` is set
+    fn new(
+        tokenizer: Tokenizer,
+        filter: Option<LineFilter>,
+        keep_text: bool,
+        settings: &TextProcessor,
+    ) -> Self {
+        let excluded_regions = settings.counting.regions.as_deref();
+        Self {
+            tokenizer,
+            filter,
+            line_counts: Vec::new(),
+            total_words: 0,
+            total_chars: 0,
+            total_bytes: 0,
+            line_stats: LineStats::default(),
+            text: keep_text.then(String::new),
+            line_offsets: settings.line_offsets.then(Vec::new),
+            frequencies: settings.counting.frequencies.then(WordFrequency::new),
+            vocabulary: (settings.counting.unique_words
+                && settings.counting.unique_words_precision.is_none())
+            .then(HashSet::new),
+            vocabulary_sketch: settings
+                .counting
+                .unique_words_precision
+                .filter(|_| settings.counting.unique_words)
+                .map(HyperLogLog::new),
+            bloom_words: settings.counting.bloom_filters.map(|_| HashSet::new()),
+            fold_case: settings.counting.case_insensitive,
+            readability: settings.counting.readability.then(Readability::default),
+            region_counts: excluded_regions.map(|_| RegionCounts::default()),
+            quotes: excluded_regions.map(|_| QuotedSpans::default()),
+            excluded_regions: excluded_regions.unwrap_or_default().to_vec(),
+            analyzers: settings
+                .counting
+                .analyzers
+                .iter()
+                .map(|factory| (factory.name.clone(), factory.make()))
+                .collect(),
+        }
+    }
+
+    /// Counts a line stripped of its terminator, `len` being its raw length in bytes
+    /// and `terminator` the characters of its terminator
+    fn push(&mut self, offset: u64, len: u64, line: &str, terminator: usize) {
+        if let Some(offsets) = self.line_offsets.as_mut() {
+            offsets.push(offset);
+        }
+        for (_, analyzer) in &mut self.analyzers {
+            analyzer.on_line(line);
+        }
+
+        // Characters and bytes are those of the raw line, words those of the counted text
+        let (mut counted, mut regions) = match self.filter.as_mut() {
+            Some(filter) => {
+                let parts = filter.apply(line);
+                (Cow::Owned(parts.text), parts.regions)
+            }
+            None => (Cow::Borrowed(line), Vec::new()),
+        };
+        if let Some(quotes) = self.quotes.as_mut() {
+            let (body, quoted) = quotes.split(&counted);
+            counted = Cow::Owned(body);
+            regions.push((Region::Quote, quoted));
+        }
+        let mut word_count = self.tokenizer.count_words(&counted);
+        if let Some(frequencies) = self.frequencies.as_mut() {
+            frequencies.add_words(self.tokenizer.analysed_words(&counted));
+        }
+        self.add_vocabulary(&counted);
+        if let Some(readability) = self.readability.as_mut() {
+            // A blank line ends a paragraph, heading or list item without punctuation
+            match counted.trim().is_empty() {
+                true => readability.end_sentence(),
+                false => readability.add_text(&counted),
+            }
+        }
+
+        for (region, text) in &regions {
+            let words = self.tokenizer.count_words(text);
+            if let Some(counts) = self.region_counts.as_mut() {
+                counts.add(*region, words);
+            }
+            if !self.excluded_regions.contains(region) {
+                word_count += words;
+                if let Some(frequencies) = self.frequencies.as_mut() {
+                    frequencies.add_words(self.tokenizer.analysed_words(text));
+                }
+                self.add_vocabulary(text);
+                if let Some(readability) = self.readability.as_mut() {
+                    readability.add_text(text);
+                }
+            }
+        }
+
+        trace!(line_number = self.line_counts.len(), words = word_count);
+        self.total_words += word_count;
+        self.line_counts.push(word_count);
+        let chars = line.chars().count();
+        self.line_stats.add(word_count, chars);
+        self.total_chars += chars + terminator;
+        self.total_bytes += len;
+
+        if let Some(text) = self.text.as_mut() {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+
+    /// Figures of the analyzers, once every line is pushed
+    fn analyses(&mut self) -> BTreeMap<String, Metrics> {
+        self.analyzers
+            .drain(..)
+            .map(|(name, analyzer)| (name, analyzer.finish()))
+            .collect()
+    }
+
+    /// Adds the words of counted text to the distinct words, if kept
+    fn add_vocabulary(&mut self, text: &str) {
+        if self.vocabulary.is_none()
+            && self.vocabulary_sketch.is_none()
+            && self.bloom_words.is_none()
+        {
+            return;
+        }
+        for word in self.tokenizer.analysed_words(text) {
+            let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+            if word.is_empty() {
+                continue;
+            }
+            let word = match self.fold_case {
+                true => Cow::Owned(word.to_lowercase()),
+                false => Cow::Borrowed(word),
+            };
+            if let Some(sketch) = self.vocabulary_sketch.as_mut() {
+                sketch.insert(&word);
+            }
+            if let Some(bloom_words) = self.bloom_words.as_mut() {
+                bloom_words.insert(word.to_lowercase());
+            }
+            if let Some(vocabulary) = self.vocabulary.as_mut() {
+                if !vocabulary.contains(word.as_ref()) {
+                    vocabulary.insert(word.into_owned());
+                }
+            }
+        }
+    }
+}
+
+// Number of distinct words of a file, exact or estimated, when they are counted
+fn unique_words(
+    vocabulary: Option<&HashSet<String>>,
+    sketch: Option<&HyperLogLog>,
+) -> Option<usize> {
+    vocabulary
+        .map(HashSet::len)
+        .or_else(|| sketch.map(HyperLogLog::estimate))
+}
+
+impl ScorerHandle {
+    /// Runs the scorer once a concurrency permit is available
+    async fn score(&self, file_path: &Path, text: &str) -> Result<f64, TextProcessorError> {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("scorer semaphore is never closed");
+        self.scorer.score(file_path, text).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fault::Fault;
+    use crate::hyperloglog::DEFAULT_SKETCH_PRECISION;
+    use crate::segmentation::JoinPolicy;
+    use crate::stopwords::StopWordList;
+    use crate::test_utils::{assert_partial_failure, assert_same_counts, create_test_file};
+    use std::fs;
+    use tempfile::TempDir;
+
+    // Verify new processor starts with empty results
+    #[tokio::test]
+    async fn test_new_processor_creates_empty_results() {
+        let processor = TextProcessor::new();
+        assert!(processor.get_results().is_empty());
+    }
+
+    // Test empty file handling
+    #[tokio::test]
+    async fn test_process_empty_file_returns_zero_counts() {
+        let temp = TempDir::new().unwrap();
+        let file_path = create_test_file(&temp, "empty.txt", "").await;
+
+        let processor = TextProcessor::new();
+        let result = processor
+            .process_single_file(file_path.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(result.line_counts.len(), 0);
+        assert_eq!(result.total_words, 0);
+    }
+
+    // Test word counting across multiple lines
+    #[tokio::test]
+    async fn test_process_multiline_file_counts_correctly() {
+        let temp = TempDir::new().unwrap();
+        let content = "one two\nthree four five\nsix";
+        let file_path = create_test_file(&temp, "multi.txt", content).await;
+
+        let processor = TextProcessor::new();
+        let result = processor
+            .process_single_file(file_path.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(result.line_counts, vec![2, 3, 1]);
+        assert_eq!(result.total_words, 6);
+    }
+
+    // Test the per-line statistics of a file
+    #[tokio::test]
+    async fn test_process_file_line_stats() {
+        let temp = TempDir::new().unwrap();
+        let content = "one two\nthree four five\nsix";
+        let file_path = create_test_file(&temp, "multi.txt", content).await;
+
+        let result = TextProcessor::new()
+            .process_single_file(file_path)
+            .await
+            .unwrap();
+
+        let stats = result.line_stats;
+        assert_eq!((stats.min, stats.max, stats.longest_line), (1, 3, 15));
+        assert_eq!(stats.mean, 2.0);
+    }
+
+    // Verify error handling for non-existent files
+    #[tokio::test]
+    async fn test_nonexistent_file_returns_error() {
+        let processor = TextProcessor::new();
+        let result = processor
+            .process_single_file(PathBuf::from("nonexistent.txt"))
+            .await;
+
+        assert!(matches!(result, Err(TextProcessorError::FileNotFound(_))));
+    }
+
+    // Test handling of empty input file list
+    #[tokio::test]
+    async fn test_empty_input_returns_error() {
+        let mut processor = TextProcessor::new();
+        let result = processor.process_files(vec![]).await;
+        assert!(matches!(result, Err(TextProcessorError::EmptyFileList)));
     }
 
     // Test concurrent processing of multiple files
@@ -213,6 +1805,32 @@ mod tests {
         assert_eq!(results.get(&file2).unwrap().total_words, 1);
     }
 
+    // Test directories are walked and their files keyed by full path
+    #[tokio::test]
+    async fn test_process_directory_recursively() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir(temp.path().join("nested")).unwrap();
+        let top = create_test_file(&temp, "top.txt", "one two").await;
+        let nested = create_test_file(&temp, "nested/inner.txt", "three").await;
+
+        let mut processor = TextProcessor::new();
+        processor
+            .process_files(vec![temp.path().to_path_buf()])
+            .await
+            .unwrap();
+        let results = processor.get_results();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results.get(&top).unwrap().total_words, 2);
+        assert_eq!(results.get(&nested).unwrap().total_words, 1);
+
+        let mut processor = TextProcessor::new().with_max_depth(1);
+        processor
+            .process_files(vec![temp.path().to_path_buf()])
+            .await
+            .unwrap();
+        assert_eq!(processor.get_results().len(), 1);
+    }
+
     // Test partial success when processing mix of valid and invalid files
     #[tokio::test]
     async fn test_partial_processing_failure() {
@@ -225,13 +1843,1178 @@ mod tests {
             .process_files(vec![valid_file.clone(), invalid_file])
             .await;
 
+        assert_partial_failure(result, 1, 2);
+        assert_eq!(processor.get_results().len(), 1);
+    }
+
+    // Test files over the size limit fail without being read, the others being processed
+    #[tokio::test]
+    async fn test_max_file_size() {
+        let temp = TempDir::new().unwrap();
+        let small = create_test_file(&temp, "small.txt", "one two").await;
+        let large = create_test_file(&temp, "large.txt", &"word ".repeat(100)).await;
+        let archive = PathBuf::from("tests/files/archives/texts.zip");
+
+        let mut processor = TextProcessor::new().with_max_file_size(100);
+        let result = processor
+            .process_files(vec![small.clone(), large.clone(), archive])
+            .await;
+        assert_partial_failure(result, 2, 3);
+        assert_eq!(processor.get_results().len(), 1);
+        assert_eq!(processor.get_results()[&small].total_words, 2);
+
+        let err = processor
+            .process_single_file(large.clone())
+            .await
+            .unwrap_err();
         assert!(matches!(
-            result,
-            Err(TextProcessorError::PartialProcessingFailure {
-                failed_count: 1,
-                total_count: 2,
-            })
+            err,
+            TextProcessorError::FileTooLarge { path, size: 500, limit: 100 } if path == large
         ));
-        assert_eq!(processor.get_results().len(), 1);
+    }
+
+    // Test a counted pattern replaces the words, per line and per file
+    #[tokio::test]
+    async fn test_count_pattern() {
+        let temp = TempDir::new().unwrap();
+        let log = create_test_file(
+            &temp,
+            "app.log",
+            "ERROR disk full\nINFO ok\nERROR retry ERROR again\n",
+        )
+        .await;
+
+        let mut processor = TextProcessor::new()
+            .with_count_pattern("ERROR".parse().unwrap())
+            .with_frequencies(true);
+        processor.process_files(vec![log.clone()]).await.unwrap();
+        let result = &processor.get_results()[&log];
+        assert_eq!(result.line_counts, vec![1, 0, 2]);
+        assert_eq!(result.total_words, 3);
+        assert_eq!(processor.get_frequencies().top(1), vec![("error", 3)]);
+
+        let result = processor.process_text("ERROR, not error");
+        assert_eq!(result.line_counts, vec![1]);
+    }
+
+    // Test distinct words are counted per file and across the corpus
+    #[tokio::test]
+    async fn test_unique_words() {
+        let temp = TempDir::new().unwrap();
+        let file1 = create_test_file(&temp, "a.txt", "The cat saw the dog.\nThe end").await;
+        let file2 = create_test_file(&temp, "b.txt", "A dog, a CAT").await;
+
+        let mut processor = TextProcessor::new();
+        processor.process_files(vec![file1.clone()]).await.unwrap();
+        assert_eq!(processor.get_results()[&file1].unique_words, None);
+        assert_eq!(processor.get_unique_words(), 0);
+
+        let mut processor = TextProcessor::new().with_unique_words(true);
+        processor
+            .process_files(vec![file1.clone(), file2.clone()])
+            .await
+            .unwrap();
+        assert_eq!(processor.get_results()[&file1].unique_words, Some(6));
+        assert_eq!(processor.get_results()[&file2].unique_words, Some(4));
+        assert_eq!(processor.get_unique_words(), 9);
+
+        let mut processor = TextProcessor::new()
+            .with_unique_words(true)
+            .with_case_insensitive(true);
+        processor
+            .process_files(vec![file1.clone(), file2.clone()])
+            .await
+            .unwrap();
+        assert_eq!(processor.get_results()[&file1].unique_words, Some(5));
+        assert_eq!(processor.get_results()[&file2].unique_words, Some(3));
+        assert_eq!(processor.get_unique_words(), 6);
+    }
+
+    // Test distinct words are estimated from sketches rather than kept
+    #[tokio::test]
+    async fn test_approximate_unique_words() {
+        let temp = TempDir::new().unwrap();
+        let file1 = create_test_file(&temp, "a.txt", "The cat saw the dog.\nThe end").await;
+        let text = (0..5000).map(|i| format!("w{i} ")).collect::<String>();
+        let file2 = create_test_file(&temp, "b.txt", &format!("{text}\n{text} cat")).await;
+
+        let mut processor = TextProcessor::new()
+            .with_unique_words(true)
+            .with_case_insensitive(true)
+            .with_approximate_unique_words(DEFAULT_SKETCH_PRECISION);
+        processor
+            .process_files(vec![file1.clone(), file2.clone()])
+            .await
+            .unwrap();
+        let result = &processor.get_results()[&file1];
+        assert_eq!(result.unique_words, Some(5));
+        assert_eq!(result.vocabulary, None);
+        assert!(result.vocabulary_sketch.is_some());
+
+        let error = |estimate: usize, exact: f64| (estimate as f64 - exact).abs() / exact;
+        let file2_words = processor.get_results()[&file2].unique_words.unwrap();
+        assert!(error(file2_words, 5001.0) < 0.03, "{file2_words}");
+        let corpus_words = processor.get_unique_words();
+        assert!(error(corpus_words, 5005.0) < 0.03, "{corpus_words}");
+
+        // Only unique words are sketched
+        let mut processor = TextProcessor::new().with_approximate_unique_words(10);
+        processor.process_files(vec![file1.clone()]).await.unwrap();
+        assert_eq!(processor.get_results()[&file1].vocabulary_sketch, None);
+        assert_eq!(processor.get_unique_words(), 0);
+    }
+
+    // Test Bloom filters of the vocabulary are kept unless content retention is off
+    #[tokio::test]
+    async fn test_bloom_filters() {
+        let temp = TempDir::new().unwrap();
+        let file = create_test_file(&temp, "a.txt", "The cat saw the dog.\nThe end").await;
+
+        let mut processor = TextProcessor::new().with_bloom_filters(0.001);
+        processor.process_files(vec![file.clone()]).await.unwrap();
+        let result = &processor.get_results()[&file];
+        assert_eq!(result.unique_words, None);
+        let filter = result.bloom_filter.as_ref().unwrap();
+        assert!(["the", "Cat", "dog", "END"]
+            .iter()
+            .all(|w| filter.may_contain(w)));
+        assert!(!filter.may_contain("bird"));
+
+        let mut processor = TextProcessor::new()
+            .with_bloom_filters(0.001)
+            .with_content_retention(false);
+        processor.process_files(vec![file.clone()]).await.unwrap();
+        assert_eq!(processor.get_results()[&file].bloom_filter, None);
+    }
+
+    // Test no word text is kept without content retention, whatever the order of the settings
+    #[tokio::test]
+    async fn test_no_content_retention() {
+        let temp = TempDir::new().unwrap();
+        let file = create_test_file(
+            &temp,
+            "a.txt",
+            "confidential merger plans
+plans",
+        )
+        .await;
+
+        for processor in [
+            TextProcessor::new()
+                .with_frequencies(true)
+                .with_unique_words(true)
+                .with_content_retention(false),
+            TextProcessor::new()
+                .with_content_retention(false)
+                .with_frequencies(true)
+                .with_unique_words(true),
+        ] {
+            let mut processor = processor;
+            processor.process_files(vec![file.clone()]).await.unwrap();
+            let result = &processor.get_results()[&file];
+            assert_eq!(result.total_words, 4);
+            assert_eq!(result.frequencies, None);
+            assert_eq!(result.vocabulary, None);
+            assert!(processor.get_frequencies().top(1).is_empty());
+        }
+
+        // Content is retained by default, however the processor is made
+        let mut processor = TextProcessor::default().with_frequencies(true);
+        processor.process_files(vec![file.clone()]).await.unwrap();
+        assert_eq!(processor.get_frequencies().top(1), vec![("plans", 2)]);
+    }
+
+    // Test custom analyzers run over the lines of each file, fresh for every file
+    #[tokio::test]
+    async fn test_analyzers() {
+        #[derive(Default)]
+        struct Todos {
+            lines: usize,
+            todos: usize,
+        }
+
+        impl Analyzer for Todos {
+            fn on_line(&mut self, line: &str) {
+                self.lines += 1;
+                self.todos += line.matches("TODO").count();
+            }
+
+            fn finish(self: Box<Self>) -> Metrics {
+                Metrics::from([
+                    ("lines".to_string(), self.lines as f64),
+                    ("todos".to_string(), self.todos as f64),
+                ])
+            }
+        }
+
+        let temp = TempDir::new().unwrap();
+        let a = create_test_file(
+            &temp,
+            "a.rs",
+            "// TODO: split
+fn main() {} // TODO",
+        )
+        .await;
+        let b = create_test_file(&temp, "b.rs", "fn done() {}").await;
+
+        let mut processor = TextProcessor::new()
+            .with_analyzer("todos", || Todos {
+                lines: 100,
+                todos: 0,
+            })
+            .with_analyzer("todos", Todos::default);
+        processor
+            .process_files(vec![a.clone(), b.clone()])
+            .await
+            .unwrap();
+        let results = processor.get_results();
+        assert_eq!(results[&a].analyses["todos"]["todos"], 2.0);
+        assert_eq!(results[&a].analyses["todos"]["lines"], 2.0);
+        assert_eq!(results[&b].analyses["todos"]["todos"], 0.0);
+        assert_eq!(results[&b].analyses.len(), 1);
+
+        let result = processor.process_text("TODO\nTODO TODO");
+        assert_eq!(result.analyses["todos"]["todos"], 3.0);
+        assert!(TextProcessor::new()
+            .process_text("TODO")
+            .analyses
+            .is_empty());
+    }
+
+    // Test readability counts sentences across lines, a blank line or the end closing one
+    #[tokio::test]
+    async fn test_readability() {
+        let temp = TempDir::new().unwrap();
+        let content = "# Title\n\nThe cat sat\non the mat. It was\nhappy";
+        let file = create_test_file(&temp, "a.md", content).await;
+
+        let processor = TextProcessor::new();
+        let result = processor.process_single_file(file.clone()).await.unwrap();
+        assert_eq!(result.readability, None);
+
+        let processor = TextProcessor::new().with_readability(true);
+        let result = processor.process_single_file(file).await.unwrap();
+        let readability = result.readability.unwrap();
+        assert_eq!(
+            (
+                readability.sentences,
+                readability.words,
+                readability.syllables
+            ),
+            (3, 10, 12)
+        );
+        assert_eq!(
+            processor.process_text(content).readability,
+            Some(readability)
+        );
+    }
+
+    // Test stop words are left out of the frequencies and unique words, not the counts
+    #[tokio::test]
+    async fn test_stop_words() {
+        let temp = TempDir::new().unwrap();
+        let file = create_test_file(&temp, "a.txt", "The river and the sea.\nA river").await;
+
+        let stop_words = StopWordFilter::new().with_list(StopWordList::English);
+        let mut processor = TextProcessor::new()
+            .with_stop_words(Arc::new(stop_words))
+            .with_frequencies(true)
+            .with_unique_words(true)
+            .with_case_insensitive(true);
+        processor.process_files(vec![file.clone()]).await.unwrap();
+        let result = &processor.get_results()[&file];
+        assert_eq!(result.line_counts, vec![5, 2]);
+        assert_eq!(result.unique_words, Some(2));
+        assert_eq!(
+            processor.get_frequencies().top(3),
+            vec![("river", 2), ("sea", 1)]
+        );
+    }
+
+    // Test external scorer results are attached to each file
+    #[tokio::test]
+    async fn test_scorer_attaches_score() {
+        let temp = TempDir::new().unwrap();
+        let file = create_test_file(&temp, "scored.txt", "one two\nthree").await;
+        let scorer = crate::CommandScorer::from_command_line("wc -l").unwrap();
+
+        let mut processor = TextProcessor::new().with_scorer_concurrency(Arc::new(scorer), 1);
+        processor.process_files(vec![file.clone()]).await.unwrap();
+
+        assert_eq!(processor.get_results().get(&file).unwrap().score, Some(2.0));
+    }
+
+    // Test head/tail line limits only count part of the file
+    #[tokio::test]
+    async fn test_partial_reads_by_lines() {
+        let temp = TempDir::new().unwrap();
+        let file = create_test_file(&temp, "lines.txt", "a\nb b\nc c c\nd d d d").await;
+
+        let processor = TextProcessor::new().with_partial_read(PartialRead::HeadLines(2));
+        let result = processor.process_single_file(file.clone()).await.unwrap();
+        assert_eq!(result.line_counts, vec![1, 2]);
+        assert!(result.partial);
+
+        let processor = TextProcessor::new().with_partial_read(PartialRead::TailLines(3));
+        let result = processor.process_single_file(file.clone()).await.unwrap();
+        assert_eq!(result.line_counts, vec![2, 3, 4]);
+        assert_eq!(result.total_words, 9);
+        assert!(result.partial);
+
+        let processor = TextProcessor::new().with_partial_read(PartialRead::HeadLines(10));
+        let result = processor.process_single_file(file).await.unwrap();
+        assert_eq!(result.line_counts.len(), 4);
+        assert!(!result.partial);
+    }
+
+    // Test per-file line ranges override the global partial read
+    #[tokio::test]
+    async fn test_file_line_range() {
+        let temp = TempDir::new().unwrap();
+        let file = create_test_file(&temp, "range.txt", "a\nb b\nc c c\nd d d d").await;
+        let range = "2-3".parse().unwrap();
+
+        let processor = TextProcessor::new()
+            .with_partial_read(PartialRead::HeadLines(1))
+            .with_file_partial_read(&file, PartialRead::Lines(range));
+        let result = processor.process_single_file(file).await.unwrap();
+
+        assert_eq!(result.line_counts, vec![2, 3]);
+        assert_eq!(result.total_words, 5);
+        assert!(result.partial);
+    }
+
+    // Test any async reader is processed like a file
+    #[tokio::test]
+    async fn test_process_reader() {
+        let mut processor = TextProcessor::new().with_partial_read(PartialRead::TailLines(2));
+        processor
+            .process_reader("-", std::io::Cursor::new(b"a\nb b\nc c c\n".to_vec()))
+            .await
+            .unwrap();
+
+        let result = processor.get_results().get(Path::new("-")).unwrap();
+        assert_eq!(result.line_counts, vec![2, 3]);
+        assert!(result.partial);
+    }
+
+    // Test in-memory buffers are counted like files
+    #[tokio::test]
+    async fn test_process_text_matches_file_processing() {
+        let temp = TempDir::new().unwrap();
+        let content = "one two\r\n\nthree four five\nsix";
+        let file = create_test_file(&temp, "buffer.txt", content).await;
+
+        let processor = TextProcessor::new().with_line_offsets(true);
+        let from_file = processor.process_single_file(file).await.unwrap();
+        let from_text = processor.process_text(content);
+
+        assert_eq!(from_text.line_counts, vec![2, 0, 3, 1]);
+        assert_eq!(from_text.line_counts, from_file.line_counts);
+        assert_eq!(from_text.line_offsets, from_file.line_offsets);
+        assert!(processor.process_text("").line_counts.is_empty());
+    }
+
+    // Test byte order marks are skipped rather than counted as part of the first word
+    #[tokio::test]
+    async fn test_bom_stripped() {
+        let temp = TempDir::new().unwrap();
+        let content = "\u{feff}Première ligne\nsecond";
+        let file = create_test_file(&temp, "bom.txt", content).await;
+
+        let processor = TextProcessor::new()
+            .with_line_offsets(true)
+            .with_frequencies(true);
+        let result = processor.process_single_file(file.clone()).await.unwrap();
+        assert!(result.had_bom);
+        assert_eq!(result.frequencies.unwrap().get("première"), 1);
+        assert_eq!(result.total_chars, content.chars().count() - 1);
+        assert_eq!(result.total_bytes, content.len() as u64);
+        assert_eq!(result.line_offsets, Some(vec![0, 19]));
+
+        let from_text = processor.process_text(content);
+        assert!(from_text.had_bom);
+        assert_eq!(from_text.total_chars, result.total_chars);
+        assert_eq!(from_text.line_offsets, result.line_offsets);
+
+        // Only a byte order mark at the start of the file is skipped
+        let processor = TextProcessor::new().with_partial_read(PartialRead::TailLines(1));
+        let result = processor.process_single_file(file).await.unwrap();
+        assert!(result.had_bom);
+        assert!(!processor.process_text("no\u{feff}mark").had_bom);
+    }
+
+    // Test line offsets are only recorded when requested
+    #[tokio::test]
+    async fn test_line_offsets_recorded() {
+        let temp = TempDir::new().unwrap();
+        let file = create_test_file(&temp, "offsets.txt", "one\ntwo words\n\nend").await;
+
+        let result = TextProcessor::new()
+            .process_single_file(file.clone())
+            .await
+            .unwrap();
+        assert_eq!(result.line_offsets, None);
+
+        let processor = TextProcessor::new()
+            .with_line_offsets(true)
+            .with_partial_read(PartialRead::TailLines(2));
+        let result = processor.process_single_file(file).await.unwrap();
+        assert_eq!(result.line_offsets, Some(vec![14, 15]));
+    }
+
+    // Test character and byte counts include line terminators, like wc
+    #[tokio::test]
+    async fn test_char_and_byte_counts() {
+        let temp = TempDir::new().unwrap();
+        let content = "héllo wörld\r\nend\n";
+        let file = create_test_file(&temp, "wc.txt", content).await;
+
+        let processor = TextProcessor::new();
+        let result = processor.process_single_file(file).await.unwrap();
+        assert_eq!(result.line_count, 2);
+        assert_eq!(result.total_chars, 17);
+        assert_eq!(result.total_bytes, content.len() as u64);
+
+        let from_text = processor.process_text(content);
+        assert_eq!(from_text.total_chars, result.total_chars);
+        assert_eq!(from_text.total_bytes, result.total_bytes);
+    }
+
+    // Test word frequencies are recorded per file and merged across the corpus
+    #[tokio::test]
+    async fn test_word_frequencies() {
+        let temp = TempDir::new().unwrap();
+        let file1 = create_test_file(&temp, "a.txt", "the cat\nThe dog").await;
+        let file2 = create_test_file(&temp, "b.txt", "the end").await;
+
+        let mut processor = TextProcessor::new();
+        processor.process_files(vec![file1.clone()]).await.unwrap();
+        assert!(processor.get_frequencies().is_empty());
+
+        let mut processor = TextProcessor::new().with_frequencies(true);
+        processor
+            .process_files(vec![file1.clone(), file2])
+            .await
+            .unwrap();
+        let file_frequencies = processor.get_results()[&file1].frequencies.as_ref();
+        assert_eq!(file_frequencies.unwrap().get("the"), 2);
+        assert_eq!(processor.get_frequencies().get("the"), 3);
+    }
+
+    // Test spilled frequencies are aggregated on disk rather than in the file results
+    #[tokio::test]
+    async fn test_frequency_spill() {
+        let temp = TempDir::new().unwrap();
+        let spill_dir = TempDir::new().unwrap();
+        let file1 = create_test_file(&temp, "a.txt", "the cat\nthe dog").await;
+        let file2 = create_test_file(&temp, "b.txt", "the end of a tale").await;
+
+        let mut processor = TextProcessor::new()
+            .with_frequencies(true)
+            .with_frequency_spill(SpillingFrequency::new(2).with_dir(spill_dir.path()))
+            .with_cache(temp.path().join("cache"));
+        processor
+            .process_files(vec![file1.clone(), file2])
+            .await
+            .unwrap();
+        assert_eq!(processor.get_results()[&file1].frequencies, None);
+        assert!(processor.get_frequencies().is_empty());
+        assert!(!temp.path().join("cache").exists());
+
+        let frequencies = processor.take_spilled_frequencies().unwrap();
+        assert_eq!(
+            frequencies.top(2).unwrap(),
+            vec![("the".to_string(), 3), ("a".to_string(), 1)]
+        );
+        assert_eq!(frequencies.words().unwrap().count(), 7);
+        assert!(processor.take_spilled_frequencies().is_none());
+    }
+
+    // Test segmentation rules apply per file, the last matching one winning
+    #[tokio::test]
+    async fn test_segmentation_rules() {
+        let temp = TempDir::new().unwrap();
+        let text = "日本語 hello,world";
+        let ja = create_test_file(&temp, "intro.ja.md", text).await;
+        let en = create_test_file(&temp, "intro.md", text).await;
+        let notes = create_test_file(&temp, "notes.txt", text).await;
+
+        let mut processor = TextProcessor::new()
+            .with_segmentation_rule("*.md".parse().unwrap(), Segmentation::Unicode)
+            .with_segmentation_rule("intro.md".parse().unwrap(), Segmentation::Whitespace)
+            .with_segmentation_rule(".ja.md".parse().unwrap(), Segmentation::Unicode);
+        processor
+            .process_files(vec![ja.clone(), en.clone(), notes.clone()])
+            .await
+            .unwrap();
+
+        let results = processor.get_results();
+        assert_eq!(results[&ja].total_words, 5);
+        assert_eq!(results[&en].total_words, 2);
+        assert_eq!(results[&notes].total_words, 2);
+    }
+
+    // Test each file result is streamed as the file completes
+    #[tokio::test]
+    async fn test_result_sender_streams_file_results() {
+        let temp = TempDir::new().unwrap();
+        let file1 = create_test_file(&temp, "one.txt", "one two").await;
+        let file2 = create_test_file(&temp, "two.txt", "three").await;
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut processor = TextProcessor::new().with_result_sender(sender);
+        processor
+            .process_files(vec![file1.clone(), file2.clone()])
+            .await
+            .unwrap();
+        processor.close_result_sender();
+
+        let (mut streamed, mut done) = (HashMap::new(), Vec::new());
+        while let Some(event) = receiver.recv().await {
+            match event {
+                StreamedResult::File { path, result } => {
+                    assert!(!done.contains(&path));
+                    streamed.insert(path, result.total_words);
+                }
+                StreamedResult::Done { path } => done.push(path),
+            }
+        }
+        assert_eq!(
+            streamed,
+            HashMap::from([(file1.clone(), 2), (file2.clone(), 1)])
+        );
+        done.sort();
+        assert_eq!(done, [file1, file2]);
+    }
+
+    // Test file results are yielded as the files complete, without being stored
+    #[tokio::test]
+    async fn test_process_files_stream() {
+        let temp = TempDir::new().unwrap();
+        let file1 = create_test_file(&temp, "one.txt", "one two").await;
+        let file2 = create_test_file(&temp, "two.txt", "three").await;
+        let missing = temp.path().join("missing.txt");
+
+        let processor = TextProcessor::new().with_concurrency(1);
+        let streamed: HashMap<_, _> = processor
+            .process_files_stream(vec![file1.clone(), file2.clone(), missing.clone()])
+            .await
+            .unwrap()
+            .map(|(path, result)| (path, result.map(|r| r.total_words).ok()))
+            .collect()
+            .await;
+        assert_eq!(
+            streamed,
+            HashMap::from([(file1.clone(), Some(2)), (file2, Some(1)), (missing, None)])
+        );
+        assert!(processor.get_results().is_empty());
+
+        // A consumer may stop at any result
+        let first: Vec<_> = processor
+            .process_files_stream(vec![file1.clone(), file1])
+            .await
+            .unwrap()
+            .take(1)
+            .collect()
+            .await;
+        assert_eq!(first.len(), 1);
+
+        let empty = TempDir::new().unwrap();
+        assert!(matches!(
+            processor
+                .process_files_stream(vec![empty.path().to_path_buf()])
+                .await,
+            Err(TextProcessorError::EmptyFileList)
+        ));
+    }
+
+    // Test a concurrency limit still processes every file
+    #[tokio::test]
+    async fn test_bounded_concurrency() {
+        let temp = TempDir::new().unwrap();
+        let mut paths = Vec::new();
+        for i in 0..5 {
+            let content = "word ".repeat(i);
+            paths.push(create_test_file(&temp, &format!("{i}.txt"), &content).await);
+        }
+
+        let mut processor = TextProcessor::new().with_concurrency(2);
+        processor.process_files(paths.clone()).await.unwrap();
+
+        let results = processor.get_results();
+        assert_eq!(results.len(), 5);
+        for (i, path) in paths.iter().enumerate() {
+            assert_eq!(results[path].total_words, i);
+        }
+    }
+
+    // Test a file hanging past the timeout fails on its own, the others being processed
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timeout() {
+        let temp = TempDir::new().unwrap();
+        let file = create_test_file(&temp, "file.txt", "one two").await;
+        // Opening a FIFO blocks until a writer shows up
+        let fifo = temp.path().join("fifo");
+        let created = std::process::Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap();
+        assert!(created.success());
+
+        let mut processor = TextProcessor::new().with_timeout(Duration::from_millis(100));
+        let result = processor
+            .process_files(vec![file.clone(), fifo.clone()])
+            .await;
+        assert_partial_failure(result, 1, 2);
+        assert_eq!(processor.get_results()[&file].total_words, 2);
+
+        let err = TextProcessor::new()
+            .with_timeout(Duration::from_millis(100))
+            .process_single_file_in_time(fifo.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TextProcessorError::Timeout { path, .. } if path == fifo));
+
+        // A writer unblocks the pending opens, so that the runtime can shut down
+        drop(fs::OpenOptions::new().write(true).open(&fifo).unwrap());
+    }
+
+    // Test binary files are skipped unless included, without failing the batch
+    #[tokio::test]
+    async fn test_binary_files_skipped() {
+        let temp = TempDir::new().unwrap();
+        let text = create_test_file(&temp, "text.txt", "one two").await;
+        let data = temp.path().join("data.bin");
+        fs::write(&data, b"\x00\x01\x02 three four\n").unwrap();
+        let pdf = temp.path().join("doc.pdf");
+        fs::write(&pdf, b"%PDF-1.7\nstream\n").unwrap();
+
+        let mut processor = TextProcessor::new();
+        processor
+            .process_files(vec![text.clone(), data.clone(), pdf.clone()])
+            .await
+            .unwrap();
+        let results = processor.get_results();
+        assert_eq!(results[&text].status, FileStatus::Processed);
+        assert_eq!(
+            results[&data].status,
+            FileStatus::Skipped("binary file (binary data)".to_string())
+        );
+        assert_eq!(
+            results[&pdf].status,
+            FileStatus::Skipped("binary file (PDF document)".to_string())
+        );
+        assert_eq!(results[&pdf].total_words, 0);
+
+        let mut processor = TextProcessor::new().with_include_binary(true);
+        processor.process_files(vec![data.clone()]).await.unwrap();
+        let result = &processor.get_results()[&data];
+        assert_eq!(result.status, FileStatus::Processed);
+        assert_eq!(result.total_words, 3);
+    }
+
+    // Test skipped, re-encoded and partly counted files give warnings, in path order
+    #[tokio::test]
+    async fn test_warnings() {
+        let temp = TempDir::new().unwrap();
+        let text = create_test_file(&temp, "c.txt", "one two\nthree\n").await;
+        let data = temp.path().join("a.bin");
+        fs::write(&data, b"\x00\x01\x02 three four\n").unwrap();
+        let latin1 = temp.path().join("b.txt");
+        fs::write(&latin1, b"caf\xe9\n").unwrap();
+
+        let mut processor =
+            TextProcessor::new().with_file_partial_read(&text, PartialRead::HeadLines(1));
+        processor
+            .process_files(vec![text.clone(), data.clone(), latin1.clone()])
+            .await
+            .unwrap();
+        assert_eq!(
+            processor.get_warnings(),
+            vec![
+                Warning::Skipped {
+                    path: data,
+                    reason: "binary file (binary data)".to_string()
+                },
+                Warning::EncodingFallback {
+                    path: latin1.clone(),
+                    encoding: Encoding::Windows1252
+                },
+                Warning::Truncated { path: text },
+            ]
+        );
+
+        // A given encoding is no fallback
+        let mut processor = TextProcessor::new().with_encoding(Encoding::Windows1252);
+        processor.process_files(vec![latin1]).await.unwrap();
+        assert!(processor.get_warnings().is_empty());
+    }
+
+    // Test injected faults fail, slow down or fragment the reads of matching files only
+    #[tokio::test]
+    async fn test_fault_injection() {
+        let temp = TempDir::new().unwrap();
+        let content = "one two\nthree four five\n".repeat(50);
+        let healthy = create_test_file(&temp, "healthy.txt", &content).await;
+        let failing = create_test_file(&temp, "failing.txt", &content).await;
+        let slow = create_test_file(&temp, "slow.txt", &content).await;
+        let injector = FaultInjector::new()
+            .with_fault(
+                "failing.txt".parse().unwrap(),
+                Fault::IoError { after: 100 },
+            )
+            .with_fault(
+                "slow.txt".parse().unwrap(),
+                Fault::Delay(Duration::from_secs(60)),
+            )
+            .with_fault(".txt".parse().unwrap(), Fault::PartialReads(7));
+
+        let mut processor = TextProcessor::new()
+            .with_fault_injector(injector)
+            .with_timeout(Duration::from_millis(100));
+        let result = processor
+            .process_files(vec![healthy.clone(), failing.clone(), slow.clone()])
+            .await;
+        assert_partial_failure(result, 2, 3);
+        let results = processor.get_results();
+        assert!(!results.contains_key(&failing) && !results.contains_key(&slow));
+        // Fragmented reads count the same
+        assert_eq!(results[&healthy].line_counts, [2, 3].repeat(50));
+        assert_eq!(results[&healthy].total_bytes, content.len() as u64);
+    }
+
+    // Test unchanged files are taken from the cache, changed ones processed again
+    #[tokio::test]
+    async fn test_result_cache() {
+        let temp = TempDir::new().unwrap();
+        let cache = temp.path().join("cache");
+        let file = create_test_file(&temp, "file.txt", "one two three").await;
+        let modified = fs::metadata(&file).unwrap().modified().unwrap();
+
+        let mut processor = TextProcessor::new().with_cache(&cache);
+        processor.process_files(vec![file.clone()]).await.unwrap();
+        assert_eq!(processor.get_results()[&file].total_words, 3);
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 1);
+
+        // Same size and modification time: the cached count is reused
+        fs::write(&file, "one-two-three").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let mut processor = TextProcessor::new().with_cache(&cache);
+        processor.process_files(vec![file.clone()]).await.unwrap();
+        assert_eq!(processor.get_results()[&file].total_words, 3);
+
+        // Other settings have their own cache
+        let mut processor = TextProcessor::new()
+            .with_cache(&cache)
+            .with_line_offsets(true);
+        processor.process_files(vec![file.clone()]).await.unwrap();
+        assert_eq!(processor.get_results()[&file].total_words, 1);
+
+        fs::write(&file, "one two").unwrap();
+        let mut processor = TextProcessor::new().with_cache(&cache);
+        processor.process_files(vec![file.clone()]).await.unwrap();
+        assert_eq!(processor.get_results()[&file].total_words, 2);
+
+        // Vocabularies are neither serialized nor cached, the cache going unused
+        let files = fs::read_dir(&cache).unwrap().count();
+        for _ in 0..2 {
+            let mut processor = TextProcessor::new()
+                .with_cache(&cache)
+                .with_unique_words(true);
+            processor.process_files(vec![file.clone()]).await.unwrap();
+            assert_eq!(processor.get_unique_words(), 2);
+            let json = serde_json::to_string(&processor.get_results()[&file]).unwrap();
+            assert!(!json.contains("vocabulary"));
+        }
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), files);
+    }
+
+    // Test gzip files are decompressed, by extension or content when asked to
+    #[tokio::test]
+    async fn test_gzip_input() {
+        let temp = TempDir::new().unwrap();
+        let plain = PathBuf::from("tests/files/longer.txt");
+        let compressed = PathBuf::from("tests/files/longer.txt.gz");
+        let renamed = temp.path().join("longer.log");
+        fs::copy(&compressed, &renamed).unwrap();
+
+        let mut processor = TextProcessor::new();
+        processor
+            .process_files(vec![plain.clone(), compressed.clone()])
+            .await
+            .unwrap();
+        let results = processor.get_results();
+        let expected = &results[&plain];
+        assert_same_counts(&results[&compressed], expected);
+
+        // Without the extension, compressed bytes are skipped as binary
+        let mut processor = TextProcessor::new();
+        processor
+            .process_files(vec![renamed.clone()])
+            .await
+            .unwrap();
+        assert_eq!(
+            processor.get_results()[&renamed].status,
+            FileStatus::Skipped("binary file (gzip data)".to_string())
+        );
+
+        let mut processor = TextProcessor::new().with_decompress(true);
+        processor
+            .process_files(vec![renamed.clone()])
+            .await
+            .unwrap();
+        assert_eq!(
+            processor.get_results()[&renamed].line_counts,
+            expected.line_counts
+        );
+
+        // Partial reads apply to the decompressed content
+        let mut processor = TextProcessor::new().with_partial_read(PartialRead::HeadLines(2));
+        processor
+            .process_files(vec![compressed.clone()])
+            .await
+            .unwrap();
+        assert_eq!(processor.get_results()[&compressed].line_counts.len(), 2);
+
+        // Line offsets are left out, not pointing into the compressed file
+        let mut processor = TextProcessor::new().with_line_offsets(true);
+        processor
+            .process_files(vec![plain.clone(), compressed.clone()])
+            .await
+            .unwrap();
+        assert!(processor.get_results()[&plain].line_offsets.is_some());
+        assert_eq!(processor.get_results()[&compressed].line_offsets, None);
+
+        // The size limit applies to the decompressed content too
+        let size = fs::metadata(&plain).unwrap().len();
+        let processor = TextProcessor::new().with_max_file_size(size);
+        processor
+            .process_single_file(compressed.clone())
+            .await
+            .unwrap();
+        let processor = TextProcessor::new().with_max_file_size(size - 1);
+        let err = processor
+            .process_single_file(compressed.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            TextProcessorError::DecompressedTooLarge { path, limit } if path == compressed && limit == size - 1
+        ));
+    }
+
+    // Test files are decoded from their detected or given encoding
+    #[tokio::test]
+    async fn test_encodings() {
+        let temp = TempDir::new().unwrap();
+        let latin1 = temp.path().join("latin1.txt");
+        fs::write(&latin1, b"d\xe9j\xe0 vu\r\nna\xefve\n").unwrap();
+        let utf16: Vec<u8> = "\u{feff}déjà vu\r\nnaïve\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let utf16_path = temp.path().join("utf16.txt");
+        fs::write(&utf16_path, &utf16).unwrap();
+
+        let mut processor = TextProcessor::new();
+        processor
+            .process_files(vec![latin1.clone(), utf16_path.clone()])
+            .await
+            .unwrap();
+        let results = processor.get_results();
+        assert_eq!(results[&latin1].encoding, Encoding::Windows1252);
+        assert_eq!(results[&latin1].line_counts, vec![2, 1]);
+        assert_eq!(results[&latin1].total_chars, 15);
+        assert_eq!(results[&latin1].total_bytes, 15);
+
+        let result = &results[&utf16_path];
+        assert_eq!(result.encoding, Encoding::Utf16Le);
+        assert_eq!(result.line_counts, vec![2, 1]);
+        // The byte order mark is skipped, though its bytes count
+        assert_eq!(result.total_chars, 15);
+        assert!(result.had_bom && !results[&latin1].had_bom);
+        assert_eq!(result.total_bytes, 32);
+
+        // A given encoding overrides the detection
+        let mut processor = TextProcessor::new().with_encoding(Encoding::Utf8);
+        assert!(processor.process_files(vec![latin1]).await.is_err());
+    }
+
+    // Test archive entries are processed like files, keyed under the archive
+    #[tokio::test]
+    async fn test_archive_input() {
+        let archive = PathBuf::from("tests/files/archives/texts.zip");
+        let mut processor = TextProcessor::new();
+        processor
+            .process_files(vec![archive.clone()])
+            .await
+            .unwrap();
+
+        let results = processor.get_results();
+        assert_eq!(results.len(), 3);
+        let second = &results[&PathBuf::from("tests/files/archives/texts.zip!/second.txt")];
+        assert_eq!(second.total_words, 3);
+        let first = &results[&PathBuf::from("tests/files/archives/texts.zip!/inner/first.txt")];
+        assert_eq!(first.line_counts, vec![140; 3]);
+
+        // Files which are not archives despite their name fail
+        let temp = TempDir::new().unwrap();
+        let fake = create_test_file(&temp, "fake.zip", "not an archive").await;
+        let mut processor = TextProcessor::new();
+        assert!(processor.process_files(vec![fake]).await.is_err());
+    }
+
+    // Test listed files are processed as given, directories not being walked again
+    #[tokio::test]
+    async fn test_process_listed_files() {
+        let temp = TempDir::new().unwrap();
+        let file = create_test_file(&temp, "a.txt", "one two").await;
+        let dir = temp.path().join("docs");
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("b.txt"), "three").unwrap();
+
+        let mut processor = TextProcessor::new();
+        let listed = expand_paths(vec![file.clone(), dir.clone()], processor.walk_options())
+            .await
+            .unwrap();
+        processor
+            .process_listed_files_with_cancel(listed, CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(processor.get_results().len(), 2);
+
+        let mut processor = TextProcessor::new();
+        let result = processor
+            .process_listed_files_with_cancel(vec![file.clone(), dir], CancellationToken::new())
+            .await;
+        assert_partial_failure(result, 1, 2);
+        assert_eq!(processor.get_results()[&file].total_words, 2);
+    }
+
+    // Test a cancelled run stops early and keeps the results gathered so far
+    #[tokio::test]
+    async fn test_process_files_with_cancel() {
+        let temp = TempDir::new().unwrap();
+        let mut paths = Vec::new();
+        for i in 0..10 {
+            paths.push(create_test_file(&temp, &format!("{i}.txt"), "one two").await);
+        }
+
+        let token = CancellationToken::new();
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let canceller = tokio::spawn({
+            let token = token.clone();
+            async move {
+                receiver.recv().await;
+                token.cancel();
+            }
+        });
+
+        let mut processor = TextProcessor::new()
+            .with_concurrency(1)
+            .with_result_sender(sender);
+        let err = processor
+            .process_files_with_cancel(paths.clone(), token.clone())
+            .await
+            .unwrap_err();
+        canceller.await.unwrap();
+
+        match err {
+            TextProcessorError::Cancelled {
+                processed_count,
+                total_count,
+            } => {
+                assert_eq!(total_count, 10);
+                assert!((1..10).contains(&processed_count));
+                assert_eq!(processor.get_results().len(), processed_count);
+            }
+            e => panic!("unexpected error: {e}"),
+        }
+
+        // An already cancelled token processes nothing
+        let mut processor = TextProcessor::new();
+        let err = processor
+            .process_files_with_cancel(paths, token)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            TextProcessorError::Cancelled {
+                processed_count: 0,
+                ..
+            }
+        ));
+        assert!(processor.get_results().is_empty());
+    }
+
+    // Test the settings name what changes the counts, and key the result cache
+    #[test]
+    fn test_settings() {
+        let processor = TextProcessor::new()
+            .with_case_insensitive(true)
+            .with_counting_policy(CountingPolicy {
+                apostrophes: JoinPolicy::Split,
+                ..Default::default()
+            });
+        let settings: HashMap<_, _> = processor.settings().into_iter().collect();
+        assert_eq!(settings["case_insensitive"], "true");
+        assert!(settings["counting_policy"].contains("apostrophes: Split"));
+        assert_ne!(
+            processor.settings_fingerprint(),
+            TextProcessor::new().settings_fingerprint()
+        );
+    }
+
+    // Test the '#' of Markdown headings are only left out when asked to
+    #[test]
+    fn test_markdown_headings() {
+        let text = "# Title\n\nSome text.\n\n```sh\n# a comment\n```\n## Part two ##";
+        let result = TextProcessor::new().process_text_as("notes.md", text);
+        assert_eq!(result.line_counts, [2, 0, 2, 0, 1, 3, 1, 4]);
+
+        let processor = TextProcessor::new().with_markdown_headings(true);
+        let result = processor.process_text_as("notes.md", text);
+        assert_eq!(result.line_counts, [1, 0, 2, 0, 1, 3, 1, 2]);
+
+        let plain = processor.process_text_as("notes.txt", "# Title");
+        assert_eq!(plain.total_words, 2);
+    }
+
+    // Test footnotes, captions and tables are counted apart, excluded ones left out of totals
+    #[test]
+    fn test_region_counts() {
+        let text = "Body text[^1].\n\n![A cat](cat.png)\n\n| a | b |\n\n[^1]: A short note.";
+        let processor = TextProcessor::new().with_regions([Region::Footnote]);
+        let result = processor.process_text_as("notes.md", text);
+
+        let regions = result.regions.unwrap();
+        assert_eq!(
+            (regions.footnotes, regions.captions, regions.tables),
+            (3, 2, 2)
+        );
+        assert_eq!(result.total_words, 6);
+
+        // Regions are only recognized in structured files
+        let plain = processor.process_text_as("notes.txt", "![A cat](cat.png)");
+        assert_eq!(plain.regions, Some(RegionCounts::default()));
+    }
+
+    // Test files are processed in parallel on a multi-threaded runtime
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_parallel_processing() {
+        let temp = TempDir::new().unwrap();
+        let mut paths = Vec::new();
+        for i in 0..8 {
+            let content = "word ".repeat(i * 100);
+            paths.push(create_test_file(&temp, &format!("{i}.txt"), &content).await);
+        }
+
+        let mut processor = TextProcessor::new().with_concurrency(4);
+        processor.process_files(paths.clone()).await.unwrap();
+
+        let results = processor.get_results();
+        for (i, path) in paths.iter().enumerate() {
+            assert_eq!(results[path].total_words, i * 100);
+        }
+    }
+
+    // Test progress events announce the files, then report each one done
+    #[tokio::test]
+    async fn test_progress_events() {
+        let temp = TempDir::new().unwrap();
+        let file = create_test_file(&temp, "one.txt", "one two\n").await;
+        let missing = temp.path().join("missing.txt");
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut processor = TextProcessor::new().with_progress_sender(sender);
+        let _ = processor
+            .process_files(vec![file.clone(), missing.clone()])
+            .await;
+        processor.close_progress_sender();
+
+        assert_eq!(receiver.recv().await, Some(Progress::Started { total: 2 }));
+        let mut done = Vec::new();
+        while let Some(progress) = receiver.recv().await {
+            done.push(progress);
+        }
+        done.sort_by_key(|progress| format!("{progress:?}"));
+        assert_eq!(
+            done,
+            [
+                Progress::FileDone {
+                    path: missing,
+                    bytes: 0
+                },
+                Progress::FileDone {
+                    path: file,
+                    bytes: 8
+                },
+            ]
+        );
+    }
+
+    // Test file events report each file starting, then completing or failing
+    #[tokio::test]
+    async fn test_file_events() {
+        let temp = TempDir::new().unwrap();
+        let file = create_test_file(&temp, "one.txt", "one two\n").await;
+        let missing = temp.path().join("missing.txt");
+        let cache = temp.path().join("cache");
+
+        for cached in [false, true] {
+            let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+            let mut processor = TextProcessor::new()
+                .with_cache(&cache)
+                .with_event_sender(sender);
+            let _ = processor
+                .process_files(vec![file.clone(), missing.clone()])
+                .await;
+            processor.close_event_sender();
+
+            let mut events = HashMap::<PathBuf, Vec<FileEvent>>::new();
+            while let Some(event) = receiver.recv().await {
+                let path = match &event {
+                    FileEvent::Started { path }
+                    | FileEvent::Completed { path, .. }
+                    | FileEvent::Failed { path, .. } => path.clone(),
+                };
+                events.entry(path).or_default().push(event);
+            }
+            assert!(matches!(
+                &events[&file][..],
+                [
+                    FileEvent::Started { .. },
+                    FileEvent::Completed { bytes: 8, cached: was_cached, .. },
+                ] if *was_cached == cached
+            ));
+            assert!(matches!(
+                &events[&missing][..],
+                [FileEvent::Started { .. }, FileEvent::Failed { error, .. }]
+                    if error.contains("not found")
+            ));
+        }
+    }
+
+    // Test block quotes and quoted spans count as quotes, apart from the original words
+    #[test]
+    fn test_quote_exclusion() {
+        let text = "> A quoted block\nHe said “hi there” twice.";
+        let processor = TextProcessor::new().with_regions([Region::Quote]);
+
+        let result = processor.process_text_as("article.md", text);
+        assert_eq!(result.regions.unwrap().quotes, 5);
+        assert_eq!(result.total_words, 3);
+
+        // Quoted spans are recognized in any file
+        let plain = processor.process_text_as("article.txt", "He said “hi there” twice.");
+        assert_eq!(plain.regions.unwrap().quotes, 2);
+        assert_eq!(plain.total_words, 3);
     }
 }
@@ -0,0 +1,47 @@
+use crate::types::FileProcessingResult;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Progress event of `TextProcessor::process_files`, e.g. to drive a progress bar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Progress {
+    /// The inputs were listed, with this many files to process
+    Started { total: usize },
+    /// A file is done, successfully or not, after reading this many bytes
+    FileDone { path: PathBuf, bytes: u64 },
+}
+
+/// Result streamed through `TextProcessor::with_result_sender`
+#[derive(Debug, Clone)]
+pub enum StreamedResult {
+    /// Result of a file, or of an archive entry named e.g. 'notes.zip!/a.txt'
+    File {
+        path: PathBuf,
+        result: Box<FileProcessingResult>,
+    },
+    /// A file listed for `process_files` is done: its results, if any, were all sent
+    /// before, none being sent for a file that failed
+    Done { path: PathBuf },
+}
+
+/// Event of a file being processed, with its timing, e.g. to drive a progress UI
+/// showing the files in flight
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileEvent {
+    /// A file started being processed, or being taken from the cache
+    Started { path: PathBuf },
+    /// A file was counted, reading this many bytes in `elapsed` since it started.
+    /// Archives report one completion per text entry.
+    Completed {
+        path: PathBuf,
+        bytes: u64,
+        elapsed: Duration,
+        cached: bool,
+    },
+    /// A file failed with this error, `elapsed` after it started
+    Failed {
+        path: PathBuf,
+        error: String,
+        elapsed: Duration,
+    },
+}
@@ -0,0 +1,88 @@
+/// Splits double-quoted spans, e.g. “like this”, from the body text. A quotation
+/// may span lines, so the splitter is stateful; it ends with its paragraph at the latest.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct QuotedSpans {
+    /// Closing mark of the quotation being read, if any
+    closing: Option<char>,
+}
+
+impl QuotedSpans {
+    /// Body text of a line without its quoted spans, and the quoted text
+    pub(crate) fn split(&mut self, text: &str) -> (String, String) {
+        if text.trim().is_empty() {
+            self.closing = None;
+        }
+
+        let mut body = String::with_capacity(text.len());
+        let mut quoted = String::new();
+        for c in text.chars() {
+            match self.closing {
+                Some(closing) => {
+                    quoted.push(c);
+                    if c == closing {
+                        self.closing = None;
+                        quoted.push(' ');
+                        body.push(' ');
+                    }
+                }
+                None => match closing_mark(c) {
+                    Some(closing) => {
+                        self.closing = Some(closing);
+                        body.push(' ');
+                        quoted.push(c);
+                    }
+                    None => body.push(c),
+                },
+            }
+        }
+        if self.closing.is_some() {
+            quoted.push(' ');
+        }
+        (body, quoted)
+    }
+}
+
+// Closing quotation mark matching an opening one
+fn closing_mark(c: char) -> Option<char> {
+    match c {
+        '"' => Some('"'),
+        '“' => Some('”'),
+        '„' => Some('“'),
+        '«' => Some('»'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(quotes: &mut QuotedSpans, text: &str) -> (Vec<String>, Vec<String>) {
+        let (body, quoted) = quotes.split(text);
+        let words = |text: &str| text.split_whitespace().map(str::to_string).collect();
+        (words(&body), words(&quoted))
+    }
+
+    #[test]
+    fn test_quoted_spans() {
+        let mut quotes = QuotedSpans::default();
+        let (body, quoted) = split(&mut quotes, "He said “stay here” and \"go\" twice.");
+        assert_eq!(body, ["He", "said", "and", "twice."]);
+        assert_eq!(quoted, ["“stay", "here”", "\"go\""]);
+    }
+
+    #[test]
+    fn test_quotations_span_lines_until_paragraph_end() {
+        let mut quotes = QuotedSpans::default();
+        assert_eq!(
+            split(&mut quotes, "She wrote « a long").1,
+            ["«", "a", "long"]
+        );
+        assert_eq!(split(&mut quotes, "quotation » here").0, ["here"]);
+
+        // An unbalanced mark does not swallow the next paragraph
+        split(&mut quotes, "An \"unclosed quote");
+        split(&mut quotes, "");
+        assert_eq!(split(&mut quotes, "Body again").0, ["Body", "again"]);
+    }
+}
@@ -0,0 +1,142 @@
+/// Vowels of English and of the common Western European languages, in lowercase
+const VOWELS: &str = "aeiouyàáâäæèéêëìíîïòóôöœùúûü";
+
+/// Sentences, words and syllables of a text, from which the Flesch readability
+/// scores follow. Sentences end at '.', '!' or '?' closing a word, or at a blank
+/// line, so that headings and list items count as sentences of their own.
+/// Syllables are estimated from the groups of vowels of each word.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Readability {
+    pub sentences: usize,
+    pub words: usize,
+    pub syllables: usize,
+    // Whether words were read since the last sentence ended
+    #[serde(skip)]
+    open: bool,
+}
+
+impl Readability {
+    /// Adds the words of a piece of text, the sentence it ends in being left open
+    pub(crate) fn add_text(&mut self, text: &str) {
+        for token in text.split_whitespace() {
+            if !token.chars().any(char::is_alphabetic) {
+                continue;
+            }
+            self.words += 1;
+            self.syllables += syllables(token);
+            self.open = true;
+            if ends_sentence(token) {
+                self.end_sentence();
+            }
+        }
+    }
+
+    /// Ends the open sentence, if any, e.g. at a blank line
+    pub(crate) fn end_sentence(&mut self) {
+        if self.open {
+            self.sentences += 1;
+            self.open = false;
+        }
+    }
+
+    /// Ends the text, its last sentence included
+    pub(crate) fn finished(mut self) -> Self {
+        self.end_sentence();
+        self
+    }
+
+    /// Flesch-Kincaid grade level: the years of US schooling needed to understand
+    /// the text, if it has words
+    pub fn grade_level(&self) -> Option<f64> {
+        let (words_per_sentence, syllables_per_word) = self.ratios()?;
+        Some(0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59)
+    }
+
+    /// Flesch reading ease, from about 0 (very hard) to 100 (very easy) and beyond,
+    /// if the text has words
+    pub fn reading_ease(&self) -> Option<f64> {
+        let (words_per_sentence, syllables_per_word) = self.ratios()?;
+        Some(206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word)
+    }
+
+    // Words per sentence and syllables per word, the open sentence included
+    fn ratios(&self) -> Option<(f64, f64)> {
+        let sentences = self.sentences + usize::from(self.open);
+        (self.words > 0).then(|| {
+            (
+                self.words as f64 / sentences as f64,
+                self.syllables as f64 / self.words as f64,
+            )
+        })
+    }
+}
+
+// Whether a word ends a sentence, ignoring the quotes and brackets closing it,
+// abbreviations such as 'e.g.' having a dot inside
+fn ends_sentence(token: &str) -> bool {
+    let token = token.trim_end_matches(['"', '\'', ')', ']', '»', '”', '’']);
+    let Some(body) = token.strip_suffix(['.', '!', '?', '…']) else {
+        return false;
+    };
+    let body = body.trim_end_matches(['.', '!', '?']);
+    !body.contains('.')
+}
+
+// Estimated syllables of a word: its groups of vowels, less a silent final 'e',
+// at least one
+fn syllables(word: &str) -> usize {
+    let letters: Vec<char> = word
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let mut groups = 0;
+    let mut in_vowels = false;
+    for &c in &letters {
+        let vowel = VOWELS.contains(c);
+        if vowel && !in_vowels {
+            groups += 1;
+        }
+        in_vowels = vowel;
+    }
+    let silent_e = matches!(letters.as_slice(), [.., c, 'e'] if *c != 'l' && !VOWELS.contains(*c));
+    if silent_e && groups > 1 {
+        groups -= 1;
+    }
+    groups.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syllables() {
+        assert_eq!(syllables("cat"), 1);
+        assert_eq!(syllables("Readability,"), 5);
+        assert_eq!(syllables("make"), 1);
+        assert_eq!(syllables("table"), 2);
+        assert_eq!(syllables("rhythm"), 1);
+        assert_eq!(syllables("café"), 2);
+    }
+
+    #[test]
+    fn test_readability() {
+        let mut readability = Readability::default();
+        readability.add_text("The cat sat on the mat. The dog ran,");
+        readability.add_text("e.g. to the park!");
+        readability.add_text("# A heading");
+        readability.end_sentence();
+        readability.add_text("\"Is it?\" she asked");
+        assert_eq!(readability.words, 19);
+        assert_eq!(readability.sentences, 4);
+
+        // One sentence of short words reads very easily
+        let mut easy = Readability::default();
+        easy.add_text("The cat sat on the mat.");
+        assert_eq!((easy.sentences, easy.words, easy.syllables), (1, 6, 6));
+        assert!((easy.reading_ease().unwrap() - 116.145).abs() < 1e-9);
+        assert!((easy.grade_level().unwrap() - -1.45).abs() < 1e-9);
+        assert_eq!(Readability::default().grade_level(), None);
+    }
+}
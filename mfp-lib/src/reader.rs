@@ -0,0 +1,378 @@
+use crate::encoding::Encoding;
+use std::io::{Cursor, Error, SeekFrom};
+use std::path::Path;
+use std::str::FromStr;
+use tokio::fs::File;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncSeekExt, BufReader};
+
+/// Size of the chunks streams are read in when they can't be seeked
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Restricts processing to the beginning or the end of each file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartialRead {
+    /// Only the first N lines
+    HeadLines(usize),
+    /// Only the first N bytes
+    HeadBytes(u64),
+    /// Only the last N lines
+    TailLines(usize),
+    /// Only the last N bytes, starting at the first full line within them
+    TailBytes(u64),
+    /// Only the lines within a range
+    Lines(LineRange),
+}
+
+/// Inclusive range of 1-based line numbers, open-ended when `end` is `None`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+impl LineRange {
+    /// Whether the 1-based line number falls within the range
+    pub fn contains(&self, line_number: usize) -> bool {
+        line_number >= self.start && self.end.is_none_or(|end| line_number <= end)
+    }
+
+    /// Whether no line past the 1-based line number can fall within the range
+    pub fn ends_before(&self, line_number: usize) -> bool {
+        self.end.is_some_and(|end| line_number > end)
+    }
+}
+
+impl FromStr for LineRange {
+    type Err = String;
+
+    /// Parses '100-200', '100..200' or open-ended '100-' / '100..'
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid line range '{s}', expected e.g. '100-200' or '100..'");
+        let (start, end) = s
+            .split_once("..")
+            .or_else(|| s.split_once('-'))
+            .ok_or_else(invalid)?;
+
+        let start: usize = start.trim().parse().map_err(|_| invalid())?;
+        let end: Option<usize> = match end.trim() {
+            "" => None,
+            end => Some(end.parse().map_err(|_| invalid())?),
+        };
+
+        if start == 0 || end.is_some_and(|end| end < start) {
+            return Err(format!(
+                "Invalid line range '{s}', lines are numbered from 1 and the end must not precede the start"
+            ));
+        }
+        Ok(LineRange { start, end })
+    }
+}
+
+/// Buffered line source files are read from
+pub(crate) type LineSource = Box<dyn AsyncBufRead + Unpin + Send>;
+
+/// Reads lines from a source while tracking their byte offsets
+pub(crate) struct LineReader {
+    source: LineSource,
+    buf: Vec<u8>,
+    offset: u64,
+    /// Encoding of the source, detected on the first read when not given
+    encoding: Option<Encoding>,
+    started: bool,
+    had_bom: bool,
+}
+
+impl LineReader {
+    /// Wraps a source whose first byte sits at `offset` in the underlying file
+    pub(crate) fn new(source: LineSource, offset: u64, encoding: Option<Encoding>) -> Self {
+        Self {
+            source,
+            buf: Vec::new(),
+            offset,
+            encoding,
+            started: false,
+            had_bom: false,
+        }
+    }
+
+    /// Encoding of the source, once known
+    pub(crate) fn encoding(&self) -> Option<Encoding> {
+        self.encoding
+    }
+
+    /// First bytes still to be read, without consuming them
+    pub(crate) async fn head(&mut self) -> Result<&[u8], Error> {
+        self.source.fill_buf().await
+    }
+
+    /// Whether the source started with a byte order mark, skipped when read
+    pub(crate) fn had_bom(&self) -> bool {
+        self.had_bom
+    }
+
+    /// Byte offset of the next line to be read
+    pub(crate) fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Reads the next line without its line terminator, like `AsyncBufReadExt::lines`,
+    /// along with the number of characters of the terminator.
+    /// An incomplete character at the very end, as left by a byte limit, is dropped.
+    pub(crate) async fn next_line(&mut self) -> Result<Option<(String, usize)>, Error> {
+        if !self.started {
+            self.start().await?;
+        }
+        let encoding = self.encoding.unwrap_or_default();
+
+        let buf = &mut self.buf;
+        buf.clear();
+        // In UTF-16, a line feed byte only ends the line as a whole code unit
+        loop {
+            if self.source.read_until(b'\n', buf).await? == 0 || !buf.ends_with(b"\n") {
+                break;
+            }
+            match encoding {
+                Encoding::Utf16Le if buf.len() % 2 == 1 => {
+                    let mut high = [0u8; 1];
+                    if self.source.read(&mut high).await? == 0 {
+                        break;
+                    }
+                    buf.push(high[0]);
+                    if high[0] == 0 {
+                        break;
+                    }
+                }
+                Encoding::Utf16Be if buf.len().is_multiple_of(2) && buf[buf.len() - 2] == 0 => {
+                    break
+                }
+                Encoding::Utf16Le | Encoding::Utf16Be => {}
+                _ => break,
+            }
+        }
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        self.offset += buf.len() as u64;
+
+        let mut line: &[u8] = buf;
+        let mut terminator = 0;
+        if let Some(rest) = line.strip_suffix(encoding.line_feed()) {
+            line = rest;
+            terminator += 1;
+            if let Some(rest) = line.strip_suffix(encoding.carriage_return()) {
+                line = rest;
+                terminator += 1;
+            }
+        }
+        Ok(Some((encoding.decode(line)?, terminator)))
+    }
+
+    // Detects the encoding unless given, then skips the byte order mark if the
+    // source starts the file with one. Its bytes still count towards the first line.
+    async fn start(&mut self) -> Result<(), Error> {
+        self.started = true;
+        let head = self.source.fill_buf().await?;
+        let encoding = *self.encoding.get_or_insert_with(|| Encoding::detect(head));
+        let bom = encoding.bom();
+        self.had_bom = self.offset == 0 && !bom.is_empty() && head.starts_with(bom);
+        if self.had_bom {
+            self.source.consume(bom.len());
+            self.offset += bom.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+/// Opens a file, applying byte-based partial reads.
+/// Also returns whether bytes were cut off, making the result partial.
+pub(crate) async fn open_file(
+    path: &Path,
+    partial: Option<PartialRead>,
+    encoding: Option<Encoding>,
+) -> Result<(LineReader, bool), Error> {
+    let mut file = File::open(path).await?;
+    let size = file.metadata().await?.len();
+
+    match partial {
+        Some(PartialRead::HeadBytes(limit)) => {
+            let source = Box::new(BufReader::new(file.take(limit)));
+            Ok((LineReader::new(source, 0, encoding), size > limit))
+        }
+        Some(PartialRead::TailBytes(limit)) if size > limit => {
+            file.seek(SeekFrom::Start(size - limit)).await?;
+            let mut reader =
+                LineReader::new(Box::new(BufReader::new(file)), size - limit, encoding);
+            // Skip the partial line the offset landed in
+            reader.next_line().await?;
+            Ok((reader, true))
+        }
+        _ => Ok((
+            LineReader::new(Box::new(BufReader::new(file)), 0, encoding),
+            false,
+        )),
+    }
+}
+
+/// Wraps any async reader, applying byte-based partial reads.
+/// Also returns whether bytes were cut off, making the result partial.
+pub(crate) async fn open_reader<R>(
+    mut reader: R,
+    partial: Option<PartialRead>,
+    encoding: Option<Encoding>,
+) -> Result<(LineReader, bool), Error>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    match partial {
+        Some(PartialRead::HeadBytes(limit)) => {
+            // One extra byte tells whether the stream went past the limit
+            let mut head = Vec::new();
+            (&mut reader).take(limit + 1).read_to_end(&mut head).await?;
+            let cut = head.len() as u64 > limit;
+            head.truncate(limit as usize);
+            Ok((
+                LineReader::new(Box::new(Cursor::new(head)), 0, encoding),
+                cut,
+            ))
+        }
+        Some(PartialRead::TailBytes(limit)) => {
+            // Streams can't seek: only keep a window of at most twice the limit
+            let mut window = Vec::new();
+            let mut chunk = vec![0; READ_CHUNK_SIZE];
+            let mut total = 0u64;
+            loop {
+                let read = reader.read(&mut chunk).await?;
+                if read == 0 {
+                    break;
+                }
+                total += read as u64;
+                window.extend_from_slice(&chunk[..read]);
+                if window.len() as u64 > limit.saturating_mul(2) {
+                    window.drain(..window.len() - limit as usize);
+                }
+            }
+
+            let cut = total > limit;
+            let start = window.len().saturating_sub(limit as usize);
+            let mut reader = LineReader::new(
+                Box::new(Cursor::new(window.split_off(start))),
+                total - (total.min(limit)),
+                encoding,
+            );
+            if cut {
+                // Skip the partial line the window starts in
+                reader.next_line().await?;
+            }
+            Ok((reader, cut))
+        }
+        _ => Ok((
+            LineReader::new(Box::new(BufReader::new(reader)), 0, encoding),
+            false,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    async fn read_all(path: &Path, partial: Option<PartialRead>) -> (Vec<String>, bool) {
+        let (mut reader, cut) = open_file(path, partial, None).await.unwrap();
+        let mut lines = Vec::new();
+        while let Some((line, _)) = reader.next_line().await.unwrap() {
+            lines.push(line);
+        }
+        (lines, cut)
+    }
+
+    #[test]
+    fn test_line_range_parsing() {
+        let range: LineRange = "100-200".parse().unwrap();
+        assert_eq!(range, "100..200".parse().unwrap());
+        assert!(range.contains(100) && range.contains(200));
+        assert!(!range.contains(99) && !range.contains(201));
+        assert!(range.ends_before(201));
+
+        let open: LineRange = "5..".parse().unwrap();
+        assert_eq!(open.end, None);
+        assert!(open.contains(1_000_000));
+
+        assert!("0-3".parse::<LineRange>().is_err());
+        assert!("9-3".parse::<LineRange>().is_err());
+        assert!("abc".parse::<LineRange>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_byte_limits() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "first line\r\nsecond line\nthird é").unwrap();
+
+        let (lines, cut) = read_all(file.path(), None).await;
+        assert_eq!(lines, vec!["first line", "second line", "third é"]);
+        assert!(!cut);
+
+        let (lines, cut) = read_all(file.path(), Some(PartialRead::HeadBytes(15))).await;
+        assert_eq!(lines, vec!["first line", "sec"]);
+        assert!(cut);
+
+        // Cutting inside the two-byte 'é' drops the incomplete character
+        let (lines, _) = read_all(file.path(), Some(PartialRead::HeadBytes(31))).await;
+        assert_eq!(lines.last().unwrap(), "third ");
+
+        let (lines, cut) = read_all(file.path(), Some(PartialRead::TailBytes(12))).await;
+        assert_eq!(lines, vec!["third é"]);
+        assert!(cut);
+    }
+
+    #[tokio::test]
+    async fn test_reader_byte_limits() {
+        let content = b"first line\r\nsecond line\nthird".to_vec();
+
+        let (mut reader, cut) = open_reader(
+            Cursor::new(content.clone()),
+            Some(PartialRead::HeadBytes(15)),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(cut);
+        assert_eq!(reader.next_line().await.unwrap().unwrap().0, "first line");
+        assert_eq!(reader.next_line().await.unwrap().unwrap().0, "sec");
+
+        let (mut reader, cut) = open_reader(
+            Cursor::new(content.clone()),
+            Some(PartialRead::TailBytes(8)),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(cut);
+        assert_eq!(reader.offset(), 24);
+        assert_eq!(reader.next_line().await.unwrap().unwrap().0, "third");
+
+        let (_, cut) = open_reader(Cursor::new(content), Some(PartialRead::HeadBytes(29)), None)
+            .await
+            .unwrap();
+        assert!(!cut);
+    }
+
+    #[tokio::test]
+    async fn test_line_offsets() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "ab\r\n\ncdé\nlast").unwrap();
+
+        let (mut reader, _) = open_file(file.path(), None, None).await.unwrap();
+        let mut offsets = vec![reader.offset()];
+        while reader.next_line().await.unwrap().is_some() {
+            offsets.push(reader.offset());
+        }
+        assert_eq!(offsets, vec![0, 4, 5, 10, 14]);
+
+        let (reader, _) = open_file(file.path(), Some(PartialRead::TailBytes(8)), None)
+            .await
+            .unwrap();
+        assert_eq!(reader.offset(), 10);
+    }
+}
@@ -0,0 +1,76 @@
+use std::str::FromStr;
+
+/// Part of a structured document that journals often count apart from the body text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Region {
+    /// Footnotes, e.g. '\footnote{..}' or '[^1]: ..'
+    Footnote,
+    /// Figure and table captions, e.g. '\caption{..}' or the alt text of '![..](..)'
+    Caption,
+    /// Table content, e.g. 'tabular' environments or pipe tables
+    Table,
+    /// Block quotes and quoted spans, e.g. '> ..', 'quote' environments or “..”
+    Quote,
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "footnotes" => Ok(Region::Footnote),
+            "captions" => Ok(Region::Caption),
+            "tables" => Ok(Region::Table),
+            "quotes" => Ok(Region::Quote),
+            _ => Err(format!(
+                "Invalid region '{s}', expected 'footnotes', 'captions', 'tables' or 'quotes'"
+            )),
+        }
+    }
+}
+
+/// Words of each region of a file, whether or not they count towards its totals
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RegionCounts {
+    pub footnotes: usize,
+    pub captions: usize,
+    pub tables: usize,
+    pub quotes: usize,
+}
+
+impl RegionCounts {
+    pub(crate) fn add(&mut self, region: Region, words: usize) {
+        match region {
+            Region::Footnote => self.footnotes += words,
+            Region::Caption => self.captions += words,
+            Region::Table => self.tables += words,
+            Region::Quote => self.quotes += words,
+        }
+    }
+}
+
+/// Text of a line split between the body and the regions it belongs to
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct LineParts {
+    pub(crate) text: String,
+    pub(crate) regions: Vec<(Region, String)>,
+}
+
+impl LineParts {
+    /// Appends a character to the body, or to a region
+    pub(crate) fn push(&mut self, region: Option<Region>, c: char) {
+        self.push_str(region, c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Appends text to the body, or to a region
+    pub(crate) fn push_str(&mut self, region: Option<Region>, s: &str) {
+        let Some(region) = region else {
+            self.text.push_str(s);
+            return;
+        };
+        match self.regions.iter_mut().find(|(r, _)| *r == region) {
+            Some((_, text)) => text.push_str(s),
+            None => self.regions.push((region, s.to_string())),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use crate::types::FileProcessingResult;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// z-score for a two-sided 95% confidence interval
+const Z_95: f64 = 1.96;
+
+/// How many of the discovered files to process
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleSize {
+    /// Fraction of the files in `(0, 1]`, e.g. `5%` is `0.05`
+    Fraction(f64),
+    /// Fixed number of files
+    Count(usize),
+}
+
+impl FromStr for SampleSize {
+    type Err = String;
+
+    /// Parses either a percentage ('5%') or a fraction ('0.05')
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let fraction = match s.strip_suffix('%') {
+            Some(percent) => percent.trim().parse::<f64>().map(|p| p / 100.0),
+            None => s.parse::<f64>(),
+        }
+        .map_err(|_| format!("Invalid sample size '{s}', expected e.g. '5%' or '0.05'"))?;
+
+        if !(fraction > 0.0 && fraction <= 1.0) {
+            return Err(format!("Sample size '{s}' must be within (0%, 100%]"));
+        }
+        Ok(SampleSize::Fraction(fraction))
+    }
+}
+
+impl SampleSize {
+    /// Number of files to draw out of `population` - at least one for a non-empty population
+    pub fn files_out_of(&self, population: usize) -> usize {
+        let wanted = match *self {
+            SampleSize::Fraction(fraction) => (population as f64 * fraction).ceil() as usize,
+            SampleSize::Count(count) => count,
+        };
+        wanted.clamp(population.min(1), population)
+    }
+}
+
+/// Draws a deterministic random sample of the given paths.
+/// The same paths, size and seed always yield the same sample, whatever the input order.
+pub fn sample_paths(mut paths: Vec<PathBuf>, size: SampleSize, seed: u64) -> Vec<PathBuf> {
+    let wanted = size.files_out_of(paths.len());
+    paths.sort();
+
+    // Partial Fisher-Yates shuffle - only the first `wanted` slots are drawn
+    let mut rng = fastrand::Rng::with_seed(seed);
+    for i in 0..wanted {
+        let j = rng.usize(i..paths.len());
+        paths.swap(i, j);
+    }
+
+    paths.truncate(wanted);
+    paths
+}
+
+/// Extrapolated corpus total with its 95% confidence margin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    /// Point estimate of the corpus total
+    pub value: f64,
+    /// Half width of the 95% confidence interval, infinite when a single file out of
+    /// several was sampled: the spread of the corpus cannot be told from one file
+    pub margin: f64,
+}
+
+impl Estimate {
+    /// Lower bound of the confidence interval, never negative
+    pub fn lower(&self) -> f64 {
+        (self.value - self.margin).max(0.0)
+    }
+
+    /// Upper bound of the confidence interval
+    pub fn upper(&self) -> f64 {
+        self.value + self.margin
+    }
+}
+
+/// Corpus totals extrapolated from a processed sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CorpusEstimate {
+    /// Number of files the sample was drawn from
+    pub population: usize,
+    /// Number of files actually processed
+    pub sampled: usize,
+    /// Estimated total words across the whole corpus
+    pub total_words: Estimate,
+    /// Estimated total lines across the whole corpus
+    pub total_lines: Estimate,
+}
+
+impl CorpusEstimate {
+    /// Extrapolates corpus totals from the results of a simple random sample
+    pub fn from_sample<'a>(
+        population: usize,
+        sample: impl IntoIterator<Item = &'a FileProcessingResult>,
+    ) -> Self {
+        let (words, lines): (Vec<f64>, Vec<f64>) = sample
+            .into_iter()
+            .map(|r| (r.total_words as f64, r.line_counts.len() as f64))
+            .unzip();
+
+        Self {
+            population,
+            sampled: words.len(),
+            total_words: extrapolate(population, &words),
+            total_lines: extrapolate(population, &lines),
+        }
+    }
+}
+
+// Scales the sample mean to the population, with finite population correction
+fn extrapolate(population: usize, values: &[f64]) -> Estimate {
+    let n = values.len() as f64;
+    let big_n = population as f64;
+    if values.is_empty() {
+        return Estimate {
+            value: 0.0,
+            margin: 0.0,
+        };
+    }
+
+    let mean = values.iter().sum::<f64>() / n;
+    let fpc = if big_n > 1.0 {
+        ((big_n - n) / (big_n - 1.0)).max(0.0)
+    } else {
+        0.0
+    };
+    // A single file has no variance to go by, unless it is the whole corpus
+    if values.len() < 2 && fpc > 0.0 {
+        return Estimate {
+            value: big_n * mean,
+            margin: f64::INFINITY,
+        };
+    }
+    let variance = if values.len() > 1 {
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)
+    } else {
+        0.0
+    };
+
+    Estimate {
+        value: big_n * mean,
+        margin: Z_95 * big_n * (variance / n * fpc).sqrt(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(total_words: usize, lines: usize) -> FileProcessingResult {
+        FileProcessingResult {
+            line_counts: vec![0; lines],
+            total_words,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sample_size_parsing() {
+        assert_eq!("5%".parse(), Ok(SampleSize::Fraction(0.05)));
+        assert_eq!("0.5".parse(), Ok(SampleSize::Fraction(0.5)));
+        assert!("0%".parse::<SampleSize>().is_err());
+        assert!("150%".parse::<SampleSize>().is_err());
+        assert!("many".parse::<SampleSize>().is_err());
+    }
+
+    #[test]
+    fn test_sample_is_deterministic_and_bounded() {
+        let paths: Vec<PathBuf> = (0..100)
+            .map(|i| PathBuf::from(format!("{i}.txt")))
+            .collect();
+        let mut reversed = paths.clone();
+        reversed.reverse();
+
+        let first = sample_paths(paths.clone(), SampleSize::Fraction(0.1), 42);
+        let second = sample_paths(reversed, SampleSize::Fraction(0.1), 42);
+        assert_eq!(first.len(), 10);
+        assert_eq!(first, second);
+
+        assert_eq!(
+            sample_paths(paths.clone(), SampleSize::Count(500), 1).len(),
+            100
+        );
+        assert_eq!(sample_paths(paths, SampleSize::Fraction(0.001), 1).len(), 1);
+    }
+
+    #[test]
+    fn test_estimate_extrapolates_mean() {
+        let sample = [result(10, 2), result(20, 4), result(30, 6)];
+        let estimate = CorpusEstimate::from_sample(30, &sample);
+
+        assert_eq!(estimate.sampled, 3);
+        assert_eq!(estimate.total_words.value, 600.0);
+        assert_eq!(estimate.total_lines.value, 120.0);
+        assert!(estimate.total_words.lower() < 600.0 && estimate.total_words.upper() > 600.0);
+    }
+
+    #[test]
+    fn test_full_sample_has_no_margin() {
+        let sample = [result(10, 1), result(20, 1)];
+        let estimate = CorpusEstimate::from_sample(2, &sample);
+        assert_eq!(estimate.total_words.value, 30.0);
+        assert_eq!(estimate.total_words.margin, 0.0);
+
+        let estimate = CorpusEstimate::from_sample(1, &[result(10, 1)]);
+        assert_eq!(estimate.total_words.margin, 0.0);
+    }
+
+    #[test]
+    fn test_single_file_sample_is_unbounded() {
+        let estimate = CorpusEstimate::from_sample(50, &[result(10, 2)]);
+        assert_eq!(estimate.total_words.value, 500.0);
+        assert_eq!(estimate.total_words.lower(), 0.0);
+        assert_eq!(estimate.total_words.upper(), f64::INFINITY);
+        assert_eq!(estimate.total_lines.upper(), f64::INFINITY);
+    }
+}
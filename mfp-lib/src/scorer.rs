@@ -0,0 +1,153 @@
+use crate::error::TextProcessorError;
+use futures::future::BoxFuture;
+use std::fmt::Debug;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::debug;
+
+/// External quality/perplexity scorer invoked once per processed file
+pub trait Scorer: Debug + Send + Sync {
+    /// Scores the given file content, returning a single numeric value
+    fn score<'a>(
+        &'a self,
+        path: &'a Path,
+        text: &'a str,
+    ) -> BoxFuture<'a, Result<f64, TextProcessorError>>;
+}
+
+/// Scorer running an external command, feeding the file content on stdin
+/// and parsing a number from its stdout
+#[derive(Debug, Clone)]
+pub struct CommandScorer {
+    program: String,
+    args: Vec<String>,
+}
+
+impl CommandScorer {
+    /// Creates a scorer from a program and its arguments
+    pub fn new(program: impl Into<String>, args: Vec<String>) -> Self {
+        Self {
+            program: program.into(),
+            args,
+        }
+    }
+
+    /// Parses a whitespace separated command line, e.g. 'python score.py --model small'
+    pub fn from_command_line(command: &str) -> Option<Self> {
+        let mut parts = command.split_whitespace().map(String::from);
+        let program = parts.next()?;
+        Some(Self::new(program, parts.collect()))
+    }
+}
+
+impl Scorer for CommandScorer {
+    fn score<'a>(
+        &'a self,
+        path: &'a Path,
+        text: &'a str,
+    ) -> BoxFuture<'a, Result<f64, TextProcessorError>> {
+        Box::pin(async move {
+            debug!("Scoring {:?} with command {}", path, self.program);
+            let mut child = Command::new(&self.program)
+                .args(&self.args)
+                .env("MFP_FILE", path)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .map_err(|e| scorer_error(path, format!("failed to spawn: {e}")))?;
+
+            // Fed from its own task while the output is read, so that a command
+            // answering before it read everything does not block on a full pipe
+            let writer = child.stdin.take().map(|mut stdin| {
+                let input = text.to_owned();
+                tokio::spawn(async move { stdin.write_all(input.as_bytes()).await })
+            });
+
+            let output = child.wait_with_output().await?;
+            if let Some(writer) = writer {
+                match writer.await {
+                    Ok(Err(e)) if e.kind() != ErrorKind::BrokenPipe => return Err(e.into()),
+                    // The command may stop reading once it has its answer
+                    _ => {}
+                }
+            }
+            if !output.status.success() {
+                return Err(scorer_error(
+                    path,
+                    format!("command exited with {}", output.status),
+                ));
+            }
+
+            parse_score(&String::from_utf8_lossy(&output.stdout))
+                .ok_or_else(|| scorer_error(path, "command did not print a number".into()))
+        })
+    }
+}
+
+/// Extracts a score from a bare number or a `{"score": <number>}` output
+fn parse_score(output: &str) -> Option<f64> {
+    let trimmed = output.trim();
+    if let Ok(score) = trimmed.parse() {
+        return Some(score);
+    }
+
+    let after_key = &trimmed[trimmed.find("\"score\"")? + "\"score\"".len()..];
+    let value = after_key.trim_start().strip_prefix(':')?.trim_start();
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')))
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+fn scorer_error(path: &Path, reason: String) -> TextProcessorError {
+    TextProcessorError::ScorerFailed {
+        path: path.to_path_buf(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time;
+
+    #[test]
+    fn test_parse_score() {
+        assert_eq!(parse_score("12.5\n"), Some(12.5));
+        assert_eq!(parse_score("{\"score\": -3e2}"), Some(-300.0));
+        assert_eq!(parse_score("{\"model\": \"x\", \"score\":7}"), Some(7.0));
+        assert_eq!(parse_score("not a number"), None);
+    }
+
+    #[tokio::test]
+    async fn test_command_scorer_reads_stdout() {
+        let scorer = CommandScorer::from_command_line("wc -w").unwrap();
+        let score = scorer
+            .score(Path::new("inline.txt"), "one two three\nfour")
+            .await
+            .unwrap();
+        assert_eq!(score, 4.0);
+    }
+
+    // Test a command writing its output before reading all its input completes
+    #[tokio::test]
+    async fn test_command_scorer_large_input() {
+        let scorer = CommandScorer::from_command_line("cat").unwrap();
+        let text = "word ".repeat(200_000);
+        let scored = time::timeout(
+            Duration::from_secs(30),
+            scorer.score(Path::new("large.txt"), &text),
+        )
+        .await
+        .expect("the scorer must not deadlock");
+        assert!(matches!(
+            scored,
+            Err(TextProcessorError::ScorerFailed { .. })
+        ));
+    }
+}
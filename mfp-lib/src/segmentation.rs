@@ -0,0 +1,667 @@
+use crate::dictionary::{Dictionary, Script};
+use crate::stopwords::StopWordFilter;
+use crate::utils::count_words;
+use crate::word_regex::WordRegex;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// How lines are split into words
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Segmentation {
+    /// Any run of non-whitespace characters is a word
+    #[default]
+    Whitespace,
+    /// Unicode word boundaries (UAX #29): punctuation separates words,
+    /// each CJK ideograph or kana counts as a word, symbols don't count
+    Unicode,
+}
+
+impl FromStr for Segmentation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "whitespace" => Ok(Segmentation::Whitespace),
+            "unicode" => Ok(Segmentation::Unicode),
+            _ => Err(format!(
+                "Invalid segmentation '{s}', expected 'whitespace' or 'unicode'"
+            )),
+        }
+    }
+}
+
+impl Segmentation {
+    /// Counts the number of words in a line
+    pub fn count_words(&self, line: &str) -> usize {
+        match self {
+            Segmentation::Whitespace => count_words(line),
+            Segmentation::Unicode => unicode_words(line).count(),
+        }
+    }
+
+    /// Iterates over the words of a line, as counted by `count_words`
+    pub fn words<'a>(&self, line: &'a str) -> Box<dyn Iterator<Item = &'a str> + 'a> {
+        match self {
+            Segmentation::Whitespace => Box::new(line.split_whitespace()),
+            Segmentation::Unicode => Box::new(unicode_words(line)),
+        }
+    }
+}
+
+/// Whether words joined by a given character count as one word or several
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JoinPolicy {
+    /// As the segmentation mode splits them: joined on whitespace, joined for
+    /// apostrophes but split for hyphens and slashes on Unicode word boundaries
+    #[default]
+    Segmentation,
+    /// One word, e.g. "don't" or "well-known"
+    Join,
+    /// One word per part, e.g. "don" and "t"
+    Split,
+}
+
+impl FromStr for JoinPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "segmentation" => Ok(JoinPolicy::Segmentation),
+            "join" => Ok(JoinPolicy::Join),
+            "split" => Ok(JoinPolicy::Split),
+            _ => Err(format!(
+                "Invalid policy '{s}', expected 'join', 'split' or 'segmentation'"
+            )),
+        }
+    }
+}
+
+/// How contractions, hyphenated compounds and slash-joined terms are counted,
+/// e.g. to match a publisher's billing rules
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CountingPolicy {
+    /// Apostrophes within words, e.g. "don't"
+    pub apostrophes: JoinPolicy,
+    /// Hyphens within words, e.g. "well-known"
+    pub hyphens: JoinPolicy,
+    /// Slashes within words, e.g. "and/or"
+    pub slashes: JoinPolicy,
+}
+
+impl CountingPolicy {
+    // Policy applying to a joining character, if it is one
+    fn for_joiner(&self, c: char) -> Option<JoinPolicy> {
+        match c {
+            '\'' | '\u{2019}' | '\u{02BC}' => Some(self.apostrophes),
+            '-' | '\u{2010}' | '\u{2011}' => Some(self.hyphens),
+            '/' => Some(self.slashes),
+            _ => None,
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        *self == CountingPolicy::default()
+    }
+}
+
+/// Characters splitting words on top of those of the segmentation mode, e.g.
+/// the separators of CSV-ish and log lines, and the regex defining words in
+/// place of the segmentation mode, if any
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenizerConfig {
+    delimiters: Vec<char>,
+    word_regex: Option<WordRegex>,
+}
+
+impl FromStr for TokenizerConfig {
+    type Err = String;
+
+    /// Parses the delimiters as a string of characters, e.g. ',;|', `\t`
+    /// standing for a tab
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let delimiters = s.replace("\\t", "\t");
+        if delimiters.is_empty() {
+            return Err("Empty delimiters, expected characters such as ',;|'".to_string());
+        }
+        Ok(TokenizerConfig::new().with_delimiters(delimiters.chars()))
+    }
+}
+
+impl TokenizerConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds characters splitting words wherever they appear
+    pub fn with_delimiters(mut self, delimiters: impl IntoIterator<Item = char>) -> Self {
+        for c in delimiters {
+            if !self.delimiters.contains(&c) {
+                self.delimiters.push(c);
+            }
+        }
+        self
+    }
+
+    /// Counts the matches of a regex as the words, e.g. `\w+` for identifiers,
+    /// instead of the words of the segmentation mode
+    pub fn with_word_regex(mut self, regex: WordRegex) -> Self {
+        self.word_regex = Some(regex);
+        self
+    }
+
+    /// Characters splitting words on top of those of the segmentation mode
+    pub fn delimiters(&self) -> &[char] {
+        &self.delimiters
+    }
+
+    /// Regex defining words, if any
+    pub fn word_regex(&self) -> Option<&WordRegex> {
+        self.word_regex.as_ref()
+    }
+}
+
+/// Segmentation mode or word regex along with the counting policy, the custom
+/// delimiters, the dictionaries splitting spaceless scripts and the stop words
+/// left out of the word analyses
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Tokenizer {
+    segmentation: Segmentation,
+    policy: CountingPolicy,
+    delimiters: Vec<char>,
+    word_regex: Option<WordRegex>,
+    count_pattern: Option<WordRegex>,
+    dictionaries: Vec<(Script, Arc<Dictionary>)>,
+    stop_words: Option<Arc<StopWordFilter>>,
+}
+
+impl Tokenizer {
+    pub(crate) fn new(
+        segmentation: Segmentation,
+        policy: CountingPolicy,
+        dictionaries: &[(Script, Arc<Dictionary>)],
+    ) -> Self {
+        Self {
+            segmentation,
+            policy,
+            delimiters: Vec::new(),
+            word_regex: None,
+            count_pattern: None,
+            dictionaries: dictionaries.to_vec(),
+            stop_words: None,
+        }
+    }
+
+    /// Applies the custom delimiters and the word regex of the configuration
+    pub(crate) fn with_config(mut self, config: &TokenizerConfig) -> Self {
+        self.delimiters = config.delimiters.clone();
+        self.word_regex = config.word_regex.clone();
+        self
+    }
+
+    /// Counts the occurrences of a pattern instead of the words, if any
+    pub(crate) fn with_count_pattern(mut self, pattern: Option<WordRegex>) -> Self {
+        self.count_pattern = pattern;
+        self
+    }
+
+    /// Leaves the stop words out of the word analyses, if any
+    pub(crate) fn with_stop_words(mut self, stop_words: Option<Arc<StopWordFilter>>) -> Self {
+        self.stop_words = stop_words;
+        self
+    }
+
+    /// Words of a line the frequencies and unique words are drawn from: all of
+    /// them but the stop words
+    pub(crate) fn analysed_words<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        let mut words = self.words(line);
+        if let Some(stop_words) = &self.stop_words {
+            words.retain(|word| !stop_words.is_stop_word(word));
+        }
+        words
+    }
+
+    /// Counts the number of words in a line
+    pub(crate) fn count_words(&self, line: &str) -> usize {
+        if self.dictionaries.is_empty()
+            && self.policy.is_default()
+            && self.delimiters.is_empty()
+            && self.word_regex.is_none()
+            && self.count_pattern.is_none()
+        {
+            self.segmentation.count_words(line)
+        } else {
+            self.words(line).len()
+        }
+    }
+
+    /// Words of a line: the occurrences of the counted pattern if any, else the
+    /// matches of the word regex or the words of the segmentation mode, split on
+    /// the custom delimiters, joined or split according to the counting policy,
+    /// with runs of a dictionary script further split into dictionary words
+    pub(crate) fn words<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        if let Some(pattern) = &self.count_pattern {
+            return pattern.find_iter(line);
+        }
+        let mut tokens: Vec<&str> = match &self.word_regex {
+            Some(regex) => regex.find_iter(line),
+            None => self.segmentation.words(line).collect(),
+        };
+        if !self.delimiters.is_empty() {
+            tokens = tokens
+                .into_iter()
+                .flat_map(|token| token.split(self.delimiters.as_slice()))
+                .filter(|token| !token.is_empty())
+                .collect();
+        }
+        if !self.policy.is_default() {
+            tokens = self.join_tokens(line, tokens);
+        }
+
+        let mut words = Vec::new();
+        for token in tokens {
+            if self.policy.is_default() {
+                self.split_token(token, &mut words);
+            } else {
+                for part in self.split_joined(token) {
+                    self.split_token(part, &mut words);
+                }
+            }
+        }
+        words
+    }
+
+    // Merges tokens separated by a single joining character whose policy is to join
+    fn join_tokens<'a>(&self, line: &'a str, tokens: Vec<&'a str>) -> Vec<&'a str> {
+        // Tokens are slices of the line: their offsets locate the gaps between them
+        let offset = |token: &str| token.as_ptr() as usize - line.as_ptr() as usize;
+        let mut joined: Vec<&'a str> = Vec::with_capacity(tokens.len());
+
+        for token in tokens {
+            if let Some(last) = joined.last_mut() {
+                let (start, end) = (offset(last), offset(last) + last.len());
+                let gap = &line[end..offset(token)];
+                let mut gap_chars = gap.chars();
+                let joins = match (gap_chars.next(), gap_chars.next()) {
+                    (Some(c), None) => self.policy.for_joiner(c) == Some(JoinPolicy::Join),
+                    _ => false,
+                };
+                let flanked = last.chars().last().is_some_and(char::is_alphanumeric)
+                    && token.chars().next().is_some_and(char::is_alphanumeric);
+                if joins && flanked {
+                    *last = &line[start..offset(token) + token.len()];
+                    continue;
+                }
+            }
+            joined.push(token);
+        }
+        joined
+    }
+
+    // Splits a token at the joining characters whose policy is to split,
+    // when they sit between two letters or digits
+    fn split_joined<'a>(&self, token: &'a str) -> Vec<&'a str> {
+        let chars: Vec<(usize, char)> = token.char_indices().collect();
+        let mut parts = Vec::new();
+        let mut start = 0;
+        for window in chars.windows(3) {
+            let [(_, before), (i, c), (next, after)] = window else {
+                continue;
+            };
+            if self.policy.for_joiner(*c) == Some(JoinPolicy::Split)
+                && before.is_alphanumeric()
+                && after.is_alphanumeric()
+            {
+                parts.push(&token[start..*i]);
+                start = *next;
+            }
+        }
+        parts.push(&token[start..]);
+        parts
+    }
+
+    fn split_token<'a>(&self, token: &'a str, words: &mut Vec<&'a str>) {
+        let dictionary_of = |c: char| {
+            self.dictionaries
+                .iter()
+                .position(|(script, _)| script.contains(c))
+        };
+        if !token.chars().any(|c| dictionary_of(c).is_some()) {
+            words.push(token);
+            return;
+        }
+
+        // Runs of characters sharing the same dictionary, if any
+        let mut start = 0;
+        let mut current = None;
+        for (i, c) in token.char_indices().chain([(token.len(), ' ')]) {
+            let dictionary = (i < token.len()).then(|| dictionary_of(c)).flatten();
+            if i > start && (dictionary != current || i == token.len()) {
+                let run = &token[start..i];
+                match current {
+                    Some(d) => words.extend(self.dictionaries[d].1.segment(run)),
+                    // Punctuation around a run doesn't make a word of its own
+                    None if run.chars().any(char::is_alphanumeric) => words.push(run),
+                    None => {}
+                }
+                start = i;
+            }
+            current = dictionary;
+        }
+    }
+}
+
+/// Word boundary class of a character, a subset of the UAX #29 word break properties
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WordClass {
+    /// Letters joining into a single word
+    Letter,
+    /// Digits joining into a single number
+    Numeric,
+    /// Katakana, joining with each other only
+    Katakana,
+    /// Ideographs and hiragana, each one a word on its own
+    Ideographic,
+    /// Combining marks, extending the preceding character
+    Extend,
+    /// Joins letters, e.g. the apostrophe of "don't"
+    MidLetter,
+    /// Joins digits, e.g. the comma of "1,000"
+    MidNum,
+    /// Joins both letters and digits, e.g. the period of "3.14" or "e.g"
+    MidNumLet,
+    /// Joins any word characters, e.g. the underscore of "snake_case"
+    ExtendNumLet,
+    /// Anything else: whitespace, punctuation and symbols
+    Other,
+}
+
+fn classify(c: char) -> WordClass {
+    match c {
+        '\'' | '.' | '\u{2018}' | '\u{2019}' | '\u{2024}' | '\u{FE52}' | '\u{FF07}'
+        | '\u{FF0E}' => WordClass::MidNumLet,
+        ':' | '\u{00B7}' | '\u{0387}' | '\u{05F4}' | '\u{2027}' | '\u{FE13}' | '\u{FE55}'
+        | '\u{FF1A}' => WordClass::MidLetter,
+        ',' | ';' | '\u{037E}' | '\u{0589}' | '\u{060C}' | '\u{066C}' | '\u{FE50}' | '\u{FE54}'
+        | '\u{FF0C}' | '\u{FF1B}' => WordClass::MidNum,
+        '_' | '\u{203F}' | '\u{2040}' | '\u{FF3F}' => WordClass::ExtendNumLet,
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{200C}'..='\u{200D}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FE20}'..='\u{FE2F}' => WordClass::Extend,
+        '\u{30A0}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}' => {
+            WordClass::Katakana
+        }
+        '\u{3005}'
+        | '\u{3007}'
+        | '\u{3040}'..='\u{309F}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{3FFFF}' => WordClass::Ideographic,
+        c if c.is_numeric() => WordClass::Numeric,
+        c if c.is_alphabetic() => WordClass::Letter,
+        _ => WordClass::Other,
+    }
+}
+
+// Whether two word characters belong to the same word when adjacent
+fn joins(prev: WordClass, next: WordClass) -> bool {
+    use WordClass::*;
+    matches!(
+        (prev, next),
+        (Letter | Numeric, Letter | Numeric)
+            | (Katakana, Katakana)
+            | (ExtendNumLet, Letter | Numeric | Katakana | ExtendNumLet)
+            | (Letter | Numeric | Katakana, ExtendNumLet)
+    )
+}
+
+// Whether a middle character joins the word characters around it
+fn joins_across(prev: WordClass, mid: WordClass, next: WordClass) -> bool {
+    use WordClass::*;
+    matches!(
+        (prev, mid, next),
+        (Letter, MidLetter | MidNumLet, Letter) | (Numeric, MidNum | MidNumLet, Numeric)
+    )
+}
+
+/// Iterates over the words of a text along Unicode word boundaries,
+/// skipping the segments made of whitespace, punctuation or symbols only
+pub(crate) fn unicode_words(text: &str) -> impl Iterator<Item = &str> {
+    UnicodeWords {
+        text,
+        chars: text.char_indices().map(|(i, c)| (i, classify(c))).collect(),
+        next: 0,
+    }
+}
+
+struct UnicodeWords<'a> {
+    text: &'a str,
+    /// Byte offset and class of each character
+    chars: Vec<(usize, WordClass)>,
+    /// Index of the next character to look at
+    next: usize,
+}
+
+impl UnicodeWords<'_> {
+    fn class(&self, i: usize) -> Option<WordClass> {
+        self.chars.get(i).map(|&(_, class)| class)
+    }
+
+    fn byte_offset(&self, i: usize) -> usize {
+        self.chars
+            .get(i)
+            .map_or(self.text.len(), |&(offset, _)| offset)
+    }
+
+    // Index of the first character past `i` not extending the previous one
+    fn skip_extend(&self, mut i: usize) -> usize {
+        while self.class(i) == Some(WordClass::Extend) {
+            i += 1;
+        }
+        i
+    }
+}
+
+impl<'a> Iterator for UnicodeWords<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        use WordClass::*;
+        loop {
+            // Only letters, digits and connectors can start a word
+            let start = (self.next..self.chars.len()).find(|&i| {
+                matches!(
+                    self.class(i),
+                    Some(Letter | Numeric | Katakana | Ideographic | ExtendNumLet)
+                )
+            })?;
+
+            let mut last = self.class(start)?;
+            let mut i = self.skip_extend(start + 1);
+            if last != Ideographic {
+                while let Some(class) = self.class(i) {
+                    if joins(last, class) {
+                        last = class;
+                        i = self.skip_extend(i + 1);
+                        continue;
+                    }
+                    let after = self.skip_extend(i + 1);
+                    match self.class(after) {
+                        Some(next) if joins_across(last, class, next) => {
+                            last = next;
+                            i = self.skip_extend(after + 1);
+                        }
+                        _ => break,
+                    }
+                }
+            }
+
+            self.next = i;
+            let word = &self.text[self.byte_offset(start)..self.byte_offset(i)];
+            // Connector-only segments such as "__" aren't words
+            if word.chars().any(char::is_alphanumeric) {
+                return Some(word);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<&str> {
+        unicode_words(text).collect()
+    }
+
+    #[test]
+    fn test_unicode_word_boundaries() {
+        assert_eq!(words("Hello,world! don't"), vec!["Hello", "world", "don't"]);
+        assert_eq!(
+            words("pi is 3.14, not 1,000."),
+            vec!["pi", "is", "3.14", "not", "1,000"]
+        );
+        assert_eq!(
+            words("snake_case and hyphen-ated"),
+            vec!["snake_case", "and", "hyphen", "ated"]
+        );
+        assert_eq!(words("cafe\u{301} ok"), vec!["cafe\u{301}", "ok"]);
+        assert_eq!(words("emoji test: 🌟 💻 🚀 -- __"), vec!["emoji", "test"]);
+    }
+
+    #[test]
+    fn test_cjk_segmentation() {
+        assert_eq!(
+            words("日本語のテキスト"),
+            vec!["日", "本", "語", "の", "テキスト"]
+        );
+        assert_eq!(words("한국어 문장"), vec!["한국어", "문장"]);
+    }
+
+    #[test]
+    fn test_dictionary_runs() {
+        let dictionary = Arc::new(Dictionary::from_words(["ฉัน", "ไป", "ทะเล"]));
+        let tokenizer = Tokenizer::new(
+            Segmentation::Whitespace,
+            CountingPolicy::default(),
+            &[(Script::Thai, dictionary)],
+        );
+
+        assert_eq!(
+            tokenizer.words("(ฉันไปทะเล) ok!"),
+            vec!["ฉัน", "ไป", "ทะเล", "ok!"]
+        );
+        assert_eq!(tokenizer.count_words("Bangkokฉัน !!"), 3);
+    }
+
+    #[test]
+    fn test_custom_delimiters() {
+        let config: TokenizerConfig = ",;|".parse().unwrap();
+        let tokenizer = |segmentation| {
+            Tokenizer::new(segmentation, CountingPolicy::default(), &[]).with_config(&config)
+        };
+        let line = "2024-05-01|INFO|user=alice;action=login, ok | done";
+
+        assert_eq!(
+            tokenizer(Segmentation::Whitespace).words(line),
+            vec![
+                "2024-05-01",
+                "INFO",
+                "user=alice",
+                "action=login",
+                "ok",
+                "done"
+            ]
+        );
+        assert_eq!(tokenizer(Segmentation::Whitespace).count_words(line), 6);
+        // Delimiters also split what Unicode boundaries keep together
+        assert_eq!(
+            tokenizer(Segmentation::Unicode).words("1,000 a|b"),
+            vec!["1", "000", "a", "b"]
+        );
+
+        let tabs: TokenizerConfig = "\\t".parse().unwrap();
+        assert_eq!(tabs.delimiters(), ['\t']);
+        assert!("".parse::<TokenizerConfig>().is_err());
+    }
+
+    #[test]
+    fn test_counting_policies() {
+        let line = "I don't think well-known and/or rock'n'roll -- /path";
+        let tokenizer = |segmentation, policy| Tokenizer::new(segmentation, policy, &[]);
+
+        let split = CountingPolicy {
+            apostrophes: JoinPolicy::Split,
+            hyphens: JoinPolicy::Split,
+            slashes: JoinPolicy::Split,
+        };
+        assert_eq!(
+            tokenizer(Segmentation::Whitespace, split).words(line),
+            vec![
+                "I", "don", "t", "think", "well", "known", "and", "or", "rock", "n", "roll", "--",
+                "/path"
+            ]
+        );
+
+        let join = CountingPolicy {
+            apostrophes: JoinPolicy::Join,
+            hyphens: JoinPolicy::Join,
+            slashes: JoinPolicy::Join,
+        };
+        assert_eq!(
+            tokenizer(Segmentation::Unicode, join).words(line),
+            vec![
+                "I",
+                "don't",
+                "think",
+                "well-known",
+                "and/or",
+                "rock'n'roll",
+                "path"
+            ]
+        );
+
+        let hyphens_only = CountingPolicy {
+            hyphens: JoinPolicy::Split,
+            ..Default::default()
+        };
+        assert_eq!(
+            tokenizer(Segmentation::Whitespace, hyphens_only).count_words(line),
+            9
+        );
+        assert_eq!("split".parse(), Ok(JoinPolicy::Split));
+    }
+
+    #[test]
+    fn test_segmentation_modes() {
+        let line = "こんにちは world,again!";
+        assert_eq!(Segmentation::Whitespace.count_words(line), 2);
+        assert_eq!(Segmentation::Unicode.count_words(line), 7);
+        assert_eq!("unicode".parse(), Ok(Segmentation::Unicode));
+        assert!("words".parse::<Segmentation>().is_err());
+    }
+
+    #[test]
+    fn test_word_regex() {
+        let config = TokenizerConfig::new().with_word_regex(r"#\w+".parse().unwrap());
+        let tokenizer = Tokenizer::new(Segmentation::Unicode, CountingPolicy::default(), &[])
+            .with_config(&config);
+        assert_eq!(
+            tokenizer.words("Loving #rustlang and #OpenSource, not # alone"),
+            vec!["#rustlang", "#OpenSource"]
+        );
+        assert_eq!(tokenizer.count_words("no tags here"), 0);
+
+        // Delimiters still split the matches
+        let config = TokenizerConfig::new()
+            .with_delimiters(['.'])
+            .with_word_regex(r"[\w.]+".parse().unwrap());
+        let tokenizer = Tokenizer::new(Segmentation::Unicode, CountingPolicy::default(), &[])
+            .with_config(&config);
+        assert_eq!(tokenizer.words("a.b c"), vec!["a", "b", "c"]);
+    }
+}
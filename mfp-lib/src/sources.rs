@@ -0,0 +1,547 @@
+use crate::encoding::Encoding;
+use crate::error::TextProcessorError;
+use crate::gzip::{update_crc, GzipDecoder};
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufReader, Error, ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+/// Separator between the path of an archive and the path of an entry inside it
+const ENTRY_SEPARATOR: &str = "!/";
+/// Entries read ahead of the processing
+const ENTRIES_AHEAD: usize = 2;
+/// Leading bytes of an entry searched for NUL bytes, telling binary entries apart
+const SNIFF_SIZE: u64 = 8000;
+/// Size of the tar headers giving the next entry a long name
+const MAX_NAME_HEADER_SIZE: u64 = 1024 * 1024;
+/// Value of the zip sizes and offsets moved to a ZIP64 extra field
+const ZIP64_MARKER: u32 = u32::MAX;
+
+const ZIP_END_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP_CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+const ZIP_LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+/// End of central directory record, without its trailing comment
+const ZIP_END_SIZE: usize = 22;
+const ZIP_CENTRAL_SIZE: usize = 46;
+const ZIP_LOCAL_SIZE: usize = 30;
+const ZIP_STORED: u16 = 0;
+const ZIP_DEFLATED: u16 = 8;
+const ZIP_FLAG_ENCRYPTED: u16 = 0x01;
+
+const TAR_BLOCK: usize = 512;
+
+/// Format of an archive, told by its file name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+/// Where the content of an input comes from: a file read as is, or an archive
+/// whose entries are each processed like a file of their own
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Source {
+    File(PathBuf),
+    Archive(PathBuf, ArchiveKind),
+}
+
+impl Source {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        match archive_kind(&path) {
+            Some(kind) => Source::Archive(path, kind),
+            None => Source::File(path),
+        }
+    }
+}
+
+/// Archive format of a path, by its `.zip`, `.tar`, `.tar.gz` or `.tgz` extension
+pub(crate) fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+    if name.ends_with(".zip") {
+        Some(ArchiveKind::Zip)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveKind::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else {
+        None
+    }
+}
+
+/// Text entry of an archive, along with its `archive.zip!/inner/path.txt` path,
+/// failing when larger than the size limit
+#[derive(Debug)]
+pub(crate) struct ArchiveEntry {
+    pub(crate) path: PathBuf,
+    pub(crate) content: Result<Vec<u8>, TextProcessorError>,
+}
+
+// Content of an entry, or its size when over the limit and left unread
+type EntryContent = Result<Vec<u8>, u64>;
+
+/// Path results of an archive entry are stored under
+pub(crate) fn entry_path(archive: &Path, name: &str) -> PathBuf {
+    let mut path = OsString::from(archive);
+    path.push(ENTRY_SEPARATOR);
+    path.push(name.trim_start_matches("./"));
+    PathBuf::from(path)
+}
+
+/// Archive an entry path such as `archive.zip!/inner/path.txt` was read from,
+/// None for the path of a file
+pub fn entry_archive(path: &Path) -> Option<&Path> {
+    let (archive, _) = path.to_str()?.split_once(ENTRY_SEPARATOR)?;
+    let archive = Path::new(archive);
+    archive_kind(archive).map(|_| archive)
+}
+
+/// Reads an archive on a blocking thread, streaming back its text entries one
+/// at a time - directories, links and binary entries are skipped, and entries
+/// over `limit` bytes once decompressed are not read.
+/// Reading stops as soon as the receiver is dropped.
+pub(crate) fn read_archive(
+    path: PathBuf,
+    kind: ArchiveKind,
+    limit: Option<u64>,
+) -> mpsc::Receiver<Result<ArchiveEntry, TextProcessorError>> {
+    let (sender, receiver) = mpsc::channel(ENTRIES_AHEAD);
+    tokio::task::spawn_blocking(move || {
+        let limit = limit.unwrap_or(u64::MAX);
+        let mut send = |name: &str, content: EntryContent| {
+            let path = entry_path(&path, name);
+            let content = content.map_err(|size| TextProcessorError::FileTooLarge {
+                path: path.clone(),
+                size,
+                limit,
+            });
+            sender
+                .blocking_send(Ok(ArchiveEntry { path, content }))
+                .is_ok()
+        };
+        let read = File::open(&path).and_then(|file| match kind {
+            ArchiveKind::Zip => read_zip(file, limit, &mut send),
+            ArchiveKind::Tar => read_tar(BufReader::new(file), limit, &mut send),
+            ArchiveKind::TarGz => {
+                read_tar(GzipDecoder::new(BufReader::new(file)), limit, &mut send)
+            }
+        });
+        let error = match read {
+            Ok(()) => return,
+            Err(e) if e.kind() == ErrorKind::Unsupported => {
+                TextProcessorError::UnsupportedArchive {
+                    path,
+                    reason: e.to_string(),
+                }
+            }
+            Err(e) => TextProcessorError::IoError(e),
+        };
+        let _ = sender.blocking_send(Err(error));
+    });
+    receiver
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid archive: {}", message),
+    )
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+// Reads an entry of the given size unless it is binary, as told by a NUL byte among
+// its first bytes in an encoding other than UTF-16, or over the limit. Reading stops
+// one byte past the size, for entries whose content does not match their header.
+fn read_text(data: impl Read, size: u64, limit: u64) -> Result<Option<EntryContent>, Error> {
+    let mut data = data.take(size.saturating_add(1));
+    let mut content = Vec::new();
+    (&mut data).take(SNIFF_SIZE).read_to_end(&mut content)?;
+    let utf16 = matches!(
+        Encoding::detect(&content),
+        Encoding::Utf16Le | Encoding::Utf16Be
+    );
+    if !utf16 && content.contains(&0) {
+        return Ok(None);
+    }
+    if size > limit {
+        return Ok(Some(Err(size)));
+    }
+    data.read_to_end(&mut content)?;
+    Ok(Some(Ok(content)))
+}
+
+// Reads the entries listed in the central directory at the end of a zip archive,
+// calling `entry` with each text one until it returns false
+fn read_zip(
+    mut file: File,
+    limit: u64,
+    entry: &mut impl FnMut(&str, EntryContent) -> bool,
+) -> Result<(), Error> {
+    // The end record is followed by a comment of at most 64KiB
+    let size = file.seek(SeekFrom::End(0))?;
+    let tail_size = size.min((ZIP_END_SIZE + u16::MAX as usize) as u64);
+    file.seek(SeekFrom::Start(size - tail_size))?;
+    let mut tail = vec![0; tail_size as usize];
+    file.read_exact(&mut tail)?;
+    if tail.len() < ZIP_END_SIZE {
+        return Err(invalid("too short for a zip archive"));
+    }
+    let end = (0..=tail.len() - ZIP_END_SIZE)
+        .rev()
+        .find(|&offset| u32_at(&tail, offset) == ZIP_END_SIGNATURE)
+        .ok_or_else(|| invalid("missing zip central directory"))?;
+    let end = &tail[end..];
+    let count = u16_at(end, 10);
+    let directory_size = u32_at(end, 12);
+    let directory_offset = u32_at(end, 16);
+    if count == u16::MAX || directory_size == ZIP64_MARKER || directory_offset == ZIP64_MARKER {
+        return Err(zip64());
+    }
+
+    file.seek(SeekFrom::Start(directory_offset as u64))?;
+    let mut directory = vec![0; directory_size as usize];
+    file.read_exact(&mut directory)?;
+
+    let mut position = 0;
+    for _ in 0..count {
+        let header = directory
+            .get(position..position + ZIP_CENTRAL_SIZE)
+            .filter(|header| u32_at(header, 0) == ZIP_CENTRAL_SIGNATURE)
+            .ok_or_else(|| invalid("corrupted zip central directory"))?;
+        let flags = u16_at(header, 8);
+        let method = u16_at(header, 10);
+        let crc = u32_at(header, 16);
+        let compressed_size = u32_at(header, 20);
+        let size = u32_at(header, 24);
+        let name_len = u16_at(header, 28) as usize;
+        let extra_len = u16_at(header, 30) as usize;
+        let comment_len = u16_at(header, 32) as usize;
+        let local_offset = u32_at(header, 42);
+        let name = directory
+            .get(position + ZIP_CENTRAL_SIZE..position + ZIP_CENTRAL_SIZE + name_len)
+            .ok_or_else(|| invalid("corrupted zip central directory"))?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        position += ZIP_CENTRAL_SIZE + name_len + extra_len + comment_len;
+
+        if name.ends_with('/') {
+            continue;
+        }
+        if [compressed_size, size, local_offset].contains(&ZIP64_MARKER) {
+            return Err(zip64());
+        }
+        if flags & ZIP_FLAG_ENCRYPTED != 0 {
+            debug!("Skipping encrypted zip entry {}", name);
+            continue;
+        }
+
+        file.seek(SeekFrom::Start(local_offset as u64))?;
+        let mut local = [0u8; ZIP_LOCAL_SIZE];
+        file.read_exact(&mut local)?;
+        if u32_at(&local, 0) != ZIP_LOCAL_SIGNATURE {
+            return Err(invalid("corrupted zip entry header"));
+        }
+        let skip = u16_at(&local, 26) as i64 + u16_at(&local, 28) as i64;
+        file.seek(SeekFrom::Current(skip))?;
+
+        let data = (&mut file).take(compressed_size as u64);
+        let content = match method {
+            ZIP_STORED => read_text(data, size as u64, limit)?,
+            ZIP_DEFLATED => read_text(
+                GzipDecoder::deflate(BufReader::new(data)),
+                size as u64,
+                limit,
+            )?,
+            _ => {
+                debug!(
+                    "Skipping zip entry {} compressed with method {}",
+                    name, method
+                );
+                continue;
+            }
+        };
+        let Some(content) = content else {
+            debug!("Skipping binary zip entry {}", name);
+            continue;
+        };
+        if let Ok(content) = &content {
+            if content.len() != size as usize || update_crc(0, content) != crc {
+                return Err(invalid(&format!("corrupted zip entry {}", name)));
+            }
+        }
+        if !entry(&name, content) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn zip64() -> Error {
+    Error::new(ErrorKind::Unsupported, "ZIP64 archives are not supported")
+}
+
+// Reads a whole block, returning false at the end of the input
+fn read_block(input: &mut impl Read, block: &mut [u8; TAR_BLOCK]) -> Result<bool, Error> {
+    let mut read = 0;
+    while read < TAR_BLOCK {
+        match input.read(&mut block[read..]) {
+            Ok(0) if read == 0 => return Ok(false),
+            Ok(0) => {
+                return Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "truncated tar archive",
+                ))
+            }
+            Ok(n) => read += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+// Text of a NUL-terminated header field
+fn c_string(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+// Octal number of a header field, or big-endian binary one when its high bit is set
+fn tar_number(field: &[u8]) -> Result<u64, Error> {
+    if field[0] & 0x80 != 0 {
+        return Ok(field[1..]
+            .iter()
+            .fold(0u64, |n, &b| n.wrapping_shl(8) | b as u64));
+    }
+    let digits = c_string(field);
+    let digits = digits.trim_matches(|c: char| c == ' ' || c == '\0');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(digits, 8).map_err(|_| invalid("bad number in tar header"))
+}
+
+fn tar_name(header: &[u8; TAR_BLOCK]) -> String {
+    let name = c_string(&header[..100]);
+    let prefix = c_string(&header[345..500]);
+    if &header[257..262] == b"ustar" && !prefix.is_empty() {
+        format!("{}/{}", prefix, name)
+    } else {
+        name
+    }
+}
+
+// Path of a pax extended header, made of '<length> <key>=<value>\n' records
+fn pax_path(records: &[u8]) -> Option<String> {
+    let mut rest = records;
+    while !rest.is_empty() {
+        let space = rest.iter().position(|&b| b == b' ')?;
+        let len: usize = std::str::from_utf8(&rest[..space]).ok()?.parse().ok()?;
+        let record = rest.get(space + 1..len)?.strip_suffix(b"\n")?;
+        if let Some(path) = record.strip_prefix(b"path=") {
+            return Some(String::from_utf8_lossy(path).into_owned());
+        }
+        rest = &rest[len..];
+    }
+    None
+}
+
+// Reads the entries of a tar stream in order, calling `entry` with each text
+// one until it returns false
+fn read_tar(
+    mut input: impl Read,
+    limit: u64,
+    entry: &mut impl FnMut(&str, EntryContent) -> bool,
+) -> Result<(), Error> {
+    let mut header = [0u8; TAR_BLOCK];
+    // Name given by a GNU long name or pax header to the next entry
+    let mut long_name = None;
+
+    while read_block(&mut input, &mut header)? {
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let checksum = tar_number(&header[148..156])?;
+        let sum: u64 = header
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| if (148..156).contains(&i) { b' ' } else { b } as u64)
+            .sum();
+        if checksum != sum {
+            return Err(invalid("bad tar header checksum"));
+        }
+
+        let size = tar_number(&header[124..136])?;
+        let kind = header[156];
+        let mut data = (&mut input).take(size);
+        let content = match kind {
+            b'0' | b'\0' | b'7' => read_text(&mut data, size, limit)?,
+            b'L' | b'x' if size > MAX_NAME_HEADER_SIZE => {
+                return Err(invalid("tar name header too long"))
+            }
+            b'L' | b'x' => {
+                let mut content = Vec::new();
+                data.read_to_end(&mut content)?;
+                Some(Ok(content))
+            }
+            _ => None,
+        };
+        io::copy(&mut data, &mut io::sink())?;
+        if data.limit() > 0 {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "truncated tar archive",
+            ));
+        }
+        let padding = (TAR_BLOCK as u64 - size % TAR_BLOCK as u64) % TAR_BLOCK as u64;
+        io::copy(&mut (&mut input).take(padding), &mut io::sink())?;
+
+        match (kind, content) {
+            (b'L', Some(Ok(content))) => long_name = Some(c_string(&content)),
+            (b'x', Some(Ok(content))) => long_name = pax_path(&content).or(long_name),
+            // Global pax headers apply to every entry, and carry no path of interest
+            (b'g', _) => {}
+            (b'0' | b'\0' | b'7', Some(content)) => {
+                let name = long_name.take().unwrap_or_else(|| tar_name(&header));
+                if !entry(&name, content) {
+                    break;
+                }
+            }
+            _ => {
+                let name = long_name.take().unwrap_or_else(|| tar_name(&header));
+                debug!("Skipping tar entry {}", name);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn entries(path: &str, kind: ArchiveKind) -> Vec<(PathBuf, String)> {
+        let file = File::open(path).unwrap();
+        let mut entries = Vec::new();
+        let mut collect = |name: &str, content: EntryContent| {
+            entries.push((
+                entry_path(Path::new(path), name),
+                String::from_utf8(content.unwrap()).unwrap(),
+            ));
+            true
+        };
+        let limit = u64::MAX;
+        match kind {
+            ArchiveKind::Zip => read_zip(file, limit, &mut collect).unwrap(),
+            ArchiveKind::Tar => read_tar(BufReader::new(file), limit, &mut collect).unwrap(),
+            ArchiveKind::TarGz => {
+                read_tar(GzipDecoder::new(BufReader::new(file)), limit, &mut collect).unwrap()
+            }
+        }
+        entries
+    }
+
+    #[test]
+    fn test_archive_kind() {
+        assert_eq!(archive_kind(Path::new("a/b.ZIP")), Some(ArchiveKind::Zip));
+        assert_eq!(archive_kind(Path::new("b.tar")), Some(ArchiveKind::Tar));
+        assert_eq!(
+            archive_kind(Path::new("b.tar.gz")),
+            Some(ArchiveKind::TarGz)
+        );
+        assert_eq!(archive_kind(Path::new("b.tgz")), Some(ArchiveKind::TarGz));
+        assert_eq!(archive_kind(Path::new("b.txt.gz")), None);
+        assert_eq!(
+            entry_path(Path::new("docs/a.zip"), "./inner/b.txt"),
+            PathBuf::from("docs/a.zip!/inner/b.txt")
+        );
+        assert_eq!(
+            entry_archive(Path::new("docs/a.zip!/inner/b.txt")),
+            Some(Path::new("docs/a.zip"))
+        );
+        assert_eq!(entry_archive(Path::new("docs/a.zip")), None);
+        assert_eq!(entry_archive(Path::new("docs/wow!/b.txt")), None);
+    }
+
+    // Test the text entries are read and the directory and binary ones skipped
+    #[test]
+    fn test_read_archives() {
+        let long_name = format!("{}/notes.md", "nested".repeat(20));
+        for (path, kind) in [
+            ("tests/files/archives/texts.zip", ArchiveKind::Zip),
+            ("tests/files/archives/texts.tar.gz", ArchiveKind::TarGz),
+        ] {
+            let entries = entries(path, kind);
+            let names: Vec<_> = entries.iter().map(|(path, _)| path.clone()).collect();
+            assert_eq!(
+                names,
+                [
+                    entry_path(Path::new(path), "inner/first.txt"),
+                    entry_path(Path::new(path), "second.txt"),
+                    entry_path(Path::new(path), &long_name),
+                ]
+            );
+            assert_eq!(entries[1].1, "Stored as is.\n");
+        }
+
+        // Not an archive
+        let file = File::open("tests/files/longer.txt").unwrap();
+        assert!(read_zip(file, u64::MAX, &mut |_, _| true).is_err());
+        let file = File::open("tests/files/longer.txt").unwrap();
+        assert!(read_tar(file, u64::MAX, &mut |_, _| true).is_err());
+    }
+
+    // Test entries over the size limit are passed on with their size, unread
+    #[test]
+    fn test_entry_size_limit() {
+        for (path, kind) in [
+            ("tests/files/archives/texts.zip", ArchiveKind::Zip),
+            ("tests/files/archives/texts.tar.gz", ArchiveKind::TarGz),
+        ] {
+            let file = File::open(path).unwrap();
+            let mut sizes = Vec::new();
+            let mut collect = |_: &str, content: EntryContent| {
+                sizes.push(content.map(|content| content.len() as u64));
+                true
+            };
+            match kind {
+                ArchiveKind::Zip => read_zip(file, 14, &mut collect).unwrap(),
+                _ => read_tar(GzipDecoder::new(BufReader::new(file)), 14, &mut collect).unwrap(),
+            }
+            // Only 'Stored as is.\n' fits in 14 bytes, the binary entry being skipped
+            assert_eq!(sizes, [Err(2523), Ok(14), Err(34)]);
+        }
+    }
+
+    // Test ZIP64 archives are rejected as such, rather than misread
+    #[tokio::test]
+    async fn test_zip64() {
+        let mut archive = fs::read("tests/files/archives/texts.zip").unwrap();
+        let end = archive.len() - ZIP_END_SIZE;
+        assert_eq!(u32_at(&archive, end), ZIP_END_SIGNATURE);
+        archive[end + 16..end + 20].copy_from_slice(&ZIP64_MARKER.to_le_bytes());
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("large.zip");
+        fs::write(&path, archive).unwrap();
+
+        let mut entries = read_archive(path.clone(), ArchiveKind::Zip, None);
+        assert!(matches!(
+            entries.recv().await,
+            Some(Err(TextProcessorError::UnsupportedArchive { path: error_path, .. })) if error_path == path
+        ));
+    }
+}
@@ -0,0 +1,355 @@
+use crate::frequency::{WordFrequency, ZipfFit};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Word and its occurrences
+type Entry = (String, usize);
+/// Entries read in some order, from a run or from memory
+type Entries<'a> = Box<dyn Iterator<Item = io::Result<Entry>> + 'a>;
+
+/// Run directories created by this process so far, naming the next one
+static RUN_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// Corpus-wide word frequencies aggregated within a memory budget, for corpora whose
+/// vocabulary does not fit in memory. Tables are merged in memory until more than
+/// `max_words` distinct words are held, which are then written to a temporary file
+/// as a run sorted by word; the runs are merged when the frequencies are read.
+#[derive(Debug)]
+pub struct SpillingFrequency {
+    table: WordFrequency,
+    max_words: usize,
+    runs: RunDir,
+}
+
+impl SpillingFrequency {
+    /// Aggregator holding at most `max_words` distinct words in memory, spilling to
+    /// the temporary directory of the system
+    pub fn new(max_words: usize) -> Self {
+        Self {
+            table: WordFrequency::new(),
+            max_words: max_words.max(1),
+            runs: RunDir::new(&env::temp_dir()),
+        }
+    }
+
+    /// Spills the runs into a directory created below `dir`, e.g. on a larger disk
+    pub fn with_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.runs = RunDir::new(dir.as_ref());
+        self
+    }
+
+    /// Adds the occurrences of a table, spilling the words held once over budget
+    pub fn add(&mut self, table: &WordFrequency) -> io::Result<()> {
+        self.table.merge(table);
+        if self.table.len() > self.max_words {
+            let table = std::mem::take(&mut self.table);
+            self.runs.write(table.sorted())?;
+        }
+        Ok(())
+    }
+
+    /// Number of runs written to disk so far
+    pub fn spilled_runs(&self) -> usize {
+        self.runs.files.len()
+    }
+
+    /// Frequencies of every table added, read from the runs and the words still held
+    pub fn finish(self) -> MergedFrequency {
+        MergedFrequency {
+            table: self.table,
+            max_words: self.max_words,
+            runs: self.runs,
+        }
+    }
+}
+
+/// Frequencies aggregated by a `SpillingFrequency`, each reading a pass over its runs.
+/// The temporary files are removed when dropped.
+#[derive(Debug)]
+pub struct MergedFrequency {
+    table: WordFrequency,
+    max_words: usize,
+    runs: RunDir,
+}
+
+impl MergedFrequency {
+    /// Words with their occurrences in alphabetical order
+    pub fn words(&self) -> io::Result<impl Iterator<Item = io::Result<Entry>> + '_> {
+        let mut sources = self.runs.readers()?;
+        sources.push(Box::new(
+            self.table
+                .sorted()
+                .into_iter()
+                .map(|(word, count)| Ok((word.to_string(), count))),
+        ));
+        Ok(Coalesce(merge(sources, word_key)?.peekable()))
+    }
+
+    /// Words by decreasing frequency, ties in alphabetical order. Runs of `max_words`
+    /// words sorted that way are spilled again when there are more words than that.
+    pub fn ranked(&self) -> io::Result<Entries<'static>> {
+        let mut runs = RunDir::new(self.runs.dir.parent().unwrap_or(&env::temp_dir()));
+        let mut chunk = Vec::new();
+        for entry in self.words()? {
+            chunk.push(entry?);
+            if chunk.len() >= self.max_words {
+                chunk.sort_unstable_by_key(rank_key);
+                runs.write(chunk.drain(..))?;
+            }
+        }
+        chunk.sort_unstable_by_key(rank_key);
+        if runs.files.is_empty() {
+            return Ok(Box::new(chunk.into_iter().map(Ok)));
+        }
+        runs.write(chunk)?;
+        Ok(Box::new(Owned {
+            entries: merge(runs.readers()?, rank_key)?,
+            _runs: runs,
+        }))
+    }
+
+    /// The `n` most frequent words with their occurrences, ties in alphabetical order
+    pub fn top(&self, n: usize) -> io::Result<Vec<Entry>> {
+        // Max-heap of the best words so far, the least frequent on top
+        let mut best = BinaryHeap::with_capacity(n + 1);
+        for entry in self.words()? {
+            best.push(rank_key(&entry?));
+            if best.len() > n {
+                best.pop();
+            }
+        }
+        Ok(best
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(Reverse(count), word)| (word, count))
+            .collect())
+    }
+
+    /// Fits Zipf's law to the rank-frequency curve, needing at least two distinct
+    /// words. The occurrences of every word, without the words, are held at once.
+    pub fn zipf_fit(&self) -> io::Result<Option<ZipfFit>> {
+        let mut counts = self
+            .words()?
+            .map(|entry| entry.map(|(_, count)| count))
+            .collect::<io::Result<Vec<usize>>>()?;
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(ZipfFit::from_ranked_counts(counts))
+    }
+}
+
+fn word_key(entry: &Entry) -> String {
+    entry.0.clone()
+}
+
+fn rank_key(entry: &Entry) -> (Reverse<usize>, String) {
+    (Reverse(entry.1), entry.0.clone())
+}
+
+/// Temporary directory of sorted runs, removed when dropped
+#[derive(Debug)]
+struct RunDir {
+    dir: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+impl RunDir {
+    // Directory below a parent, created along with the first run
+    fn new(parent: &Path) -> Self {
+        let id = RUN_DIRS.fetch_add(1, Ordering::Relaxed);
+        Self {
+            dir: parent.join(format!("mfp-spill-{}-{}", process::id(), id)),
+            files: Vec::new(),
+        }
+    }
+
+    // Writes entries, already in order, as the next run: one 'count<TAB>word' line each
+    fn write<W: AsRef<str>>(
+        &mut self,
+        entries: impl IntoIterator<Item = (W, usize)>,
+    ) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!("run-{}", self.files.len()));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        for (word, count) in entries {
+            writeln!(writer, "{}\t{}", count, word.as_ref())?;
+        }
+        writer.flush()?;
+        self.files.push(path);
+        Ok(())
+    }
+
+    fn readers(&self) -> io::Result<Vec<Entries<'static>>> {
+        self.files
+            .iter()
+            .map(|path| Ok(Box::new(read_run(File::open(path)?)) as Entries<'static>))
+            .collect()
+    }
+}
+
+impl Drop for RunDir {
+    fn drop(&mut self) {
+        if !self.files.is_empty() {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+fn read_run(file: File) -> impl Iterator<Item = io::Result<Entry>> {
+    BufReader::new(file).lines().map(|line| {
+        let line = line?;
+        line.split_once('\t')
+            .and_then(|(count, word)| Some((word.to_string(), count.parse().ok()?)))
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "corrupt frequency run"))
+    })
+}
+
+// Merges sources each sorted by a key into one sorted stream
+fn merge<K: Ord>(mut sources: Vec<Entries<'_>>, key: fn(&Entry) -> K) -> io::Result<Merge<'_, K>> {
+    let mut heads = BinaryHeap::with_capacity(sources.len());
+    for (index, source) in sources.iter_mut().enumerate() {
+        if let Some(entry) = source.next() {
+            let entry = entry?;
+            heads.push(Reverse((key(&entry), index, entry)));
+        }
+    }
+    Ok(Merge {
+        sources,
+        heads,
+        key,
+    })
+}
+
+/// Entries of sorted sources merged by key, the first source first on ties
+struct Merge<'a, K> {
+    sources: Vec<Entries<'a>>,
+    heads: BinaryHeap<Reverse<(K, usize, Entry)>>,
+    key: fn(&Entry) -> K,
+}
+
+impl<K: Ord> Iterator for Merge<'_, K> {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((_, index, entry)) = self.heads.pop()?;
+        match self.sources[index].next() {
+            Some(Ok(next)) => self.heads.push(Reverse(((self.key)(&next), index, next))),
+            Some(Err(e)) => return Some(Err(e)),
+            None => {}
+        }
+        Some(Ok(entry))
+    }
+}
+
+/// Entries sorted by word with the occurrences of a word in several runs summed
+struct Coalesce<I: Iterator>(Peekable<I>);
+
+impl<I: Iterator<Item = io::Result<Entry>>> Iterator for Coalesce<I> {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (word, mut count) = match self.0.next()? {
+            Ok(entry) => entry,
+            Err(e) => return Some(Err(e)),
+        };
+        while let Some(Ok((next, more))) = self.0.peek() {
+            if *next != word {
+                break;
+            }
+            count += more;
+            self.0.next();
+        }
+        Some(Ok((word, count)))
+    }
+}
+
+/// Entries read from runs that go away along with them
+struct Owned<I> {
+    entries: I,
+    _runs: RunDir,
+}
+
+impl<I: Iterator> Iterator for Owned<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segmentation::Segmentation;
+    use tempfile::TempDir;
+
+    fn table(text: &str) -> WordFrequency {
+        let mut table = WordFrequency::new();
+        table.add_line(text, Segmentation::Whitespace);
+        table
+    }
+
+    #[test]
+    fn test_spilled_frequencies_match_in_memory() {
+        let temp = TempDir::new().unwrap();
+        let texts = [
+            "the cat sat on the mat",
+            "the dog ate the cat food",
+            "a bird on a wire",
+            "zebra the end",
+        ];
+        let mut expected = WordFrequency::new();
+        let mut spilling = SpillingFrequency::new(3).with_dir(temp.path());
+        for text in texts {
+            expected.merge(&table(text));
+            spilling.add(&table(text)).unwrap();
+        }
+        assert!(spilling.spilled_runs() >= 3);
+
+        let merged = spilling.finish();
+        let words: Vec<Entry> = merged.words().unwrap().map(Result::unwrap).collect();
+        let mut sorted: Vec<Entry> = expected
+            .sorted()
+            .into_iter()
+            .map(|(word, count)| (word.to_string(), count))
+            .collect();
+        sorted.sort();
+        assert_eq!(words, sorted);
+
+        let ranked: Vec<Entry> = merged.ranked().unwrap().map(Result::unwrap).collect();
+        let expected_ranked: Vec<Entry> = expected
+            .ranked()
+            .into_iter()
+            .map(|(word, count)| (word.to_string(), count))
+            .collect();
+        assert_eq!(ranked, expected_ranked);
+        assert_eq!(
+            merged.top(2).unwrap(),
+            vec![("the".to_string(), 5), ("a".to_string(), 2)]
+        );
+        assert_eq!(merged.zipf_fit().unwrap(), expected.zipf_fit());
+
+        // The runs are removed along with the frequencies
+        drop(merged);
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_no_spill_within_budget() {
+        let mut spilling = SpillingFrequency::new(100);
+        spilling.add(&table("one two two")).unwrap();
+        assert_eq!(spilling.spilled_runs(), 0);
+        let merged = spilling.finish();
+        assert_eq!(
+            merged.top(5).unwrap(),
+            vec![("two".to_string(), 2), ("one".to_string(), 1)]
+        );
+        assert!(merged.top(0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use crate::error::TextProcessorError;
+use crate::utils::validate_file_path;
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+use tokio::fs;
+
+const ENGLISH: &str = "\
+    a about above after again against all am an and any are as at be because been \
+    before being below between both but by can could did do does doing down during \
+    each few for from further had has have having he her here hers herself him \
+    himself his how i if in into is it its itself just me more most my myself no nor \
+    not now of off on once only or other our ours ourselves out over own same she \
+    should so some such than that the their theirs them themselves then there these \
+    they this those through to too under until up very was we were what when where \
+    which while who whom why will with would you your yours yourself yourselves";
+const GERMAN: &str = "\
+    aber alle als also am an auch auf aus bei bin bis bist da damit dann das dass \
+    dein dem den der des dich die dir doch dort du durch ein eine einem einen einer \
+    eines er es euch euer für hat hatte ich ihr ihre im in ist ja jede jeder kann \
+    kein keine man mein mich mir mit muss nach nicht noch nun nur ob oder ohne sehr \
+    sein seine sich sie sind so über um und uns unser unter vom von vor war waren \
+    was weil wenn wer wie wir wird wo zu zum zur";
+const FRENCH: &str = "\
+    à au aux avec ce ces cette dans de des du elle elles en est et eux il ils je la \
+    le les leur leurs lui ma mais me même mes moi mon ne nos notre nous on ont ou où \
+    par pas pour qu que qui sa se ses si son sont sur ta te tes toi ton tu un une \
+    vos votre vous été être avoir fait comme plus tout tous";
+
+/// Built-in list of stop words
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StopWordList {
+    English,
+    German,
+    French,
+}
+
+impl FromStr for StopWordList {
+    type Err = String;
+
+    /// Parses a language code ('en', 'de', 'fr') or a language name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "en" | "english" => Ok(StopWordList::English),
+            "de" | "german" => Ok(StopWordList::German),
+            "fr" | "french" => Ok(StopWordList::French),
+            _ => Err(format!(
+                "Unknown stop-word list '{s}', expected 'english', 'german' or 'french'"
+            )),
+        }
+    }
+}
+
+impl StopWordList {
+    /// Words of the list, in lowercase
+    pub fn words(&self) -> impl Iterator<Item = &'static str> {
+        match self {
+            StopWordList::English => ENGLISH,
+            StopWordList::German => GERMAN,
+            StopWordList::French => FRENCH,
+        }
+        .split_whitespace()
+    }
+}
+
+/// Common words left out of the word frequencies and unique words, so that these
+/// analyses show the words that matter. Word counts are unaffected. Words are
+/// matched case-insensitively, trimmed of surrounding punctuation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StopWordFilter {
+    words: HashSet<String>,
+}
+
+impl StopWordFilter {
+    /// Creates an empty filter, leaving out no word
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leaves out the words of a built-in list too
+    pub fn with_list(self, list: StopWordList) -> Self {
+        self.with_words(list.words())
+    }
+
+    /// Leaves out the given words too
+    pub fn with_words<S: AsRef<str>>(mut self, words: impl IntoIterator<Item = S>) -> Self {
+        self.words.extend(
+            words
+                .into_iter()
+                .map(|word| word.as_ref().to_lowercase())
+                .filter(|word| !word.is_empty()),
+        );
+        self
+    }
+
+    /// Leaves out the words of a file too, one per line, skipping blank lines and
+    /// '#' comments
+    pub async fn with_file(self, path: &Path) -> Result<Self, TextProcessorError> {
+        validate_file_path(path)
+            .await
+            .map_err(|_| TextProcessorError::FileNotFound(path.to_path_buf()))?;
+        let text = fs::read_to_string(path).await?;
+        Ok(self.with_words(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#')),
+        ))
+    }
+
+    /// Whether a word is left out
+    pub fn is_stop_word(&self, word: &str) -> bool {
+        let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+        self.words.contains(&word.to_lowercase())
+    }
+
+    /// Number of stop words
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Whether no word is left out
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Stop words in a stable order, e.g. to fingerprint the filter
+    pub(crate) fn sorted_words(&self) -> Vec<&str> {
+        let mut words: Vec<&str> = self.words.iter().map(String::as_str).collect();
+        words.sort_unstable();
+        words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_stop_words() {
+        let filter = StopWordFilter::new().with_list(StopWordList::English);
+        assert!(filter.is_stop_word("the"));
+        assert!(filter.is_stop_word("The,"));
+        assert!(!filter.is_stop_word("river"));
+
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("stopwords.txt");
+        std::fs::write(&path, "# project words\nLorem\n\nipsum\n").unwrap();
+        let filter = filter.with_file(&path).await.unwrap();
+        assert!(filter.is_stop_word("lorem") && filter.is_stop_word("ipsum"));
+        assert!(filter.is_stop_word("the"));
+
+        assert!(StopWordFilter::new()
+            .with_file(&temp.path().join("missing.txt"))
+            .await
+            .is_err());
+        assert_eq!("FR".parse(), Ok(StopWordList::French));
+        assert!("klingon".parse::<StopWordList>().is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use crate::types::FileProcessingResult;
+use crate::word_regex::WordRegex;
+
+/// Descriptive statistics of the per-file word counts across a corpus
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CorpusStats {
+    /// Number of files
+    pub files: usize,
+    /// Sum of the words of every file
+    pub total_words: usize,
+    /// Mean words per file
+    pub mean: f64,
+    /// Population standard deviation of the words per file
+    pub stddev: f64,
+    /// Fewest words in a file
+    pub min: usize,
+    /// 25th percentile of the words per file
+    pub p25: f64,
+    /// Median words per file
+    pub median: f64,
+    /// 75th percentile of the words per file
+    pub p75: f64,
+    /// 90th percentile of the words per file
+    pub p90: f64,
+    /// 99th percentile of the words per file
+    pub p99: f64,
+    /// Most words in a file
+    pub max: usize,
+    /// Gini coefficient of the words across files, from 0 when every file holds
+    /// as many words to nearly 1 when a single file holds them all
+    pub gini: f64,
+}
+
+impl CorpusStats {
+    /// Computes the word count distribution of the given results
+    pub fn from_results<'a>(results: impl IntoIterator<Item = &'a FileProcessingResult>) -> Self {
+        let mut words: Vec<usize> = results.into_iter().map(|r| r.total_words).collect();
+        words.sort_unstable();
+
+        let n = words.len() as f64;
+        let total_words: usize = words.iter().sum();
+        let mean = if words.is_empty() {
+            0.0
+        } else {
+            total_words as f64 / n
+        };
+        let variance = if words.is_empty() {
+            0.0
+        } else {
+            words
+                .iter()
+                .map(|&w| (w as f64 - mean).powi(2))
+                .sum::<f64>()
+                / n
+        };
+
+        Self {
+            files: words.len(),
+            total_words,
+            mean,
+            stddev: variance.sqrt(),
+            min: words.first().copied().unwrap_or(0),
+            p25: percentile(&words, 25.0),
+            median: percentile(&words, 50.0),
+            p75: percentile(&words, 75.0),
+            p90: percentile(&words, 90.0),
+            p99: percentile(&words, 99.0),
+            max: words.last().copied().unwrap_or(0),
+            gini: gini(&words, total_words),
+        }
+    }
+}
+
+/// Words per line of a file and the length of its longest line, tallied as the
+/// lines are read
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LineStats {
+    /// Mean words per line
+    pub mean: f64,
+    /// Population standard deviation of the words per line
+    pub stddev: f64,
+    /// Fewest words in a line
+    pub min: usize,
+    /// Most words in a line
+    pub max: usize,
+    /// Characters of the longest line, its terminator excluded
+    pub longest_line: usize,
+    // Lines tallied so far and sum of the squared deviations from their mean,
+    // as in Welford's online algorithm
+    #[serde(skip)]
+    lines: usize,
+    #[serde(skip)]
+    squared_deviations: f64,
+}
+
+impl LineStats {
+    /// Adds a line of the given words and characters
+    pub(crate) fn add(&mut self, words: usize, chars: usize) {
+        self.lines += 1;
+        self.min = match self.lines {
+            1 => words,
+            _ => self.min.min(words),
+        };
+        self.max = self.max.max(words);
+        self.longest_line = self.longest_line.max(chars);
+        let deviation = words as f64 - self.mean;
+        self.mean += deviation / self.lines as f64;
+        self.squared_deviations += deviation * (words as f64 - self.mean);
+    }
+
+    /// Ends the tally, setting the standard deviation
+    pub(crate) fn finished(mut self) -> Self {
+        if self.lines > 0 {
+            self.stddev = (self.squared_deviations / self.lines as f64).sqrt();
+        }
+        self
+    }
+}
+
+/// Matches of a counted pattern across a corpus, as `grep -c` and `grep -l` would tally them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternMatches {
+    /// The pattern as given
+    pub pattern: String,
+    /// Occurrences of the pattern in every file
+    pub matches: usize,
+    /// Lines holding at least one occurrence
+    pub lines: usize,
+    /// Files holding at least one occurrence
+    pub files: usize,
+}
+
+impl PatternMatches {
+    /// Tallies the matches of a pattern from the results of a processor counting it
+    pub fn from_results<'a>(
+        pattern: &WordRegex,
+        results: impl IntoIterator<Item = &'a FileProcessingResult>,
+    ) -> Self {
+        let mut summary = Self {
+            pattern: pattern.as_str().to_string(),
+            matches: 0,
+            lines: 0,
+            files: 0,
+        };
+        for result in results {
+            summary.matches += result.total_words;
+            summary.lines += result.line_counts.iter().filter(|&&n| n > 0).count();
+            summary.files += usize::from(result.total_words > 0);
+        }
+        summary
+    }
+}
+
+// Percentile of sorted values, linearly interpolated between closest ranks
+fn percentile(sorted: &[usize], p: f64) -> f64 {
+    match sorted.len() {
+        0 => 0.0,
+        1 => sorted[0] as f64,
+        len => {
+            let rank = p / 100.0 * (len - 1) as f64;
+            let (low, high) = (rank.floor() as usize, rank.ceil() as usize);
+            let weight = rank - low as f64;
+            sorted[low] as f64 * (1.0 - weight) + sorted[high] as f64 * weight
+        }
+    }
+}
+
+// Gini coefficient of sorted values, using the rank-weighted sum formula
+fn gini(sorted: &[usize], total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    let n = sorted.len() as f64;
+    let weighted: f64 = sorted
+        .iter()
+        .enumerate()
+        .map(|(i, &w)| (i + 1) as f64 * w as f64)
+        .sum();
+    2.0 * weighted / (n * total as f64) - (n + 1.0) / n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(words: &[usize]) -> Vec<FileProcessingResult> {
+        words
+            .iter()
+            .map(|&total_words| FileProcessingResult {
+                total_words,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_distribution() {
+        let stats = CorpusStats::from_results(&results(&[40, 10, 30, 20, 50]));
+
+        assert_eq!(stats.files, 5);
+        assert_eq!(stats.total_words, 150);
+        assert_eq!(stats.mean, 30.0);
+        assert_eq!(stats.stddev, 200f64.sqrt());
+        assert_eq!((stats.min, stats.max), (10, 50));
+        assert_eq!(stats.p25, 20.0);
+        assert_eq!(stats.median, 30.0);
+        assert_eq!(stats.p90, 46.0);
+        assert!((stats.gini - 4.0 / 15.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_concentration_extremes() {
+        let even = CorpusStats::from_results(&results(&[7, 7, 7, 7]));
+        assert_eq!(even.gini, 0.0);
+        assert_eq!(even.stddev, 0.0);
+
+        let concentrated = CorpusStats::from_results(&results(&[0, 0, 0, 100]));
+        assert_eq!(concentrated.gini, 0.75);
+
+        let empty = CorpusStats::from_results(&results(&[]));
+        assert_eq!(empty.files, 0);
+        assert_eq!(empty.gini, 0.0);
+        assert_eq!(empty.median, 0.0);
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        let results = [
+            FileProcessingResult {
+                line_counts: vec![2, 0, 1],
+                total_words: 3,
+                ..Default::default()
+            },
+            FileProcessingResult {
+                line_counts: vec![0, 0],
+                ..Default::default()
+            },
+        ];
+        let summary = PatternMatches::from_results(&"TODO".parse().unwrap(), &results);
+        assert_eq!(summary.pattern, "TODO");
+        assert_eq!((summary.matches, summary.lines, summary.files), (3, 2, 1));
+    }
+
+    #[test]
+    fn test_line_stats() {
+        let mut stats = LineStats::default();
+        for (words, chars) in [
+            (2, 10),
+            (4, 25),
+            (4, 18),
+            (4, 20),
+            (5, 30),
+            (5, 28),
+            (7, 40),
+            (9, 52),
+        ] {
+            stats.add(words, chars);
+        }
+        let stats = stats.finished();
+        assert_eq!(stats.mean, 5.0);
+        assert_eq!(stats.stddev, 2.0);
+        assert_eq!((stats.min, stats.max, stats.longest_line), (2, 9, 52));
+        assert_eq!(LineStats::default().finished().stddev, 0.0);
+    }
+}
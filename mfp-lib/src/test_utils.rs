@@ -0,0 +1,157 @@
+use crate::corpus::{CorpusGenerator, CorpusManifest, CORPUS_TEXTS_DIR};
+use crate::error::TextProcessorError;
+use crate::types::FileProcessingResult;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Writes a file of the given content in a temporary directory, returning its path.
+/// Like the other helpers, it panics on failure as a test would.
+pub async fn create_test_file(dir: &TempDir, filename: &str, content: &str) -> PathBuf {
+    let file_path = dir.path().join(filename);
+    if let Some(parent) = file_path.parent() {
+        tokio::fs::create_dir_all(parent).await.unwrap();
+    }
+    tokio::fs::write(&file_path, content).await.unwrap();
+    file_path
+}
+
+/// Temporary directory of test files, removed when dropped
+#[derive(Debug)]
+pub struct TestCorpus {
+    dir: TempDir,
+    paths: Vec<PathBuf>,
+}
+
+impl TestCorpus {
+    /// Empty corpus in a new temporary directory
+    pub fn new() -> Self {
+        Self {
+            dir: TempDir::new().unwrap(),
+            paths: Vec::new(),
+        }
+    }
+
+    /// Adds a file, `name` being relative to the corpus directory
+    pub fn with_file(mut self, name: &str, content: impl AsRef<[u8]>) -> Self {
+        let path = self.dir.path().join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        std::fs::write(&path, content).unwrap();
+        self.paths.push(path);
+        self
+    }
+
+    /// Adds the files of a generated corpus, returning their expected counts
+    pub async fn with_generated(mut self, generator: CorpusGenerator) -> (Self, CorpusManifest) {
+        let manifest = generator.generate(self.dir.path()).await.unwrap();
+        let texts = self.dir.path().join(CORPUS_TEXTS_DIR);
+        self.paths
+            .extend(manifest.files.iter().map(|file| texts.join(&file.path)));
+        (self, manifest)
+    }
+
+    /// Directory of the corpus
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Path of a file of the corpus
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    /// Paths of the files added, in order
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.paths.clone()
+    }
+}
+
+impl Default for TestCorpus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Asserts a file was processed with the given words per line, and as many
+/// words in total
+#[track_caller]
+pub fn assert_line_counts(
+    results: &HashMap<PathBuf, FileProcessingResult>,
+    path: impl AsRef<Path>,
+    expected: &[usize],
+) {
+    let path = path.as_ref();
+    let result = results
+        .get(path)
+        .unwrap_or_else(|| panic!("No result for {}", path.display()));
+    assert_eq!(
+        result.line_counts,
+        expected,
+        "Incorrect line word counts for {}",
+        path.display()
+    );
+    assert_eq!(result.line_count, expected.len());
+    assert_eq!(
+        result.total_words,
+        expected.iter().sum::<usize>(),
+        "Incorrect total word count for {}",
+        path.display()
+    );
+}
+
+/// Asserts two results hold the same counts: words per line, characters and bytes
+#[track_caller]
+pub fn assert_same_counts(actual: &FileProcessingResult, expected: &FileProcessingResult) {
+    assert_eq!(actual.line_counts, expected.line_counts);
+    assert_eq!(actual.total_words, expected.total_words);
+    assert_eq!(actual.total_chars, expected.total_chars);
+    assert_eq!(actual.total_bytes, expected.total_bytes);
+}
+
+/// Asserts processing failed for `failed` of `total` files, the others being processed
+#[track_caller]
+pub fn assert_partial_failure(result: Result<(), TextProcessorError>, failed: usize, total: usize) {
+    match result {
+        Err(TextProcessorError::PartialProcessingFailure {
+            failed_count,
+            total_count,
+        }) => assert_eq!(
+            (failed_count, total_count),
+            (failed, total),
+            "Expected {failed} of {total} files to fail"
+        ),
+        other => panic!("Expected {failed} of {total} files to fail, got {other:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::TextProcessor;
+
+    #[tokio::test]
+    async fn test_corpus_helpers() {
+        let (corpus, manifest) = TestCorpus::new()
+            .with_file("notes/a.txt", "one two\nthree")
+            .with_generated(CorpusGenerator::new(2, 5).with_seed(3))
+            .await;
+        assert_eq!(corpus.paths().len(), 3);
+        assert_eq!(corpus.paths()[0], corpus.file("notes/a.txt"));
+
+        let mut processor = TextProcessor::new();
+        processor.process_files(corpus.paths()).await.unwrap();
+        let results = processor.get_results();
+        assert_line_counts(results, corpus.file("notes/a.txt"), &[2, 1]);
+        for (path, file) in corpus.paths()[1..].iter().zip(&manifest.files) {
+            assert_line_counts(results, path, &file.line_counts);
+        }
+
+        let missing = corpus.file("missing.txt");
+        let result = TextProcessor::new()
+            .process_files(vec![corpus.file("notes/a.txt"), missing])
+            .await;
+        assert_partial_failure(result, 1, 2);
+    }
+}
@@ -1,7 +1,103 @@
-#[derive(Debug, Clone)]
+use crate::analyzer::Metrics;
+use crate::bloom::BloomFilter;
+use crate::encoding::Encoding;
+use crate::frequency::WordFrequency;
+use crate::hyperloglog::HyperLogLog;
+use crate::readability::Readability;
+use crate::region::RegionCounts;
+use crate::summary::LineStats;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Whether a file was counted
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    #[default]
+    Processed,
+    /// Not counted for the given reason, e.g. binary content, its counts left at zero
+    Skipped(String),
+}
+
+/// Policy decision taken silently along a run, surfaced so that reports can show it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// The file was not counted for the given reason, e.g. binary content
+    Skipped { path: PathBuf, reason: String },
+    /// The file was not valid UTF-8 and was decoded with a fallback encoding instead
+    EncodingFallback { path: PathBuf, encoding: Encoding },
+    /// Only part of the file was counted, e.g. as cut by a partial read
+    Truncated { path: PathBuf },
+    /// A symlink met while walking a directory was not followed, pointing to a
+    /// directory or to nothing
+    SymlinkNotFollowed { path: PathBuf },
+}
+
+impl Warning {
+    /// Path the warning is about
+    pub fn path(&self) -> &Path {
+        match self {
+            Warning::Skipped { path, .. }
+            | Warning::EncodingFallback { path, .. }
+            | Warning::Truncated { path }
+            | Warning::SymlinkNotFollowed { path } => path,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[non_exhaustive]
 pub struct FileProcessingResult {
     /// Number of words in each line
     pub line_counts: Vec<usize>,
     /// Total number of words in the file
     pub total_words: usize,
+    /// Number of characters processed, line terminators included and byte order mark excluded
+    pub total_chars: usize,
+    /// Number of bytes processed, line terminators included
+    pub total_bytes: u64,
+    /// Number of lines processed
+    pub line_count: usize,
+    /// Words per line and longest line
+    #[serde(default)]
+    pub line_stats: LineStats,
+    /// Score attached by an external scorer, if one is configured
+    pub score: Option<f64>,
+    /// Whether only part of the file was processed
+    pub partial: bool,
+    /// Byte offset of each counted line start, when line offsets are recorded,
+    /// never for gzip files
+    pub line_offsets: Option<Vec<u64>>,
+    /// Occurrences of each word, when word frequencies are recorded
+    pub frequencies: Option<WordFrequency>,
+    /// Number of distinct words, when unique words are counted
+    pub unique_words: Option<usize>,
+    /// Distinct words, when unique words are counted, to count them across files.
+    /// Left out when serialized, and so of the result cache.
+    #[serde(skip)]
+    pub vocabulary: Option<HashSet<String>>,
+    /// Sketch of the distinct words instead, when they are counted approximately
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vocabulary_sketch: Option<HyperLogLog>,
+    /// Bloom filter of the distinct words, when kept to look words up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bloom_filter: Option<BloomFilter>,
+    /// Sentences, words and syllables, when readability is measured
+    pub readability: Option<Readability>,
+    /// Number of citation keys, when citations are excluded from the counts
+    pub citations: Option<usize>,
+    /// Words of footnotes, captions and tables, when counted separately
+    pub regions: Option<RegionCounts>,
+    /// Figures of the custom analyzers by the name they were registered with
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub analyses: BTreeMap<String, Metrics>,
+    /// Character encoding the file was decoded from
+    pub encoding: Encoding,
+    /// Whether the file started with a byte order mark, not counted as a character
+    pub had_bom: bool,
+    /// Whether the file was counted or skipped
+    pub status: FileStatus,
+    /// Time taken to count the file, as of the run that counted it for cached results
+    pub elapsed: Duration,
 }
@@ -13,6 +13,21 @@ pub async fn validate_file_path(path: &Path) -> Result<(), Error> {
     Ok(())
 }
 
+/// 64-bit FNV-1a hash with a final mix spreading every input bit over the whole hash,
+/// stable across runs and platforms as sketches and filters are stored
+pub(crate) fn stable_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^ (hash >> 33)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
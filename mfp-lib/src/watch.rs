@@ -0,0 +1,223 @@
+use crate::error::TextProcessorError;
+use crate::input::expand_paths;
+use crate::processor::TextProcessor;
+use crate::types::FileProcessingResult;
+use futures::stream::{self, Stream};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::fs;
+use tracing::{debug, warn};
+
+/// Default delay between two scans of the watched paths
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Default quiet period a file needs after its last change before being processed
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Change to the results of a watched file
+#[derive(Debug)]
+pub enum WatchEvent {
+    /// The file was processed, when first seen or after it changed
+    Updated {
+        path: PathBuf,
+        result: Box<FileProcessingResult>,
+    },
+    /// The file changed but could not be processed
+    Failed {
+        path: PathBuf,
+        error: TextProcessorError,
+    },
+    /// The file is gone
+    Removed { path: PathBuf },
+}
+
+/// Keeps the results of files and directories up to date, re-processing files
+/// as they change. Changes are detected by polling modification times and sizes,
+/// and debounced so that a file being written is processed once it settles.
+#[derive(Debug)]
+pub struct WatchingProcessor {
+    processor: TextProcessor,
+    paths: Vec<PathBuf>,
+    poll_interval: Duration,
+    debounce: Duration,
+}
+
+/// Modification time and size of a file, telling whether it changed
+type Stamp = (Option<SystemTime>, u64);
+
+struct WatchState {
+    watcher: WatchingProcessor,
+    stamps: HashMap<PathBuf, Stamp>,
+    /// Changed files along with when they last changed
+    pending: HashMap<PathBuf, Instant>,
+    events: VecDeque<WatchEvent>,
+    first_scan: bool,
+}
+
+impl WatchingProcessor {
+    /// Watches the given files and directories, processing them with the
+    /// settings of `processor`
+    pub fn new(processor: TextProcessor, paths: Vec<PathBuf>) -> Self {
+        Self {
+            processor,
+            paths,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            debounce: DEFAULT_DEBOUNCE,
+        }
+    }
+
+    /// Sets the delay between two scans of the watched paths, 500ms by default
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Sets how long a file has to stay unchanged before being processed, 300ms by default
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Endless stream of result updates: every file at first, then the files
+    /// which changed, appeared or disappeared
+    pub fn watch(self) -> impl Stream<Item = WatchEvent> {
+        let state = WatchState {
+            watcher: self,
+            stamps: HashMap::new(),
+            pending: HashMap::new(),
+            events: VecDeque::new(),
+            first_scan: true,
+        };
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(event) = state.events.pop_front() {
+                    return Some((event, state));
+                }
+                if !state.first_scan {
+                    tokio::time::sleep(state.watcher.poll_interval).await;
+                }
+                state.scan().await;
+                state.process_settled().await;
+                state.first_scan = false;
+            }
+        })
+    }
+}
+
+impl WatchState {
+    // Records the files which appeared, changed or disappeared since the last scan
+    async fn scan(&mut self) {
+        let files = match expand_paths(
+            self.watcher.paths.clone(),
+            self.watcher.processor.walk_options(),
+        )
+        .await
+        {
+            Ok(files) => files,
+            Err(e) => {
+                warn!("Failed to list watched paths: {}", e);
+                return;
+            }
+        };
+
+        let now = Instant::now();
+        let mut stamps = HashMap::with_capacity(files.len());
+        for path in files {
+            let Ok(metadata) = fs::metadata(&path).await else {
+                continue;
+            };
+            let stamp = (metadata.modified().ok(), metadata.len());
+            if self.stamps.get(&path) != Some(&stamp) {
+                debug!("Change detected in {:?}", path);
+                self.pending.insert(path.clone(), now);
+            }
+            stamps.insert(path, stamp);
+        }
+
+        for path in self.stamps.keys() {
+            if !stamps.contains_key(path) {
+                self.pending.remove(path);
+                self.events
+                    .push_back(WatchEvent::Removed { path: path.clone() });
+            }
+        }
+        self.stamps = stamps;
+    }
+
+    // Processes the changed files which have been quiet for the debounce delay,
+    // all of them on the first scan
+    async fn process_settled(&mut self) {
+        let debounce = self.watcher.debounce;
+        let mut settled: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, changed)| self.first_scan || changed.elapsed() >= debounce)
+            .map(|(path, _)| path.clone())
+            .collect();
+        settled.sort();
+
+        for path in settled {
+            self.pending.remove(&path);
+            // Archives give an event per text entry
+            let outcomes = self.watcher.processor.process_source(path).await;
+            for (path, result) in outcomes {
+                let event = match result {
+                    Ok(result) => WatchEvent::Updated {
+                        path,
+                        result: Box::new(result),
+                    },
+                    Err(error) => WatchEvent::Failed { path, error },
+                };
+                self.events.push_back(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::fs as std_fs;
+    use tempfile::TempDir;
+
+    // Waits for the next event, failing the test rather than hanging
+    async fn next_event(events: &mut (impl Stream<Item = WatchEvent> + Unpin)) -> WatchEvent {
+        tokio::time::timeout(Duration::from_secs(5), events.next())
+            .await
+            .expect("no watch event")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_watch_updates() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("notes.txt");
+        std_fs::write(&file, "one two").unwrap();
+
+        let watcher = WatchingProcessor::new(TextProcessor::new(), vec![temp.path().to_path_buf()])
+            .with_poll_interval(Duration::from_millis(10))
+            .with_debounce(Duration::from_millis(30));
+        let mut events = Box::pin(watcher.watch());
+
+        match next_event(&mut events).await {
+            WatchEvent::Updated { path, result } => {
+                assert_eq!(path, file);
+                assert_eq!(result.total_words, 2);
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+
+        std_fs::write(&file, "one two three").unwrap();
+        match next_event(&mut events).await {
+            WatchEvent::Updated { result, .. } => assert_eq!(result.total_words, 3),
+            event => panic!("unexpected event: {event:?}"),
+        }
+
+        std_fs::remove_file(&file).unwrap();
+        match next_event(&mut events).await {
+            WatchEvent::Removed { path } => assert_eq!(path, file),
+            event => panic!("unexpected event: {event:?}"),
+        }
+    }
+}
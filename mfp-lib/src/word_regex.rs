@@ -0,0 +1,474 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Regular expression defining what counts as a word, e.g. `\w+` for
+/// identifiers, `\d+` for numbers only or `#\w+` for hashtags.
+/// Supports literals, `.`, the `\w`, `\d` and `\s` classes and their negations,
+/// `[a-z]` / `[^a-z]` sets, `(...)` and `(?:...)` groups, `|`, the `*`, `+`, `?`
+/// and `{n,m}` quantifiers, greedy or lazy, and the `^`, `$` and `\b` anchors.
+/// Matching backtracks, so nested quantifiers such as `(a+)+` may be slow on long lines.
+#[derive(Clone)]
+pub struct WordRegex {
+    source: String,
+    node: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    LineStart,
+    LineEnd,
+    WordBoundary,
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Class {
+    negated: bool,
+    items: Vec<ClassItem>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ClassItem {
+    Range(char, char),
+    Word(bool),
+    Digit(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn contains(self, c: char) -> bool {
+        match self {
+            ClassItem::Range(low, high) => (low..=high).contains(&c),
+            ClassItem::Word(negated) => is_word_char(c) != negated,
+            ClassItem::Digit(negated) => c.is_numeric() != negated,
+            ClassItem::Space(negated) => c.is_whitespace() != negated,
+        }
+    }
+}
+
+impl Class {
+    fn contains(&self, c: char) -> bool {
+        self.items.iter().any(|item| item.contains(c)) != self.negated
+    }
+}
+
+// Letters, digits and the underscore, as `\w` matches
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl FromStr for WordRegex {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("Empty word regex".to_string());
+        }
+        let chars: Vec<char> = s.chars().collect();
+        let mut parser = Parser { chars, pos: 0 };
+        let node = parser
+            .alternation()
+            .and_then(|node| match parser.peek() {
+                Some(')') => Err("unmatched ')'".to_string()),
+                _ => Ok(node),
+            })
+            .map_err(|e| format!("Invalid word regex '{s}', {e}"))?;
+        Ok(WordRegex {
+            source: s.to_string(),
+            node,
+        })
+    }
+}
+
+impl fmt::Debug for WordRegex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WordRegex").field(&self.source).finish()
+    }
+}
+
+impl fmt::Display for WordRegex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl PartialEq for WordRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for WordRegex {}
+
+impl WordRegex {
+    /// Expression as written
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Non-overlapping matches of a line from left to right, empty matches left out
+    pub fn find_iter<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        let chars: Vec<char> = line.chars().collect();
+        // Byte offset of each character, and of the end of the line
+        let offsets: Vec<usize> = line
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([line.len()])
+            .collect();
+
+        let mut words = Vec::new();
+        let mut start = 0;
+        while start < chars.len() {
+            let mut end = None;
+            match_node(&self.node, &chars, start, &mut |next| {
+                end = Some(next).filter(|&next| next > start);
+                end.is_some()
+            });
+            match end {
+                Some(end) => {
+                    words.push(&line[offsets[start]..offsets[end]]);
+                    start = end;
+                }
+                None => start += 1,
+            }
+        }
+        words
+    }
+}
+
+// Backtracking matcher: whether the node matches at `pos` with the rest of the
+// expression, `next`, matching from where it ends
+fn match_node(node: &Node, text: &[char], pos: usize, next: &mut dyn FnMut(usize) -> bool) -> bool {
+    match node {
+        Node::Empty => next(pos),
+        Node::Char(c) => text.get(pos) == Some(c) && next(pos + 1),
+        Node::Any => pos < text.len() && next(pos + 1),
+        Node::Class(class) => text.get(pos).is_some_and(|&c| class.contains(c)) && next(pos + 1),
+        Node::LineStart => pos == 0 && next(pos),
+        Node::LineEnd => pos == text.len() && next(pos),
+        Node::WordBoundary => {
+            let before = pos > 0 && is_word_char(text[pos - 1]);
+            let after = text.get(pos).is_some_and(|&c| is_word_char(c));
+            before != after && next(pos)
+        }
+        Node::Concat(nodes) => match_concat(nodes, text, pos, next),
+        Node::Alternation(branches) => branches
+            .iter()
+            .any(|branch| match_node(branch, text, pos, next)),
+        Node::Repeat {
+            node,
+            min,
+            max,
+            greedy,
+        } => match_repeat(node, (*min, *max, *greedy), 0, text, pos, next),
+    }
+}
+
+fn match_concat(
+    nodes: &[Node],
+    text: &[char],
+    pos: usize,
+    next: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    match nodes.split_first() {
+        None => next(pos),
+        Some((first, rest)) => match_node(first, text, pos, &mut |end| {
+            match_concat(rest, text, end, next)
+        }),
+    }
+}
+
+fn match_repeat(
+    node: &Node,
+    (min, max, greedy): (usize, Option<usize>, bool),
+    count: usize,
+    text: &[char],
+    pos: usize,
+    next: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    let can_stop = count >= min;
+    if !greedy && can_stop && next(pos) {
+        return true;
+    }
+    // Once the minimum is reached, a repetition matching nothing would loop forever
+    let more = max.is_none_or(|max| count < max)
+        && match_node(node, text, pos, &mut |end| {
+            (end != pos || !can_stop)
+                && match_repeat(node, (min, max, greedy), count + 1, text, end, next)
+        });
+    more || (greedy && can_stop && next(pos))
+}
+
+// Recursive descent parser of the supported syntax
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.concat()?];
+        while self.eat('|') {
+            branches.push(self.concat()?);
+        }
+        Ok(match branches.len() {
+            1 => branches.pop().unwrap_or(Node::Empty),
+            _ => Node::Alternation(branches),
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantified(atom)?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.pop().unwrap_or(Node::Empty),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let Some(c) = self.peek() else {
+            return Err("unexpected end".to_string());
+        };
+        self.pos += 1;
+        match c {
+            '(' => {
+                // Groups do not capture, so '(?:' is the same as '('
+                if self.eat('?') && !self.eat(':') {
+                    return Err("only '(?:' groups are supported".to_string());
+                }
+                let node = self.alternation()?;
+                if !self.eat(')') {
+                    return Err("unclosed group".to_string());
+                }
+                Ok(node)
+            }
+            '[' => self.class(),
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::LineStart),
+            '$' => Ok(Node::LineEnd),
+            '\\' => match self.escape()? {
+                Escape::Char(c) => Ok(Node::Char(c)),
+                Escape::Item(item) => Ok(Node::Class(Class {
+                    negated: false,
+                    items: vec![item],
+                })),
+                Escape::WordBoundary => Ok(Node::WordBoundary),
+            },
+            '*' | '+' | '?' | '{' => Err(format!("nothing to repeat before '{c}'")),
+            c => Ok(Node::Char(c)),
+        }
+    }
+
+    fn escape(&mut self) -> Result<Escape, String> {
+        let Some(c) = self.peek() else {
+            return Err("trailing '\\'".to_string());
+        };
+        self.pos += 1;
+        Ok(match c {
+            'w' | 'W' => Escape::Item(ClassItem::Word(c == 'W')),
+            'd' | 'D' => Escape::Item(ClassItem::Digit(c == 'D')),
+            's' | 'S' => Escape::Item(ClassItem::Space(c == 'S')),
+            'b' => Escape::WordBoundary,
+            't' => Escape::Char('\t'),
+            c if c.is_alphanumeric() => return Err(format!("unsupported escape '\\{c}'")),
+            c => Escape::Char(c),
+        })
+    }
+
+    // Set after its '[': a leading ']' or a '-' at either end is literal
+    fn class(&mut self) -> Result<Node, String> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let Some(c) = self.peek() else {
+                return Err("unclosed '['".to_string());
+            };
+            self.pos += 1;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let low = match c {
+                '\\' => match self.escape()? {
+                    Escape::Char(c) => c,
+                    Escape::Item(item) => {
+                        items.push(item);
+                        continue;
+                    }
+                    Escape::WordBoundary => return Err("'\\b' in a set".to_string()),
+                },
+                c => c,
+            };
+            let is_range =
+                self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|&c| c != ']');
+            if !is_range {
+                items.push(ClassItem::Range(low, low));
+                continue;
+            }
+            self.pos += 1;
+            let high = match self.peek() {
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.escape()? {
+                        Escape::Char(c) => c,
+                        _ => return Err(format!("invalid range end after '{low}-'")),
+                    }
+                }
+                Some(c) => {
+                    self.pos += 1;
+                    c
+                }
+                None => return Err("unclosed '['".to_string()),
+            };
+            if high < low {
+                return Err(format!("invalid range '{low}-{high}'"));
+            }
+            items.push(ClassItem::Range(low, high));
+        }
+        Ok(Node::Class(Class { negated, items }))
+    }
+
+    fn quantified(&mut self, atom: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.pos += 1;
+                self.bounds()?
+            }
+            _ => return Ok(atom),
+        };
+        // Past the quantifier character, or the '}' of the bounds
+        self.pos += 1;
+        if matches!(atom, Node::LineStart | Node::LineEnd | Node::WordBoundary) {
+            return Err("nothing to repeat before a quantifier".to_string());
+        }
+        let greedy = !self.eat('?');
+        if matches!(self.peek(), Some('*' | '+' | '?' | '{')) {
+            return Err("nested quantifiers need a group".to_string());
+        }
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+            greedy,
+        })
+    }
+
+    // Bounds of a '{n}', '{n,}' or '{n,m}' quantifier after its '{', up to its '}'
+    fn bounds(&mut self) -> Result<(usize, Option<usize>), String> {
+        let end = self.chars[self.pos..]
+            .iter()
+            .position(|&c| c == '}')
+            .ok_or("unclosed '{'")?;
+        let inner: String = self.chars[self.pos..self.pos + end].iter().collect();
+        let number = |s: &str| {
+            s.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid repetition '{{{inner}}}'"))
+        };
+        let (min, max) = match inner.split_once(',') {
+            None => (number(&inner)?, Some(number(&inner)?)),
+            Some((min, "")) => (number(min)?, None),
+            Some((min, max)) => (number(min)?, Some(number(max)?)),
+        };
+        if max.is_some_and(|max| max < min) {
+            return Err(format!("invalid repetition '{{{inner}}}'"));
+        }
+        self.pos += end;
+        Ok((min, max))
+    }
+}
+
+enum Escape {
+    Char(char),
+    Item(ClassItem),
+    WordBoundary,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words<'a>(regex: &str, line: &'a str) -> Vec<&'a str> {
+        regex.parse::<WordRegex>().unwrap().find_iter(line)
+    }
+
+    #[test]
+    fn test_find_iter() {
+        assert_eq!(
+            words(r"\w+", "let x_1 = foo(bar); // déjà"),
+            ["let", "x_1", "foo", "bar", "déjà"]
+        );
+        assert_eq!(
+            words(r"\d+", "3 apples, 12 pears and 1.5 kg"),
+            ["3", "12", "1", "5"]
+        );
+        assert_eq!(
+            words(r"#[\w-]+", "#rust is #fun, #open-source #"),
+            ["#rust", "#fun", "#open-source"]
+        );
+        assert_eq!(
+            words(r"[A-Za-z_][A-Za-z0-9_]*", "fn main() { 2x }"),
+            ["fn", "main", "x"]
+        );
+        assert_eq!(words(r"(?:ab|a)c?", "abc ac a"), ["abc", "ac", "a"]);
+        assert_eq!(words(r"\d{2,3}", "1 12 1234"), ["12", "123"]);
+        assert_eq!(words(r"<.+?>", "<a><b>"), ["<a>", "<b>"]);
+        assert_eq!(words(r"^\w+", "first second"), ["first"]);
+        assert_eq!(words(r"\w+$", "first second"), ["second"]);
+        assert_eq!(words(r"\bcat\b", "cat concat cat."), ["cat", "cat"]);
+        assert_eq!(words(r"[^\s,]+", "a,b c"), ["a", "b", "c"]);
+        // Only empty matches, which are left out
+        assert!(words(r"x*", "abc").is_empty());
+        assert_eq!(words(r"(a*)+b", "aab"), ["aab"]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for invalid in [
+            "", "(ab", "ab)", "[a-", "*a", "a{2", "a{3,1}", r"\q", "a**", r"\",
+        ] {
+            assert!(
+                invalid.parse::<WordRegex>().is_err(),
+                "'{invalid}' should be rejected"
+            );
+        }
+        let regex: WordRegex = r"\w+".parse().unwrap();
+        assert_eq!(regex.to_string(), r"\w+");
+        assert_eq!(regex, r"\w+".parse().unwrap());
+    }
+}
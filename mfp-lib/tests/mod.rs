@@ -1,4 +1,4 @@
-use mfp_lib::{TextProcessor, TextProcessorError};
+use mfp_lib::{CorpusGenerator, Preset, TextProcessor, TextProcessorError, CORPUS_TEXTS_DIR};
 use std::path::PathBuf;
 
 // Test file definitions with their expected results
@@ -16,6 +16,14 @@ const TEST_FILES: &[(&str, &[usize], usize)] = &[
     ("larger_spaces.txt", &[0, 5, 1, 0, 0, 2, 2, 2], 12),
 ];
 
+// Preset fixtures with the total reported by the tool each preset approximates
+const PRESET_FILES: &[(&str, Preset, usize)] = &[
+    // (filename, preset, reference_total)
+    ("presets/report.txt", Preset::MsWord, 26),
+    // texcount: 23 words in text and 1 in headers
+    ("presets/paper.tex", Preset::TexCount, 24),
+];
+
 /// Helper function to construct the path to a test asset.
 fn asset_path(filename: &str) -> PathBuf {
     PathBuf::from("tests").join("files").join(filename)
@@ -117,3 +125,49 @@ async fn test_partial_failure_with_nonexistent_file() {
         verify_file_result(results, filename, expected_counts, expected_total);
     }
 }
+
+#[tokio::test]
+async fn test_presets_approximate_reference_counts() {
+    for &(filename, preset, reference_total) in PRESET_FILES {
+        let path = asset_path(filename);
+        let mut processor = TextProcessor::new().with_preset(preset);
+        processor.process_files(vec![path.clone()]).await.unwrap();
+
+        let total = processor.get_results()[&path].total_words;
+        // Within 2% of the reference tool
+        assert!(
+            total.abs_diff(reference_total) * 50 <= reference_total,
+            "{filename}: counted {total} words, reference is {reference_total}"
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_generated_corpus_matches_manifest() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let manifest = CorpusGenerator::new(200, 50)
+        .with_seed(42)
+        .generate(temp.path())
+        .await
+        .unwrap();
+
+    let texts = temp.path().join(CORPUS_TEXTS_DIR);
+    let mut processor = TextProcessor::new();
+    processor.process_files(vec![texts.clone()]).await.unwrap();
+
+    let results = processor.get_results();
+    assert_eq!(results.len(), manifest.files.len());
+    for expected in &manifest.files {
+        let result = &results[&texts.join(&expected.path)];
+        assert_eq!(
+            result.line_counts, expected.line_counts,
+            "{:?}",
+            expected.path
+        );
+        assert_eq!(result.total_words, expected.total_words);
+        assert_eq!(result.total_chars, expected.total_chars);
+        assert_eq!(result.total_bytes, expected.total_bytes);
+    }
+    let total: usize = results.values().map(|r| r.total_words).sum();
+    assert_eq!(total, manifest.total_words);
+}